@@ -0,0 +1,209 @@
+use std::str::FromStr;
+
+use tracing::info;
+
+use recmari_proto::proto::{Match, PlayerState};
+
+/// One side's character as given by the user: a name, optionally with the character's
+/// maximum health (`Zangief=11000`). recmari has no table of maximum health, so
+/// absolute values are only estimated for characters given one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Character {
+    pub name: String,
+    pub max_health: Option<u32>,
+}
+
+impl FromStr for Character {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, max_health) = match s.split_once('=') {
+            Some((name, max)) => {
+                let max = max
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&max| max > 0)
+                    .ok_or_else(|| format!("invalid maximum health in '{s}'"))?;
+                (name.trim(), Some(max))
+            }
+            None => (s.trim(), None),
+        };
+        if name.is_empty() {
+            return Err(format!("missing character name in '{s}'"));
+        }
+        Ok(Character {
+            name: name.to_string(),
+            max_health,
+        })
+    }
+}
+
+/// Characters of player 1 and player 2, given as `P1,P2` where each side is a name
+/// with an optional maximum health (e.g. `Ryu=10000,Zangief=11000`). recmari does not
+/// recognize characters on screen, so they are supplied by the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Characters {
+    pub player1: Character,
+    pub player2: Character,
+}
+
+impl FromStr for Characters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(',').collect::<Vec<_>>()[..] {
+            [p1, p2] => Ok(Characters {
+                player1: p1.parse()?,
+                player2: p2.parse()?,
+            }),
+            _ => Err(format!(
+                "invalid characters '{s}' (expected two names as P1,P2)"
+            )),
+        }
+    }
+}
+
+/// Record `characters` in the source of every match. They apply to the whole video,
+/// so it should contain matches between the same two characters only.
+pub fn attach_characters(matches: &mut [Match], characters: &Characters) {
+    let Characters { player1, player2 } = characters;
+    for source in matches.iter_mut().filter_map(|m| m.source.as_mut()) {
+        source.player1_character = player1.name.clone();
+        source.player2_character = player2.name.clone();
+        source.player1_max_health = player1.max_health;
+        source.player2_max_health = player2.max_health;
+    }
+    info!(
+        matches = matches.len(),
+        player1 = %player1.name,
+        player2 = %player2.name,
+        player1_max_health = ?player1.max_health,
+        player2_max_health = ?player2.max_health,
+        "characters recorded"
+    );
+}
+
+/// Fill in the absolute health of every frame of matches with a known maximum health.
+/// Other matches are left as they are.
+pub fn attach_absolute_health(matches: &mut [Match]) {
+    let mut converted = 0usize;
+    for m in matches.iter_mut() {
+        let Some(source) = &m.source else {
+            continue;
+        };
+        let max = [source.player1_max_health, source.player2_max_health];
+        if max.iter().all(Option::is_none) {
+            continue;
+        }
+        for fd in m.rounds.iter_mut().flat_map(|r| r.frames.iter_mut()) {
+            for (state, max) in [fd.player1.as_mut(), fd.player2.as_mut()]
+                .into_iter()
+                .zip(max)
+            {
+                if let (Some(state), Some(max)) = (state, max) {
+                    set_health(state, max);
+                }
+            }
+        }
+        converted += 1;
+    }
+    info!(matches = converted, "absolute health attached");
+}
+
+fn set_health(state: &mut PlayerState, max: u32) {
+    state.health = state.health_ratio.map(|ratio| ratio * max as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{FrameData, Round, SourceMetadata};
+
+    use super::*;
+
+    #[test]
+    fn characters_parse_as_pair() {
+        assert_eq!(
+            " Dee Jay , Zangief = 11000".parse::<Characters>(),
+            Ok(Characters {
+                player1: Character {
+                    name: "Dee Jay".into(),
+                    max_health: None,
+                },
+                player2: Character {
+                    name: "Zangief".into(),
+                    max_health: Some(11000),
+                },
+            })
+        );
+        assert!("Ryu".parse::<Characters>().is_err());
+        assert!("Ryu,".parse::<Characters>().is_err());
+        assert!("Ryu,Ken,Luke".parse::<Characters>().is_err());
+        assert!("Ryu=,Ken".parse::<Characters>().is_err());
+        assert!("Ryu=0,Ken".parse::<Characters>().is_err());
+        assert!("=10000,Ken".parse::<Characters>().is_err());
+    }
+
+    #[test]
+    fn attached_characters_give_absolute_health() {
+        let player = || PlayerState {
+            health_ratio: Some(0.5),
+            ..Default::default()
+        };
+        let mut matches = vec![Match {
+            source: Some(SourceMetadata::default()),
+            rounds: vec![Round {
+                frames: vec![FrameData {
+                    player1: Some(player()),
+                    player2: Some(player()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let characters: Characters = "Ryu,Zangief=11000".parse().unwrap();
+        attach_characters(&mut matches, &characters);
+        attach_absolute_health(&mut matches);
+        let source = matches[0].source.as_ref().unwrap();
+        assert_eq!(source.player1_character, "Ryu");
+        assert_eq!(source.player2_max_health, Some(11000));
+        let fd = &matches[0].rounds[0].frames[0];
+        assert_eq!(fd.player1.as_ref().unwrap().health, None);
+        assert_eq!(fd.player2.as_ref().unwrap().health, Some(5500.0));
+    }
+
+    #[test]
+    fn converts_ratios_of_sides_with_max_health() {
+        let player = |ratio| PlayerState {
+            health_ratio: ratio,
+            ..Default::default()
+        };
+        let mut matches = [Match {
+            source: Some(SourceMetadata {
+                player1_max_health: Some(11000),
+                ..Default::default()
+            }),
+            rounds: vec![Round {
+                frames: vec![
+                    FrameData {
+                        player1: Some(player(Some(0.5))),
+                        player2: Some(player(Some(0.5))),
+                        ..Default::default()
+                    },
+                    FrameData {
+                        player1: Some(player(None)),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        attach_absolute_health(&mut matches);
+        let frames = &matches[0].rounds[0].frames;
+        assert_eq!(frames[0].player1.as_ref().unwrap().health, Some(5500.0));
+        assert_eq!(frames[0].player2.as_ref().unwrap().health, None);
+        assert_eq!(frames[1].player1.as_ref().unwrap().health, None);
+    }
+}
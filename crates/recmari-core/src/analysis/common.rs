@@ -1,7 +1,8 @@
 use std::fmt::{self, Formatter};
 
 use image::{Rgb, RgbImage};
-use tracing::{debug, info};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
 
 /// Horizontal scanline defined by y coordinate and x range.
 ///
@@ -14,7 +15,8 @@ use tracing::{debug, info};
 /// ```
 ///
 /// Pixel count = |start − end| = `width()`.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Scanline {
     pub x_start: u32,
     pub x_end: u32,
@@ -118,6 +120,27 @@ impl fmt::Display for Hsv {
     }
 }
 
+/// Inclusive `[min, max]` bounds on each HSV channel.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HsvRange {
+    pub h: [f32; 2],
+    pub s: [f32; 2],
+    pub v: [f32; 2],
+}
+
+impl HsvRange {
+    pub fn contains(&self, hsv: Hsv) -> bool {
+        let within = |[min, max]: [f32; 2], x: f32| x >= min && x <= max;
+        within(self.h, hsv.h) && within(self.s, hsv.s) && within(self.v, hsv.v)
+    }
+
+    /// Whether every channel has `min <= max`.
+    pub fn is_valid(&self) -> bool {
+        [self.h, self.s, self.v].iter().all(|[min, max]| min <= max)
+    }
+}
+
 pub fn rgb_to_hsv(rgb: Rgb<u8>) -> Hsv {
     let r = rgb[0] as f32 / 255.0;
     let g = rgb[1] as f32 / 255.0;
@@ -145,16 +168,111 @@ pub fn rgb_to_hsv(rgb: Rgb<u8>) -> Hsv {
     Hsv { h, s, v }
 }
 
+/// HSV of every pixel along `scanline`, in scan order, converted as it is consumed so
+/// that callers stopping early skip the rest of the row.
+pub fn scanline_hsv<'a>(
+    image: &'a RgbImage,
+    scanline: &Scanline,
+) -> impl Iterator<Item = Hsv> + 'a {
+    scanline_pixels(image, *scanline).map(rgb_to_hsv)
+}
+
+fn scanline_pixels(image: &RgbImage, scanline: Scanline) -> impl Iterator<Item = Rgb<u8>> + '_ {
+    (0..scanline.width()).map(move |i| *image.get_pixel(scanline.x_at(i), scanline.y))
+}
+
+/// Rows above/below a fully occluded scanline that are tried before giving up.
+/// One row stays within the thickness of every gauge bar.
+const OCCLUSION_FALLBACK_ROWS: u32 = 1;
+
+/// Result of scanning a single row of a bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BarScan {
+    /// Fill ratio 0.0–1.0.
+    Boundary(f64),
+    /// Foreground or background is visible, but the boundary between them is hidden.
+    Hidden,
+    /// Every pixel is Unknown (e.g. a sprite covers the row).
+    Occluded,
+}
+
+/// Find the fill ratio (0.0–1.0) of a bar along `scanline`.
+///
+/// When the scanline is fully occluded, the rows just above and below it are tried
+/// before giving up.
 pub fn find_bar_boundary(
     image: &RgbImage,
     scanline: &Scanline,
-    classifier: impl Fn(Rgb<u8>) -> BarSegment,
+    classifier: impl Fn(Hsv) -> BarSegment,
 ) -> Option<f64> {
-    assert!(scanline.y < image.height(), "y exceeds image height");
+    if scanline.y >= image.height() || scanline.x_start.max(scanline.x_end) > image.width() {
+        warn!(
+            ?scanline,
+            width = image.width(),
+            height = image.height(),
+            "bar scanline outside the image"
+        );
+        return None;
+    }
+
+    let neighbors = (1..=OCCLUSION_FALLBACK_ROWS)
+        .flat_map(|d| [scanline.y.checked_sub(d), Some(scanline.y + d)])
+        .flatten()
+        .filter(|&y| y < image.height());
+    for y in std::iter::once(scanline.y).chain(neighbors) {
+        let row = Scanline { y, ..*scanline };
+        match scan_bar_row(image, &row, &classifier) {
+            BarScan::Boundary(fill) => {
+                if y != scanline.y {
+                    info!(
+                        y,
+                        original_y = scanline.y,
+                        fill,
+                        "bar read from neighboring row"
+                    );
+                }
+                return Some(fill);
+            }
+            BarScan::Hidden => return None,
+            BarScan::Occluded => debug!(y, "bar row fully occluded"),
+        }
+    }
 
+    info!(y = scanline.y, "bar fully occluded on every fallback row");
+    None
+}
+
+/// Fraction (0.0–1.0) of the pixels along `scanline` that `classifier` recognizes as
+/// foreground or background, i.e. how much of the bar is neither covered nor
+/// off-color.
+pub fn classified_fraction(
+    image: &RgbImage,
+    scanline: &Scanline,
+    classifier: impl Fn(Hsv) -> BarSegment,
+) -> f64 {
+    assert!(
+        scanline.y < image.height() && scanline.x_start.max(scanline.x_end) <= image.width(),
+        "scanline {scanline:?} outside the {}x{} image",
+        image.width(),
+        image.height()
+    );
+    let width = scanline.width();
+    assert!(width > 0, "empty scanline {scanline:?}");
+    let known = scanline_hsv(image, scanline)
+        .filter(|&hsv| classifier(hsv) != BarSegment::Unknown)
+        .count();
+    known as f64 / width as f64
+}
+
+fn scan_bar_row(
+    image: &RgbImage,
+    scanline: &Scanline,
+    classifier: &impl Fn(Hsv) -> BarSegment,
+) -> BarScan {
     let width = scanline.width();
     let mut prev_segment = BarSegment::Foreground;
     let mut last_fg_i: Option<u32> = None;
+    let mut any_known = false;
     debug!(
         x_start = scanline.x_start,
         x_end = scanline.x_end,
@@ -162,15 +280,12 @@ pub fn find_bar_boundary(
         width,
         "scanning bar boundary"
     );
-    for i in 0..width {
-        let x = scanline.x_at(i);
-
-        let pixel_rgb = image.get_pixel(x, scanline.y);
-        let segment = classifier(*pixel_rgb);
+    for (i, hsv) in (0..width).zip(scanline_hsv(image, scanline)) {
+        let segment = classifier(hsv);
 
         debug!(
-            x,
-            hsv = format!("{}", rgb_to_hsv(*pixel_rgb)),
+            x = scanline.x_at(i),
+            hsv = format!("{}", hsv),
             segment = format!("{:?}", segment),
             "pixel sample"
         );
@@ -178,31 +293,247 @@ pub fn find_bar_boundary(
         match segment {
             BarSegment::Foreground => {
                 last_fg_i = Some(i);
+                any_known = true;
             }
             BarSegment::Unknown => {}
             BarSegment::Background => {
                 if prev_segment == BarSegment::Foreground {
                     let boundary = i as f64 / width as f64;
-                    return Some(boundary);
+                    return BarScan::Boundary(boundary);
                 } else if prev_segment == BarSegment::Unknown {
                     if let Some(fg_i) = last_fg_i {
                         let boundary = (fg_i + 1) as f64 / width as f64;
-                        return Some(boundary);
+                        return BarScan::Boundary(boundary);
                     }
                     info!("border between foreground and background is hidden by unknown object",);
-                    return None;
+                    return BarScan::Hidden;
                 }
+                any_known = true;
             }
         }
 
         prev_segment = segment;
     }
 
+    if !any_known {
+        return BarScan::Occluded;
+    }
     if prev_segment == BarSegment::Unknown {
         if let Some(fg_i) = last_fg_i {
-            return Some((fg_i + 1) as f64 / width as f64);
+            return BarScan::Boundary((fg_i + 1) as f64 / width as f64);
+        }
+    }
+
+    BarScan::Boundary(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FG: Rgb<u8> = Rgb([255, 255, 0]);
+    const BG: Rgb<u8> = Rgb([0, 0, 255]);
+    const SPRITE: Rgb<u8> = Rgb([255, 0, 255]);
+
+    fn classify(hsv: Hsv) -> BarSegment {
+        match hsv.h as u32 {
+            60 => BarSegment::Foreground,
+            240 => BarSegment::Background,
+            _ => BarSegment::Unknown,
+        }
+    }
+
+    /// A 3-row bar filled 60%, with the given rows fully covered by a sprite.
+    fn bar_image(covered_rows: &[u32]) -> RgbImage {
+        RgbImage::from_fn(10, 3, |x, y| {
+            if covered_rows.contains(&y) {
+                SPRITE
+            } else if x < 6 {
+                FG
+            } else {
+                BG
+            }
+        })
+    }
+
+    const SCAN: Scanline = Scanline {
+        x_start: 0,
+        x_end: 10,
+        y: 1,
+    };
+
+    #[test]
+    fn classified_fraction_counts_recognized_pixels() {
+        let mut image = bar_image(&[]);
+        assert_eq!(classified_fraction(&image, &SCAN, classify), 1.0);
+        for x in 2..5 {
+            image.put_pixel(x, 1, SPRITE);
         }
+        assert!((classified_fraction(&image, &SCAN, classify) - 0.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reads_visible_row() {
+        assert_eq!(
+            find_bar_boundary(&bar_image(&[]), &SCAN, classify),
+            Some(0.6)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_neighboring_row() {
+        assert_eq!(
+            find_bar_boundary(&bar_image(&[1]), &SCAN, classify),
+            Some(0.6)
+        );
+        assert_eq!(
+            find_bar_boundary(&bar_image(&[0, 1]), &SCAN, classify),
+            Some(0.6)
+        );
+    }
+
+    #[test]
+    fn fully_occluded_bar_is_unreadable() {
+        assert_eq!(
+            find_bar_boundary(&bar_image(&[0, 1, 2]), &SCAN, classify),
+            None
+        );
+    }
+
+    #[test]
+    fn scanline_outside_image_is_unreadable() {
+        let image = bar_image(&[]);
+        let below = Scanline { y: 3, ..SCAN };
+        let too_wide = Scanline { x_end: 11, ..SCAN };
+        assert_eq!(find_bar_boundary(&image, &below, classify), None);
+        assert_eq!(find_bar_boundary(&image, &too_wide, classify), None);
     }
 
-    Some(1.0)
+    mod properties {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        /// A single-row image holding `segments` in scan order, read forward (P1 side) or
+        /// backward (P2 side).
+        fn scan_row(segments: &[BarSegment], backward: bool) -> Option<f64> {
+            let width = segments.len() as u32;
+            let scan = if backward {
+                Scanline {
+                    x_start: width,
+                    x_end: 0,
+                    y: 0,
+                }
+            } else {
+                Scanline {
+                    x_start: 0,
+                    x_end: width,
+                    y: 0,
+                }
+            };
+            let mut image = RgbImage::new(width, 1);
+            for (i, segment) in segments.iter().enumerate() {
+                let color = match segment {
+                    BarSegment::Foreground => FG,
+                    BarSegment::Background => BG,
+                    BarSegment::Unknown => SPRITE,
+                };
+                image.put_pixel(scan.x_at(i as u32), 0, color);
+            }
+            find_bar_boundary(&image, &scan, classify)
+        }
+
+        /// A fully visible bar `width` pixels wide with `fill` foreground pixels.
+        fn clean_bar(width: usize, fill: usize) -> Vec<BarSegment> {
+            (0..width)
+                .map(|i| {
+                    if i < fill {
+                        BarSegment::Foreground
+                    } else {
+                        BarSegment::Background
+                    }
+                })
+                .collect()
+        }
+
+        fn segment() -> impl Strategy<Value = BarSegment> {
+            prop_oneof![
+                Just(BarSegment::Foreground),
+                Just(BarSegment::Background),
+                Just(BarSegment::Unknown),
+            ]
+        }
+
+        /// Runs of foreground, background and unknown pixels.
+        fn runs() -> impl Strategy<Value = Vec<BarSegment>> {
+            prop::collection::vec((segment(), 1usize..40), 1..8).prop_map(|runs| {
+                runs.into_iter()
+                    .flat_map(|(segment, len)| std::iter::repeat_n(segment, len))
+                    .collect()
+            })
+        }
+
+        /// A bar width and a fill pixel count within it.
+        fn bar() -> impl Strategy<Value = (usize, usize)> {
+            (1usize..300).prop_flat_map(|width| (Just(width), 0..=width))
+        }
+
+        proptest! {
+            #[test]
+            fn fill_is_within_bounds(segments in runs(), backward in any::<bool>()) {
+                if let Some(fill) = scan_row(&segments, backward) {
+                    prop_assert!((0.0..=1.0).contains(&fill), "fill {fill} out of bounds");
+                }
+            }
+
+            #[test]
+            fn clean_bar_reads_exact_fill((width, fill) in bar(), backward in any::<bool>()) {
+                let read = scan_row(&clean_bar(width, fill), backward);
+                prop_assert_eq!(read, Some(fill as f64 / width as f64));
+            }
+
+            #[test]
+            fn reading_is_monotonic_in_fill(
+                (width, a) in bar(),
+                b in 0usize..300,
+                backward in any::<bool>(),
+            ) {
+                let b = b.min(width);
+                let (lo, hi) = (a.min(b), a.max(b));
+                let read_lo = scan_row(&clean_bar(width, lo), backward).unwrap();
+                let read_hi = scan_row(&clean_bar(width, hi), backward).unwrap();
+                prop_assert!(read_lo <= read_hi);
+            }
+
+            #[test]
+            fn occluder_never_overestimates(
+                (width, fill) in bar(),
+                start in 0usize..300,
+                len in 1usize..100,
+                backward in any::<bool>(),
+            ) {
+                let start = start % width;
+                let end = (start + len).min(width);
+                let mut segments = clean_bar(width, fill);
+                segments[start..end].fill(BarSegment::Unknown);
+                let exact = fill as f64 / width as f64;
+
+                let read = scan_row(&segments, backward);
+                if let Some(read) = read {
+                    prop_assert!(read <= exact, "read {read} above true fill {exact}");
+                }
+                // The last foreground pixel, or the first background pixel of an
+                // empty bar, is visible: the reading is exact.
+                if end < fill || start > fill || (start == fill && fill > 0) {
+                    prop_assert_eq!(read, Some(exact));
+                }
+            }
+
+            #[test]
+            fn fully_unknown_row_is_unreadable(width in 1usize..300, backward in any::<bool>()) {
+                let segments = vec![BarSegment::Unknown; width];
+                prop_assert_eq!(scan_row(&segments, backward), None);
+            }
+        }
+    }
 }
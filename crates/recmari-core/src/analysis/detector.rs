@@ -0,0 +1,167 @@
+use std::ops::Range;
+
+use tracing::{debug, info};
+
+use crate::analysis::huds;
+use crate::analysis::{Hud, HudType};
+use crate::config::HudConfig;
+use crate::video::frame::Frame;
+
+/// Number of detected frames after which every registered HUD is tried again, so that
+/// a switch to a more specific HUD is noticed while the current one still detects.
+pub const DEFAULT_RECHECK_INTERVAL: u32 = 30;
+
+/// Chooses the HUD implementation for each frame.
+///
+/// The current HUD is kept while it keeps detecting; every registered HUD is tried
+/// again when it stops detecting or every `recheck_interval` frames.
+pub struct HudDetector {
+    huds: Vec<Box<dyn Hud>>,
+    current: Option<usize>,
+    frames_since_check: u32,
+    recheck_interval: u32,
+}
+
+impl HudDetector {
+    /// `huds` are tried in order; more specific HUDs must come first.
+    pub fn new(huds: Vec<Box<dyn Hud>>, recheck_interval: u32) -> Self {
+        assert!(
+            !huds.is_empty(),
+            "at least one HUD implementation is required"
+        );
+        assert!(recheck_interval > 0, "recheck_interval must be positive");
+        info!(
+            count = huds.len(),
+            recheck_interval, "HUD detector initialized"
+        );
+        Self {
+            huds,
+            current: None,
+            frames_since_check: 0,
+            recheck_interval,
+        }
+    }
+
+    /// Detector over every registered HUD for the given frame size.
+    pub fn with_all_huds(frame_width: u32, frame_height: u32, config: &HudConfig) -> Self {
+        Self::new(
+            huds::all_huds(frame_width, frame_height, config),
+            DEFAULT_RECHECK_INTERVAL,
+        )
+    }
+
+    /// Return the HUD present in the frame, or None if no registered HUD matches.
+    pub fn detect(&mut self, frame: &Frame) -> Option<&dyn Hud> {
+        if let Some(i) = self.current {
+            if self.frames_since_check < self.recheck_interval && self.huds[i].detect_hud(frame) {
+                self.frames_since_check += 1;
+                return Some(self.huds[i].as_ref());
+            }
+        }
+
+        let found = huds::select_hud(&self.huds, frame)?;
+        if self.current != Some(found) {
+            info!(
+                frame_number = frame.frame_number,
+                from = ?self.current.map(|i| self.huds[i].hud_type()),
+                to = %self.huds[found].hud_type(),
+                "HUD type switched"
+            );
+        } else {
+            debug!(frame_number = frame.frame_number, "HUD type re-checked");
+        }
+        self.current = Some(found);
+        self.frames_since_check = 0;
+        Some(self.huds[found].as_ref())
+    }
+
+    /// The most recently detected HUD, or the first registered one if none was detected yet.
+    /// Used for debug overlays on frames without a detected HUD.
+    pub fn current_or_default(&self) -> &dyn Hud {
+        self.huds[self.current.unwrap_or(0)].as_ref()
+    }
+
+    /// Type of the most recently detected HUD.
+    pub fn current_type(&self) -> Option<HudType> {
+        self.current.map(|i| self.huds[i].hud_type())
+    }
+
+    /// Merged HUD rows of every registered HUD.
+    pub fn hud_rows(&self) -> Vec<Range<u32>> {
+        huds::merge_row_bands(self.huds.iter().flat_map(|h| h.hud_rows()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::analysis::huds::tests::paint_sa_frame;
+    use crate::test_support::{video_frame, StubHud};
+
+    fn frame(frame_number: u32) -> Frame {
+        let mut image = RgbImage::from_pixel(1920, 1080, Rgb([180, 170, 150]));
+        paint_sa_frame(&mut image);
+        video_frame(frame_number, image)
+    }
+
+    fn blank(frame_number: u32) -> Frame {
+        video_frame(frame_number, RgbImage::new(1920, 1080))
+    }
+
+    #[test]
+    fn no_hud_keeps_previous_selection() {
+        let mut detector = HudDetector::with_all_huds(1920, 1080, &HudConfig::default());
+        assert_eq!(
+            detector.detect(&frame(0)).map(|h| h.hud_type()),
+            Some(HudType::Manemon)
+        );
+        assert!(detector.detect(&blank(1)).is_none());
+        assert_eq!(detector.current_type(), Some(HudType::Manemon));
+        assert_eq!(detector.current_or_default().hud_type(), HudType::Manemon);
+    }
+
+    #[test]
+    fn chosen_hud_still_detects_its_presence() {
+        let hud = huds::create("manemon", 1920, 1080, &HudConfig::default()).unwrap();
+        let mut detector = HudDetector::new(vec![hud], DEFAULT_RECHECK_INTERVAL);
+        assert!(detector.detect(&blank(0)).is_none());
+        assert_eq!(
+            detector.detect(&frame(1)).map(|h| h.hud_type()),
+            Some(HudType::Manemon)
+        );
+    }
+
+    /// HP read by the HUD the detector picks for frame `frame_number`; the test HUDs
+    /// tell each other apart by the HP they read.
+    fn picked(detector: &mut HudDetector, frame_number: u32) -> Option<f64> {
+        let frame = video_frame(frame_number, RgbImage::new(1, 1));
+        detector
+            .detect(&frame)
+            .and_then(|hud| hud.analyze_hp(&frame).p1)
+    }
+
+    #[test]
+    fn switches_when_current_hud_disappears() {
+        let specific = StubHud::detecting(|frame| frame.frame_number < 2, Some(0.1));
+        let generic = StubHud::reading(Some(0.2));
+        let mut detector = HudDetector::new(vec![Box::new(specific), Box::new(generic)], 30);
+        let picks: Vec<Option<f64>> = (0..4).map(|n| picked(&mut detector, n)).collect();
+        assert_eq!(picks, [Some(0.1), Some(0.1), Some(0.2), Some(0.2)]);
+    }
+
+    #[test]
+    fn rechecks_periodically() {
+        // The generic HUD keeps detecting, so only the periodic re-check notices that
+        // the more specific HUD appeared at frame 1.
+        let specific = StubHud::detecting(|frame| frame.frame_number >= 1, Some(0.1));
+        let generic = StubHud::reading(Some(0.2));
+        let mut detector = HudDetector::new(vec![Box::new(specific), Box::new(generic)], 2);
+        let picks: Vec<Option<f64>> = (0..5).map(|n| picked(&mut detector, n)).collect();
+        assert_eq!(
+            picks,
+            [Some(0.2), Some(0.2), Some(0.2), Some(0.1), Some(0.1)]
+        );
+    }
+}
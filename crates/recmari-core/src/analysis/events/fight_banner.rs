@@ -0,0 +1,86 @@
+use tracing::debug;
+
+use crate::analysis::common::{rgb_to_hsv, Hsv};
+use crate::rect::PixelRect;
+use crate::video::frame::Frame;
+
+const REF_WIDTH: u32 = 1920;
+const REF_HEIGHT: u32 = 1080;
+
+/// Screen-center area covered by the round-intro "FIGHT" graphic at 1920x1080.
+/// TODO: Calibrate against round-start fixtures.
+const BANNER_AREA: PixelRect = PixelRect {
+    x: 560,
+    y: 400,
+    w: 800,
+    h: 280,
+};
+
+/// Distance between sampled pixels inside the banner area.
+const SAMPLE_STEP: u32 = 6;
+/// Fraction of sampled pixels that must belong to the banner lettering.
+const MIN_BANNER_RATIO: f64 = 0.25;
+
+/// Whether the round-intro "FIGHT" graphic covers the screen center.
+pub fn detect_fight_banner(frame: &Frame) -> bool {
+    let (w, h) = frame.image.dimensions();
+    let rect = BANNER_AREA.scale_to(w, h, REF_WIDTH, REF_HEIGHT);
+
+    let mut total = 0u32;
+    let mut banner = 0u32;
+    for y in (rect.y..rect.y + rect.h).step_by(SAMPLE_STEP as usize) {
+        for x in (rect.x..rect.x + rect.w).step_by(SAMPLE_STEP as usize) {
+            total += 1;
+            if is_banner_pixel(rgb_to_hsv(*frame.image.get_pixel(x, y))) {
+                banner += 1;
+            }
+        }
+    }
+    assert!(total > 0, "banner area is empty");
+
+    let ratio = banner as f64 / total as f64;
+    debug!(
+        frame_number = frame.frame_number,
+        ratio, "fight banner check"
+    );
+    ratio >= MIN_BANNER_RATIO
+}
+
+/// The lettering is near-white with bright yellow/orange paint strokes.
+fn is_banner_pixel(hsv: Hsv) -> bool {
+    let white = hsv.v > 0.90 && hsv.s < 0.25;
+    let paint = hsv.v > 0.85 && hsv.s > 0.60 && (20.0..=65.0).contains(&hsv.h);
+    white || paint
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    fn frame(image: RgbImage) -> Frame {
+        Frame {
+            image,
+            frame_number: 0,
+            timestamp_seconds: 0.0,
+        }
+    }
+
+    #[test]
+    fn detects_bright_center_graphic() {
+        let mut image = RgbImage::from_pixel(1280, 720, Rgb([40, 60, 50]));
+        for y in 300..420 {
+            for x in 400..880 {
+                image.put_pixel(x, y, Rgb([250, 200, 40]));
+            }
+        }
+        assert!(detect_fight_banner(&frame(image)));
+    }
+
+    #[test]
+    fn ignores_gameplay_frame() {
+        let image = RgbImage::from_pixel(1920, 1080, Rgb([90, 110, 140]));
+        assert!(!detect_fight_banner(&frame(image)));
+    }
+}
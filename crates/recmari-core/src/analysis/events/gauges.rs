@@ -0,0 +1,103 @@
+use tracing::info;
+
+use recmari_proto::proto::{FrameData, PlayerState};
+
+use crate::analysis::events::{EventKind, GameEvent};
+
+/// Health below this counts as KO (the bar is empty apart from reading noise).
+const KO_HEALTH: f64 = 0.01;
+/// An SA drop of at least this much is spending a stock (one stock is 1.0); smaller
+/// drops are reading noise.
+pub(crate) const SA_USE_MIN_DROP: f64 = 0.9;
+
+/// Whether a health reading is a KO.
+pub fn is_ko(health: Option<f64>) -> bool {
+    health.is_some_and(|h| h < KO_HEALTH)
+}
+
+/// Whether the SA gauge dropped from `before` to `sa` by at least one stock.
+pub fn is_sa_use(before: Option<f64>, sa: Option<f64>) -> bool {
+    matches!((before, sa), (Some(b), Some(s)) if b - s >= SA_USE_MIN_DROP)
+}
+
+/// Detect KO and SA-use events from the gauge readings of consecutive frames.
+/// Frames without a reading for a player keep that player's last known values.
+pub fn detect_gauge_events(frames: &[FrameData]) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    let mut last_health: [Option<f64>; 2] = [None; 2];
+    let mut last_sa: [Option<f64>; 2] = [None; 2];
+
+    for fd in frames {
+        let players: [Option<&PlayerState>; 2] = [fd.player1.as_ref(), fd.player2.as_ref()];
+        for (k, state) in players.into_iter().enumerate() {
+            let Some(state) = state else {
+                continue;
+            };
+            let player = Some(k as u8 + 1);
+            let event = |kind| GameEvent {
+                kind,
+                frame_number: fd.frame_number,
+                timestamp_seconds: fd.timestamp_seconds,
+                player,
+                sa_level: None,
+            };
+            if state.health_ratio.is_some() {
+                let was_alive = last_health[k].is_some_and(|h| h >= KO_HEALTH);
+                if was_alive && is_ko(state.health_ratio) {
+                    events.push(event(EventKind::Ko));
+                }
+                last_health[k] = state.health_ratio;
+            }
+            if state.sa_gauge.is_some() {
+                if is_sa_use(last_sa[k], state.sa_gauge) {
+                    events.push(event(EventKind::SaUse));
+                }
+                last_sa[k] = state.sa_gauge;
+            }
+        }
+    }
+
+    info!(
+        ko = events.iter().filter(|e| e.kind == EventKind::Ko).count(),
+        sa_use = events.iter().filter(|e| e.kind == EventKind::SaUse).count(),
+        "gauge events detected"
+    );
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{frame, health};
+
+    #[test]
+    fn ko_and_sa_use_from_gauge_drops() {
+        let frames = [
+            (0, (Some(1.0), 2.5), 0.4),
+            (60, (None, 2.4), 0.3), // P1 HP unreadable, small SA noise
+            (120, (Some(0.9), 0.4), 0.2),
+            (180, (Some(0.9), 0.4), 0.0),
+            (240, (Some(0.9), 0.4), 0.0),
+        ]
+        .map(|(n, (hp, sa), p2_hp)| FrameData {
+            player1: Some(PlayerState {
+                sa_gauge: Some(sa),
+                ..health(hp)
+            }),
+            player2: Some(health(Some(p2_hp))),
+            ..frame(n)
+        });
+        let events = detect_gauge_events(&frames);
+        let summary: Vec<(EventKind, u32, Option<u8>)> = events
+            .iter()
+            .map(|e| (e.kind, e.frame_number, e.player))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (EventKind::SaUse, 120, Some(1)),
+                (EventKind::Ko, 180, Some(2)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use tracing::info;
+
+use recmari_proto::proto::{FrameData, Match};
+
+use crate::analysis::events::{EventKind, GameEvent};
+
+/// A health difference smaller than this is a tie (reading noise), not a lead.
+const LEAD_MIN_MARGIN: f64 = 0.02;
+
+/// Health lead of P1 over P2 (positive when P1 is ahead), or None unless both are read.
+pub fn health_lead(fd: &FrameData) -> Option<f64> {
+    let p1 = fd.player1.as_ref()?.health_ratio?;
+    let p2 = fd.player2.as_ref()?.health_ratio?;
+    Some(p1 - p2)
+}
+
+/// `(timestamp, lead)` for every frame where both health bars were read.
+pub fn health_lead_series(frames: &[FrameData]) -> Vec<(f64, f64)> {
+    frames
+        .iter()
+        .filter_map(|fd| health_lead(fd).map(|lead| (fd.timestamp_seconds, lead)))
+        .collect()
+}
+
+/// Frames of one round where the leader changes, including the first lead. Ties do
+/// not end a lead; the next lead by the same player is not a change.
+pub fn detect_lead_changes(frames: &[FrameData]) -> Vec<GameEvent> {
+    let mut leader: Option<u8> = None;
+    let mut events = Vec::new();
+    for fd in frames {
+        let Some(lead) = health_lead(fd) else {
+            continue;
+        };
+        let ahead = if lead >= LEAD_MIN_MARGIN {
+            1
+        } else if lead <= -LEAD_MIN_MARGIN {
+            2
+        } else {
+            continue;
+        };
+        if leader != Some(ahead) {
+            leader = Some(ahead);
+            events.push(GameEvent {
+                kind: EventKind::LeadChange,
+                frame_number: fd.frame_number,
+                timestamp_seconds: fd.timestamp_seconds,
+                player: Some(ahead),
+                sa_level: None,
+            });
+        }
+    }
+    events
+}
+
+/// Add lead change events to every round, keeping its events in chronological order.
+pub fn attach_lead_changes(matches: &mut [Match]) {
+    let mut changes = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        let found = detect_lead_changes(&round.frames);
+        changes += found.len();
+        round
+            .events
+            .extend(found.iter().map(GameEvent::to_round_event));
+        round
+            .events
+            .sort_by(|a, b| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+    }
+    info!(changes, "lead changes attached");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{frame, health};
+
+    #[test]
+    fn leads_flip_between_players() {
+        let frames = [
+            (0, Some(1.0), 1.0),
+            (60, Some(1.0), 0.9),   // P1 leads
+            (120, Some(0.9), 0.9),  // tie
+            (180, Some(0.95), 0.9), // still P1
+            (240, None, 0.9),
+            (300, Some(0.5), 0.9), // P2 leads
+            (360, Some(0.5), 0.2), // P1 again
+        ]
+        .map(|(n, p1, p2)| FrameData {
+            player1: Some(health(p1)),
+            player2: Some(health(Some(p2))),
+            ..frame(n)
+        });
+        let changes: Vec<(u32, Option<u8>)> = detect_lead_changes(&frames)
+            .iter()
+            .map(|e| (e.frame_number, e.player))
+            .collect();
+        assert_eq!(changes, vec![(60, Some(1)), (300, Some(2)), (360, Some(1))]);
+        assert_eq!(health_lead_series(&frames).len(), 6);
+        assert!((health_lead_series(&frames)[5].1 - 0.3).abs() < 1e-9);
+    }
+}
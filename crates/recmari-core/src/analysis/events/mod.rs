@@ -0,0 +1,287 @@
+pub mod fight_banner;
+pub mod gauges;
+pub mod lead;
+pub mod screen_effects;
+pub mod super_arts;
+
+use std::collections::HashMap;
+
+use tracing::info;
+
+use recmari_proto::proto::{EventType, Match, RoundEvent};
+
+use crate::video::frame::Frame;
+
+/// A FIGHT banner this long after a round's first frame still starts that round
+/// (the round intro is shown between the HP reset and the banner).
+const FIGHT_BANNER_MAX_DELAY_SECONDS: f64 = 10.0;
+/// Screen effects last several frames; detections of the same kind closer than this
+/// are one event.
+const EFFECT_DEBOUNCE_SECONDS: f64 = 1.0;
+
+/// Kind of a timestamped in-game event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// The round-intro "FIGHT" graphic; marks the moment players can act.
+    FightBanner,
+    /// Full-screen flash of a Drive Impact hit.
+    DriveImpact,
+    /// Green screen tint of a Drive Rush.
+    DriveRush,
+    /// Yellow screen tint of a stun.
+    Stun,
+    /// A player's health reached zero.
+    Ko,
+    /// A player spent SA gauge stocks.
+    SaUse,
+    /// Darkened screen of a Super Art activation.
+    SuperFlash,
+    /// The health lead passed to a player.
+    LeadChange,
+}
+
+impl From<EventKind> for EventType {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::FightBanner => EventType::FightBanner,
+            EventKind::DriveImpact => EventType::DriveImpact,
+            EventKind::DriveRush => EventType::DriveRush,
+            EventKind::Stun => EventType::Stun,
+            EventKind::Ko => EventType::Ko,
+            EventKind::SaUse => EventType::SaUse,
+            EventKind::SuperFlash => EventType::SuperFlash,
+            EventKind::LeadChange => EventType::LeadChange,
+        }
+    }
+}
+
+/// An event detected on a single sampled frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameEvent {
+    pub kind: EventKind,
+    pub frame_number: u32,
+    pub timestamp_seconds: f64,
+    /// Player (1 or 2) of gauge events; None for screen-wide events.
+    pub player: Option<u8>,
+    /// Super Art level (1-3) of super flash events, once attributed.
+    pub sa_level: Option<u8>,
+}
+
+impl GameEvent {
+    pub fn to_round_event(&self) -> RoundEvent {
+        RoundEvent {
+            r#type: EventType::from(self.kind).into(),
+            frame_number: self.frame_number,
+            timestamp_seconds: self.timestamp_seconds,
+            player: self.player.map_or(0, u32::from),
+            sa_level: self.sa_level.map_or(0, u32::from),
+        }
+    }
+
+    fn at(kind: EventKind, frame: &Frame) -> Self {
+        Self {
+            kind,
+            frame_number: frame.frame_number,
+            timestamp_seconds: frame.timestamp_seconds,
+            player: None,
+            sa_level: None,
+        }
+    }
+}
+
+/// Time of the latest reported event of each kind, so a screen effect spanning several
+/// frames is reported once.
+#[derive(Debug, Default)]
+pub struct EventDebounce {
+    last_seen: HashMap<EventKind, f64>,
+}
+
+impl EventDebounce {
+    /// Whether a detection of `kind` at `timestamp_seconds` is a new event rather than
+    /// a repeat of the one reported last; new events are remembered.
+    fn is_new(&mut self, kind: EventKind, timestamp_seconds: f64) -> bool {
+        let repeated = self
+            .last_seen
+            .get(&kind)
+            .is_some_and(|t| timestamp_seconds - t < EFFECT_DEBOUNCE_SECONDS);
+        if !repeated {
+            self.last_seen.insert(kind, timestamp_seconds);
+        }
+        !repeated
+    }
+}
+
+/// Detect events on a frame with a detected HUD. `banner_window` tells whether to look
+/// for the FIGHT banner, which is only shown while both players are at full health.
+pub fn detect_frame_events(
+    frame: &Frame,
+    banner_window: bool,
+    debounce: &mut EventDebounce,
+    events: &mut Vec<GameEvent>,
+) {
+    let kind = if banner_window && fight_banner::detect_fight_banner(frame) {
+        Some(EventKind::FightBanner)
+    } else {
+        screen_effects::classify(frame)
+    };
+    let Some(kind) = kind else {
+        return;
+    };
+
+    if !debounce.is_new(kind, frame.timestamp_seconds) {
+        return;
+    }
+    info!(
+        frame_number = frame.frame_number,
+        timestamp_seconds = frame.timestamp_seconds,
+        ?kind,
+        "event detected"
+    );
+    events.push(GameEvent::at(kind, frame));
+}
+
+/// Copy every event inside a round (from its start timestamp to its last frame) into it.
+/// Call after [`assign_round_starts`].
+pub fn attach_round_events(matches: &mut [Match], events: &[GameEvent]) {
+    let mut attached = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        let Some(last) = round.frames.last() else {
+            continue;
+        };
+        let (start, end) = (round.start_timestamp_seconds, last.timestamp_seconds);
+        round.events = events
+            .iter()
+            .filter(|e| e.timestamp_seconds >= start && e.timestamp_seconds <= end)
+            .map(GameEvent::to_round_event)
+            .collect();
+        attached += round.events.len();
+    }
+    info!(events = events.len(), attached, "round events attached");
+}
+
+/// Set each round's start timestamp from the first FIGHT banner between the end of the
+/// previous round and shortly after the round's first frame. Rounds without a banner
+/// fall back to their first frame.
+pub fn assign_round_starts(matches: &mut [Match], events: &[GameEvent]) {
+    let banners: Vec<f64> = events
+        .iter()
+        .filter(|e| e.kind == EventKind::FightBanner)
+        .map(|e| e.timestamp_seconds)
+        .collect();
+    let mut previous_end = f64::NEG_INFINITY;
+    let mut from_banner = 0usize;
+
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        let (Some(first), Some(last)) = (round.frames.first(), round.frames.last()) else {
+            continue;
+        };
+        let first_ts = first.timestamp_seconds;
+        let banner = banners
+            .iter()
+            .copied()
+            .find(|&t| t > previous_end && t <= first_ts + FIGHT_BANNER_MAX_DELAY_SECONDS);
+        round.start_timestamp_seconds = banner.unwrap_or(first_ts);
+        round.start_from_fight_banner = banner.is_some();
+        from_banner += usize::from(banner.is_some());
+        previous_end = last.timestamp_seconds;
+    }
+
+    info!(
+        banners = banners.len(),
+        rounds_from_banner = from_banner,
+        "round start timestamps assigned"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{FrameData, Round};
+
+    use super::*;
+
+    fn round(start: f64, end: f64) -> Round {
+        let frame = |ts: f64| FrameData {
+            timestamp_seconds: ts,
+            ..Default::default()
+        };
+        Round {
+            frames: vec![frame(start), frame(end)],
+            ..Default::default()
+        }
+    }
+
+    fn event(kind: EventKind, ts: f64) -> GameEvent {
+        GameEvent {
+            kind,
+            frame_number: (ts * 60.0) as u32,
+            timestamp_seconds: ts,
+            player: None,
+            sa_level: None,
+        }
+    }
+
+    fn banner(ts: f64) -> GameEvent {
+        event(EventKind::FightBanner, ts)
+    }
+
+    #[test]
+    fn banner_sets_round_start() {
+        let mut matches = vec![Match {
+            rounds: vec![round(10.0, 60.0), round(70.0, 120.0), round(130.0, 150.0)],
+            ..Default::default()
+        }];
+        // R1: banner after the first frame; R2: none; R3: banner too late.
+        assign_round_starts(&mut matches, &[banner(12.5), banner(145.0)]);
+
+        let rounds = &matches[0].rounds;
+        assert_eq!(rounds[0].start_timestamp_seconds, 12.5);
+        assert!(rounds[0].start_from_fight_banner);
+        assert_eq!(rounds[1].start_timestamp_seconds, 70.0);
+        assert!(!rounds[1].start_from_fight_banner);
+        assert_eq!(rounds[2].start_timestamp_seconds, 130.0);
+        assert!(!rounds[2].start_from_fight_banner);
+    }
+
+    #[test]
+    fn events_attach_to_enclosing_round() {
+        let mut matches = vec![Match {
+            rounds: vec![round(10.0, 60.0), round(70.0, 120.0)],
+            ..Default::default()
+        }];
+        let events = [
+            banner(12.0),
+            event(EventKind::DriveImpact, 11.0), // before the FIGHT banner
+            event(EventKind::DriveRush, 30.0),
+            event(EventKind::Stun, 65.0), // between rounds
+            event(EventKind::DriveImpact, 90.0),
+        ];
+        assign_round_starts(&mut matches, &events);
+        attach_round_events(&mut matches, &events);
+
+        let types = |i: usize| -> Vec<EventType> {
+            matches[0].rounds[i]
+                .events
+                .iter()
+                .map(|e| e.r#type())
+                .collect()
+        };
+        assert_eq!(types(0), vec![EventType::FightBanner, EventType::DriveRush]);
+        assert_eq!(types(1), vec![EventType::DriveImpact]);
+    }
+
+    #[test]
+    fn repeated_detections_are_debounced() {
+        let mut events = Vec::new();
+        let mut debounce = EventDebounce::default();
+        let frame = |timestamp_seconds: f64| Frame {
+            image: image::RgbImage::from_pixel(640, 360, image::Rgb([40, 200, 60])),
+            frame_number: (timestamp_seconds * 60.0) as u32,
+            timestamp_seconds,
+        };
+        for ts in [5.0, 5.5, 7.0] {
+            detect_frame_events(&frame(ts), false, &mut debounce, &mut events);
+        }
+        let times: Vec<f64> = events.iter().map(|e| e.timestamp_seconds).collect();
+        assert_eq!(times, [5.0, 7.0]);
+    }
+}
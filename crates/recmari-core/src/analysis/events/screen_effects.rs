@@ -0,0 +1,122 @@
+use tracing::debug;
+
+use crate::analysis::common::rgb_to_hsv;
+use crate::analysis::events::EventKind;
+use crate::video::frame::Frame;
+
+/// Distance between sampled pixels over the whole frame.
+const SAMPLE_STEP: u32 = 16;
+/// Rows above/below this fraction of the height hold the HUD and are not sampled.
+const STAGE_TOP: f64 = 0.15;
+const STAGE_BOTTOM: f64 = 0.85;
+
+/// Drive Impact hit: the stage flashes near-white.
+const FLASH_MIN_RATIO: f64 = 0.60;
+/// Drive Rush: green afterimage tint over the stage.
+const GREEN_TINT_MIN_RATIO: f64 = 0.35;
+/// Stun: yellow tint over the stage.
+const YELLOW_TINT_MIN_RATIO: f64 = 0.35;
+/// Super Art activation: the background goes near-black; only the characters stay lit.
+const DARKEN_MIN_RATIO: f64 = 0.55;
+
+/// Fractions of sampled stage pixels per screen-effect color.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EffectRatios {
+    pub flash: f64,
+    pub green: f64,
+    pub yellow: f64,
+    pub dark: f64,
+}
+
+/// Measure how much of the stage is covered by each screen-effect color.
+pub fn measure(frame: &Frame) -> EffectRatios {
+    let (w, h) = frame.image.dimensions();
+    let y_start = (h as f64 * STAGE_TOP) as u32;
+    let y_end = (h as f64 * STAGE_BOTTOM) as u32;
+
+    let (mut total, mut flash, mut green, mut yellow, mut dark) = (0u32, 0u32, 0u32, 0u32, 0u32);
+    for y in (y_start..y_end).step_by(SAMPLE_STEP as usize) {
+        for x in (0..w).step_by(SAMPLE_STEP as usize) {
+            let hsv = rgb_to_hsv(*frame.image.get_pixel(x, y));
+            total += 1;
+            if hsv.v > 0.92 && hsv.s < 0.15 {
+                flash += 1;
+            } else if hsv.s > 0.45 && hsv.v > 0.35 && (90.0..=160.0).contains(&hsv.h) {
+                green += 1;
+            } else if hsv.s > 0.45 && hsv.v > 0.55 && (45.0..90.0).contains(&hsv.h) {
+                yellow += 1;
+            } else if hsv.v < 0.12 {
+                dark += 1;
+            }
+        }
+    }
+    assert!(total > 0, "frame {w}x{h} has no stage pixels to sample");
+
+    let ratio = |n: u32| n as f64 / total as f64;
+    EffectRatios {
+        flash: ratio(flash),
+        green: ratio(green),
+        yellow: ratio(yellow),
+        dark: ratio(dark),
+    }
+}
+
+/// Classify the dominant screen effect of a frame, if any.
+/// TODO: Calibrate thresholds against Drive Impact / Drive Rush / stun / super fixtures.
+pub fn classify(frame: &Frame) -> Option<EventKind> {
+    let ratios = measure(frame);
+    debug!(
+        frame_number = frame.frame_number,
+        ?ratios,
+        "screen effect check"
+    );
+
+    if ratios.flash >= FLASH_MIN_RATIO {
+        Some(EventKind::DriveImpact)
+    } else if ratios.green >= GREEN_TINT_MIN_RATIO {
+        Some(EventKind::DriveRush)
+    } else if ratios.yellow >= YELLOW_TINT_MIN_RATIO {
+        Some(EventKind::Stun)
+    } else if ratios.dark >= DARKEN_MIN_RATIO {
+        Some(EventKind::SuperFlash)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    fn frame(color: Rgb<u8>) -> Frame {
+        Frame {
+            image: RgbImage::from_pixel(640, 360, color),
+            frame_number: 0,
+            timestamp_seconds: 0.0,
+        }
+    }
+
+    #[test]
+    fn classifies_tints() {
+        assert_eq!(
+            classify(&frame(Rgb([250, 250, 250]))),
+            Some(EventKind::DriveImpact)
+        );
+        assert_eq!(
+            classify(&frame(Rgb([40, 200, 60]))),
+            Some(EventKind::DriveRush)
+        );
+        assert_eq!(classify(&frame(Rgb([230, 210, 40]))), Some(EventKind::Stun));
+        assert_eq!(
+            classify(&frame(Rgb([10, 8, 14]))),
+            Some(EventKind::SuperFlash)
+        );
+    }
+
+    #[test]
+    fn ordinary_stage_has_no_effect() {
+        assert_eq!(classify(&frame(Rgb([90, 80, 120]))), None);
+    }
+}
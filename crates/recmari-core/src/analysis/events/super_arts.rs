@@ -0,0 +1,114 @@
+use tracing::info;
+
+use recmari_proto::proto::{FrameData, PlayerState};
+
+use crate::analysis::events::gauges::SA_USE_MIN_DROP;
+use crate::analysis::events::{EventKind, GameEvent};
+
+/// The spent stocks show on the SA gauge within this long after the flash (the gauge
+/// may be hidden or unreadable while the super freeze darkens the screen).
+const SA_DROP_WINDOW_SECONDS: f64 = 3.0;
+
+/// Attribute each super flash to the player whose SA gauge dropped around it, and set
+/// its Super Art level from the stocks spent (a Critical Art spends all three).
+/// Flashes without a matching gauge drop are left unattributed.
+pub fn attribute_super_flashes(frames: &[FrameData], events: &mut [GameEvent]) {
+    let mut attributed = 0usize;
+    let mut flashes = 0usize;
+    for event in events.iter_mut() {
+        if event.kind != EventKind::SuperFlash {
+            continue;
+        }
+        flashes += 1;
+        let drops = [
+            sa_drop(frames, event.timestamp_seconds, |fd| fd.player1.as_ref()),
+            sa_drop(frames, event.timestamp_seconds, |fd| fd.player2.as_ref()),
+        ];
+        let best = (0..2)
+            .filter_map(|k| drops[k].map(|drop| (k, drop)))
+            .filter(|&(_, drop)| drop >= SA_USE_MIN_DROP)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((k, drop)) = best else {
+            continue;
+        };
+        let player = k as u8 + 1;
+        let level = drop.round().clamp(1.0, 3.0) as u8;
+        event.player = Some(player);
+        event.sa_level = Some(level);
+        attributed += 1;
+        info!(
+            frame_number = event.frame_number,
+            timestamp_seconds = event.timestamp_seconds,
+            "SA{level} activated by P{player}"
+        );
+    }
+    info!(flashes, attributed, "super flashes attributed");
+}
+
+/// Drop of one player's SA gauge from the last reading before `at` to the lowest
+/// reading in the window after it.
+fn sa_drop(
+    frames: &[FrameData],
+    at: f64,
+    player: impl Fn(&FrameData) -> Option<&PlayerState>,
+) -> Option<f64> {
+    let sa = |fd: &FrameData| player(fd).and_then(|p| p.sa_gauge);
+    let i = frames.partition_point(|fd| fd.timestamp_seconds < at);
+    let before = frames[..i].iter().rev().find_map(sa)?;
+    let after = frames[i..]
+        .iter()
+        .take_while(|fd| fd.timestamp_seconds <= at + SA_DROP_WINDOW_SECONDS)
+        .filter_map(sa)
+        .reduce(f64::min)?;
+    Some(before - after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::frame_at;
+
+    fn flash(ts: f64) -> GameEvent {
+        GameEvent {
+            kind: EventKind::SuperFlash,
+            frame_number: (ts * 60.0) as u32,
+            timestamp_seconds: ts,
+            player: None,
+            sa_level: None,
+        }
+    }
+
+    #[test]
+    fn flashes_get_player_and_level_from_sa_drop() {
+        let frames = [
+            (0.0, Some(2.4), Some(3.0)),
+            (1.0, None, None), // flash: gauges hidden
+            (2.0, Some(2.5), Some(0.1)),
+            (10.0, Some(2.6), Some(0.2)),
+            (12.0, Some(0.7), Some(0.3)),
+            (20.0, Some(0.8), Some(0.4)),
+        ]
+        .map(|(ts, p1_sa, p2_sa)| {
+            let [player1, player2] = [p1_sa, p2_sa].map(|sa_gauge| {
+                Some(PlayerState {
+                    sa_gauge,
+                    ..Default::default()
+                })
+            });
+            FrameData {
+                player1,
+                player2,
+                ..frame_at(ts)
+            }
+        });
+        let mut events = [flash(1.0), flash(11.5), flash(20.0)];
+        attribute_super_flashes(&frames, &mut events);
+
+        let summary: Vec<(Option<u8>, Option<u8>)> =
+            events.iter().map(|e| (e.player, e.sa_level)).collect();
+        assert_eq!(
+            summary,
+            vec![(Some(2), Some(3)), (Some(1), Some(2)), (None, None)]
+        );
+    }
+}
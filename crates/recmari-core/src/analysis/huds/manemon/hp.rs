@@ -1,7 +1,11 @@
 use image::RgbImage;
 use tracing::debug;
 
-use crate::analysis::common::{rgb_to_hsv, Hsv, Scanline};
+use crate::analysis::common::{
+    classified_fraction, rgb_to_hsv, BarSegment, HpSegment, Hsv, HsvRange, Scanline,
+};
+
+use crate::config::ColorOverrides;
 
 use super::REF_WIDTH;
 
@@ -30,16 +34,51 @@ fn is_hp_bar_frame(hsv: Hsv) -> bool {
 }
 
 /// HP bar fill at normal health levels (yellow, H≈49-64°).
-fn is_hp_yellow(hsv: Hsv) -> bool {
-    hsv.h >= 48.0 && hsv.h <= 66.0 && hsv.s >= 0.3 && hsv.v >= 0.9
-}
+const HP_FILL: HsvRange = HsvRange {
+    h: [48.0, 66.0],
+    s: [0.3, 1.0],
+    v: [0.9, 1.0],
+};
 
 /// HP bar fill at low health below ~25% (orange, H≈40-49°).
-fn is_hp_orange(hsv: Hsv) -> bool {
-    hsv.h >= 38.0 && hsv.h <= 50.0 && hsv.s >= 0.85 && hsv.v >= 0.9
+const HP_LOW_FILL: HsvRange = HsvRange {
+    h: [38.0, 50.0],
+    s: [0.85, 1.0],
+    v: [0.9, 1.0],
+};
+
+/// Fill colors of the HP bar; overridable per capture setup.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct HpColors {
+    pub fill: HsvRange,
+    pub low_fill: HsvRange,
+}
+
+impl Default for HpColors {
+    fn default() -> Self {
+        Self {
+            fill: HP_FILL,
+            low_fill: HP_LOW_FILL,
+        }
+    }
+}
+
+impl HpColors {
+    /// The built-in colors with the profile's overrides applied.
+    pub(super) fn with_overrides(overrides: &ColorOverrides) -> Self {
+        Self {
+            fill: overrides.hp_fill.unwrap_or(HP_FILL),
+            low_fill: overrides.hp_low_fill.unwrap_or(HP_LOW_FILL),
+        }
+    }
+
+    /// HP bar fill at any health level.
+    pub(super) fn is_fill(&self, hsv: Hsv) -> bool {
+        self.fill.contains(hsv) || self.low_fill.contains(hsv)
+    }
 }
 
-fn is_hp_border_white(hsv: Hsv) -> bool {
+pub(super) fn is_hp_border_white(hsv: Hsv) -> bool {
     hsv.s < 0.25 && hsv.v > 0.9
 }
 
@@ -59,7 +98,30 @@ fn is_damage(hsv: Hsv) -> bool {
     hsv.h >= 17.0 && hsv.h <= 25.0 && hsv.s >= 0.9 && hsv.v >= 0.9
 }
 
-fn find_border(image: &RgbImage, scanline: &Scanline) -> Option<u32> {
+fn classify_hp_pixel(hsv: Hsv, colors: &HpColors) -> HpSegment {
+    if colors.is_fill(hsv) {
+        HpSegment::Healthy
+    } else if is_hp_border_white(hsv) || is_hp_border_orange(hsv) {
+        HpSegment::Border
+    } else if is_damage(hsv) {
+        HpSegment::Damage
+    } else if is_provisional_damage(hsv) {
+        HpSegment::ProvisionalDamage
+    } else if is_hp_background(hsv) || is_hp_bar_frame(hsv) {
+        HpSegment::Background
+    } else {
+        HpSegment::Unknown
+    }
+}
+
+/// Confidence of an HP reading: the fraction of bar pixels with a known HP bar color.
+pub(super) fn hp_confidence(image: &RgbImage, scanline: &Scanline, colors: &HpColors) -> f64 {
+    classified_fraction(image, scanline, |hsv| {
+        BarSegment::from(classify_hp_pixel(hsv, colors))
+    })
+}
+
+fn find_border(image: &RgbImage, scanline: &Scanline, colors: &HpColors) -> Option<u32> {
     let mut yellow_count = 0;
     let mut orange_count = 0;
     let mut background_count = 0;
@@ -75,14 +137,14 @@ fn find_border(image: &RgbImage, scanline: &Scanline) -> Option<u32> {
 
         debug!("@{x}: {hsv}");
 
-        if orange_count < 8 && is_hp_yellow(hsv) {
+        if orange_count < 8 && colors.fill.contains(hsv) {
             yellow_count += 1;
             border_count = 0;
             debug!("    hp yellow");
             continue;
         }
 
-        if yellow_count < 8 && is_hp_orange(hsv) {
+        if yellow_count < 8 && colors.low_fill.contains(hsv) {
             orange_count += 1;
             border_count = 0;
             debug!("    hp orange");
@@ -119,7 +181,7 @@ fn find_border(image: &RgbImage, scanline: &Scanline) -> Option<u32> {
             debug!("@{x} Found background pixel");
             debug!("   yellow: {yellow_count}, orange: {orange_count}");
 
-            if border_count >= 1 && border_count <= 4 {
+            if (1..=4).contains(&border_count) {
                 debug!("Confirmed border at x={border_i}, width={border_count}");
                 return Some(border_i + 1);
             } else {
@@ -149,8 +211,21 @@ fn find_border(image: &RgbImage, scanline: &Scanline) -> Option<u32> {
     None
 }
 
-pub(super) fn analyze_hp(image: &image::RgbImage, scanline: &Scanline) -> Option<f64> {
-    let border = find_border(image, scanline);
+/// HP ratio of a bar without masked pixels.
+#[cfg(test)]
+fn analyze_hp(image: &image::RgbImage, scanline: &Scanline, colors: &HpColors) -> Option<f64> {
+    analyze_visible_hp(image, scanline, scanline, colors)
+}
+
+/// HP ratio of `scanline` read only from `visible`, its part in front of an overlay
+/// mask. None when the border is not within `visible`.
+pub(super) fn analyze_visible_hp(
+    image: &image::RgbImage,
+    scanline: &Scanline,
+    visible: &Scanline,
+    colors: &HpColors,
+) -> Option<f64> {
+    let border = find_border(image, visible, colors);
     if border.is_none() {
         debug!("HP border not found, classifying entire bar as unknown");
         return None;
@@ -196,7 +271,7 @@ mod tests {
     #[traced_test]
     fn test_find_border() {
         let image = load_fixture("p2_hp_head_covered.png");
-        let border = find_border(&image, &P2_HEALTH);
+        let border = find_border(&image, &P2_HEALTH, &HpColors::default());
 
         assert_eq!(P2_HEALTH.x_at(border.unwrap()), 1684);
     }
@@ -205,7 +280,7 @@ mod tests {
     #[traced_test]
     fn test_find_border_orange() {
         let image = load_fixture("frame_6120.png");
-        let border = find_border(&image, &P1_HEALTH);
+        let border = find_border(&image, &P1_HEALTH, &HpColors::default());
 
         assert_eq!(P1_HEALTH.x_at(border.unwrap()), 851);
     }
@@ -214,7 +289,7 @@ mod tests {
     #[traced_test]
     fn test_find_border_frame_7080() {
         let image = load_fixture("frame_7080.png");
-        let border = find_border(&image, &P2_HEALTH);
+        let border = find_border(&image, &P2_HEALTH, &HpColors::default());
 
         assert_eq!(P2_HEALTH.x_at(border.unwrap()), 1543);
     }
@@ -223,7 +298,7 @@ mod tests {
     #[traced_test]
     fn test_find_border_ko() {
         let image = load_fixture("p1_ko.png");
-        let border = find_border(&image, &P1_HEALTH);
+        let border = find_border(&image, &P1_HEALTH, &HpColors::default());
 
         assert_eq!(Some(0), border);
     }
@@ -232,7 +307,7 @@ mod tests {
     #[traced_test]
     fn test_find_border_p2_hp_border_hidden() {
         let image = load_fixture("p2_hp_border_hidden.png");
-        let border = find_border(&image, &P2_HEALTH);
+        let border = find_border(&image, &P2_HEALTH, &HpColors::default());
 
         assert_eq!(None, border);
     }
@@ -241,7 +316,7 @@ mod tests {
     #[traced_test]
     fn test_analyze_hp() {
         let image = load_fixture("p2_hp_head_covered.png");
-        let hp = analyze_hp(&image, &P2_HEALTH);
+        let hp = analyze_hp(&image, &P2_HEALTH, &HpColors::default());
         assert_hp(Some(0.93), hp);
     }
 
@@ -249,7 +324,7 @@ mod tests {
     #[traced_test]
     fn test_analyze_hp_full() {
         let image = load_fixture("p2_hp_head_covered.png");
-        let hp = analyze_hp(&image, &P1_HEALTH);
+        let hp = analyze_hp(&image, &P1_HEALTH, &HpColors::default());
         assert_hp(Some(1.0), hp);
     }
 
@@ -258,11 +333,11 @@ mod tests {
     fn test_analyze_hp_fully_covered() {
         let image = load_fixture("frame_5280.png");
 
-        let hp = analyze_hp(&image, &P1_HEALTH);
+        let hp = analyze_hp(&image, &P1_HEALTH, &HpColors::default());
         assert_hp(Some(0.19), hp);
 
         // P2's HP bar is fully covered by a character.
-        let hp = analyze_hp(&image, &P2_HEALTH);
+        let hp = analyze_hp(&image, &P2_HEALTH, &HpColors::default());
         assert_hp(None, hp);
     }
 
@@ -270,7 +345,7 @@ mod tests {
     #[traced_test]
     fn test_analyze_hp_orange() {
         let image = load_fixture("frame_2760.png");
-        let hp = analyze_hp(&image, &P1_HEALTH);
+        let hp = analyze_hp(&image, &P1_HEALTH, &HpColors::default());
         assert_hp(Some(0.20), hp);
     }
 
@@ -278,7 +353,7 @@ mod tests {
     #[traced_test]
     fn test_analyze_hp_frame1800() {
         let image = load_fixture("frame_1800.png");
-        let hp = analyze_hp(&image, &P1_HEALTH);
+        let hp = analyze_hp(&image, &P1_HEALTH, &HpColors::default());
         assert_hp(Some(0.85), hp);
     }
 
@@ -286,10 +361,10 @@ mod tests {
     #[traced_test]
     fn test_round_start() {
         let image = load_fixture("round1_fight.png");
-        let hp = analyze_hp(&image, &P1_HEALTH);
+        let hp = analyze_hp(&image, &P1_HEALTH, &HpColors::default());
         assert_hp(Some(1.0), hp);
 
-        let hp = analyze_hp(&image, &P2_HEALTH);
+        let hp = analyze_hp(&image, &P2_HEALTH, &HpColors::default());
         assert_hp(Some(1.0), hp);
     }
 }
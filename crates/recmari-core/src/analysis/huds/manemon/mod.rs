@@ -1,34 +1,77 @@
 mod hp;
 mod od;
 mod position;
+mod probe;
+mod reference;
 mod sa;
 
+pub use position::detect_center_line;
+pub use probe::{probe_hp_bars, probe_od_gauges, OdGaugeProbe};
+pub use reference::{paint_reference_hud, ReferenceGauges};
 pub use sa::{scan_sa_digit_probes, ProbeScanEntry};
 
+use std::ops::Range;
+
 use image::Rgb;
 use tracing::{debug, info};
 
-use crate::analysis::common::{rgb_to_hsv, Hsv, Scanline};
+use crate::analysis::common::{scanline_hsv, Hsv, HsvRange, Scanline};
+use crate::analysis::huds::merge_row_bands;
 use crate::analysis::{DebugRegion, HpReading, Hud, HudType, OdReading, OdValue, SaReading};
+use crate::config::HudProfile;
 use crate::rect::PixelRect;
 use crate::video::frame::Frame;
 
-use hp::{P1_HEALTH, P2_HEALTH};
-use od::{read_od_value, P1_OD_GAUGE, P2_OD_GAUGE};
-use sa::{read_sa_value, P1_SA_DIGIT, P1_SA_GAUGE, P2_SA_DIGIT, P2_SA_GAUGE, SA_DIGIT_PROBES};
+use hp::{HpColors, P1_HEALTH, P2_HEALTH};
+use od::{
+    od_confidence, read_od_value, split_scanline_for_segments, OD_SEG_CEIL_OFFSET_Y,
+    OD_SEG_FLOOR_OFFSET_Y, P1_OD_GAUGE, P2_OD_GAUGE,
+};
+use sa::{
+    read_sa_value, sa_confidence, P1_SA_DIGIT, P1_SA_GAUGE, P2_SA_DIGIT, P2_SA_GAUGE,
+    SA_DIGIT_PROBES,
+};
 
-const SA_FRAME: Scanline = Scanline {
+pub(crate) const SA_FRAME: Scanline = Scanline {
     x_start: 208,
     x_end: 220,
     y: 1027,
 };
 
+/// P2's SA gauge frame — horizontal mirror of P1's.
+/// TODO: Verify against a fixture where only the P2 side is visible.
+pub(crate) const P2_SA_FRAME: Scanline = Scanline {
+    x_start: REF_WIDTH - SA_FRAME.x_end,
+    x_end: REF_WIDTH - SA_FRAME.x_start,
+    y: SA_FRAME.y,
+};
+
 const REF_WIDTH: u32 = 1920;
 const REF_HEIGHT: u32 = 1080;
 
+/// Rows read by the analyzers at 1920x1080, with a few pixels of margin.
+/// Top: HP bar (y=80 ±2) and OD gauge (y=122, segment border -8/+7).
+/// Bottom: SA digits (y=960..1020), SA gauge (y=1002) and SA frame (y=1027).
+const HUD_ROWS: [Range<u32>; 2] = [70..140, 950..1040];
+
+/// Rows decoded around a scanline moved by a HUD profile (HP reads y ±2).
+const OVERRIDE_ROW_MARGIN: u32 = 3;
+
 /// Thickness of the debug overlay line (pixels at target resolution).
 const DEBUG_LINE_H: u32 = 3;
 
+/// Fewest visible HP bar pixels in front of a mask to search for the border in (the
+/// search starts at the fourth pixel).
+const MIN_VISIBLE_HP: u32 = 4;
+
+/// Per-player elements (P1 first) touched by an overlay mask of the profile.
+#[derive(Debug, Clone, Copy, Default)]
+struct MaskedElements {
+    sa_frame: [bool; 2],
+    sa: [bool; 2],
+    od: [bool; 2],
+}
+
 /// The "manemon" HUD analyzer. All layout details are internal.
 pub struct ManemonHud {
     p1_scan: Scanline,
@@ -39,6 +82,14 @@ pub struct ManemonHud {
     p2_sa_digit_probes: [(u32, u32); 4],
     p1_od_scan: Scanline,
     p2_od_scan: Scanline,
+    hp_colors: HpColors,
+    /// HP scanlines cut short at the first masked pixel; None when too little is visible.
+    hp_visible: [Option<Scanline>; 2],
+    masked: MaskedElements,
+    masks: Vec<PixelRect>,
+    /// Rows of profile-overridden scanlines at 1920x1080, outside [`HUD_ROWS`] possibly.
+    override_rows: Vec<u32>,
+    frame_height: u32,
 }
 
 impl ManemonHud {
@@ -48,24 +99,114 @@ impl ManemonHud {
         position::detect_center_line(&frame.image)
     }
 
+    /// Whether each player's SA gauge frame is visible, P1 first. Overlays (e.g. a
+    /// camera in local-versus captures) can cover one side only.
+    pub fn detect_sides(&self, frame: &Frame) -> [bool; 2] {
+        let [p1, p2] = self.masked.sa_frame;
+        [(SA_FRAME, p1), (P2_SA_FRAME, p2)]
+            .map(|(scan, masked)| !masked && is_sa_frame_visible(frame, &scan))
+    }
+
+    /// P1 and P2 HP bar scanlines at the frame resolution.
+    pub fn hp_scanlines(&self) -> [Scanline; 2] {
+        [self.p1_scan, self.p2_scan]
+    }
+
+    /// HP bar fill colors at normal and low health.
+    pub fn hp_fill_ranges(&self) -> [HsvRange; 2] {
+        [self.hp_colors.fill, self.hp_colors.low_fill]
+    }
+
+    /// Replace the HP bar fill colors, e.g. while sweeping candidate ranges.
+    pub fn set_hp_fill_ranges(&mut self, [fill, low_fill]: [HsvRange; 2]) {
+        self.hp_colors = HpColors { fill, low_fill };
+    }
+
+    /// Frame size the HUD layout is defined at; cropped recordings are scaled to it.
+    pub const REFERENCE_SIZE: (u32, u32) = (REF_WIDTH, REF_HEIGHT);
+
+    /// Whether frames of this size can be analyzed (only 1920x1080 for now).
+    pub fn supports_resolution(frame_width: u32, frame_height: u32) -> bool {
+        (frame_width, frame_height) == (REF_WIDTH, REF_HEIGHT)
+    }
+
     pub fn new(frame_width: u32, frame_height: u32) -> Self {
+        Self::with_profile(frame_width, frame_height, &HudProfile::default())
+    }
+
+    /// Construct with the layout and colors of `profile` overriding the built-in ones.
+    pub fn with_profile(frame_width: u32, frame_height: u32, profile: &HudProfile) -> Self {
         assert!(
-            frame_width == 1920 && frame_height == 1080,
+            Self::supports_resolution(frame_width, frame_height),
             "currently only supports 1920x1080 videos"
         );
 
-        let p1_scan = P1_HEALTH.scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT);
-        let p2_scan = P2_HEALTH.scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT);
-        let p1_sa_scan = P1_SA_GAUGE.scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT);
-        let p2_sa_scan = P2_SA_GAUGE.scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT);
-
-        let p1_sa_digit_probes = SA_DIGIT_PROBES.map(|p| (p.x, p.y));
+        let scan = |over: Option<Scanline>, default: Scanline| {
+            over.unwrap_or(default)
+                .scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT)
+        };
+        let lines = &profile.scanlines;
+        let p1_scan = scan(lines.hp_p1, P1_HEALTH);
+        let p2_scan = scan(lines.hp_p2, P2_HEALTH);
+        let p1_sa_scan = scan(lines.sa_p1, P1_SA_GAUGE);
+        let p2_sa_scan = scan(lines.sa_p2, P2_SA_GAUGE);
+
+        let p1_sa_digit_probes = profile
+            .sa_digit_probes
+            .map(|probes| probes.map(|[x, y]| (x, y)))
+            .unwrap_or(SA_DIGIT_PROBES.map(|p| (p.x, p.y)));
         let p2_sa_digit_probes =
-            SA_DIGIT_PROBES.map(|p| (P2_SA_DIGIT.x + p.x - P1_SA_DIGIT.x, p.y));
+            p1_sa_digit_probes.map(|(x, y)| (P2_SA_DIGIT.x + x - P1_SA_DIGIT.x, y));
         let p1_od_scan = P1_OD_GAUGE.scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT);
         let p2_od_scan = P2_OD_GAUGE.scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT);
 
-        info!(frame_width, frame_height, "manemon HUD initialized");
+        let hp_colors = HpColors::with_overrides(&profile.colors);
+
+        let masks: Vec<PixelRect> = profile
+            .masks
+            .iter()
+            .map(|m| m.scale_to(frame_width, frame_height, REF_WIDTH, REF_HEIGHT))
+            .collect();
+        let masked = |rect: PixelRect| masks.iter().any(|m| m.intersects(&rect));
+        let sa_rect = |probes: &[(u32, u32); 4], scan: &Scanline| {
+            PixelRect::around(
+                probes
+                    .iter()
+                    .copied()
+                    .chain([scan.first_pos(), scan.last_pos()]),
+                1,
+            )
+        };
+        let od_rect = |scan: &Scanline| {
+            let (first, last) = (scan.first_pos().0, scan.last_pos().0);
+            PixelRect::around(
+                [
+                    (first, scan.y - OD_SEG_CEIL_OFFSET_Y),
+                    (last, scan.y + OD_SEG_FLOOR_OFFSET_Y),
+                ],
+                0,
+            )
+        };
+        let masked_elements = MaskedElements {
+            sa_frame: [SA_FRAME, P2_SA_FRAME]
+                .map(|s| masked(PixelRect::around([s.first_pos(), s.last_pos()], 0))),
+            sa: [
+                masked(sa_rect(&p1_sa_digit_probes, &p1_sa_scan)),
+                masked(sa_rect(&p2_sa_digit_probes, &p2_sa_scan)),
+            ],
+            od: [masked(od_rect(&p1_od_scan)), masked(od_rect(&p2_od_scan))],
+        };
+        let hp_visible = [p1_scan, p2_scan].map(|scan| visible_hp_scan(&scan, &masks));
+
+        info!(
+            frame_width,
+            frame_height,
+            customized = *profile != HudProfile::default(),
+            masks = masks.len(),
+            ?hp_visible,
+            masked = ?masked_elements,
+            "manemon HUD initialized"
+        );
 
         Self {
             p1_scan,
@@ -76,10 +217,41 @@ impl ManemonHud {
             p2_sa_digit_probes,
             p1_od_scan,
             p2_od_scan,
+            hp_colors,
+            hp_visible,
+            masked: masked_elements,
+            masks,
+            override_rows: lines.rows(),
+            frame_height,
         }
     }
 }
 
+/// The part of HP scanline `scan` in front of the first masked pixel, keeping the pixel
+/// just outside the bar that the border search reads. None when too little is visible.
+fn visible_hp_scan(scan: &Scanline, masks: &[PixelRect]) -> Option<Scanline> {
+    let masked =
+        (0..=scan.width()).find(|&i| masks.iter().any(|m| m.contains(scan.x_at(i), scan.y)));
+    match masked {
+        None => Some(*scan),
+        Some(i) if i <= MIN_VISIBLE_HP => None,
+        Some(i) => Some(Scanline {
+            x_end: scan.x_at(i - 1),
+            ..*scan
+        }),
+    }
+}
+
+/// Every pixel of the scanline shows the SA (or Critical Art) gauge frame color.
+fn is_sa_frame_visible(frame: &Frame, scan: &Scanline) -> bool {
+    scanline_hsv(&frame.image, scan)
+        .enumerate()
+        .all(|(i, hsv)| {
+            debug!("SA frame check @{}: {hsv}", scan.x_at(i as u32));
+            is_sa_frame(hsv) || is_ca_frame(hsv)
+        })
+}
+
 fn is_ca_frame(hsv: Hsv) -> bool {
     hsv.h > 180.0 && hsv.h < 210.0 && hsv.s > 0.8 && hsv.v > 0.8
 }
@@ -95,45 +267,84 @@ impl Hud for ManemonHud {
 
     fn detect_hud(&self, frame: &Frame) -> bool {
         // Check SA gauge's frame since it's not covered by other objects.
-        for i in 0..SA_FRAME.width() {
-            let x = SA_FRAME.x_at(i);
-            let pixel = frame.image.get_pixel(x, SA_FRAME.y);
-            let hsv = rgb_to_hsv(*pixel);
-            debug!("SA frame check @{x}: {hsv}");
-            if !is_sa_frame(hsv) && !is_ca_frame(hsv) {
-                return false;
-            }
+        // Either side is enough, so an overlay on one side does not hide the whole HUD.
+        let [p1, p2] = self.detect_sides(frame);
+        if p1 != p2 {
+            debug!(
+                frame_number = frame.frame_number,
+                p1, p2, "HUD detected on one side only"
+            );
         }
-        true
+        p1 || p2
     }
 
     fn analyze_hp(&self, frame: &Frame) -> HpReading {
-        let p1 = hp::analyze_hp(&frame.image, &self.p1_scan);
-        let p2 = hp::analyze_hp(&frame.image, &self.p2_scan);
+        let [(p1, p1_confidence), (p2, p2_confidence)] = [
+            (self.p1_scan, self.hp_visible[0]),
+            (self.p2_scan, self.hp_visible[1]),
+        ]
+        .map(|(scan, visible)| {
+            let Some(visible) = visible else {
+                return (None, None);
+            };
+            let hp = hp::analyze_visible_hp(&frame.image, &scan, &visible, &self.hp_colors);
+            let confidence = hp.map(|_| hp::hp_confidence(&frame.image, &visible, &self.hp_colors));
+            (hp, confidence)
+        });
 
         debug!(
             frame_number = frame.frame_number,
-            p1, p2, "manemon HP reading"
+            p1, p2, p1_confidence, p2_confidence, "manemon HP reading"
         );
 
-        HpReading { p1, p2 }
+        HpReading {
+            p1,
+            p2,
+            p1_confidence,
+            p2_confidence,
+        }
     }
 
     fn analyze_sa(&self, frame: &Frame) -> SaReading {
-        let p1 = read_sa_value(&frame.image, &self.p1_sa_digit_probes, &self.p1_sa_scan);
-        let p2 = read_sa_value(&frame.image, &self.p2_sa_digit_probes, &self.p2_sa_scan);
+        let [p1_masked, p2_masked] = self.masked.sa;
+        let p1 = (!p1_masked)
+            .then(|| read_sa_value(&frame.image, &self.p1_sa_digit_probes, &self.p1_sa_scan))
+            .flatten();
+        let p2 = (!p2_masked)
+            .then(|| read_sa_value(&frame.image, &self.p2_sa_digit_probes, &self.p2_sa_scan))
+            .flatten();
+        let p1_confidence =
+            p1.map(|v| sa_confidence(&frame.image, &self.p1_sa_digit_probes, &self.p1_sa_scan, v));
+        let p2_confidence =
+            p2.map(|v| sa_confidence(&frame.image, &self.p2_sa_digit_probes, &self.p2_sa_scan, v));
 
         debug!(
             frame_number = frame.frame_number,
-            p1, p2, "manemon SA reading"
+            ?p1,
+            ?p2,
+            p1_confidence,
+            p2_confidence,
+            "manemon SA reading"
         );
 
-        SaReading { p1, p2 }
+        SaReading {
+            p1,
+            p2,
+            p1_confidence,
+            p2_confidence,
+        }
     }
 
     fn analyze_od(&self, frame: &Frame) -> OdReading {
-        let p1 = read_od_value(&frame.image, true);
-        let p2 = read_od_value(&frame.image, false);
+        let [p1_masked, p2_masked] = self.masked.od;
+        let p1 = (!p1_masked)
+            .then(|| read_od_value(&frame.image, true))
+            .flatten();
+        let p2 = (!p2_masked)
+            .then(|| read_od_value(&frame.image, false))
+            .flatten();
+        let p1_confidence = p1.map(|v| od_confidence(&frame.image, true, v));
+        let p2_confidence = p2.map(|v| od_confidence(&frame.image, false, v));
 
         debug!(
             frame_number = frame.frame_number,
@@ -145,10 +356,17 @@ impl Hud for ManemonHud {
                 OdValue::Normal(x) => x,
                 OdValue::Burnout(x) => -x,
             }),
+            p1_confidence,
+            p2_confidence,
             "manemon OD reading"
         );
 
-        OdReading { p1, p2 }
+        OdReading {
+            p1,
+            p2,
+            p1_confidence,
+            p2_confidence,
+        }
     }
 
     fn debug_regions(&self) -> Vec<DebugRegion> {
@@ -162,7 +380,7 @@ impl Hud for ManemonHud {
             w: scan.x_end.abs_diff(scan.x_start) + 1,
             h: DEBUG_LINE_H,
         };
-        vec![
+        let mut regions = vec![
             DebugRegion {
                 rect: scanline_to_rect(&self.p1_scan),
                 color: Rgb([0, 255, 0]),
@@ -187,7 +405,52 @@ impl Hud for ManemonHud {
                 rect: scanline_to_rect(&self.p2_od_scan),
                 color: Rgb([0, 255, 128]),
             },
-        ]
+        ];
+        regions.extend(self.masks.iter().map(|&rect| DebugRegion {
+            rect,
+            color: Rgb([128, 128, 128]),
+        }));
+        regions
+    }
+
+    fn probe_regions(&self) -> Vec<DebugRegion> {
+        let probes = self
+            .p1_sa_digit_probes
+            .iter()
+            .chain(&self.p2_sa_digit_probes)
+            .map(|&probe| DebugRegion {
+                rect: PixelRect::around([probe], 1),
+                color: Rgb([255, 0, 0]),
+            });
+        let segments = [self.p1_od_scan, self.p2_od_scan]
+            .into_iter()
+            .flat_map(|scan| split_scanline_for_segments(&scan))
+            .map(|seg| DebugRegion {
+                rect: PixelRect::around(
+                    [
+                        (seg.x_start, seg.y - OD_SEG_CEIL_OFFSET_Y),
+                        (seg.x_end, seg.y + OD_SEG_FLOOR_OFFSET_Y),
+                    ],
+                    0,
+                ),
+                color: Rgb([0, 255, 255]),
+            });
+        probes.chain(segments).collect()
+    }
+
+    fn hud_rows(&self) -> Vec<Range<u32>> {
+        let overrides = self
+            .override_rows
+            .iter()
+            .map(|&y| y.saturating_sub(OVERRIDE_ROW_MARGIN)..y + OVERRIDE_ROW_MARGIN + 1);
+        let rows = merge_row_bands(HUD_ROWS.iter().cloned().chain(overrides).collect());
+        rows.iter()
+            .map(|r| {
+                let start = r.start * self.frame_height / REF_HEIGHT;
+                let end = (r.end * self.frame_height).div_ceil(REF_HEIGHT);
+                start..end.min(self.frame_height)
+            })
+            .collect()
     }
 }
 
@@ -199,6 +462,7 @@ mod tests {
     use tracing_test::traced_test;
 
     use super::*;
+    use crate::analysis::SaValue;
 
     fn load_fixture(name: &str) -> RgbImage {
         let path = Path::new(env!("CARGO_MANIFEST_DIR"))
@@ -262,4 +526,59 @@ mod tests {
         let hud = ManemonHud::new(frame.image.width(), frame.image.height());
         assert!(!hud.detect_hud(&frame));
     }
+
+    #[test]
+    fn detect_hud_with_one_side_covered() {
+        let mut image = RgbImage::from_pixel(1920, 1080, Rgb([20, 20, 24]));
+        for i in 0..P2_SA_FRAME.width() {
+            image.put_pixel(P2_SA_FRAME.x_at(i), P2_SA_FRAME.y, Rgb([30, 90, 240]));
+        }
+        let frame = Frame {
+            frame_number: 0,
+            timestamp_seconds: 0.0,
+            image,
+        };
+        let hud = ManemonHud::new(1920, 1080);
+        assert_eq!(hud.detect_sides(&frame), [false, true]);
+        assert!(hud.detect_hud(&frame));
+    }
+
+    #[test]
+    fn masked_pixels_are_never_read() {
+        let mut image = RgbImage::from_pixel(1920, 1080, Rgb([90, 110, 140]));
+        let gauges = ReferenceGauges {
+            hp: 0.5,
+            sa: SaValue::Stocks(1.0),
+            od: OdValue::Normal(4.0),
+        };
+        paint_reference_hud(&mut image, &[gauges; 2]);
+        let frame = Frame {
+            frame_number: 0,
+            timestamp_seconds: 0.0,
+            image,
+        };
+        let hud_with = |masks: Vec<PixelRect>| {
+            let profile = HudProfile {
+                masks,
+                ..Default::default()
+            };
+            ManemonHud::with_profile(1920, 1080, &profile)
+        };
+        let rect = |x, y, w, h| PixelRect { x, y, w, h };
+
+        // A scoreboard over the depleted end of P1's HP bar leaves the border visible.
+        let hud = hud_with(vec![rect(0, 40, 320, 60)]);
+        let hp = hud.analyze_hp(&frame);
+        assert!((hp.p1.unwrap() - 0.5).abs() < 0.01);
+
+        // Over the border, and over P2's Drive gauge.
+        let hud = hud_with(vec![rect(0, 40, 700, 60), rect(1100, 110, 100, 20)]);
+        let hp = hud.analyze_hp(&frame);
+        assert_eq!(hp.p1, None);
+        assert!((hp.p2.unwrap() - 0.5).abs() < 0.01);
+        let od = hud.analyze_od(&frame);
+        assert!(od.p1.is_some());
+        assert!(od.p2.is_none());
+        assert!(hud.analyze_sa(&frame).p2.is_some());
+    }
 }
@@ -3,7 +3,9 @@ use std::sync::OnceLock;
 use image::{Rgb, RgbImage};
 use tracing::debug;
 
-use crate::analysis::common::{find_bar_boundary, rgb_to_hsv, BarSegment, Hsv, Scanline};
+use crate::analysis::common::{
+    classified_fraction, find_bar_boundary, rgb_to_hsv, BarSegment, Hsv, Scanline,
+};
 use crate::analysis::OdValue;
 
 use super::REF_WIDTH;
@@ -36,8 +38,8 @@ fn get_p2_od_segments() -> &'static Vec<Scanline> {
 
 /// Pixel width of each OD segment at 1920x1080 (including white border).
 const OD_SEG_WIDTH: u32 = 52;
-const OD_SEG_CEIL_OFFSET_Y: u32 = 8;
-const OD_SEG_FLOOR_OFFSET_Y: u32 = 7;
+pub(super) const OD_SEG_CEIL_OFFSET_Y: u32 = 8;
+pub(super) const OD_SEG_FLOOR_OFFSET_Y: u32 = 7;
 
 /// Pixel width of each gap between OD segments at 1920x1080.
 const OD_GAP_WIDTH: u32 = 3;
@@ -55,14 +57,14 @@ enum OdSegmentState {
     Unknown,
 }
 
-//// OD segment Full
+// OD segment Full
 
 fn is_od_segment_full_border(rgb: &Rgb<u8>) -> bool {
     let hsv = rgb_to_hsv(*rgb);
     hsv.s < 0.25 && hsv.v > 0.90
 }
 
-fn is_od_segment_full_background(rgb: &Rgb<u8>) -> bool {
+pub(super) fn is_od_segment_full_background(rgb: &Rgb<u8>) -> bool {
     let hsv = rgb_to_hsv(*rgb);
 
     // Check the center pixel is light-green or not.
@@ -79,7 +81,7 @@ fn is_od_segment_full_background(rgb: &Rgb<u8>) -> bool {
     false
 }
 
-//// OD segment Partial
+// OD segment Partial
 
 fn is_partial_fill_border_orange(hsv: Hsv) -> bool {
     hsv.h >= 30.0 && hsv.h <= 60.0 && hsv.s >= 0.20 && hsv.v >= 0.80
@@ -144,8 +146,8 @@ pub(super) fn read_od_value(image: &RgbImage, player_one: bool) -> Option<OdValu
     };
 
     let mut last_state = OdSegmentState::Full;
-    for i in 0..6 {
-        let state: OdSegmentState = classify_od_segment(image, &seg_scanlines[i]);
+    for (i, seg_scan) in seg_scanlines.iter().enumerate() {
+        let state: OdSegmentState = classify_od_segment(image, seg_scan);
         match state {
             OdSegmentState::Full => {
                 debug!(segment = i, "OD segment classified as FULL");
@@ -174,6 +176,24 @@ pub(super) fn read_od_value(image: &RgbImage, player_one: bool) -> Option<OdValu
     None
 }
 
+/// Confidence of the OD reading `value`: the fraction of recognized burnout gauge
+/// pixels in burnout, otherwise the fraction of segments with a recognized state.
+pub(super) fn od_confidence(image: &RgbImage, player_one: bool, value: OdValue) -> f64 {
+    let (od_scanline, seg_scanlines) = if player_one {
+        (&P1_OD_GAUGE, get_p1_od_segments())
+    } else {
+        (&P2_OD_GAUGE, get_p2_od_segments())
+    };
+    if let OdValue::Burnout(_) = value {
+        return classified_fraction(image, od_scanline, classify_burnout_pixel);
+    }
+    let known = seg_scanlines
+        .iter()
+        .filter(|seg_scan| classify_od_segment(image, seg_scan) != OdSegmentState::Unknown)
+        .count();
+    known as f64 / seg_scanlines.len() as f64
+}
+
 // Fast check for OD segments.
 fn is_segment_full_fast(image: &RgbImage, seg_scan: &Scanline) -> bool {
     let ceil_y = seg_scan.y - OD_SEG_CEIL_OFFSET_Y;
@@ -209,7 +229,7 @@ fn is_segment_full_fast(image: &RgbImage, seg_scan: &Scanline) -> bool {
         return false;
     }
 
-    let center_rgb = image.get_pixel(center_x as u32, seg_scan.y);
+    let center_rgb = image.get_pixel(center_x, seg_scan.y);
     // Check the center pixel is light-green or not.
     // for od-value > 3.
     if !is_od_segment_full_background(center_rgb) {
@@ -283,7 +303,7 @@ fn is_segment_empty_fast(image: &RgbImage, seg_scan: &Scanline) -> bool {
     blue_count >= 4
 }
 
-fn split_scanline_for_segments(od_scanline: &Scanline) -> Vec<Scanline> {
+pub(super) fn split_scanline_for_segments(od_scanline: &Scanline) -> Vec<Scanline> {
     let mut v = Vec::with_capacity(6);
 
     let dx = od_scanline.dx();
@@ -305,7 +325,7 @@ fn split_scanline_for_segments(od_scanline: &Scanline) -> Vec<Scanline> {
         v.push(seg_scan);
     }
 
-    return v;
+    v
 }
 
 fn read_maybe_partial_segment(image: &RgbImage, seg_scan: &Scanline) -> Option<f64> {
@@ -358,12 +378,10 @@ fn classify_od_segment(image: &RgbImage, seg_scan: &Scanline) -> OdSegmentState
 
 /// Measure burnout recovery progress (0.0 = just entered, 1.0 = fully recovered).
 /// The gauge transitions from dark gray (unrecovered) to bright white (recovered).
+/// All dark gray reads as 0.0 (the first pixel is already background); None when the
+/// gauge is occluded.
 fn read_burnout_recovery(image: &RgbImage, od_scan: &Scanline) -> Option<OdValue> {
-    if let Some(fill) = find_bar_boundary(image, od_scan, classify_burnout_pixel) {
-        return Some(OdValue::Burnout(fill));
-    }
-    // All dark gray → just entered burnout, recovery = 0.0
-    Some(OdValue::Burnout(0.0))
+    find_bar_boundary(image, od_scan, classify_burnout_pixel).map(OdValue::Burnout)
 }
 
 /// Detect burnout by sampling multiple points along the scanline.
@@ -393,8 +411,7 @@ fn is_burnout_unrecovered(hsv: Hsv) -> bool {
     hsv.s < 0.15 && hsv.v < 0.50
 }
 
-fn classify_burnout_pixel(rgb: Rgb<u8>) -> BarSegment {
-    let hsv = rgb_to_hsv(rgb);
+fn classify_burnout_pixel(hsv: Hsv) -> BarSegment {
     if is_burnout_recovered(hsv) {
         BarSegment::Foreground
     } else if is_burnout_unrecovered(hsv) {
@@ -454,7 +471,7 @@ mod tests {
         ];
         for i in 0..6 {
             let state = classify_od_segment(&image, &p1_seg_scanlines[i]);
-            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i as usize]);
+            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i]);
         }
 
         let p2_seg_scanlines = get_p2_od_segments();
@@ -468,7 +485,7 @@ mod tests {
         ];
         for i in 0..6 {
             let state = classify_od_segment(&image, &p2_seg_scanlines[i]);
-            assert_od_segment(&format!("P2 Segment {i}"), state, expected[i as usize]);
+            assert_od_segment(&format!("P2 Segment {i}"), state, expected[i]);
         }
     }
 
@@ -487,7 +504,7 @@ mod tests {
         ];
         for i in 0..6 {
             let state = classify_od_segment(&image, &p1_seg_scanlines[i]);
-            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i as usize]);
+            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i]);
         }
     }
 
@@ -508,7 +525,7 @@ mod tests {
         ];
         for i in 0..6 {
             let state = classify_od_segment(&image, &p1_seg_scanlines[i]);
-            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i as usize]);
+            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i]);
         }
     }
 
@@ -527,7 +544,7 @@ mod tests {
         for i in 0..6 {
             println!("P1 Segment {i}: ");
             let state = classify_od_segment(&image, &p1_seg_scanlines[i]);
-            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i as usize]);
+            assert_od_segment(&format!("P1 Segment {i}"), state, expected[i]);
         }
 
         let p2_seg_scanlines = get_p2_od_segments();
@@ -542,7 +559,7 @@ mod tests {
         for i in 0..6 {
             println!("P2 Segment {i}: ");
             let state = classify_od_segment(&image, &p2_seg_scanlines[i]);
-            assert_od_segment(&format!("P2 Segment {i}"), state, expected[i as usize]);
+            assert_od_segment(&format!("P2 Segment {i}"), state, expected[i]);
         }
     }
 
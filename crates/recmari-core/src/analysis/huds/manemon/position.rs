@@ -34,7 +34,10 @@ const LINE_WIDTH_MAX: u32 = 20;
 /// Returns the x-coordinate of the center line, or None if not visible.
 pub fn detect_center_line(image: &RgbImage) -> Option<u32> {
     let (w, h) = (image.width(), image.height());
-    assert!(w == 1920 && h == 1080, "currently only supports 1920x1080");
+    if (w, h) != (1920, 1080) {
+        debug!(w, h, "center line detection only supports 1920x1080");
+        return None;
+    }
 
     let profile = build_brightness_profile(image);
 
@@ -108,7 +111,7 @@ pub fn detect_center_line(image: &RgbImage) -> Option<u32> {
             continue;
         }
         let (width, center) = measure_dip(&profile, x as usize);
-        if width >= LINE_WIDTH_MIN && width <= LINE_WIDTH_MAX {
+        if (LINE_WIDTH_MIN..=LINE_WIDTH_MAX).contains(&width) {
             info!(x = center, width, contrast, "center line detected");
             return Some(center);
         }
@@ -123,7 +126,12 @@ pub fn detect_center_line(image: &RgbImage) -> Option<u32> {
 fn build_brightness_profile(image: &RgbImage) -> Vec<f32> {
     let w = image.width() as usize;
     let mut profile = vec![f32::NAN; w];
-    for x in X_MIN as usize..X_MAX as usize {
+    for (x, value) in profile
+        .iter_mut()
+        .enumerate()
+        .take(X_MAX as usize)
+        .skip(X_MIN as usize)
+    {
         let mut sum = 0.0f32;
         let mut count = 0u32;
         for y in (WALL_Y_MIN..WALL_Y_MAX).step_by(WALL_Y_STEP) {
@@ -135,7 +143,7 @@ fn build_brightness_profile(image: &RgbImage) -> Vec<f32> {
             count += 1;
         }
         if count >= MIN_CONTRIB_ROWS {
-            profile[x] = sum / count as f32;
+            *value = sum / count as f32;
         }
     }
     profile
@@ -191,6 +199,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "video")]
     fn video_detection_rate() {
         use crate::analysis::huds::manemon::ManemonHud;
         use crate::analysis::Hud;
@@ -209,10 +218,7 @@ mod tests {
         let mut detected = 0u32;
         let mut not_detected = 0u32;
 
-        loop {
-            let Some(frame) = decoder.next_frame().unwrap() else {
-                break;
-            };
+        while let Some(frame) = decoder.next_frame().unwrap() {
             if frame.frame_number % 60 != 0 {
                 continue;
             }
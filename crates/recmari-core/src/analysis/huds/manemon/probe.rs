@@ -0,0 +1,201 @@
+//! Measure gauge positions on reference screenshots, to re-derive the layout after a
+//! HUD change instead of hand-measuring coordinates.
+
+use std::ops::Range;
+
+use image::{Rgb, RgbImage};
+use tracing::debug;
+
+use crate::analysis::common::{rgb_to_hsv, Scanline};
+use crate::config::ColorOverrides;
+
+use super::hp::{is_hp_border_white, HpColors, P1_HEALTH, P2_HEALTH};
+use super::od::{is_od_segment_full_background, OD_SEG_CEIL_OFFSET_Y, P1_OD_GAUGE, P2_OD_GAUGE};
+use super::{REF_HEIGHT, REF_WIDTH};
+
+/// Pixels searched around the built-in gauge position, horizontally and vertically.
+const PROBE_MARGIN: u32 = 48;
+const OD_SEGMENTS: usize = 6;
+
+/// Measured OD gauge layout of one player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OdGaugeProbe {
+    /// Scanline over all segments, from the screen center outward.
+    pub gauge: Scanline,
+    /// Width of one segment including its white border.
+    pub segment_width: u32,
+    /// Gap between neighbouring segments.
+    pub gap_width: u32,
+}
+
+/// Measure the HP bar scanlines of P1 and P2 on a 1920x1080 frame where both players
+/// have full health: the row in the middle of the bar and the bar's horizontal extent,
+/// scanning from the screen center outward. None for a side without a bar near the
+/// built-in position.
+pub fn probe_hp_bars(image: &RgbImage, colors: &ColorOverrides) -> [Option<Scanline>; 2] {
+    assert_reference_size(image);
+    let colors = HpColors::with_overrides(colors);
+    let is_bar = |rgb: Rgb<u8>| {
+        let hsv = rgb_to_hsv(rgb);
+        colors.is_fill(hsv) || is_hp_border_white(hsv)
+    };
+    [(P1_HEALTH, true), (P2_HEALTH, false)].map(|(builtin, player_one)| {
+        let xs = search_columns(&builtin, player_one);
+        // The longest run per row; the bar's rows share the full width.
+        let widest: Vec<(u32, Range<u32>)> = search_rows(&builtin)
+            .filter_map(|y| {
+                let run = runs(image, y, xs.clone(), is_bar)
+                    .into_iter()
+                    .max_by_key(|r| r.len())?;
+                Some((y, run))
+            })
+            .collect();
+        let width = widest.iter().map(|(_, run)| run.len()).max()?;
+        let rows: Vec<&(u32, Range<u32>)> = widest
+            .iter()
+            .filter(|(_, run)| run.len() == width)
+            .collect();
+        let (y, run) = rows[rows.len() / 2].clone();
+        debug!(player_one, y, ?run, rows = rows.len(), "HP bar probed");
+        Some(oriented(run, y, player_one))
+    })
+}
+
+/// Measure the OD gauges of P1 and P2 on a 1920x1080 frame where both players have a
+/// full Drive gauge. None for a side where no row shows six filled segments.
+pub fn probe_od_gauges(image: &RgbImage) -> [Option<OdGaugeProbe>; 2] {
+    assert_reference_size(image);
+    let is_fill = |rgb: Rgb<u8>| is_od_segment_full_background(&rgb);
+    [(P1_OD_GAUGE, true), (P2_OD_GAUGE, false)].map(|(builtin, player_one)| {
+        let xs = search_columns(&builtin, player_one);
+        let rows: Vec<(u32, Vec<Range<u32>>)> = search_rows(&builtin)
+            .map(|y| (y, runs(image, y, xs.clone(), is_fill)))
+            .filter(|(_, segments)| segments.len() == OD_SEGMENTS)
+            .collect();
+        // The segment interiors start one row below the top border, which the readers
+        // expect OD_SEG_CEIL_OFFSET_Y rows above the scanline.
+        let (top, interiors) = rows.first()?;
+        let y = top - 1 + OD_SEG_CEIL_OFFSET_Y;
+        // Each interior is framed by a one pixel white border.
+        let segments: Vec<Range<u32>> = interiors.iter().map(|r| r.start - 1..r.end + 1).collect();
+        let segment_width = segments[0].len() as u32;
+        let gap_width = segments[1].start - segments[0].end;
+        let gauge = segments[0].start..segments[OD_SEGMENTS - 1].end;
+        debug!(player_one, y, ?segments, "OD gauge probed");
+        Some(OdGaugeProbe {
+            gauge: oriented(gauge, y, player_one),
+            segment_width,
+            gap_width,
+        })
+    })
+}
+
+fn assert_reference_size(image: &RgbImage) {
+    assert_eq!(
+        image.dimensions(),
+        (REF_WIDTH, REF_HEIGHT),
+        "gauges are probed at 1920x1080 only"
+    );
+}
+
+/// Columns of the player's half of the screen around the built-in scanline.
+fn search_columns(builtin: &Scanline, player_one: bool) -> Range<u32> {
+    let left = builtin
+        .x_start
+        .min(builtin.x_end)
+        .saturating_sub(PROBE_MARGIN);
+    let right = builtin.x_start.max(builtin.x_end) + PROBE_MARGIN;
+    let half = REF_WIDTH / 2;
+    if player_one {
+        left..right.min(half)
+    } else {
+        left.max(half)..right.min(REF_WIDTH)
+    }
+}
+
+fn search_rows(builtin: &Scanline) -> Range<u32> {
+    builtin.y.saturating_sub(PROBE_MARGIN)..(builtin.y + PROBE_MARGIN).min(REF_HEIGHT)
+}
+
+/// Runs of consecutive matching pixels of row `y` within `xs`.
+fn runs(
+    image: &RgbImage,
+    y: u32,
+    xs: Range<u32>,
+    matches: impl Fn(Rgb<u8>) -> bool,
+) -> Vec<Range<u32>> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for x in xs.clone() {
+        match (matches(*image.get_pixel(x, y)), start) {
+            (true, None) => start = Some(x),
+            (false, Some(s)) => {
+                runs.push(s..x);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push(s..xs.end);
+    }
+    runs
+}
+
+/// Scanline over the pixels `xs` that runs from the screen center outward: right to
+/// left for P1, left to right for P2.
+fn oriented(xs: Range<u32>, y: u32, player_one: bool) -> Scanline {
+    if player_one {
+        Scanline {
+            x_start: xs.end,
+            x_end: xs.start,
+            y,
+        }
+    } else {
+        Scanline {
+            x_start: xs.start,
+            x_end: xs.end,
+            y,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{OdValue, SaValue};
+
+    use super::super::reference::{paint_reference_hud, ReferenceGauges};
+    use super::*;
+
+    fn reference(hp: f64, od: OdValue) -> RgbImage {
+        let mut image = RgbImage::from_pixel(REF_WIDTH, REF_HEIGHT, Rgb([90, 110, 140]));
+        let gauges = ReferenceGauges {
+            hp,
+            sa: SaValue::Stocks(0.0),
+            od,
+        };
+        paint_reference_hud(&mut image, &[gauges; 2]);
+        image
+    }
+
+    #[test]
+    fn finds_the_built_in_layout() {
+        let image = reference(1.0, OdValue::Normal(6.0));
+        assert_eq!(
+            probe_hp_bars(&image, &ColorOverrides::default()),
+            [Some(P1_HEALTH), Some(P2_HEALTH)]
+        );
+        let [p1, p2] = probe_od_gauges(&image);
+        assert_eq!(p1.map(|p| p.gauge), Some(P1_OD_GAUGE));
+        assert_eq!(p2.map(|p| p.gauge), Some(P2_OD_GAUGE));
+        assert_eq!(p1.map(|p| (p.segment_width, p.gap_width)), Some((52, 3)));
+    }
+
+    #[test]
+    fn incomplete_gauges_are_not_probed() {
+        let image = reference(0.5, OdValue::Normal(4.0));
+        assert_eq!(probe_od_gauges(&image), [None, None]);
+        let [p1, _] = probe_hp_bars(&image, &ColorOverrides::default());
+        assert!(p1.unwrap().width() < P1_HEALTH.width());
+    }
+}
@@ -0,0 +1,190 @@
+use image::{Rgb, RgbImage};
+
+use crate::analysis::common::Scanline;
+use crate::analysis::{OdValue, SaValue};
+
+// Layout of the drawn HUD at 1920x1080 ([P1, P2]), written out here instead of taken
+// from the reader's constants: a change to the reader's layout then fails the
+// self-test rather than moving the references along with it. These are synthetic
+// frames; they show that the reader agrees with this layout, not that the layout
+// matches real captures (`verify-fixtures` checks that).
+const HP_BARS: [Scanline; 2] = [scan(886, 187, 80), scan(1034, 1733, 80)];
+const SA_FRAMES: [Scanline; 2] = [scan(208, 220, 1027), scan(1700, 1712, 1027)];
+const SA_GAUGES: [Scanline; 2] = [scan(188, 413, 1002), scan(1732, 1507, 1002)];
+const OD_GAUGES: [Scanline; 2] = [scan(888, 561, 122), scan(1032, 1359, 122)];
+/// Offset of the P2 SA digit from the P1 one.
+const P2_SA_DIGIT_DX: u32 = 1640;
+/// Pixels of the P1 SA digit area that are lit for 0, 1, 2 and 3 (all of them for CA).
+const SA_DIGIT_PIXELS: [(u32, u32); 4] = [(129, 989), (138, 985), (144, 961), (133, 995)];
+/// Rows of the OD gauge above and below its scanline.
+const OD_ROWS_ABOVE: u32 = 8;
+const OD_ROWS_BELOW: u32 = 7;
+const OD_SEGMENT_WIDTH: u32 = 52;
+const OD_SEGMENT_GAP: u32 = 3;
+
+const fn scan(x_start: u32, x_end: u32, y: u32) -> Scanline {
+    Scanline { x_start, x_end, y }
+}
+
+/// Colors of the drawn HUD, each inside the range its classifier accepts.
+const GAUGE_EMPTY: Rgb<u8> = Rgb([0, 75, 210]);
+const SA_FRAME_BLUE: Rgb<u8> = Rgb([30, 90, 240]);
+const HP_FILL: Rgb<u8> = Rgb([255, 230, 40]);
+const HP_LOW_FILL: Rgb<u8> = Rgb([255, 170, 10]);
+const HP_LOW_BORDER: Rgb<u8> = Rgb([255, 210, 60]);
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+const CA_GOLD: Rgb<u8> = Rgb([240, 160, 30]);
+const SA_P1_PINK: Rgb<u8> = Rgb([255, 60, 180]);
+const SA_P2_CYAN: Rgb<u8> = Rgb([60, 220, 255]);
+const OD_FULL_GREEN: Rgb<u8> = Rgb([80, 240, 20]);
+const BURNOUT_GRAY: Rgb<u8> = Rgb([60, 60, 60]);
+
+/// HP below this is drawn in the low-health orange.
+const LOW_HP: f64 = 0.25;
+/// Width of the white (or orange) edge drawn at the end of the HP fill.
+const HP_BORDER_WIDTH: u32 = 2;
+
+/// Gauge values of one player in a reference frame.
+#[derive(Debug, Clone, Copy)]
+pub struct ReferenceGauges {
+    pub hp: f64,
+    pub sa: SaValue,
+    /// Normal values must be whole segments; partial segments are not drawn.
+    pub od: OdValue,
+}
+
+/// Draw the battle HUD showing `players` over `image` (1920x1080): the SA gauge
+/// frames, HP bars, SA digits and bars and OD gauges, in the colors the classifiers
+/// expect. Values are rounded to whole pixels.
+pub fn paint_reference_hud(image: &mut RgbImage, players: &[ReferenceGauges; 2]) {
+    assert_eq!(
+        image.dimensions(),
+        (super::REF_WIDTH, super::REF_HEIGHT),
+        "reference HUD is drawn at 1920x1080 only"
+    );
+    for scan in SA_FRAMES {
+        paint_run(image, &scan, 0..scan.width(), 0, SA_FRAME_BLUE);
+    }
+    let sides = [
+        (HP_BARS[0], SA_GAUGES[0], 0, OD_GAUGES[0], SA_P1_PINK),
+        (
+            HP_BARS[1],
+            SA_GAUGES[1],
+            P2_SA_DIGIT_DX,
+            OD_GAUGES[1],
+            SA_P2_CYAN,
+        ),
+    ];
+    for (gauges, (hp_scan, sa_scan, digit_dx, od_scan, sa_color)) in players.iter().zip(sides) {
+        paint_hp(image, &hp_scan, gauges.hp);
+        paint_sa(image, &sa_scan, digit_dx, sa_color, gauges.sa);
+        paint_od(image, &od_scan, gauges.od);
+    }
+}
+
+/// Color pixels `range` along `scan`, `half_height` rows above and below it too.
+fn paint_run(
+    image: &mut RgbImage,
+    scan: &Scanline,
+    range: std::ops::Range<u32>,
+    half_height: u32,
+    color: Rgb<u8>,
+) {
+    for i in range {
+        for y in scan.y - half_height..=scan.y + half_height {
+            image.put_pixel(scan.x_at(i), y, color);
+        }
+    }
+}
+
+fn paint_hp(image: &mut RgbImage, scan: &Scanline, hp: f64) {
+    assert!((0.0..=1.0).contains(&hp), "invalid HP {hp}");
+    let width = scan.width();
+    let filled = (hp * width as f64).round() as u32;
+    let border = filled.min(HP_BORDER_WIDTH);
+    let (fill, edge) = if hp < LOW_HP {
+        (HP_LOW_FILL, HP_LOW_BORDER)
+    } else {
+        (HP_FILL, WHITE)
+    };
+    // The reader also looks at the pixel just past the bar.
+    paint_run(image, scan, 0..width + 1, 2, GAUGE_EMPTY);
+    paint_run(image, scan, 0..filled - border, 2, fill);
+    paint_run(image, scan, filled - border..filled, 2, edge);
+}
+
+fn paint_sa(image: &mut RgbImage, scan: &Scanline, digit_dx: u32, color: Rgb<u8>, sa: SaValue) {
+    let (pixels, digit_color, bar) = match sa {
+        SaValue::CaReady => (&SA_DIGIT_PIXELS[..], CA_GOLD, 1.0),
+        SaValue::Stocks(stocks) => {
+            assert!((0.0..=3.0).contains(&stocks), "invalid SA {stocks}");
+            let digit = stocks.floor() as usize;
+            let bar = if digit == 3 { 1.0 } else { stocks.fract() };
+            (&SA_DIGIT_PIXELS[digit..digit + 1], SA_FRAME_BLUE, bar)
+        }
+    };
+    for &(px, py) in pixels {
+        for y in py - 1..=py + 1 {
+            for x in px - 1..=px + 1 {
+                image.put_pixel(x + digit_dx, y, digit_color);
+            }
+        }
+    }
+    let width = scan.width();
+    let filled = (bar * width as f64).round() as u32;
+    paint_run(image, scan, 0..width, 1, GAUGE_EMPTY);
+    paint_run(image, scan, 0..filled, 1, color);
+}
+
+fn paint_od(image: &mut RgbImage, scan: &Scanline, od: OdValue) {
+    let gauge_rows = |image: &mut RgbImage, scan: &Scanline, color| {
+        for i in 0..scan.width() {
+            for y in scan.y - OD_ROWS_ABOVE..=scan.y + OD_ROWS_BELOW {
+                image.put_pixel(scan.x_at(i), y, color);
+            }
+        }
+    };
+    match od {
+        OdValue::Burnout(recovered) => {
+            assert!(
+                (0.0..=1.0).contains(&recovered),
+                "invalid burnout {recovered}"
+            );
+            gauge_rows(image, scan, BURNOUT_GRAY);
+            let filled = (recovered * scan.width() as f64).round() as u32;
+            paint_run(image, scan, 0..filled, 1, WHITE);
+        }
+        OdValue::Normal(stocks) => {
+            assert!(
+                stocks.fract() == 0.0 && (0.0..=6.0).contains(&stocks),
+                "reference OD must be whole segments, got {stocks}"
+            );
+            gauge_rows(image, scan, GAUGE_EMPTY);
+            for seg in od_segments(scan).iter().take(stocks as usize) {
+                gauge_rows(image, seg, WHITE);
+                let inner = seg.width() - 1;
+                for y in seg.y - OD_ROWS_ABOVE + 1..seg.y + OD_ROWS_BELOW {
+                    for i in 1..inner {
+                        image.put_pixel(seg.x_at(i), y, OD_FULL_GREEN);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The six segments of an OD gauge, from its start.
+fn od_segments(gauge: &Scanline) -> Vec<Scanline> {
+    (0..6)
+        .map(|i| {
+            let offset = i * (OD_SEGMENT_WIDTH + OD_SEGMENT_GAP);
+            if gauge.dx() == 1 {
+                let start = gauge.x_start + offset;
+                scan(start, start + OD_SEGMENT_WIDTH, gauge.y)
+            } else {
+                let start = gauge.x_start - offset;
+                scan(start, start - OD_SEGMENT_WIDTH, gauge.y)
+            }
+        })
+        .collect()
+}
@@ -1,7 +1,10 @@
 use image::{Rgb, RgbImage};
 use tracing::{debug, warn};
 
-use crate::analysis::common::{find_bar_boundary, rgb_to_hsv, BarSegment, Hsv, Scanline};
+use crate::analysis::common::{
+    classified_fraction, find_bar_boundary, rgb_to_hsv, BarSegment, Hsv, Scanline,
+};
+use crate::analysis::SaValue;
 use crate::rect::PixelRect;
 
 use super::REF_WIDTH;
@@ -55,20 +58,38 @@ pub(super) const SA_DIGIT_PROBES: [Probe; 4] = [
     Probe { x: 133, y: 995 }, // foreground for: 3
 ];
 
-/// Combine digit recognition with bar fill to produce a 0.0–3.0 SA value.
+/// Probes on the golden CA text needed to read the digit area as CA.
+const CA_MIN_PROBES: usize = 2;
+
+/// What the SA stock digit area shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SaDigit {
+    Stock(u8),
+    /// The golden "CA" text.
+    Ca,
+}
+
+/// Combine digit recognition with bar fill to produce an SA value.
 pub(super) fn read_sa_value(
     image: &RgbImage,
     probes: &[(u32, u32); 4],
     sa_scan: &Scanline,
-) -> Option<f64> {
-    let Some(stock) = classify_sa_digit(image, probes) else {
-        warn!("SA digit classification failed");
-        return None;
+) -> Option<SaValue> {
+    let stock = match classify_sa_digit(image, probes) {
+        Some(SaDigit::Ca) => return Some(SaValue::CaReady),
+        Some(SaDigit::Stock(stock)) => stock,
+        None => {
+            warn!("SA digit classification failed");
+            return None;
+        }
     };
-    assert!(stock <= 3, "SA stock count must be 0–3, got {stock}");
+    if stock > 3 {
+        warn!(stock, "SA stock count out of range");
+        return None;
+    }
 
     if stock >= 3 {
-        return Some(3.0);
+        return Some(SaValue::Stocks(3.0));
     }
 
     debug!("SA bar scan");
@@ -77,20 +98,20 @@ pub(super) fn read_sa_value(
         return None;
     };
 
-    assert!(bar_fill <= 1.0, "bar_fill must be 0.0–1.0, got {bar_fill}");
-    Some(stock as f64 + bar_fill)
+    if !(0.0..=1.0).contains(&bar_fill) {
+        warn!(stock, bar_fill, "SA bar fill out of range");
+        return None;
+    }
+    Some(SaValue::Stocks(stock as f64 + bar_fill))
 }
 
 /// Recognize the SA stock digit (0–3) or CA text from probe positions.
 /// Returns None if the digit is unreadable.
-fn classify_sa_digit(image: &RgbImage, probes: &[(u32, u32); 4]) -> Option<u8> {
-    let ca_count = probes
-        .iter()
-        .filter(|&&(x, y)| is_ca_text_pixel(*image.get_pixel(x, y)))
-        .count();
-    if ca_count >= 2 {
+fn classify_sa_digit(image: &RgbImage, probes: &[(u32, u32); 4]) -> Option<SaDigit> {
+    let ca_count = ca_probe_count(image, probes);
+    if ca_count >= CA_MIN_PROBES {
         debug!("SA digit classified as CA");
-        return Some(3);
+        return Some(SaDigit::Ca);
     }
 
     let mut digit = None;
@@ -108,7 +129,38 @@ fn classify_sa_digit(image: &RgbImage, probes: &[(u32, u32); 4]) -> Option<u8> {
     };
 
     debug!(digit, "SA digit classified");
-    Some(digit)
+    Some(SaDigit::Stock(digit))
+}
+
+/// Confidence of the SA reading `value`: the share of probes agreeing on CA, or for a
+/// stock digit 1 / (probes showing a digit), times the fraction of recognized gauge
+/// pixels when the bar was read.
+pub(super) fn sa_confidence(
+    image: &RgbImage,
+    probes: &[(u32, u32); 4],
+    sa_scan: &Scanline,
+    value: SaValue,
+) -> f64 {
+    let stocks = match value {
+        SaValue::CaReady => return ca_probe_count(image, probes) as f64 / probes.len() as f64,
+        SaValue::Stocks(stocks) => stocks,
+    };
+    let foreground = probes
+        .iter()
+        .filter(|&&(x, y)| is_sa_digit_foreground(image, x, y))
+        .count();
+    let digit_confidence = 1.0 / foreground.max(1) as f64;
+    if stocks >= 3.0 {
+        return digit_confidence;
+    }
+    digit_confidence * classified_fraction(image, sa_scan, classify_sa_pixel)
+}
+
+fn ca_probe_count(image: &RgbImage, probes: &[(u32, u32); 4]) -> usize {
+    probes
+        .iter()
+        .filter(|&&(x, y)| is_ca_text_pixel(*image.get_pixel(x, y)))
+        .count()
 }
 
 /// Check if a pixel belongs to the golden "CA" text overlay.
@@ -171,8 +223,7 @@ fn is_gauge_sa(hsv: Hsv) -> bool {
     (p1_pink || p2_cyan) && hsv.s >= 0.15 && hsv.v >= 0.80
 }
 
-fn classify_sa_pixel(rgb: Rgb<u8>) -> BarSegment {
-    let hsv = rgb_to_hsv(rgb);
+fn classify_sa_pixel(hsv: Hsv) -> BarSegment {
     if is_gauge_sa(hsv) {
         BarSegment::Foreground
     } else if is_gauge_empty(hsv) {
@@ -261,27 +312,29 @@ mod tests {
         let p1_probes = SA_DIGIT_PROBES.map(|p| (p.x, p.y));
         let p2_probes = SA_DIGIT_PROBES.map(|p| (P2_SA_DIGIT.x + p.x - P1_SA_DIGIT.x, p.y));
 
-        let cases: &[(&str, &[(u32, u32); 4], u8)] = &[
-            ("both_sa0.png", &p1_probes, 0),
-            ("both_sa0.png", &p2_probes, 0),
-            ("both_sa1.png", &p1_probes, 1),
-            ("both_sa1.png", &p2_probes, 1),
-            ("both_sa2.png", &p1_probes, 2),
-            ("both_sa2.png", &p2_probes, 2),
-            ("both_sa3.png", &p1_probes, 3),
-            ("both_sa3.png", &p2_probes, 3),
-            ("p1_ca.png", &p1_probes, 3),
-            ("p1_ca.png", &p2_probes, 0),
-            ("frame_1560.png", &p1_probes, 0),
-            ("frame_1560.png", &p2_probes, 0),
-            ("frame_3600.png", &p1_probes, 1),
-            ("frame_3600.png", &p2_probes, 1),
-            ("frame_4080.png", &p1_probes, 1),
-            ("frame_4080.png", &p2_probes, 2),
-            ("frame_2640.png", &p1_probes, 0),
-            ("frame_2640.png", &p2_probes, 1),
-            ("frame_4920.png", &p1_probes, 2),
-            ("frame_4920.png", &p2_probes, 3),
+        type Probes = [(u32, u32); 4];
+        use SaDigit::{Ca, Stock};
+        let cases: &[(&str, &Probes, SaDigit)] = &[
+            ("both_sa0.png", &p1_probes, Stock(0)),
+            ("both_sa0.png", &p2_probes, Stock(0)),
+            ("both_sa1.png", &p1_probes, Stock(1)),
+            ("both_sa1.png", &p2_probes, Stock(1)),
+            ("both_sa2.png", &p1_probes, Stock(2)),
+            ("both_sa2.png", &p2_probes, Stock(2)),
+            ("both_sa3.png", &p1_probes, Stock(3)),
+            ("both_sa3.png", &p2_probes, Stock(3)),
+            ("p1_ca.png", &p1_probes, Ca),
+            ("p1_ca.png", &p2_probes, Stock(0)),
+            ("frame_1560.png", &p1_probes, Stock(0)),
+            ("frame_1560.png", &p2_probes, Stock(0)),
+            ("frame_3600.png", &p1_probes, Stock(1)),
+            ("frame_3600.png", &p2_probes, Stock(1)),
+            ("frame_4080.png", &p1_probes, Stock(1)),
+            ("frame_4080.png", &p2_probes, Stock(2)),
+            ("frame_2640.png", &p1_probes, Stock(0)),
+            ("frame_2640.png", &p2_probes, Stock(1)),
+            ("frame_4920.png", &p1_probes, Stock(2)),
+            ("frame_4920.png", &p2_probes, Stock(3)),
         ];
 
         for &(file, probes, expected) in cases {
@@ -319,8 +372,8 @@ mod tests {
 
         for &(file, expected_p1, expected_p2) in cases {
             let img = load_fixture(file);
-            let p1 = read_sa_value(&img, &p1_probes, &P1_SA_GAUGE);
-            let p2 = read_sa_value(&img, &p2_probes, &P2_SA_GAUGE);
+            let p1 = read_sa_value(&img, &p1_probes, &P1_SA_GAUGE).map(SaValue::gauge);
+            let p2 = read_sa_value(&img, &p2_probes, &P2_SA_GAUGE).map(SaValue::gauge);
             assert_sa_approx(p1, expected_p1, 0.05, &format!("{file} P1"));
             assert_sa_approx(p2, expected_p2, 0.05, &format!("{file} P2"));
         }
@@ -1 +1,136 @@
 pub mod manemon;
+
+use std::ops::Range;
+
+use tracing::{error, info};
+
+use crate::analysis::{Hud, HudType};
+use crate::config::HudConfig;
+use crate::error::HudError;
+use crate::video::frame::Frame;
+
+use manemon::ManemonHud;
+
+/// Every HUD, tried in this order by detection. More specific HUDs must come first,
+/// since their detection implies the generic ones.
+const HUD_TYPES: [HudType; 1] = [HudType::Manemon];
+
+/// Names accepted by [`create`], in detection order.
+pub fn hud_names() -> impl Iterator<Item = String> {
+    HUD_TYPES.iter().map(HudType::to_string)
+}
+
+/// Construct the HUD called `name` (see [`hud_names`]) for the given frame size,
+/// applying its profile of `config`.
+pub fn create(
+    name: &str,
+    frame_width: u32,
+    frame_height: u32,
+    config: &HudConfig,
+) -> Result<Box<dyn Hud>, HudError> {
+    let hud_type: HudType = name.parse().map_err(|_| {
+        error!(name, "unknown HUD");
+        HudError::UnknownHud {
+            name: name.to_string(),
+            available: hud_names().collect::<Vec<_>>().join(", "),
+        }
+    })?;
+    if !ManemonHud::supports_resolution(frame_width, frame_height) {
+        error!(frame_width, frame_height, "unsupported HUD resolution");
+        return Err(HudError::UnsupportedResolution {
+            width: frame_width,
+            height: frame_height,
+        });
+    }
+    info!(%hud_type, frame_width, frame_height, "HUD created");
+    Ok(build(hud_type, frame_width, frame_height, config))
+}
+
+fn build(hud_type: HudType, w: u32, h: u32, config: &HudConfig) -> Box<dyn Hud> {
+    let profile = config.profile(hud_type);
+    match hud_type {
+        HudType::Manemon => Box::new(ManemonHud::with_profile(w, h, profile)),
+    }
+}
+
+/// Construct every known HUD implementation for the given frame size in detection
+/// order, applying the per-HUD profiles of `config`.
+pub fn all_huds(frame_width: u32, frame_height: u32, config: &HudConfig) -> Vec<Box<dyn Hud>> {
+    let huds: Vec<Box<dyn Hud>> = HUD_TYPES
+        .iter()
+        .map(|&hud_type| build(hud_type, frame_width, frame_height, config))
+        .collect();
+    info!(
+        count = huds.len(),
+        frame_width, frame_height, "HUD registry initialized"
+    );
+    huds
+}
+
+/// Return the index of the first HUD present in the frame, or None if no HUD matches.
+pub fn select_hud(huds: &[Box<dyn Hud>], frame: &Frame) -> Option<usize> {
+    huds.iter().position(|hud| hud.detect_hud(frame))
+}
+
+/// Sort and merge overlapping or touching row ranges.
+pub(crate) fn merge_row_bands(mut rows: Vec<Range<u32>>) -> Vec<Range<u32>> {
+    rows.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<u32>> = Vec::with_capacity(rows.len());
+    for row in rows {
+        match merged.last_mut() {
+            Some(last) if row.start <= last.end => last.end = last.end.max(row.end),
+            _ => merged.push(row),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    /// Paint the SA gauge frame that the battle HUD uses for detection.
+    pub(crate) fn paint_sa_frame(image: &mut RgbImage) {
+        let scan = manemon::SA_FRAME;
+        for i in 0..scan.width() {
+            image.put_pixel(scan.x_at(i), scan.y, Rgb([30, 90, 240]));
+        }
+    }
+
+    #[test]
+    fn merge_row_bands_joins_overlaps() {
+        let merged = merge_row_bands(vec![950..1040, 70..140, 100..200, 200..210]);
+        assert_eq!(merged, vec![70..210, 950..1040]);
+    }
+
+    #[test]
+    fn create_builds_huds_by_name() {
+        let config = HudConfig::default();
+        let names: Vec<String> = hud_names().collect();
+        assert_eq!(names, ["manemon"]);
+        for name in &names {
+            let hud = create(name, 1920, 1080, &config).unwrap();
+            assert_eq!(hud.hud_type().to_string(), *name);
+        }
+        assert!(matches!(
+            create("arcade", 1920, 1080, &config),
+            Err(HudError::UnknownHud { available, .. }) if available == "manemon"
+        ));
+        assert!(matches!(
+            create("manemon", 1280, 720, &config),
+            Err(HudError::UnsupportedResolution { .. })
+        ));
+    }
+
+    #[test]
+    fn select_hud_none_without_hud() {
+        let frame = Frame {
+            image: RgbImage::new(1920, 1080),
+            frame_number: 0,
+            timestamp_seconds: 0.0,
+        };
+        assert!(select_hud(&all_huds(1920, 1080, &HudConfig::default()), &frame).is_none());
+    }
+}
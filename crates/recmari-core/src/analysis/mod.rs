@@ -1,7 +1,11 @@
 pub mod common;
+pub mod detector;
+pub mod events;
 pub mod huds;
 
 use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
 
 use image::Rgb;
 
@@ -22,19 +26,55 @@ impl fmt::Display for HudType {
     }
 }
 
+impl FromStr for HudType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "manemon" => Ok(HudType::Manemon),
+            _ => Err(format!("unknown HUD '{s}' (expected manemon)")),
+        }
+    }
+}
+
 /// HP reading for a single frame. Each player's value is None if unreadable.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct HpReading {
     pub p1: Option<f64>,
     pub p2: Option<f64>,
+    /// How much each player's value is trusted (0.0-1.0); None when unreadable or when
+    /// the HUD does not score its readings.
+    pub p1_confidence: Option<f64>,
+    pub p2_confidence: Option<f64>,
+}
+
+/// SA gauge state for a single player.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaValue {
+    /// Stock count (integer part) + bar fill ratio (fractional part), 0.0 to 3.0.
+    Stocks(f64),
+    /// Full gauge shown as "CA": usable as a Critical Art (HP below 25%).
+    CaReady,
+}
+
+impl SaValue {
+    /// Gauge level 0.0 to 3.0; CA-ready counts as full.
+    pub fn gauge(self) -> f64 {
+        match self {
+            SaValue::Stocks(v) => v,
+            SaValue::CaReady => 3.0,
+        }
+    }
 }
 
 /// SA gauge reading for a single frame. Each player's value is None if unreadable.
-/// The value is stock count (integer part) + bar fill ratio (fractional part), ranging 0.0 to 3.0.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct SaReading {
-    pub p1: Option<f64>,
-    pub p2: Option<f64>,
+    pub p1: Option<SaValue>,
+    pub p2: Option<SaValue>,
+    /// Confidence of each player's value, as in [`HpReading`].
+    pub p1_confidence: Option<f64>,
+    pub p2_confidence: Option<f64>,
 }
 
 /// OD (Drive) gauge state for a single player.
@@ -47,10 +87,13 @@ pub enum OdValue {
 }
 
 /// OD gauge reading for a single frame. Each player's value is None if unreadable.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct OdReading {
     pub p1: Option<OdValue>,
     pub p2: Option<OdValue>,
+    /// Confidence of each player's value, as in [`HpReading`].
+    pub p1_confidence: Option<f64>,
+    pub p2_confidence: Option<f64>,
 }
 
 /// A region to draw on debug frames.
@@ -78,4 +121,14 @@ pub trait Hud {
 
     /// Return the regions to draw on debug frames.
     fn debug_regions(&self) -> Vec<DebugRegion>;
+
+    /// Return individual sample points and gauge segment boundaries, drawn over
+    /// [`Self::debug_regions`] when checking coordinates against a capture.
+    fn probe_regions(&self) -> Vec<DebugRegion> {
+        Vec::new()
+    }
+
+    /// Pixel rows (sorted, disjoint) that contain every HUD element this implementation reads.
+    /// Used to decode only the HUD rows of each frame.
+    fn hud_rows(&self) -> Vec<Range<u32>>;
 }
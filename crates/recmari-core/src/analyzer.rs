@@ -0,0 +1,244 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use tracing::{error, info};
+
+use recmari_proto::proto::{CustomEvent, CustomMetric, FrameData, Match};
+
+use crate::analysis::HudType;
+use crate::anomaly::{AnomalyCause, AnomalyLog};
+use crate::video::frame::Frame;
+
+/// What the pipeline knows about a frame when custom analyzers run.
+pub struct FrameContext<'a> {
+    /// HUD layout of the frame, None when no HUD was detected.
+    pub hud_type: Option<HudType>,
+    /// Readings of the built-in HUD analysis (players absent without a HUD).
+    pub frame_data: &'a FrameData,
+}
+
+/// A result of a [`FrameAnalyzer`] for one frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalyzerOutput {
+    /// A named value of this frame, stored in `FrameData.custom_metrics`.
+    Metric { name: String, value: f64 },
+    /// A named event on this frame, stored in `Round.custom_events` of the round it
+    /// falls in. `player` is 1 or 2, or 0 for screen-wide events.
+    Event { name: String, player: u32 },
+}
+
+/// Custom per-frame analysis run by the pipeline after the built-in HUD analysis, on
+/// every sampled frame (with or without a HUD), e.g. to track a stream overlay.
+pub trait FrameAnalyzer {
+    /// Name recorded with each metric and event.
+    fn name(&self) -> &str;
+
+    fn analyze(&mut self, frame: &Frame, ctx: &FrameContext) -> Vec<AnalyzerOutput>;
+}
+
+type AnalyzerFactory = dyn Fn() -> Box<dyn FrameAnalyzer> + Send + Sync;
+
+/// Custom analyzers of a pipeline run. Each run creates fresh analyzers from the
+/// registered factories, so state does not leak between runs.
+#[derive(Clone, Default)]
+pub struct AnalyzerRegistry {
+    factories: Vec<Arc<AnalyzerFactory>>,
+}
+
+impl AnalyzerRegistry {
+    pub fn register(
+        &mut self,
+        factory: impl Fn() -> Box<dyn FrameAnalyzer> + Send + Sync + 'static,
+    ) {
+        self.factories.push(Arc::new(factory));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.factories.is_empty()
+    }
+
+    pub fn create(&self) -> Vec<Box<dyn FrameAnalyzer>> {
+        self.factories.iter().map(|factory| factory()).collect()
+    }
+}
+
+/// Run `analyzers` on a frame: metrics are added to `fd`, events to `events`. A
+/// panicking analyzer loses its outputs for this frame and is recorded in `anomalies`;
+/// the other analyzers and the run continue.
+pub fn run_analyzers(
+    analyzers: &mut [Box<dyn FrameAnalyzer>],
+    frame: &Frame,
+    hud_type: Option<HudType>,
+    fd: &mut FrameData,
+    events: &mut Vec<CustomEvent>,
+    anomalies: &mut AnomalyLog,
+) {
+    for analyzer in analyzers.iter_mut() {
+        let ctx = FrameContext {
+            hud_type,
+            frame_data: fd,
+        };
+        let analyzed = panic::catch_unwind(AssertUnwindSafe(|| analyzer.analyze(frame, &ctx)));
+        let Ok(outputs) = analyzed else {
+            error!(
+                analyzer = analyzer.name(),
+                frame_number = frame.frame_number,
+                "custom analyzer panicked; its outputs are dropped"
+            );
+            anomalies.record(frame, AnomalyCause::AnalyzerFailed);
+            continue;
+        };
+        for output in outputs {
+            match output {
+                AnalyzerOutput::Metric { name, value } => fd.custom_metrics.push(CustomMetric {
+                    analyzer: analyzer.name().to_string(),
+                    name,
+                    value,
+                }),
+                AnalyzerOutput::Event { name, player } => events.push(CustomEvent {
+                    analyzer: analyzer.name().to_string(),
+                    name,
+                    frame_number: frame.frame_number,
+                    timestamp_seconds: frame.timestamp_seconds,
+                    player,
+                }),
+            }
+        }
+    }
+}
+
+/// Attach each custom event to the round whose time span contains it.
+pub fn attach_custom_events(matches: &mut [Match], events: &[CustomEvent]) {
+    let mut attached = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        let Some(last) = round.frames.last() else {
+            continue;
+        };
+        let (start, end) = (round.start_timestamp_seconds, last.timestamp_seconds);
+        round.custom_events = events
+            .iter()
+            .filter(|e| e.timestamp_seconds >= start && e.timestamp_seconds <= end)
+            .cloned()
+            .collect();
+        attached += round.custom_events.len();
+    }
+    info!(events = events.len(), attached, "custom events attached");
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::Round;
+
+    use super::*;
+
+    /// Reports the mean brightness of the top-left pixel and an event when it is white.
+    struct Corner;
+
+    impl FrameAnalyzer for Corner {
+        fn name(&self) -> &str {
+            "corner"
+        }
+
+        fn analyze(&mut self, frame: &Frame, ctx: &FrameContext) -> Vec<AnalyzerOutput> {
+            let [r, g, b] = frame.image.get_pixel(0, 0).0;
+            let mut outputs = vec![AnalyzerOutput::Metric {
+                name: "brightness".into(),
+                value: (r as f64 + g as f64 + b as f64) / 3.0,
+            }];
+            if r == 255 && ctx.hud_type.is_some() {
+                outputs.push(AnalyzerOutput::Event {
+                    name: "white".into(),
+                    player: 1,
+                });
+            }
+            outputs
+        }
+    }
+
+    #[test]
+    fn registered_analyzers_add_metrics_and_round_events() {
+        let mut registry = AnalyzerRegistry::default();
+        assert!(registry.is_empty());
+        registry.register(|| Box::new(Corner));
+        let mut analyzers = registry.create();
+
+        let mut events = Vec::new();
+        let mut frames = Vec::new();
+        for (n, value) in [(0u32, 30u8), (60, 255)] {
+            let frame = Frame {
+                image: image::RgbImage::from_pixel(2, 2, image::Rgb([value; 3])),
+                frame_number: n,
+                timestamp_seconds: n as f64 / 60.0,
+            };
+            let mut fd = FrameData {
+                frame_number: n,
+                timestamp_seconds: frame.timestamp_seconds,
+                ..Default::default()
+            };
+            run_analyzers(
+                &mut analyzers,
+                &frame,
+                Some(HudType::Manemon),
+                &mut fd,
+                &mut events,
+                &mut AnomalyLog::default(),
+            );
+            frames.push(fd);
+        }
+        assert_eq!(frames[0].custom_metrics[0].analyzer, "corner");
+        assert_eq!(frames[1].custom_metrics[0].value, 255.0);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].frame_number, 60);
+
+        let mut matches = vec![Match {
+            rounds: vec![Round {
+                frames,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        attach_custom_events(&mut matches, &events);
+        assert_eq!(matches[0].rounds[0].custom_events, events);
+    }
+
+    /// Panics on every frame.
+    struct Broken;
+
+    impl FrameAnalyzer for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn analyze(&mut self, _: &Frame, _: &FrameContext) -> Vec<AnalyzerOutput> {
+            panic!("analyzer bug");
+        }
+    }
+
+    #[test]
+    fn panicking_analyzer_is_recorded_and_skipped() {
+        let mut registry = AnalyzerRegistry::default();
+        registry.register(|| Box::new(Broken));
+        registry.register(|| Box::new(Corner));
+        let mut analyzers = registry.create();
+
+        let frame = Frame {
+            image: image::RgbImage::from_pixel(2, 2, image::Rgb([30; 3])),
+            frame_number: 5,
+            timestamp_seconds: 0.1,
+        };
+        let mut fd = FrameData::default();
+        let mut anomalies = AnomalyLog::default();
+        run_analyzers(
+            &mut analyzers,
+            &frame,
+            None,
+            &mut fd,
+            &mut Vec::new(),
+            &mut anomalies,
+        );
+        assert_eq!(fd.custom_metrics.len(), 1);
+        assert_eq!(fd.custom_metrics[0].analyzer, "corner");
+        assert_eq!(anomalies.counts()[&AnomalyCause::AnalyzerFailed], 1);
+        assert_eq!(anomalies.anomalies[0].frame_number, 5);
+    }
+}
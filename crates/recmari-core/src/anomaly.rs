@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fmt::Write as _;
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::video::frame::Frame;
+
+const SVG_WIDTH: f64 = 960.0;
+const SVG_LABEL_WIDTH: f64 = 110.0;
+const SVG_ROW_HEIGHT: f64 = 16.0;
+
+/// Why a sampled frame could not be (fully) analyzed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnomalyCause {
+    /// No registered HUD was detected (menus, loading screens, replays, ...).
+    NoHud,
+    /// At least one HP bar was unreadable; the value was gap-filled.
+    HpUnreadable,
+    /// At least one SA gauge was unreadable; the value was gap-filled.
+    SaUnreadable,
+    /// At least one OD gauge was unreadable; the value was gap-filled.
+    OdUnreadable,
+    /// Reading the HUD failed unexpectedly (an internal error); every value was gap-filled.
+    AnalysisFailed,
+    /// A custom analyzer panicked; its outputs for the frame are missing. The HUD
+    /// readings are unaffected.
+    AnalyzerFailed,
+}
+
+impl AnomalyCause {
+    pub const ALL: [AnomalyCause; 6] = [
+        AnomalyCause::NoHud,
+        AnomalyCause::HpUnreadable,
+        AnomalyCause::SaUnreadable,
+        AnomalyCause::OdUnreadable,
+        AnomalyCause::AnalysisFailed,
+        AnomalyCause::AnalyzerFailed,
+    ];
+
+    /// Timeline color for this cause.
+    pub fn color(self) -> &'static str {
+        match self {
+            AnomalyCause::NoHud => "#9e9e9e",
+            AnomalyCause::HpUnreadable => "#e53935",
+            AnomalyCause::SaUnreadable => "#1e88e5",
+            AnomalyCause::OdUnreadable => "#43a047",
+            AnomalyCause::AnalysisFailed => "#8e24aa",
+            AnomalyCause::AnalyzerFailed => "#fb8c00",
+        }
+    }
+}
+
+impl fmt::Display for AnomalyCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnomalyCause::NoHud => write!(f, "no_hud"),
+            AnomalyCause::HpUnreadable => write!(f, "hp_unreadable"),
+            AnomalyCause::SaUnreadable => write!(f, "sa_unreadable"),
+            AnomalyCause::OdUnreadable => write!(f, "od_unreadable"),
+            AnomalyCause::AnalysisFailed => write!(f, "analysis_failed"),
+            AnomalyCause::AnalyzerFailed => write!(f, "analyzer_failed"),
+        }
+    }
+}
+
+/// A sampled frame with an analysis problem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub frame_number: u32,
+    pub timestamp_seconds: f64,
+    pub cause: AnomalyCause,
+    /// Whether the problem affected player 1 and player 2 (both for frame-wide causes).
+    pub players: [bool; 2],
+}
+
+/// Every analysis problem of a run, for judging how trustworthy an output file is.
+#[derive(Debug, Clone, Default)]
+pub struct AnomalyLog {
+    pub sampled_frames: u32,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl AnomalyLog {
+    /// Count a sampled frame and extend the covered time range.
+    pub fn note_frame(&mut self, frame: &Frame) {
+        if self.sampled_frames == 0 {
+            self.start_seconds = frame.timestamp_seconds;
+        }
+        self.sampled_frames += 1;
+        self.end_seconds = self.end_seconds.max(frame.timestamp_seconds);
+    }
+
+    /// Record a problem affecting the whole frame.
+    pub fn record(&mut self, frame: &Frame, cause: AnomalyCause) {
+        self.record_players(frame, cause, [true, true]);
+    }
+
+    /// Record a problem affecting only the flagged players.
+    pub fn record_players(&mut self, frame: &Frame, cause: AnomalyCause, players: [bool; 2]) {
+        assert!(
+            players.contains(&true),
+            "anomaly {cause} at frame {} affects no player",
+            frame.frame_number
+        );
+        self.anomalies.push(Anomaly {
+            frame_number: frame.frame_number,
+            timestamp_seconds: frame.timestamp_seconds,
+            cause,
+            players,
+        });
+    }
+
+    /// Add the frames and anomalies of a later run over another part of the video.
+    pub fn merge(&mut self, other: AnomalyLog) {
+        if other.sampled_frames == 0 {
+            return;
+        }
+        if self.sampled_frames == 0 {
+            self.start_seconds = other.start_seconds;
+        }
+        self.sampled_frames += other.sampled_frames;
+        self.start_seconds = self.start_seconds.min(other.start_seconds);
+        self.end_seconds = self.end_seconds.max(other.end_seconds);
+        self.anomalies.extend(other.anomalies);
+    }
+
+    /// Number of anomalies per cause (causes without anomalies are included as 0).
+    pub fn counts(&self) -> BTreeMap<AnomalyCause, u32> {
+        let mut counts: BTreeMap<AnomalyCause, u32> =
+            AnomalyCause::ALL.iter().map(|&c| (c, 0)).collect();
+        for a in &self.anomalies {
+            *counts.get_mut(&a.cause).unwrap() += 1;
+        }
+        counts
+    }
+
+    /// Log a one-line summary per cause.
+    pub fn log_summary(&self) {
+        for (cause, count) in self.counts() {
+            info!(
+                %cause,
+                count,
+                sampled_frames = self.sampled_frames,
+                "anomaly summary"
+            );
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let counts: serde_json::Map<String, Value> = self
+            .counts()
+            .into_iter()
+            .map(|(cause, n)| (cause.to_string(), json!(n)))
+            .collect();
+        let anomalies: Vec<Value> = self
+            .anomalies
+            .iter()
+            .map(|a| {
+                json!({
+                    "frame_number": a.frame_number,
+                    "timestamp_seconds": a.timestamp_seconds,
+                    "cause": a.cause.to_string(),
+                    "players": (1..=2u32)
+                        .filter(|&p| a.players[p as usize - 1])
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        json!({
+            "sampled_frames": self.sampled_frames,
+            "start_seconds": self.start_seconds,
+            "end_seconds": self.end_seconds,
+            "counts": counts,
+            "anomalies": anomalies,
+        })
+    }
+
+    /// Render a compact timeline: one row per cause, one tick per affected pixel column.
+    pub fn render_svg(&self) -> String {
+        let height = SVG_ROW_HEIGHT * AnomalyCause::ALL.len() as f64;
+        let plot_w = SVG_WIDTH - SVG_LABEL_WIDTH;
+        let span = (self.end_seconds - self.start_seconds).max(1e-9);
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SVG_WIDTH}" height="{height}" font-family="sans-serif" font-size="11">"#
+        )
+        .unwrap();
+        writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+
+        for (row, cause) in AnomalyCause::ALL.iter().enumerate() {
+            let y = SVG_ROW_HEIGHT * row as f64;
+            writeln!(
+                svg,
+                r#"<text x="4" y="{}" fill="{}">{cause}</text>"#,
+                y + 12.0,
+                cause.color()
+            )
+            .unwrap();
+            let columns: BTreeSet<u32> = self
+                .anomalies
+                .iter()
+                .filter(|a| a.cause == *cause)
+                .map(|a| {
+                    ((a.timestamp_seconds - self.start_seconds) / span * (plot_w - 1.0)) as u32
+                })
+                .collect();
+            for x in columns {
+                writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="1" height="{}" fill="{}"/>"#,
+                    SVG_LABEL_WIDTH + x as f64,
+                    y + 2.0,
+                    SVG_ROW_HEIGHT - 4.0,
+                    cause.color()
+                )
+                .unwrap();
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbImage;
+
+    use super::*;
+    use crate::test_support::video_frame;
+
+    fn frame(frame_number: u32) -> Frame {
+        video_frame(frame_number, RgbImage::new(1, 1))
+    }
+
+    fn sample_log() -> AnomalyLog {
+        let mut log = AnomalyLog::default();
+        for n in [60, 120, 180, 240] {
+            log.note_frame(&frame(n));
+        }
+        log.record(&frame(120), AnomalyCause::NoHud);
+        log.record(&frame(180), AnomalyCause::HpUnreadable);
+        log.record_players(&frame(180), AnomalyCause::SaUnreadable, [false, true]);
+        log
+    }
+
+    #[test]
+    fn note_frame_tracks_range() {
+        let log = sample_log();
+        assert_eq!(log.sampled_frames, 4);
+        assert_eq!(log.start_seconds, 1.0);
+        assert_eq!(log.end_seconds, 4.0);
+    }
+
+    #[test]
+    fn json_counts_every_cause() {
+        let json = sample_log().to_json();
+        assert_eq!(json["counts"]["no_hud"], 1);
+        assert_eq!(json["counts"]["od_unreadable"], 0);
+        assert_eq!(json["anomalies"].as_array().unwrap().len(), 3);
+        assert_eq!(json["anomalies"][1]["cause"], "hp_unreadable");
+        assert_eq!(json["anomalies"][1]["players"], json!([1, 2]));
+        assert_eq!(json["anomalies"][2]["players"], json!([2]));
+    }
+
+    #[test]
+    fn svg_has_one_tick_per_anomaly_column() {
+        let svg = sample_log().render_svg();
+        assert_eq!(svg.matches(r#"width="1""#).count(), 3);
+        assert!(svg.contains(AnomalyCause::HpUnreadable.color()));
+    }
+}
@@ -0,0 +1,186 @@
+//! Audio cues: the round-start announcer and the KO sound, found in the soundtrack by
+//! matching reference clips against its loudness envelope.
+
+use tracing::{debug, info};
+
+/// Sample rate the soundtrack and reference clips are decoded at (mono).
+pub const AUDIO_SAMPLE_RATE: u32 = 8000;
+/// Samples per envelope value (10 ms).
+pub const HOP_SAMPLES: usize = 80;
+/// Minimum normalized correlation between a clip and the soundtrack to report a cue.
+const MIN_CUE_SCORE: f32 = 0.8;
+/// Reference clips shorter than this (in envelope values) match almost anything.
+pub const MIN_TEMPLATE_HOPS: usize = 20;
+
+/// Which sound a cue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueKind {
+    /// The announcer calling the round at its start.
+    RoundStart,
+    /// The KO sound at the end of a round.
+    Ko,
+}
+
+/// Loudness over time: the RMS of each hop of [`HOP_SAMPLES`] samples.
+#[derive(Debug, Clone, Default)]
+pub struct Envelope {
+    pub values: Vec<f32>,
+}
+
+impl Envelope {
+    /// Seconds at the start of envelope value `index`.
+    pub fn seconds(index: usize) -> f64 {
+        (index * HOP_SAMPLES) as f64 / AUDIO_SAMPLE_RATE as f64
+    }
+}
+
+/// RMS of one hop, scaled to 0..=1.
+pub fn hop_rms(hop: &[i16]) -> f32 {
+    assert!(!hop.is_empty(), "empty audio hop");
+    let sum: f64 = hop.iter().map(|&s| (s as f64 / 32768.0).powi(2)).sum();
+    (sum / hop.len() as f64).sqrt() as f32
+}
+
+/// A reference clip of one cue kind.
+#[derive(Debug, Clone)]
+pub struct CueTemplate {
+    pub kind: CueKind,
+    pub envelope: Envelope,
+}
+
+/// A cue found in the soundtrack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioCue {
+    /// Seconds from the start of the source where the matched clip begins.
+    pub timestamp_seconds: f64,
+    pub kind: CueKind,
+    /// Normalized correlation with the reference clip (up to 1).
+    pub score: f32,
+}
+
+/// Find where `template` occurs in `track`: positions where the normalized correlation
+/// of the envelopes is at least [`MIN_CUE_SCORE`], keeping only the best match within
+/// one clip length. Loudness is normalized away, so a quieter game mix still matches.
+pub fn find_cues(track: &Envelope, template: &CueTemplate) -> Vec<AudioCue> {
+    let t = &template.envelope.values;
+    assert!(
+        t.len() >= MIN_TEMPLATE_HOPS,
+        "reference clip too short: {} hops",
+        t.len()
+    );
+    let Some(positions) = track.values.len().checked_sub(t.len() - 1) else {
+        return Vec::new();
+    };
+    let Some(t_norm) = normalized(t) else {
+        return Vec::new();
+    };
+
+    let mut cues: Vec<AudioCue> = Vec::new();
+    for start in 0..positions {
+        let Some(w_norm) = normalized(&track.values[start..start + t.len()]) else {
+            continue;
+        };
+        let score = w_norm.iter().zip(&t_norm).map(|(a, b)| a * b).sum::<f32>();
+        if score < MIN_CUE_SCORE {
+            continue;
+        }
+        let cue = AudioCue {
+            timestamp_seconds: Envelope::seconds(start),
+            kind: template.kind,
+            score,
+        };
+        match cues.last_mut() {
+            Some(last) if start - seconds_to_hop(last.timestamp_seconds) < t.len() => {
+                if score > last.score {
+                    *last = cue;
+                }
+            }
+            _ => cues.push(cue),
+        }
+    }
+    for cue in &cues {
+        debug!(?cue.kind, cue.timestamp_seconds, cue.score, "audio cue");
+    }
+    info!(kind = ?template.kind, count = cues.len(), "audio cue search complete");
+    cues
+}
+
+fn seconds_to_hop(seconds: f64) -> usize {
+    (seconds * AUDIO_SAMPLE_RATE as f64 / HOP_SAMPLES as f64).round() as usize
+}
+
+/// `values` shifted to zero mean and scaled to unit length, or None when flat (silence).
+fn normalized(values: &[f32]) -> Option<Vec<f32>> {
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let centered: Vec<f32> = values.iter().map(|v| v - mean).collect();
+    let norm = centered.iter().map(|v| v * v).sum::<f32>().sqrt();
+    (norm > 1e-6).then(|| centered.iter().map(|v| v / norm).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clip shape: a sharp attack followed by a decay, with a second hit.
+    fn clip(gain: f32) -> Vec<f32> {
+        (0..40)
+            .map(|i| {
+                let decay = (-(i as f32) / 8.0).exp();
+                let second = if i >= 25 { 0.5 } else { 0.0 };
+                gain * (decay + second)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rms_of_full_scale_square_wave_is_one() {
+        let hop: Vec<i16> = (0..HOP_SAMPLES)
+            .map(|i| if i % 2 == 0 { i16::MAX } else { -32768 })
+            .collect();
+        assert!((hop_rms(&hop) - 1.0).abs() < 1e-3);
+        assert_eq!(hop_rms(&[0; HOP_SAMPLES]), 0.0);
+    }
+
+    #[test]
+    fn finds_quieter_copies_of_the_clip() {
+        let template = CueTemplate {
+            kind: CueKind::Ko,
+            envelope: Envelope { values: clip(1.0) },
+        };
+        let mut track = vec![0.05f32; 500];
+        for (i, v) in clip(0.3).into_iter().enumerate() {
+            track[100 + i] += v;
+            track[350 + i] += v;
+        }
+        // Noise that is loud but shaped differently.
+        for v in &mut track[200..260] {
+            *v = 0.4;
+        }
+        let cues = find_cues(&Envelope { values: track }, &template);
+        let starts: Vec<f64> = cues.iter().map(|c| c.timestamp_seconds).collect();
+        assert_eq!(starts, vec![Envelope::seconds(100), Envelope::seconds(350)]);
+        assert!(cues.iter().all(|c| c.kind == CueKind::Ko && c.score > 0.99));
+    }
+
+    #[test]
+    fn silence_and_short_tracks_have_no_cues() {
+        let template = CueTemplate {
+            kind: CueKind::RoundStart,
+            envelope: Envelope { values: clip(1.0) },
+        };
+        assert!(find_cues(
+            &Envelope {
+                values: vec![0.0; 300]
+            },
+            &template
+        )
+        .is_empty());
+        assert!(find_cues(
+            &Envelope {
+                values: clip(1.0)[..30].to_vec()
+            },
+            &template
+        )
+        .is_empty());
+    }
+}
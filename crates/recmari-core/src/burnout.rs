@@ -0,0 +1,115 @@
+use tracing::info;
+
+use recmari_proto::proto::{Burnout, BurnoutRecoverySample, FrameData, Match, Round};
+
+use crate::players::player_state;
+
+/// Burnout stretches of both players in `round`, in start order. A stretch starts on
+/// a frame in burnout after one showing the Drive gauge and ends on the next frame
+/// showing the gauge again; frames showing neither keep the previous state.
+pub fn round_burnouts(round: &Round) -> Vec<Burnout> {
+    let mut burnouts = Vec::new();
+    for player in [1, 2] {
+        player_burnouts(&round.frames, player, &mut burnouts);
+    }
+    burnouts.sort_by(|a, b| a.start_seconds.total_cmp(&b.start_seconds));
+    burnouts
+}
+
+fn player_burnouts(frames: &[FrameData], player: u32, burnouts: &mut Vec<Burnout>) {
+    let mut in_burnout: Option<bool> = None;
+    let mut current: Option<Burnout> = None;
+    for fd in frames {
+        let Some(state) = player_state(fd, player) else {
+            continue;
+        };
+        let burnout = state.burnout_gauge.is_some();
+        if !burnout && state.od_gauge.is_none() {
+            continue;
+        }
+        match (in_burnout, burnout) {
+            (Some(false), true) => {
+                current = Some(Burnout {
+                    player,
+                    start_frame_number: fd.frame_number,
+                    start_seconds: fd.timestamp_seconds,
+                    ..Default::default()
+                });
+            }
+            (Some(true), false) => {
+                if let Some(mut b) = current.take() {
+                    b.end_frame_number = Some(fd.frame_number);
+                    b.end_seconds = Some(fd.timestamp_seconds);
+                    b.duration_seconds = fd.timestamp_seconds - b.start_seconds;
+                    burnouts.push(b);
+                }
+            }
+            _ => {}
+        }
+        if let (Some(b), Some(recovery)) = (current.as_mut(), state.burnout_gauge) {
+            b.recovery.push(BurnoutRecoverySample {
+                timestamp_seconds: fd.timestamp_seconds,
+                recovery,
+            });
+        }
+        in_burnout = Some(burnout);
+    }
+    if let (Some(mut b), Some(last)) = (current, frames.last()) {
+        b.duration_seconds = last.timestamp_seconds - b.start_seconds;
+        burnouts.push(b);
+    }
+}
+
+/// Fill in the burnouts of every round.
+pub fn attach_burnouts(matches: &mut [Match]) {
+    let mut count = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        round.burnouts = round_burnouts(round);
+        count += round.burnouts.len();
+    }
+    info!(burnouts = count, "burnouts attached");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{drive, frame};
+
+    #[test]
+    fn burnouts_have_duration_and_recovery_curve() {
+        let round = Round {
+            frames: [
+                (0, (Some(6.0), None), Some(6.0)),
+                (60, (Some(0.5), None), Some(2.0)),
+                (120, (None, Some(0.0)), Some(1.0)),
+                (180, (None, None), Some(0.5)),
+                (240, (None, Some(0.6)), None),
+                (300, (Some(3.0), None), None),
+                (360, (Some(3.0), None), None),
+            ]
+            .map(|(n, (od, burnout), p2_od)| FrameData {
+                player1: Some(drive(od, burnout)),
+                player2: Some(drive(p2_od, p2_od.is_none().then_some(0.0))),
+                ..frame(n)
+            })
+            .to_vec(),
+            ..Default::default()
+        };
+        let burnouts = round_burnouts(&round);
+        assert_eq!(burnouts.len(), 2);
+
+        let p1 = &burnouts[0];
+        assert_eq!((p1.player, p1.start_frame_number), (1, 120));
+        assert_eq!(p1.end_frame_number, Some(300));
+        assert!((p1.duration_seconds - 3.0).abs() < 1e-9);
+        let curve: Vec<f64> = p1.recovery.iter().map(|s| s.recovery).collect();
+        assert_eq!(curve, [0.0, 0.6]);
+
+        // Player 2 is still in burnout when the round ends.
+        let p2 = &burnouts[1];
+        assert_eq!((p2.player, p2.start_frame_number), (2, 240));
+        assert_eq!(p2.end_frame_number, None);
+        assert!((p2.duration_seconds - 2.0).abs() < 1e-9);
+        assert_eq!(p2.recovery.len(), 3);
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Write;
+
+use image::RgbImage;
+use tracing::{error, info};
+
+use crate::analysis::common::{rgb_to_hsv, Hsv, HsvRange};
+use crate::analysis::huds::manemon::ManemonHud;
+use crate::analysis::{Hud, HudType};
+use crate::config::HudProfile;
+use crate::error::HudError;
+use crate::video::frame::Frame;
+
+/// Pixels skipped at both ends of an HP scanline (bar frame and border).
+const EDGE_PIXELS: u32 = 3;
+/// Fraction of samples ignored at each end of every channel, so that stray pixels
+/// (hit sparks, compression noise) do not widen the range.
+const OUTLIER_FRACTION: f64 = 0.02;
+/// Slack added around the measured range.
+const HUE_MARGIN: f32 = 3.0;
+const SV_MARGIN: f32 = 0.05;
+/// The measured hue must be yellow-ish; anything else means the image does not show
+/// full HP bars (or the scanlines are off).
+const FILL_HUE_BOUNDS: [f32; 2] = [30.0, 80.0];
+
+/// Measure the HP bar fill color from frames where both players have full health.
+/// Scanlines overridden in `profile` are honored.
+pub fn calibrate_hp_fill(images: &[RgbImage], profile: &HudProfile) -> Result<HsvRange, HudError> {
+    assert!(
+        !images.is_empty(),
+        "at least one reference image is required"
+    );
+
+    let mut samples: Vec<Hsv> = Vec::new();
+    for (index, image) in images.iter().enumerate() {
+        let (w, h) = image.dimensions();
+        if (w, h) != (1920, 1080) {
+            error!(index, w, h, "reference image is not 1920x1080");
+            return Err(HudError::Calibration(format!(
+                "reference image {} is {w}x{h}, expected 1920x1080",
+                index + 1
+            )));
+        }
+        let hud = ManemonHud::with_profile(w, h, profile);
+        for scan in hud.hp_scanlines() {
+            for i in EDGE_PIXELS..scan.width() - EDGE_PIXELS {
+                let x = scan.x_at(i);
+                for y in scan.y - 1..=scan.y + 1 {
+                    samples.push(rgb_to_hsv(*image.get_pixel(x, y)));
+                }
+            }
+        }
+    }
+
+    let range = HsvRange {
+        h: widen(percentiles(&samples, |p| p.h), HUE_MARGIN, [0.0, 360.0]),
+        s: widen(percentiles(&samples, |p| p.s), SV_MARGIN, [0.0, 1.0]),
+        v: widen(percentiles(&samples, |p| p.v), SV_MARGIN, [0.0, 1.0]),
+    };
+    let median_hue = percentile(&samples, |p| p.h, 0.5);
+    if !(FILL_HUE_BOUNDS[0]..=FILL_HUE_BOUNDS[1]).contains(&median_hue) {
+        error!(median_hue, "HP bars are not yellow in the reference images");
+        return Err(HudError::Calibration(format!(
+            "reference images do not show full HP bars (median hue {median_hue:.0}°)"
+        )));
+    }
+
+    info!(
+        images = images.len(),
+        samples = samples.len(),
+        ?range,
+        "HP fill color calibrated"
+    );
+    Ok(range)
+}
+
+/// Readability of one HUD element for each player of a reference frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SideCheck {
+    pub element: &'static str,
+    pub p1: bool,
+    pub p2: bool,
+}
+
+impl SideCheck {
+    pub fn passed(&self) -> bool {
+        self.p1 && self.p2
+    }
+}
+
+/// Check every HUD element of a reference frame per player, so that a capture where
+/// one side is covered or scaled differently reports which side fails.
+pub fn check_sides(image: &RgbImage, profile: &HudProfile) -> Result<Vec<SideCheck>, HudError> {
+    let (w, h) = image.dimensions();
+    if (w, h) != (1920, 1080) {
+        error!(w, h, "reference image is not 1920x1080");
+        return Err(HudError::Calibration(format!(
+            "reference image is {w}x{h}, expected 1920x1080"
+        )));
+    }
+    let hud = ManemonHud::with_profile(w, h, profile);
+    let frame = Frame {
+        image: image.clone(),
+        frame_number: 0,
+        timestamp_seconds: 0.0,
+    };
+
+    let [frame_p1, frame_p2] = hud.detect_sides(&frame);
+    let hp = hud.analyze_hp(&frame);
+    let sa = hud.analyze_sa(&frame);
+    let od = hud.analyze_od(&frame);
+    let checks = vec![
+        SideCheck {
+            element: "hud_frame",
+            p1: frame_p1,
+            p2: frame_p2,
+        },
+        SideCheck {
+            element: "hp",
+            p1: hp.p1.is_some(),
+            p2: hp.p2.is_some(),
+        },
+        SideCheck {
+            element: "sa",
+            p1: sa.p1.is_some(),
+            p2: sa.p2.is_some(),
+        },
+        SideCheck {
+            element: "od",
+            p1: od.p1.is_some(),
+            p2: od.p2.is_some(),
+        },
+    ];
+    for check in checks.iter().filter(|c| !c.passed()) {
+        info!(?check, "HUD element unreadable on some side");
+    }
+    Ok(checks)
+}
+
+/// Lower and upper [`OUTLIER_FRACTION`] percentiles of one channel.
+fn percentiles(samples: &[Hsv], channel: impl Fn(&Hsv) -> f32) -> [f32; 2] {
+    [
+        percentile(samples, &channel, OUTLIER_FRACTION),
+        percentile(samples, &channel, 1.0 - OUTLIER_FRACTION),
+    ]
+}
+
+fn percentile(samples: &[Hsv], channel: impl Fn(&Hsv) -> f32, q: f64) -> f32 {
+    let mut values: Vec<f32> = samples.iter().map(channel).collect();
+    values.sort_by(f32::total_cmp);
+    values[((values.len() - 1) as f64 * q).round() as usize]
+}
+
+fn widen([min, max]: [f32; 2], margin: f32, [lo, hi]: [f32; 2]) -> [f32; 2] {
+    [(min - margin).max(lo), (max + margin).min(hi)]
+}
+
+/// A `--hud-config` snippet setting the HP fill color of `hud_type`.
+pub fn hp_fill_profile_toml(hud_type: HudType, range: &HsvRange, images: usize) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# Calibrated from {images} full-HP reference frame(s)."
+    );
+    let _ = writeln!(out, "[{hud_type}.colors]");
+    let _ = writeln!(
+        out,
+        "hp_fill = {{ h = [{:.1}, {:.1}], s = [{:.3}, {:.3}], v = [{:.3}, {:.3}] }}",
+        range.h[0], range.h[1], range.s[0], range.s[1], range.v[0], range.v[1]
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+    use crate::config::parse_hud_config;
+
+    /// A frame with both HP bars painted in `color`.
+    fn full_hp_frame(color: Rgb<u8>) -> RgbImage {
+        let mut image = RgbImage::from_pixel(1920, 1080, Rgb([20, 20, 30]));
+        let hud = ManemonHud::new(1920, 1080);
+        for scan in hud.hp_scanlines() {
+            for i in 0..scan.width() {
+                for y in scan.y - 2..=scan.y + 2 {
+                    image.put_pixel(scan.x_at(i), y, color);
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn calibrated_range_round_trips_through_config() {
+        // A capture card that dims the bars to V≈0.85, below the built-in range.
+        let color = Rgb([216, 190, 60]);
+        let range = calibrate_hp_fill(&[full_hp_frame(color)], &HudProfile::default()).unwrap();
+        assert!(range.contains(rgb_to_hsv(color)));
+
+        let toml = hp_fill_profile_toml(HudType::Manemon, &range, 1);
+        let config = parse_hud_config(&toml).unwrap();
+        let parsed = config.profile(HudType::Manemon).colors.hp_fill.unwrap();
+        assert!(parsed.contains(rgb_to_hsv(color)));
+    }
+
+    #[test]
+    fn non_yellow_bars_are_rejected() {
+        let image = full_hp_frame(Rgb([40, 80, 220]));
+        assert!(calibrate_hp_fill(&[image], &HudProfile::default()).is_err());
+    }
+
+    #[test]
+    fn side_check_reports_covered_side() {
+        let mut image = full_hp_frame(Rgb([250, 230, 70]));
+        // Cover P2's half of the screen, as a camera overlay would.
+        for y in 0..1080 {
+            for x in 960..1920 {
+                image.put_pixel(x, y, Rgb([20, 20, 30]));
+            }
+        }
+        let checks = check_sides(&image, &HudProfile::default()).unwrap();
+        let hp = checks.iter().find(|c| c.element == "hp").unwrap();
+        assert!(!hp.p2);
+        assert!(checks.iter().all(|c| !c.p2));
+    }
+}
@@ -0,0 +1,423 @@
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use tracing::info;
+
+use recmari_proto::proto::{FrameData, Match, PlayerState, Round};
+
+const WIDTH: f64 = 960.0;
+const HEIGHT: f64 = 400.0;
+const MARGIN_LEFT: f64 = 50.0;
+const MARGIN_RIGHT: f64 = 160.0;
+const MARGIN_TOP: f64 = 30.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+const PALETTE: [&str; 8] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+];
+
+/// Gauge plotted on the y axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Hp,
+    Sa,
+    Od,
+}
+
+impl Metric {
+    fn value(self, player: &PlayerState) -> Option<f64> {
+        match self {
+            Metric::Hp => player.health_ratio,
+            Metric::Sa => player.sa_gauge,
+            Metric::Od => player.od_gauge,
+        }
+    }
+
+    pub(crate) fn max(self) -> f64 {
+        match self {
+            Metric::Hp => 1.0,
+            Metric::Sa => 3.0,
+            Metric::Od => 6.0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Metric::Hp => "HP",
+            Metric::Sa => "SA",
+            Metric::Od => "OD",
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hp" => Ok(Metric::Hp),
+            "sa" => Ok(Metric::Sa),
+            "od" => Ok(Metric::Od),
+            _ => Err(format!("unknown metric '{s}' (expected hp, sa or od)")),
+        }
+    }
+}
+
+/// Which player's gauges to plot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    P1,
+    P2,
+}
+
+impl Side {
+    fn state(self, fd: &FrameData) -> Option<&PlayerState> {
+        match self {
+            Side::P1 => fd.player1.as_ref(),
+            Side::P2 => fd.player2.as_ref(),
+        }
+    }
+}
+
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "p1" | "1" => Ok(Side::P1),
+            "p2" | "2" => Ok(Side::P2),
+            _ => Err(format!("unknown player '{s}' (expected p1 or p2)")),
+        }
+    }
+}
+
+/// One labelled line. Unreadable frames split it into separate segments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub label: String,
+    pub segments: Vec<Vec<(f64, f64)>>,
+}
+
+/// Both players' curves for one match, in seconds since the match's first frame.
+pub fn timeline_series(m: &Match, metric: Metric) -> Vec<Series> {
+    let frames: Vec<&FrameData> = m.rounds.iter().flat_map(|r| r.frames.iter()).collect();
+    let t0 = frames.first().map_or(0.0, |fd| fd.timestamp_seconds);
+    [(Side::P1, "P1"), (Side::P2, "P2")]
+        .into_iter()
+        .map(|(side, label)| Series {
+            label: label.to_string(),
+            segments: segments(&frames, side, metric, t0),
+        })
+        .collect()
+}
+
+/// Both players' curves for one round, aligned to the round's start.
+pub fn round_series(round: &Round, metric: Metric) -> Vec<Series> {
+    let frames: Vec<&FrameData> = round.frames.iter().collect();
+    [(Side::P1, "P1"), (Side::P2, "P2")]
+        .into_iter()
+        .map(|(side, label)| Series {
+            label: label.to_string(),
+            segments: segments(&frames, side, metric, round.start_timestamp_seconds),
+        })
+        .collect()
+}
+
+/// A labelled vertical line at `x`, e.g. an event of the round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub x: f64,
+    pub label: String,
+}
+
+/// One player's curve for every round of every match, aligned to round start.
+pub fn overlay_series(matches: &[(usize, &Match)], side: Side, metric: Metric) -> Vec<Series> {
+    let mut series = Vec::new();
+    for &(match_index, m) in matches {
+        for round in &m.rounds {
+            let Some(first) = round.frames.first() else {
+                continue;
+            };
+            let frames: Vec<&FrameData> = round.frames.iter().collect();
+            series.push(Series {
+                label: format!("M{} R{}", match_index + 1, round.round_index + 1),
+                segments: segments(&frames, side, metric, first.timestamp_seconds),
+            });
+        }
+    }
+    info!(
+        rounds = series.len(),
+        ?side,
+        ?metric,
+        "overlay series built"
+    );
+    series
+}
+
+pub(crate) fn segments(
+    frames: &[&FrameData],
+    side: Side,
+    metric: Metric,
+    t0: f64,
+) -> Vec<Vec<(f64, f64)>> {
+    let mut segments: Vec<Vec<(f64, f64)>> = vec![Vec::new()];
+    for fd in frames {
+        match side.state(fd).and_then(|p| metric.value(p)) {
+            Some(v) => segments
+                .last_mut()
+                .unwrap()
+                .push((fd.timestamp_seconds - t0, v)),
+            None if !segments.last().unwrap().is_empty() => segments.push(Vec::new()),
+            None => {}
+        }
+    }
+    segments.retain(|s| !s.is_empty());
+    segments
+}
+
+/// Render line series as a standalone SVG document.
+pub fn render_svg(title: &str, x_label: &str, metric: Metric, series: &[Series]) -> String {
+    render_svg_with_markers(title, x_label, metric, series, &[])
+}
+
+/// [`render_svg`] with the `markers` drawn as dashed vertical lines behind the series.
+pub fn render_svg_with_markers(
+    title: &str,
+    x_label: &str,
+    metric: Metric,
+    series: &[Series],
+    markers: &[Marker],
+) -> String {
+    let x_max = series
+        .iter()
+        .flat_map(|s| s.segments.iter().flatten())
+        .map(|&(x, _)| x)
+        .chain(markers.iter().map(|m| m.x))
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let y_max = metric.max();
+    let plot_w = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_h = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let px = |x: f64| MARGIN_LEFT + x / x_max * plot_w;
+    let py = |y: f64| MARGIN_TOP + (1.0 - y.clamp(0.0, y_max) / y_max) * plot_h;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="12">"#
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{MARGIN_LEFT}" y="18" font-size="14">{}</text>"#,
+        escape(title)
+    )
+    .unwrap();
+    write_axes(&mut svg, x_label, metric, x_max, (plot_w, plot_h));
+
+    for (i, marker) in markers.iter().enumerate() {
+        let x = px(marker.x.max(0.0));
+        // Alternate label rows so neighbouring markers stay legible.
+        let ly = MARGIN_TOP + 12.0 + 12.0 * (i % 3) as f64;
+        writeln!(
+            svg,
+            r##"<line x1="{x:.1}" y1="{MARGIN_TOP}" x2="{x:.1}" y2="{}" stroke="#999" stroke-dasharray="4 3"/><text x="{:.1}" y="{ly}" font-size="10" fill="#555">{}</text>"##,
+            MARGIN_TOP + plot_h,
+            x + 2.0,
+            escape(&marker.label)
+        )
+        .unwrap();
+    }
+
+    for (i, s) in series.iter().enumerate() {
+        let color = PALETTE[i % PALETTE.len()];
+        for segment in &s.segments {
+            let points: Vec<String> = segment
+                .iter()
+                .map(|&(x, y)| format!("{:.1},{:.1}", px(x), py(y)))
+                .collect();
+            writeln!(
+                svg,
+                r#"<polyline fill="none" stroke="{color}" stroke-width="1.5" points="{}"/>"#,
+                points.join(" ")
+            )
+            .unwrap();
+        }
+        let ly = MARGIN_TOP + 16.0 * i as f64;
+        let lx = WIDTH - MARGIN_RIGHT + 10.0;
+        writeln!(
+            svg,
+            r#"<line x1="{lx}" y1="{ly}" x2="{}" y2="{ly}" stroke="{color}" stroke-width="3"/><text x="{}" y="{}">{}</text>"#,
+            lx + 20.0,
+            lx + 26.0,
+            ly + 4.0,
+            escape(&s.label)
+        )
+        .unwrap();
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn write_axes(svg: &mut String, x_label: &str, metric: Metric, x_max: f64, plot: (f64, f64)) {
+    let (plot_w, plot_h) = plot;
+    let bottom = MARGIN_TOP + plot_h;
+    writeln!(
+        svg,
+        r##"<rect x="{MARGIN_LEFT}" y="{MARGIN_TOP}" width="{plot_w}" height="{plot_h}" fill="none" stroke="#888"/>"##
+    )
+    .unwrap();
+    for i in 0..=4 {
+        let y = MARGIN_TOP + plot_h * i as f64 / 4.0;
+        let value = metric.max() * (1.0 - i as f64 / 4.0);
+        writeln!(
+            svg,
+            r##"<line x1="{MARGIN_LEFT}" y1="{y}" x2="{}" y2="{y}" stroke="#ddd"/><text x="{}" y="{}" text-anchor="end">{value:.2}</text>"##,
+            MARGIN_LEFT + plot_w,
+            MARGIN_LEFT - 4.0,
+            y + 4.0
+        )
+        .unwrap();
+    }
+    writeln!(
+        svg,
+        r#"<text x="{MARGIN_LEFT}" y="{}">0</text><text x="{}" y="{}" text-anchor="end">{x_max:.0} s</text>"#,
+        bottom + 16.0,
+        MARGIN_LEFT + plot_w,
+        bottom + 16.0
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle">{} / {}</text>"#,
+        MARGIN_LEFT + plot_w / 2.0,
+        bottom + 32.0,
+        escape(x_label),
+        metric.label()
+    )
+    .unwrap();
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::Round;
+
+    use super::*;
+    use crate::test_support::{frame_at, health};
+
+    fn round(index: u32, frames: Vec<FrameData>) -> Round {
+        Round {
+            round_index: index,
+            frames,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlay_aligns_rounds_to_start() {
+        let m = Match {
+            rounds: vec![
+                round(
+                    0,
+                    vec![
+                        FrameData {
+                            player1: Some(health(Some(1.0))),
+                            ..frame_at(10.0)
+                        },
+                        FrameData {
+                            player1: Some(health(Some(0.5))),
+                            ..frame_at(12.0)
+                        },
+                    ],
+                ),
+                round(
+                    1,
+                    vec![
+                        FrameData {
+                            player1: Some(health(Some(1.0))),
+                            ..frame_at(40.0)
+                        },
+                        FrameData {
+                            player1: Some(health(Some(0.2))),
+                            ..frame_at(41.0)
+                        },
+                    ],
+                ),
+            ],
+            ..Default::default()
+        };
+        let series = overlay_series(&[(2, &m)], Side::P1, Metric::Hp);
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].label, "M3 R1");
+        assert_eq!(series[0].segments, vec![vec![(0.0, 1.0), (2.0, 0.5)]]);
+        assert_eq!(series[1].segments, vec![vec![(0.0, 1.0), (1.0, 0.2)]]);
+    }
+
+    #[test]
+    fn unreadable_frames_split_segments() {
+        let m = Match {
+            rounds: vec![round(
+                0,
+                vec![
+                    FrameData {
+                        player1: Some(health(Some(1.0))),
+                        ..frame_at(0.0)
+                    },
+                    FrameData {
+                        player1: Some(health(None)),
+                        ..frame_at(1.0)
+                    },
+                    FrameData {
+                        player1: Some(health(Some(0.8))),
+                        ..frame_at(2.0)
+                    },
+                ],
+            )],
+            ..Default::default()
+        };
+        let series = timeline_series(&m, Metric::Hp);
+        assert_eq!(series[0].segments.len(), 2);
+        assert!(series[1].segments.is_empty());
+    }
+
+    #[test]
+    fn timeline_starts_at_the_first_frame_of_the_match() {
+        let m = Match {
+            rounds: vec![round(
+                0,
+                vec![
+                    FrameData {
+                        player1: Some(health(Some(1.0))),
+                        ..frame_at(600.0)
+                    },
+                    FrameData {
+                        player1: Some(health(Some(0.5))),
+                        ..frame_at(610.0)
+                    },
+                ],
+            )],
+            ..Default::default()
+        };
+        let series = timeline_series(&m, Metric::Hp);
+        assert_eq!(series[0].segments, vec![vec![(0.0, 1.0), (10.0, 0.5)]]);
+    }
+
+    #[test]
+    fn render_svg_draws_every_segment() {
+        let series = vec![Series {
+            label: "A<B".to_string(),
+            segments: vec![vec![(0.0, 1.0), (5.0, 0.5)], vec![(6.0, 0.4)]],
+        }];
+        let svg = render_svg("test", "seconds", Metric::Hp, &series);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("A&lt;B"));
+    }
+}
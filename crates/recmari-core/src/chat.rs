@@ -0,0 +1,246 @@
+use std::path::Path;
+
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use recmari_proto::proto::{ChatSpike, Match};
+
+use crate::error::PipelineError;
+
+/// Width of the buckets chat messages are counted in.
+const BUCKET_SECONDS: f64 = 10.0;
+/// A bucket is a spike when its count exceeds mean + this many standard deviations.
+const SPIKE_STDDEV: f64 = 2.0;
+/// Buckets with fewer messages than this are never spikes (quiet streams).
+const MIN_SPIKE_MESSAGES: u32 = 5;
+/// Chat reacts with a delay; spikes starting this long after a round ends still belong to it.
+const REACTION_DELAY_SECONDS: f64 = 15.0;
+/// Messages later than this (48 hours) are bogus; a VOD is never that long, and the
+/// bucket array grows with the latest timestamp.
+const MAX_CHAT_SECONDS: f64 = 48.0 * 3600.0;
+
+/// Load chat message timestamps (seconds from the start of the VOD) from a JSON chat log.
+pub fn load_chat_log(path: &Path) -> Result<Vec<f64>, PipelineError> {
+    info!(?path, "loading chat log");
+    let text = std::fs::read_to_string(path).map_err(PipelineError::io("read chat log", path))?;
+    let timestamps = parse_chat_log(&text)?;
+    info!(?path, messages = timestamps.len(), "chat log loaded");
+    Ok(timestamps)
+}
+
+/// Parse a chat log. Accepted formats:
+/// - TwitchDownloader export: `{"comments": [{"content_offset_seconds": 12.5, ...}, ...]}`
+/// - A plain array: `[{"timestamp": 12.5, ...}, ...]`
+pub fn parse_chat_log(text: &str) -> Result<Vec<f64>, PipelineError> {
+    let root: Value = serde_json::from_str(text)
+        .map_err(|e| PipelineError::Parse(format!("chat log is not valid JSON: {e}")))?;
+
+    let (messages, key) = if let Some(comments) = root.get("comments").and_then(Value::as_array) {
+        (comments, "content_offset_seconds")
+    } else if let Some(array) = root.as_array() {
+        (array, "timestamp")
+    } else {
+        error!("chat log must be an array or an object with a \"comments\" array");
+        return Err(PipelineError::Parse("unrecognized chat log format".into()));
+    };
+
+    let mut timestamps = Vec::with_capacity(messages.len());
+    for (i, message) in messages.iter().enumerate() {
+        let Some(t) = message.get(key).and_then(Value::as_f64) else {
+            error!(index = i, key, "chat message has no numeric timestamp");
+            return Err(PipelineError::Parse(format!(
+                "chat message {i} has no numeric \"{key}\""
+            )));
+        };
+        timestamps.push(t);
+    }
+    let total = timestamps.len();
+    timestamps.retain(|t| in_range(*t));
+    if timestamps.len() < total {
+        warn!(
+            skipped = total - timestamps.len(),
+            max_seconds = MAX_CHAT_SECONDS,
+            "chat messages with out-of-range timestamps skipped"
+        );
+    }
+    timestamps.sort_by(f64::total_cmp);
+    Ok(timestamps)
+}
+
+/// Find windows where the chat message rate is well above the stream's baseline.
+/// Adjacent spike buckets are merged into a single spike.
+pub fn find_spikes(timestamps: &[f64]) -> Vec<ChatSpike> {
+    let counts = bucket_counts(timestamps);
+    if counts.is_empty() {
+        return Vec::new();
+    }
+
+    let n = counts.len() as f64;
+    let mean = counts.iter().map(|&c| c as f64).sum::<f64>() / n;
+    let variance = counts
+        .iter()
+        .map(|&c| (c as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    let threshold = (mean + SPIKE_STDDEV * variance.sqrt()).max(MIN_SPIKE_MESSAGES as f64);
+
+    let mut spikes: Vec<ChatSpike> = Vec::new();
+    for (i, &count) in counts.iter().enumerate() {
+        if (count as f64) < threshold {
+            continue;
+        }
+        let start = i as f64 * BUCKET_SECONDS;
+        match spikes.last_mut() {
+            Some(last) if (last.end_seconds - start).abs() < 1e-9 => {
+                last.end_seconds = start + BUCKET_SECONDS;
+                last.message_count += count;
+            }
+            _ => spikes.push(ChatSpike {
+                start_seconds: start,
+                end_seconds: start + BUCKET_SECONDS,
+                message_count: count,
+            }),
+        }
+    }
+
+    info!(
+        buckets = counts.len(),
+        mean,
+        threshold,
+        spikes = spikes.len(),
+        "chat spike detection complete"
+    );
+    spikes
+}
+
+/// Whether a message timestamp is usable: from the start of the VOD up to
+/// [`MAX_CHAT_SECONDS`].
+fn in_range(t: f64) -> bool {
+    (0.0..=MAX_CHAT_SECONDS).contains(&t)
+}
+
+fn bucket_counts(timestamps: &[f64]) -> Vec<u32> {
+    let Some(last) = timestamps
+        .iter()
+        .copied()
+        .filter(|t| in_range(*t))
+        .reduce(f64::max)
+    else {
+        return Vec::new();
+    };
+    let len = (last / BUCKET_SECONDS) as usize + 1;
+    assert!(
+        len <= (MAX_CHAT_SECONDS / BUCKET_SECONDS) as usize + 1,
+        "too many chat buckets: {len}"
+    );
+    let mut counts = vec![0u32; len];
+    for &t in timestamps.iter().filter(|t| in_range(**t)) {
+        counts[(t / BUCKET_SECONDS) as usize] += 1;
+    }
+    counts
+}
+
+/// Attach each spike to every round it overlaps (allowing for chat reaction delay).
+pub fn attach_chat_spikes(matches: &mut [Match], spikes: &[ChatSpike]) {
+    let mut attached = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        let (Some(first), Some(last)) = (round.frames.first(), round.frames.last()) else {
+            continue;
+        };
+        let start = first.timestamp_seconds;
+        let end = last.timestamp_seconds + REACTION_DELAY_SECONDS;
+        round.chat_spikes = spikes
+            .iter()
+            .filter(|s| s.start_seconds < end && s.end_seconds > start)
+            .cloned()
+            .collect();
+        attached += round.chat_spikes.len();
+    }
+    info!(attached, "chat spikes attached to rounds");
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{FrameData, Round};
+
+    use super::*;
+
+    #[test]
+    fn parse_twitch_downloader_format() {
+        let text = r#"{"comments": [
+            {"content_offset_seconds": 3.5, "message": {"body": "hi"}},
+            {"content_offset_seconds": 1.0}
+        ]}"#;
+        assert_eq!(parse_chat_log(text).unwrap(), vec![1.0, 3.5]);
+    }
+
+    #[test]
+    fn parse_plain_array_format() {
+        let text = r#"[{"timestamp": 2.0}, {"timestamp": 0.5}]"#;
+        assert_eq!(parse_chat_log(text).unwrap(), vec![0.5, 2.0]);
+    }
+
+    #[test]
+    fn parse_rejects_missing_timestamp() {
+        assert!(parse_chat_log(r#"[{"text": "gg"}]"#).is_err());
+        assert!(parse_chat_log(r#"{"messages": []}"#).is_err());
+    }
+
+    #[test]
+    fn spikes_merge_adjacent_buckets() {
+        // One message per bucket for 200s, then a burst spanning two buckets.
+        let mut timestamps: Vec<f64> = (0..20).map(|i| i as f64 * 10.0 + 1.0).collect();
+        timestamps.extend((0..40).map(|i| 100.0 + i as f64 * 0.5));
+        timestamps.sort_by(f64::total_cmp);
+
+        let spikes = find_spikes(&timestamps);
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].start_seconds, 100.0);
+        assert_eq!(spikes[0].end_seconds, 120.0);
+        assert_eq!(spikes[0].message_count, 42);
+    }
+
+    #[test]
+    fn out_of_range_timestamps_are_skipped() {
+        let text = r#"[{"timestamp": 2.0}, {"timestamp": 1e12}, {"timestamp": -3.0}]"#;
+        assert_eq!(parse_chat_log(text).unwrap(), vec![2.0]);
+        assert_eq!(bucket_counts(&[5.0, 1e12, f64::INFINITY]), vec![1]);
+    }
+
+    #[test]
+    fn quiet_chat_has_no_spikes() {
+        let timestamps: Vec<f64> = (0..30).map(|i| i as f64 * 7.0).collect();
+        assert!(find_spikes(&timestamps).is_empty());
+    }
+
+    #[test]
+    fn attach_uses_reaction_delay() {
+        let frame = |ts: f64| FrameData {
+            timestamp_seconds: ts,
+            ..Default::default()
+        };
+        let mut matches = vec![Match {
+            rounds: vec![Round {
+                frames: vec![frame(10.0), frame(50.0)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let spike = |start: f64| ChatSpike {
+            start_seconds: start,
+            end_seconds: start + 10.0,
+            message_count: 9,
+        };
+
+        attach_chat_spikes(
+            &mut matches,
+            &[spike(0.0), spike(5.0), spike(60.0), spike(70.0)],
+        );
+        let starts: Vec<f64> = matches[0].rounds[0]
+            .chat_spikes
+            .iter()
+            .map(|s| s.start_seconds)
+            .collect();
+        assert_eq!(starts, vec![5.0, 60.0]);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use prost::Message;
+use tracing::{error, info, warn};
+
+use recmari_proto::proto::{CheckpointRecord, CustomEvent, FrameData};
+
+use crate::error::PipelineError;
+
+/// Frames buffered between flushes; at most this many frames are lost on a crash.
+const FLUSH_INTERVAL: usize = 300;
+
+/// Append-only file of length-delimited `CheckpointRecord`s, written while a video is
+/// analyzed so that a crashed run can be resumed.
+pub struct CheckpointWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    pending: usize,
+}
+
+impl CheckpointWriter {
+    /// Open `path` for appending; a new file is started unless `append` is set.
+    pub fn open(path: &Path, append: bool) -> Result<Self, PipelineError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(append)
+            .write(true)
+            .truncate(!append)
+            .open(path)
+            .map_err(PipelineError::io("open checkpoint", path))?;
+        info!(?path, append, "checkpoint opened");
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+            pending: 0,
+        })
+    }
+
+    /// Append a frame with the custom events reported on it.
+    pub fn append(
+        &mut self,
+        fd: &FrameData,
+        custom_events: &[CustomEvent],
+    ) -> Result<(), PipelineError> {
+        let record = CheckpointRecord {
+            frame: Some(fd.clone()),
+            custom_events: custom_events.to_vec(),
+        };
+        let mut buf = Vec::with_capacity(record.encoded_len() + 4);
+        record
+            .encode_length_delimited(&mut buf)
+            .expect("encoding into a Vec never runs out of space");
+        self.file
+            .write_all(&buf)
+            .map_err(PipelineError::io("write checkpoint", &self.path))?;
+
+        self.pending += 1;
+        if self.pending >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), PipelineError> {
+        if let Err(e) = self.file.flush() {
+            error!(path = ?self.path, %e, "failed to flush checkpoint");
+            return Err(PipelineError::io("flush checkpoint", &self.path)(e));
+        }
+        info!(path = ?self.path, frames = self.pending, "checkpoint flushed");
+        self.pending = 0;
+        Ok(())
+    }
+}
+
+/// Frames and custom events read back from a checkpoint.
+#[derive(Debug, Default, PartialEq)]
+pub struct Checkpoint {
+    pub frames: Vec<FrameData>,
+    pub custom_events: Vec<CustomEvent>,
+}
+
+/// Read every complete record of a checkpoint. A record cut off by a crash is dropped
+/// and the file is truncated to the last complete record, so appending can continue.
+pub fn load_checkpoint(path: &Path) -> Result<Checkpoint, PipelineError> {
+    let bytes = std::fs::read(path).map_err(PipelineError::io("read checkpoint", path))?;
+
+    let mut rest = bytes.as_slice();
+    let mut checkpoint = Checkpoint::default();
+    while !rest.is_empty() {
+        let mut cursor = rest;
+        match CheckpointRecord::decode_length_delimited(&mut cursor) {
+            Ok(record) => {
+                checkpoint.frames.push(record.frame.unwrap_or_default());
+                checkpoint.custom_events.extend(record.custom_events);
+                rest = cursor;
+            }
+            Err(e) => {
+                warn!(?path, %e, dropped_bytes = rest.len(), "dropping incomplete checkpoint record");
+                break;
+            }
+        }
+    }
+
+    let valid_len = (bytes.len() - rest.len()) as u64;
+    if !rest.is_empty() {
+        let file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(PipelineError::io("open checkpoint", path))?;
+        file.set_len(valid_len)
+            .map_err(PipelineError::io("truncate checkpoint", path))?;
+    }
+
+    info!(
+        ?path,
+        frames = checkpoint.frames.len(),
+        custom_events = checkpoint.custom_events.len(),
+        last_frame = ?checkpoint.frames.last().map(|fd| fd.frame_number),
+        "checkpoint loaded"
+    );
+    Ok(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::frame;
+
+    #[test]
+    fn resume_appends_after_truncated_record() {
+        let dir = std::env::temp_dir().join(format!("recmari-checkpoint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("run.checkpoint");
+
+        let event = CustomEvent {
+            analyzer: "corner".into(),
+            name: "white".into(),
+            frame_number: 120,
+            ..Default::default()
+        };
+        let mut writer = CheckpointWriter::open(&path, false).unwrap();
+        writer.append(&frame(60), &[]).unwrap();
+        writer
+            .append(&frame(120), std::slice::from_ref(&event))
+            .unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        // Simulate a crash in the middle of writing the third record.
+        let mut partial = Vec::new();
+        CheckpointRecord {
+            frame: Some(frame(180)),
+            custom_events: Vec::new(),
+        }
+        .encode_length_delimited(&mut partial)
+        .unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&partial[..partial.len() - 1]).unwrap();
+        drop(file);
+
+        let checkpoint = load_checkpoint(&path).unwrap();
+        assert_eq!(checkpoint.frames, vec![frame(60), frame(120)]);
+        assert_eq!(checkpoint.custom_events, vec![event.clone()]);
+
+        let mut writer = CheckpointWriter::open(&path, true).unwrap();
+        writer.append(&frame(180), &[]).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        assert_eq!(
+            load_checkpoint(&path).unwrap(),
+            Checkpoint {
+                frames: vec![frame(60), frame(120), frame(180)],
+                custom_events: vec![event],
+            }
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
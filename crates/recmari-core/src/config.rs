@@ -0,0 +1,283 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use recmari_proto::proto::HudElement;
+
+use crate::analysis::common::{HsvRange, Scanline};
+use crate::analysis::HudType;
+use crate::error::{HudError, PipelineError};
+use crate::rect::PixelRect;
+
+/// Coordinates in HUD profiles are given at this resolution and scaled to the video.
+const PROFILE_REF_WIDTH: u32 = 1920;
+const PROFILE_REF_HEIGHT: u32 = 1080;
+
+/// User-tunable HUD settings loaded from a TOML file (`--hud-config`).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HudConfig {
+    pub elements: HudElements,
+    /// Overrides for the battle HUD.
+    pub manemon: HudProfile,
+}
+
+impl HudConfig {
+    pub fn profile(&self, hud_type: HudType) -> &HudProfile {
+        match hud_type {
+            HudType::Manemon => &self.manemon,
+        }
+    }
+}
+
+/// Per-HUD-type overrides of built-in layout and colors, for capture setups whose
+/// colors or scaling differ. Unset values keep the built-in ones. Coordinates are at
+/// 1920x1080. The OD gauge layout is not configurable yet.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HudProfile {
+    pub scanlines: ScanlineOverrides,
+    /// P1's SA stock digit probes `[x, y]`, in cascade order; P2's are mirrored.
+    pub sa_digit_probes: Option<[[u32; 2]; 4]>,
+    pub colors: ColorOverrides,
+    /// Areas covered by stream overlays (scoreboards, cameras), `{ x, y, w, h }`.
+    /// Masked pixels are never read: a gauge they touch is unreadable, except that an
+    /// HP bar is still read up to the first masked pixel.
+    #[serde(rename = "mask")]
+    pub masks: Vec<PixelRect>,
+}
+
+/// Gauge scanlines, each `{ x_start, x_end, y }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanlineOverrides {
+    pub hp_p1: Option<Scanline>,
+    pub hp_p2: Option<Scanline>,
+    pub sa_p1: Option<Scanline>,
+    pub sa_p2: Option<Scanline>,
+}
+
+impl ScanlineOverrides {
+    fn all(&self) -> [(&'static str, Option<Scanline>); 4] {
+        [
+            ("hp_p1", self.hp_p1),
+            ("hp_p2", self.hp_p2),
+            ("sa_p1", self.sa_p1),
+            ("sa_p2", self.sa_p2),
+        ]
+    }
+
+    /// Rows covered by the overridden scanlines.
+    pub fn rows(&self) -> Vec<u32> {
+        self.all()
+            .into_iter()
+            .filter_map(|(_, scan)| scan.map(|s| s.y))
+            .collect()
+    }
+}
+
+/// Color ranges, each `{ h = [min, max], s = [min, max], v = [min, max] }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorOverrides {
+    /// HP bar fill at normal health (yellow).
+    pub hp_fill: Option<HsvRange>,
+    /// HP bar fill at low health (orange).
+    pub hp_low_fill: Option<HsvRange>,
+}
+
+impl HudProfile {
+    fn validate(&self, name: &str) -> Result<(), HudError> {
+        for (key, scan) in self.scanlines.all() {
+            let Some(scan) = scan else { continue };
+            let max_x = scan.x_start.max(scan.x_end);
+            // HP reads two rows above and below its scanline.
+            if scan.width() == 0
+                || max_x > PROFILE_REF_WIDTH
+                || scan.y < 2
+                || scan.y + 2 >= PROFILE_REF_HEIGHT
+            {
+                error!(profile = name, key, ?scan, "scanline outside 1920x1080");
+                return Err(HudError::InvalidConfig(format!(
+                    "[{name}.scanlines] {key} is empty or outside 1920x1080"
+                )));
+            }
+        }
+        for [x, y] in self.sa_digit_probes.iter().flatten() {
+            if *x >= PROFILE_REF_WIDTH || *y >= PROFILE_REF_HEIGHT {
+                error!(profile = name, x, y, "SA digit probe outside 1920x1080");
+                return Err(HudError::InvalidConfig(format!(
+                    "[{name}] sa_digit_probes ({x}, {y}) is outside 1920x1080"
+                )));
+            }
+        }
+        for mask in &self.masks {
+            if mask.w == 0
+                || mask.h == 0
+                || mask.x + mask.w > PROFILE_REF_WIDTH
+                || mask.y + mask.h > PROFILE_REF_HEIGHT
+            {
+                error!(profile = name, ?mask, "mask outside 1920x1080");
+                return Err(HudError::InvalidConfig(format!(
+                    "[[{name}.mask]] {mask:?} is empty or outside 1920x1080"
+                )));
+            }
+        }
+        let colors = [
+            ("hp_fill", self.colors.hp_fill),
+            ("hp_low_fill", self.colors.hp_low_fill),
+        ];
+        for (key, range) in colors {
+            if range.is_some_and(|r| !r.is_valid()) {
+                error!(profile = name, key, ?range, "color range with min > max");
+                return Err(HudError::InvalidConfig(format!(
+                    "[{name}.colors] {key} has min > max"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which HUD elements the capture shows. Custom overlays may hide some of them;
+/// disabled elements are not read and are recorded as missing in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HudElements {
+    pub hp: bool,
+    pub sa: bool,
+    pub od: bool,
+}
+
+impl Default for HudElements {
+    fn default() -> Self {
+        Self {
+            hp: true,
+            sa: true,
+            od: true,
+        }
+    }
+}
+
+impl HudElements {
+    /// Elements that are disabled, in proto form.
+    pub fn disabled(&self) -> Vec<HudElement> {
+        [
+            (self.hp, HudElement::Hp),
+            (self.sa, HudElement::Sa),
+            (self.od, HudElement::Od),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| !enabled)
+        .map(|(_, element)| element)
+        .collect()
+    }
+}
+
+/// Load a HUD config file. See [`parse_hud_config`].
+pub fn load_hud_config(path: &Path) -> Result<HudConfig, PipelineError> {
+    info!(?path, "loading HUD config");
+    let text = std::fs::read_to_string(path).map_err(PipelineError::io("read HUD config", path))?;
+    let config = parse_hud_config(&text).map_err(|e| match e {
+        HudError::InvalidConfig(message) => {
+            HudError::InvalidConfig(format!("{}: {message}", path.display()))
+        }
+        e => e,
+    })?;
+    info!(?path, ?config, "HUD config loaded");
+    Ok(config)
+}
+
+/// Parse a HUD config. Every table and key is optional:
+///
+/// ```toml
+/// [elements]
+/// od = false  # the overlay hides the Drive gauges
+///
+/// [manemon.scanlines]
+/// hp_p1 = { x_start = 886, x_end = 187, y = 81 }
+///
+/// [manemon.colors]
+/// hp_fill = { h = [45.0, 66.0], s = [0.3, 1.0], v = [0.85, 1.0] }
+///
+/// [[manemon.mask]]  # tournament scoreboard over the P1 HP bar's outer end
+/// x = 0
+/// y = 50
+/// w = 320
+/// h = 60
+/// ```
+pub fn parse_hud_config(text: &str) -> Result<HudConfig, HudError> {
+    let config: HudConfig = match toml::from_str(text) {
+        Ok(config) => config,
+        Err(e) => {
+            error!(%e, "failed to parse HUD config");
+            return Err(HudError::InvalidConfig(e.to_string()));
+        }
+    };
+    config.manemon.validate("manemon")?;
+    if !config.elements.hp {
+        warn!("HP bars disabled: rounds and matches cannot be segmented");
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_enables_everything() {
+        let config = parse_hud_config("").unwrap();
+        assert_eq!(config, HudConfig::default());
+        assert!(config.elements.disabled().is_empty());
+    }
+
+    #[test]
+    fn elements_can_be_disabled() {
+        let config = parse_hud_config("[elements]\nod = false\n").unwrap();
+        assert!(config.elements.hp && config.elements.sa);
+        assert_eq!(config.elements.disabled(), vec![HudElement::Od]);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(parse_hud_config("[elements]\nstun = false\n").is_err());
+    }
+
+    #[test]
+    fn profiles_override_per_hud_type() {
+        assert_eq!(
+            HudConfig::default().profile(HudType::Manemon),
+            &HudProfile::default()
+        );
+        let text = "[manemon.scanlines]\n\
+                    hp_p1 = { x_start = 880, x_end = 190, y = 82 }\n\
+                    [manemon.colors]\n\
+                    hp_fill = { h = [45.0, 66.0], s = [0.3, 1.0], v = [0.85, 1.0] }\n";
+        let config = parse_hud_config(text).unwrap();
+        let manemon = config.profile(HudType::Manemon);
+        assert_eq!(manemon.scanlines.rows(), vec![82]);
+        assert_eq!(manemon.colors.hp_fill.unwrap().v, [0.85, 1.0]);
+    }
+
+    #[test]
+    fn invalid_profiles_are_rejected() {
+        let outside = "[manemon.scanlines]\nsa_p1 = { x_start = 10, x_end = 2000, y = 5 }\n";
+        assert!(parse_hud_config(outside).is_err());
+        let inverted =
+            "[manemon.colors]\nhp_fill = { h = [66.0, 45.0], s = [0.3, 1.0], v = [0.9, 1.0] }\n";
+        assert!(parse_hud_config(inverted).is_err());
+        let mask = "[[manemon.mask]]\nx = 1800\ny = 0\nw = 200\nh = 40\n";
+        assert!(parse_hud_config(mask).is_err());
+    }
+
+    #[test]
+    fn masks_are_listed_per_profile() {
+        let text = "[[manemon.mask]]\nx = 0\ny = 50\nw = 320\nh = 60\n\
+                    [[manemon.mask]]\nx = 1600\ny = 900\nw = 320\nh = 180\n";
+        let config = parse_hud_config(text).unwrap();
+        assert_eq!(config.manemon.masks.len(), 2);
+        assert_eq!(config.manemon.masks[1].y, 900);
+    }
+}
@@ -0,0 +1,193 @@
+use tracing::info;
+
+use recmari_proto::proto::{DamageEvent, DamageTotals, FrameData, Match};
+
+use crate::players::player_state;
+
+/// Health changes smaller than this are reading noise, not damage or recovery.
+pub(crate) const DAMAGE_MIN_CHANGE: f64 = 0.005;
+
+/// Health changes of one player (1 or 2) over a round's frames.
+///
+/// Each reading is compared with the last accepted health level rather than the previous
+/// frame, so a bar draining over several frames still adds up. Drops are damage; rises
+/// recover gray health up to the damage not yet recovered. Frames without the player's
+/// health read are skipped.
+pub fn damage_events(
+    frames: &[FrameData],
+    player: u32,
+    max_health: Option<u32>,
+) -> Vec<DamageEvent> {
+    assert!(player == 1 || player == 2, "invalid player {player}");
+    let mut events = Vec::new();
+    let mut level: Option<f64> = None;
+    let mut recoverable = 0.0;
+    for fd in frames {
+        let Some(hp) = player_state(fd, player).and_then(|p| p.health_ratio) else {
+            continue;
+        };
+        let Some(prev) = level else {
+            level = Some(hp);
+            continue;
+        };
+        let change = prev - hp;
+        if change.abs() < DAMAGE_MIN_CHANGE {
+            continue;
+        }
+        level = Some(hp);
+        let amount = if change > 0.0 {
+            recoverable += change;
+            change
+        } else {
+            let recovered = (-change).min(recoverable);
+            recoverable -= recovered;
+            -recovered
+        };
+        if amount.abs() < DAMAGE_MIN_CHANGE {
+            continue;
+        }
+        events.push(DamageEvent {
+            frame_number: fd.frame_number,
+            timestamp_seconds: fd.timestamp_seconds,
+            player,
+            amount,
+            absolute_amount: max_health.map(|max| amount * max as f64),
+        });
+    }
+    events
+}
+
+/// Damage events of one player (1 or 2) that are hits, i.e. health drops rather than
+/// gray health recovery. Every "was the player hit" question goes through this, so
+/// statistics agree with the round's damage events.
+pub(crate) fn hits(frames: &[FrameData], player: u32) -> Vec<DamageEvent> {
+    let mut events = damage_events(frames, player, None);
+    events.retain(|e| e.amount > 0.0);
+    events
+}
+
+/// Received and recovered damage of one player from their own `events`.
+fn received(events: &[DamageEvent], player: u32) -> (f64, f64) {
+    let mut taken = 0.0;
+    let mut recovered = 0.0;
+    for e in events.iter().filter(|e| e.player == player) {
+        if e.amount > 0.0 {
+            taken += e.amount;
+        } else {
+            recovered -= e.amount;
+        }
+    }
+    (taken - recovered, recovered)
+}
+
+/// Per-round damage totals of both players from the round's `events`.
+pub fn damage_totals(events: &[DamageEvent]) -> [DamageTotals; 2] {
+    let (p1_received, p1_recovered) = received(events, 1);
+    let (p2_received, p2_recovered) = received(events, 2);
+    [
+        DamageTotals {
+            dealt: p2_received,
+            received: p1_received,
+            recovered: p1_recovered,
+        },
+        DamageTotals {
+            dealt: p1_received,
+            received: p2_received,
+            recovered: p2_recovered,
+        },
+    ]
+}
+
+/// Fill in the damage events and totals of every round. Absolute amounts are added for
+/// sides with a known maximum health.
+pub fn attach_damage(matches: &mut [Match]) {
+    let mut events = 0usize;
+    for m in matches.iter_mut() {
+        let source = m.source.as_ref();
+        let p1_max = source.and_then(|s| s.player1_max_health);
+        let p2_max = source.and_then(|s| s.player2_max_health);
+        for round in &mut m.rounds {
+            let mut round_events = damage_events(&round.frames, 1, p1_max);
+            round_events.extend(damage_events(&round.frames, 2, p2_max));
+            round_events.sort_by(|a, b| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+            let [p1, p2] = damage_totals(&round_events);
+            round.player1_damage = Some(p1);
+            round.player2_damage = Some(p2);
+            events += round_events.len();
+            round.damage_events = round_events;
+        }
+    }
+    info!(events, "damage events computed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{frame_at, health, hp_frame};
+
+    fn amounts(events: &[DamageEvent]) -> Vec<f64> {
+        events
+            .iter()
+            .map(|e| (e.amount * 1000.0).round() / 1000.0)
+            .collect()
+    }
+
+    #[test]
+    fn drops_are_damage_and_rises_recover_gray_health() {
+        let frames = [
+            (0.0, Some(1.0)),
+            (1.0, Some(0.9)), // chip damage, gray
+            (2.0, None),
+            (3.0, Some(0.95)), // gray health recovered
+            (4.0, Some(0.7)),
+            (5.0, Some(1.0)), // more than was lost: only 0.3 is recoverable
+        ]
+        .map(|(ts, hp)| FrameData {
+            player1: Some(health(hp)),
+            ..frame_at(ts)
+        });
+        let events = damage_events(&frames, 1, Some(10000));
+        assert_eq!(amounts(&events), vec![0.1, -0.05, 0.25, -0.3]);
+        assert_eq!(events[0].timestamp_seconds, 1.0);
+        assert_eq!(events[0].frame_number, 60);
+        assert!((events[0].absolute_amount.unwrap() - 1000.0).abs() < 1e-6);
+
+        let [p1, p2] = damage_totals(&events);
+        assert!((p1.received - 0.0).abs() < 1e-9);
+        assert!((p1.recovered - 0.35).abs() < 1e-9);
+        assert!((p2.dealt - p1.received).abs() < 1e-9);
+        assert_eq!(p2.received, 0.0);
+    }
+
+    #[test]
+    fn slow_drain_adds_up_and_noise_is_ignored() {
+        let frames = [
+            (0.0, Some(1.0)),
+            (0.1, Some(0.997)),
+            (0.2, Some(0.994)),
+            (0.3, Some(0.991)),
+            (0.4, Some(0.994)), // noise after a drop: recovers nothing real
+            (0.5, Some(0.991)),
+        ]
+        .map(|(ts, hp)| FrameData {
+            player1: Some(health(hp)),
+            ..frame_at(ts)
+        });
+        let events = damage_events(&frames, 1, None);
+        assert_eq!(amounts(&events), vec![0.006]);
+        assert_eq!(events[0].absolute_amount, None);
+        assert!(damage_events(&frames, 2, None).is_empty());
+    }
+
+    #[test]
+    fn totals_attribute_damage_to_the_opponent() {
+        let frames = [hp_frame(0.0, 1.0, 1.0), hp_frame(1.0, 0.6, 0.8)];
+        let mut events = damage_events(&frames, 1, None);
+        events.extend(damage_events(&frames, 2, None));
+        let [p1, p2] = damage_totals(&events);
+        assert!((p1.received - 0.4).abs() < 1e-9);
+        assert!((p1.dealt - 0.2).abs() < 1e-9);
+        assert!((p2.received - 0.2).abs() < 1e-9);
+        assert!((p2.dealt - 0.4).abs() < 1e-9);
+    }
+}
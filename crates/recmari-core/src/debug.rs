@@ -1,34 +1,66 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use ab_glyph::{FontVec, PxScale};
-use anyhow::{Context, Result};
 use image::{Rgb, RgbImage};
-use imageproc::drawing::{draw_hollow_rect_mut, draw_line_segment_mut, draw_text_mut};
+use imageproc::drawing::{
+    draw_filled_rect_mut, draw_hollow_rect_mut, draw_line_segment_mut, draw_text_mut, text_size,
+};
 use imageproc::rect::Rect;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
-use recmari_proto::proto::FrameData;
+use recmari_proto::proto::{FrameData, PlayerState};
 
 use crate::analysis::Hud;
+use crate::error::PipelineError;
+use crate::video::encoder::VideoEncoder;
 use crate::video::frame::Frame;
 
-const FONT_PATH: &str = "C:\\Windows\\Fonts\\consola.ttf";
+/// DejaVu Sans Mono (Bitstream Vera license, see `assets/fonts/LICENSE-DejaVu.txt`).
+const BUNDLED_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
 
 const TEXT_SCALE: f32 = 28.0;
 const TEXT_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
 const TEXT_LINE_HEIGHT: i32 = 30;
+const BACKDROP_COLOR: Rgb<u8> = Rgb([0, 0, 0]);
+/// Top of the annotation block as a fraction of the frame height, below the HUD bars.
+const ANNOTATION_TOP: f64 = 0.2;
 
 /// Renders debug overlay images with HUD region markers and analysis text.
 pub struct DebugRenderer {
-    font: Option<FontVec>,
+    font: FontVec,
+}
+
+impl Default for DebugRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DebugRenderer {
+    /// A renderer using the bundled font.
     pub fn new() -> Self {
-        let font = Self::load_font();
+        let font =
+            FontVec::try_from_vec(BUNDLED_FONT.to_vec()).expect("bundled debug font is valid");
         Self { font }
     }
 
+    /// A renderer using the TrueType/OpenType font at `path`.
+    pub fn with_font(path: &Path) -> Result<Self, PipelineError> {
+        let data = std::fs::read(path).map_err(PipelineError::io("read font file", path))?;
+        let font = match FontVec::try_from_vec(data) {
+            Ok(font) => font,
+            Err(e) => {
+                error!(?path, error = %e, "failed to parse font file");
+                return Err(PipelineError::Parse(format!(
+                    "failed to parse font file {}: {e}",
+                    path.display()
+                )));
+            }
+        };
+        info!(?path, "loaded debug font");
+        Ok(Self { font })
+    }
+
     pub fn save_frame(
         &self,
         frame: &Frame,
@@ -36,7 +68,29 @@ impl DebugRenderer {
         data: Option<&FrameData>,
         center_x: Option<u32>,
         dir: &Path,
-    ) -> Result<()> {
+    ) -> Result<(), PipelineError> {
+        let img = self.render(frame, hud, data, center_x);
+        let path = dir.join(format!("frame_{:08}.png", frame.frame_number));
+        if let Err(source) = img.save(&path) {
+            return Err(PipelineError::Image {
+                action: "save debug frame",
+                path,
+                source,
+            });
+        }
+
+        debug!(?path, "saved debug frame");
+        Ok(())
+    }
+
+    /// Draw HUD regions, the center line and analysis text over a copy of the frame.
+    pub fn render(
+        &self,
+        frame: &Frame,
+        hud: &dyn Hud,
+        data: Option<&FrameData>,
+        center_x: Option<u32>,
+    ) -> RgbImage {
         let mut img = frame.image.clone();
 
         for region in hud.debug_regions() {
@@ -54,13 +108,31 @@ impl DebugRenderer {
         }
 
         self.draw_text_overlay(&mut img, frame, hud, data, center_x);
+        img
+    }
 
-        let path = dir.join(format!("frame_{:08}.png", frame.frame_number));
-        img.save(&path)
-            .with_context(|| format!("failed to save debug frame to {}", path.display()))?;
-
-        debug!(?path, "saved debug frame");
-        Ok(())
+    /// Draw the HUD's regions, probe points and gauge segment boundaries over a copy of
+    /// `image`, labelled with the HUD type. Needs no analysis, so coordinates can be
+    /// checked on captures the HUD is not detected in yet.
+    pub fn render_regions(&self, image: &RgbImage, hud: &dyn Hud) -> RgbImage {
+        let mut img = image.clone();
+        for region in hud.debug_regions().into_iter().chain(hud.probe_regions()) {
+            let rect = Rect::at(region.rect.x as i32, region.rect.y as i32)
+                .of_size(region.rect.w, region.rect.h);
+            draw_hollow_rect_mut(&mut img, rect, region.color);
+        }
+        let label = format!("HUD:{}", hud.hud_type());
+        let y = (img.height() as f64 * ANNOTATION_TOP) as i32;
+        draw_text_mut(
+            &mut img,
+            TEXT_COLOR,
+            10,
+            y,
+            PxScale::from(TEXT_SCALE),
+            &self.font,
+            &label,
+        );
+        img
     }
 
     fn draw_text_overlay(
@@ -71,7 +143,7 @@ impl DebugRenderer {
         data: Option<&FrameData>,
         center_x: Option<u32>,
     ) {
-        let Some(font) = &self.font else { return };
+        let font = &self.font;
         let scale = PxScale::from(TEXT_SCALE);
         let x = 10;
         let mut y = 10;
@@ -91,67 +163,158 @@ impl DebugRenderer {
         let p1 = fd.player1.as_ref().unwrap();
         let p2 = fd.player2.as_ref().unwrap();
 
-        let p1_hp_text = match p1.health_ratio {
-            Some(hp) => format!("P1 HP:{:.0}%", hp * 100.0),
-            None => "P1 HP:--".to_string(),
-        };
-        draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &p1_hp_text);
-        y += TEXT_LINE_HEIGHT;
-
-        if let Some(sa) = p1.sa_gauge {
-            let sa_text = format!("P1 SA:{:.2}", truncate_decimal(sa, 2));
-            draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &sa_text);
+        for line in player_lines("P1", p1).iter().chain(&player_lines("P2", p2)) {
+            draw_text_mut(img, TEXT_COLOR, x, y, scale, font, line);
             y += TEXT_LINE_HEIGHT;
         }
 
-        let p1_od_text = format_od_text("P1", p1.od_gauge, p1.burnout_gauge);
-        draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &p1_od_text);
-        y += TEXT_LINE_HEIGHT;
+        if let Some(cx) = center_x {
+            let center_text = format!("CTR:{cx}");
+            draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &center_text);
+        }
+    }
 
-        let p2_hp_text = match p2.health_ratio {
-            Some(hp) => format!("P2 HP:{:.0}%", hp * 100.0),
-            None => "P2 HP:--".to_string(),
-        };
-        draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &p2_hp_text);
-        y += TEXT_LINE_HEIGHT;
+    /// Draw the analyzed values of `data` and the `captions` over a copy of the frame,
+    /// on a dark backdrop so they stay readable in shared clips. Unlike [`Self::render`]
+    /// this needs no HUD and draws no regions.
+    pub fn annotate(
+        &self,
+        frame: &Frame,
+        data: Option<&FrameData>,
+        captions: &[String],
+    ) -> RgbImage {
+        let mut img = frame.image.clone();
+        let mut lines = Vec::new();
+        if let Some(fd) = data {
+            for (label, player) in [("P1", &fd.player1), ("P2", &fd.player2)] {
+                if let Some(p) = player {
+                    lines.extend(player_lines(label, p));
+                }
+            }
+        }
+        lines.extend(captions.iter().cloned());
+        if lines.is_empty() {
+            return img;
+        }
 
-        if let Some(sa) = p2.sa_gauge {
-            let sa_text = format!("P2 SA:{:.2}", truncate_decimal(sa, 2));
-            draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &sa_text);
+        let scale = PxScale::from(TEXT_SCALE);
+        let text_width = lines
+            .iter()
+            .map(|line| text_size(scale, &self.font, line).0)
+            .max()
+            .unwrap_or(0);
+        let x = 10;
+        let mut y = (img.height() as f64 * ANNOTATION_TOP) as i32;
+        let backdrop = Rect::at(0, y - 5).of_size(
+            text_width + 20,
+            lines.len() as u32 * TEXT_LINE_HEIGHT as u32 + 10,
+        );
+        draw_filled_rect_mut(&mut img, backdrop, BACKDROP_COLOR);
+        for line in &lines {
+            draw_text_mut(&mut img, TEXT_COLOR, x, y, scale, &self.font, line);
             y += TEXT_LINE_HEIGHT;
         }
+        img
+    }
+}
 
-        let p2_od_text = format_od_text("P2", p2.od_gauge, p2.burnout_gauge);
-        draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &p2_od_text);
-        y += TEXT_LINE_HEIGHT;
+/// Where annotated debug frames go.
+pub enum DebugSink {
+    /// One PNG per frame in a directory.
+    Frames(PathBuf),
+    /// A single video file; the encoder starts with the first frame's size.
+    Video {
+        path: PathBuf,
+        fps: f64,
+        encoder: Option<VideoEncoder>,
+    },
+}
 
-        if let Some(cx) = center_x {
-            let center_text = format!("CTR:{cx}");
-            draw_text_mut(img, TEXT_COLOR, x, y, scale, font, &center_text);
+/// Renders debug overlays and writes them to a [`DebugSink`].
+pub struct DebugOutput {
+    renderer: DebugRenderer,
+    sink: DebugSink,
+}
+
+impl DebugOutput {
+    /// PNG frames in `dir`, created if missing.
+    pub fn frames(dir: &Path, renderer: DebugRenderer) -> Result<Self, PipelineError> {
+        std::fs::create_dir_all(dir).map_err(PipelineError::io("create", dir))?;
+        info!(?dir, "debug frames directory ready");
+        Ok(Self {
+            renderer,
+            sink: DebugSink::Frames(dir.to_path_buf()),
+        })
+    }
+
+    /// A video at `path` played back at `fps`.
+    pub fn video(path: &Path, fps: f64, renderer: DebugRenderer) -> Self {
+        info!(?path, fps, "debug video enabled");
+        Self {
+            renderer,
+            sink: DebugSink::Video {
+                path: path.to_path_buf(),
+                fps,
+                encoder: None,
+            },
         }
     }
 
-    fn load_font() -> Option<FontVec> {
-        let data = match std::fs::read(FONT_PATH) {
-            Ok(data) => data,
-            Err(e) => {
-                warn!(path = FONT_PATH, error = %e, "failed to read font file");
-                return None;
-            }
-        };
-        match FontVec::try_from_vec(data) {
-            Ok(font) => {
-                info!(path = FONT_PATH, "loaded debug font");
-                Some(font)
+    pub fn write(
+        &mut self,
+        frame: &Frame,
+        hud: &dyn Hud,
+        data: Option<&FrameData>,
+        center_x: Option<u32>,
+    ) -> Result<(), PipelineError> {
+        match &mut self.sink {
+            DebugSink::Frames(dir) => self.renderer.save_frame(frame, hud, data, center_x, dir),
+            DebugSink::Video { path, fps, encoder } => {
+                let img = self.renderer.render(frame, hud, data, center_x);
+                let encoder = match encoder {
+                    Some(encoder) => encoder,
+                    None => {
+                        encoder.insert(VideoEncoder::create(path, img.width(), img.height(), *fps)?)
+                    }
+                };
+                Ok(encoder.write_frame(&img)?)
             }
-            Err(e) => {
-                warn!(path = FONT_PATH, error = %e, "failed to parse font file");
-                None
+        }
+    }
+
+    /// Flush the video, if any. PNG frames are already on disk.
+    pub fn finish(self) -> Result<(), PipelineError> {
+        match self.sink {
+            DebugSink::Video {
+                encoder: Some(encoder),
+                ..
+            } => Ok(encoder.finish()?),
+            DebugSink::Video { path, .. } => {
+                warn!(?path, "no frames for the debug video");
+                Ok(())
             }
+            DebugSink::Frames(_) => Ok(()),
         }
     }
 }
 
+/// HP, SA and OD lines of one player, e.g. `P1 HP:52%`.
+fn player_lines(label: &str, p: &PlayerState) -> Vec<String> {
+    let mut lines = vec![match p.health_ratio {
+        Some(hp) => format!("{label} HP:{:.0}%", hp * 100.0),
+        None => format!("{label} HP:--"),
+    }];
+    if let Some(sa) = p.sa_gauge {
+        lines.push(if p.ca_ready == Some(true) {
+            format!("{label} SA:CA")
+        } else {
+            format!("{label} SA:{:.2}", truncate_decimal(sa, 2))
+        });
+    }
+    lines.push(format_od_text(label, p.od_gauge, p.burnout_gauge));
+    lines
+}
+
 /// Format OD gauge text: shows burnout if active, otherwise normal OD value.
 fn format_od_text(player: &str, od_gauge: Option<f64>, burnout_gauge: Option<f64>) -> String {
     if let Some(bo) = burnout_gauge {
@@ -168,3 +331,45 @@ fn truncate_decimal(value: f64, decimals: u32) -> f64 {
     let factor = 10f64.powi(decimals as i32);
     (value * factor).floor() / factor
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::huds::manemon::ManemonHud;
+
+    #[test]
+    fn bundled_font_draws_overlay_text() {
+        let frame = Frame {
+            image: RgbImage::new(1920, 1080),
+            frame_number: 42,
+            timestamp_seconds: 0.7,
+        };
+        let hud = ManemonHud::new(1920, 1080);
+        let img = DebugRenderer::new().render(&frame, &hud, None, None);
+        let header_has_text = (10..10 + TEXT_LINE_HEIGHT as u32)
+            .any(|y| (10..120).any(|x| *img.get_pixel(x, y) == TEXT_COLOR));
+        assert!(header_has_text);
+    }
+
+    #[test]
+    fn regions_include_probes_and_od_segments() {
+        let hud = ManemonHud::new(1920, 1080);
+        let probes = hud.probe_regions();
+        // 4 SA digit probes and 6 OD segments per player.
+        assert_eq!(probes.len(), 2 * (4 + 6));
+
+        let img = DebugRenderer::new().render_regions(&RgbImage::new(1920, 1080), &hud);
+        for region in hud.debug_regions().iter().chain(&probes) {
+            let corner = img.get_pixel(region.rect.x, region.rect.y);
+            assert_ne!(*corner, Rgb([0, 0, 0]), "{:?} not drawn", region.rect);
+        }
+    }
+
+    #[test]
+    fn invalid_font_file_is_rejected() {
+        let path = std::env::temp_dir().join("recmari_debug_font_test.ttf");
+        std::fs::write(&path, b"not a font").unwrap();
+        assert!(DebugRenderer::with_font(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
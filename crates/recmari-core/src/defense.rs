@@ -0,0 +1,142 @@
+use tracing::info;
+
+use recmari_proto::proto::{FrameData, Match, PressureInterval};
+
+use crate::damage;
+use crate::players::player_state;
+
+/// A Drive gauge drop smaller than this between readings is reading noise.
+const DRAIN_MIN_DROP: f64 = 0.1;
+/// Readings further apart than this end the current interval and are not compared.
+const PRESSURE_MAX_GAP_SECONDS: f64 = 2.0;
+/// Intervals losing less Drive than this (about one chip-guarded hit) are dropped.
+const PRESSURE_MIN_DRIVE_LOST: f64 = 0.5;
+
+/// Stretches of a round where the Drive gauge of `player` (1 or 2) drained without
+/// health loss.
+///
+/// Burnout readings and frames without the Drive gauge read are skipped; a hit (see
+/// [`damage::hits`]) or readings more than [`PRESSURE_MAX_GAP_SECONDS`] apart end the
+/// current interval.
+pub fn pressure_intervals(frames: &[FrameData], player: u32) -> Vec<PressureInterval> {
+    assert!(player == 1 || player == 2, "invalid player {player}");
+    let hit_frames: Vec<u32> = damage::hits(frames, player)
+        .iter()
+        .map(|e| e.frame_number)
+        .collect();
+    let mut intervals = Vec::new();
+    let mut current: Option<PressureInterval> = None;
+    let mut last: Option<(u32, f64, f64)> = None;
+
+    for fd in frames {
+        let Some(od) = player_state(fd, player)
+            .filter(|p| p.burnout_gauge.is_none())
+            .and_then(|p| p.od_gauge)
+        else {
+            continue;
+        };
+        let ts = fd.timestamp_seconds;
+        if let Some((prev_frame, prev_ts, prev_od)) = last {
+            let hit = hit_frames
+                .iter()
+                .any(|&n| n > prev_frame && n <= fd.frame_number);
+            let gap = ts - prev_ts > PRESSURE_MAX_GAP_SECONDS;
+            let drained = prev_od - od >= DRAIN_MIN_DROP;
+            if hit || gap {
+                intervals.extend(current.take());
+            }
+            if drained && !hit && !gap {
+                let interval = current.get_or_insert(PressureInterval {
+                    start_seconds: prev_ts,
+                    end_seconds: prev_ts,
+                    drive_lost: 0.0,
+                });
+                interval.end_seconds = ts;
+                interval.drive_lost += prev_od - od;
+            }
+        }
+        last = Some((fd.frame_number, ts, od));
+    }
+    intervals.extend(current);
+    intervals.retain(|i| i.drive_lost >= PRESSURE_MIN_DRIVE_LOST);
+    intervals
+}
+
+/// Seconds covered by `intervals`, a rough measure of defensive load.
+pub fn pressure_seconds(intervals: &[PressureInterval]) -> f64 {
+    intervals
+        .iter()
+        .map(|i| i.end_seconds - i.start_seconds)
+        .sum()
+}
+
+/// Fill in both players' pressure intervals of every round.
+pub fn attach_pressure(matches: &mut [Match]) {
+    let mut intervals = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        round.player1_pressure = pressure_intervals(&round.frames, 1);
+        round.player2_pressure = pressure_intervals(&round.frames, 2);
+        intervals += round.player1_pressure.len() + round.player2_pressure.len();
+    }
+    info!(intervals, "pressure intervals computed");
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::PlayerState;
+
+    use super::*;
+    use crate::test_support::{drive, frame_at};
+
+    #[test]
+    fn drain_without_damage_is_pressure() {
+        let frames = [
+            (0.0, Some(6.0), 1.0),
+            (1.0, Some(5.5), 1.0), // blocking
+            (2.0, Some(5.0), 1.0),
+            (3.0, None, 1.0),
+            (4.0, Some(4.4), 1.0),
+            (5.0, Some(4.0), 0.8), // hit: ends the interval
+            (6.0, Some(3.8), 0.8),
+            (10.0, Some(3.8), 0.8),
+            (11.0, Some(3.7), 0.8), // small drain only
+            (20.0, Some(3.7), 0.8),
+        ]
+        .map(|(ts, od, hp)| FrameData {
+            player1: Some(PlayerState {
+                health_ratio: Some(hp),
+                ..drive(od, None)
+            }),
+            ..frame_at(ts)
+        });
+        let intervals = pressure_intervals(&frames, 1);
+        assert_eq!(intervals.len(), 1);
+        let i = &intervals[0];
+        assert_eq!((i.start_seconds, i.end_seconds), (0.0, 4.0));
+        assert!((i.drive_lost - 1.6).abs() < 1e-9);
+        assert_eq!(pressure_seconds(&intervals), 4.0);
+    }
+
+    #[test]
+    fn long_gap_splits_intervals() {
+        let frames = [
+            (0.0, Some(6.0), 1.0),
+            (1.0, Some(5.0), 1.0),
+            (2.0, Some(5.0), 1.0),
+            (9.0, Some(4.5), 1.0),
+            (10.0, Some(3.5), 1.0),
+        ]
+        .map(|(ts, od, hp)| FrameData {
+            player1: Some(PlayerState {
+                health_ratio: Some(hp),
+                ..drive(od, None)
+            }),
+            ..frame_at(ts)
+        });
+        let spans: Vec<(f64, f64)> = pressure_intervals(&frames, 1)
+            .iter()
+            .map(|i| (i.start_seconds, i.end_seconds))
+            .collect();
+        assert_eq!(spans, vec![(0.0, 1.0), (9.0, 10.0)]);
+    }
+}
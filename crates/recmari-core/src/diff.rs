@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use tracing::info;
+
+use crate::error::PipelineError;
+use recmari_proto::proto::{FrameData, Match, PlayerState};
+
+/// One reading of a frame; None when unread.
+type Reading = fn(&FrameData) -> Option<f64>;
+
+/// Readings compared by [`diff_matches`], with their labels.
+const READINGS: [(&str, Reading); 8] = [
+    ("P1 HP", |fd| player1(fd, |p| p.health_ratio)),
+    ("P2 HP", |fd| player2(fd, |p| p.health_ratio)),
+    ("P1 SA", |fd| player1(fd, |p| p.sa_gauge)),
+    ("P2 SA", |fd| player2(fd, |p| p.sa_gauge)),
+    ("P1 OD", |fd| player1(fd, |p| p.od_gauge)),
+    ("P2 OD", |fd| player2(fd, |p| p.od_gauge)),
+    ("P1 burnout", |fd| player1(fd, |p| p.burnout_gauge)),
+    ("P2 burnout", |fd| player2(fd, |p| p.burnout_gauge)),
+];
+
+fn player1(fd: &FrameData, value: fn(&PlayerState) -> Option<f64>) -> Option<f64> {
+    fd.player1.as_ref().and_then(value)
+}
+
+fn player2(fd: &FrameData, value: fn(&PlayerState) -> Option<f64>) -> Option<f64> {
+    fd.player2.as_ref().and_then(value)
+}
+
+/// A reading of one frame that differs between two runs. None is an unread value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadingChange {
+    pub frame_number: u32,
+    pub timestamp_seconds: f64,
+    pub reading: &'static str,
+    pub old: Option<f64>,
+    pub new: Option<f64>,
+}
+
+/// Differences within one round, identified by its position in the new run (or in the
+/// old run for frames the new run lacks).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoundDiff {
+    /// Match index (0-based) in the output file.
+    pub match_index: usize,
+    pub round_index: u32,
+    /// Frames present in both runs.
+    pub compared: usize,
+    pub only_old: usize,
+    pub only_new: usize,
+    /// Changed readings in frame order.
+    pub changes: Vec<ReadingChange>,
+}
+
+/// Compare the frames of two analysis runs of the same video, aligned by frame number.
+/// A reading changes when it differs by more than `tolerance` or is read in only one
+/// run. Rounds are returned in match and round order.
+pub fn diff_matches(old: &[Match], new: &[Match], tolerance: f64) -> Vec<RoundDiff> {
+    assert!(tolerance >= 0.0, "tolerance must be >= 0");
+    let old_frames = index_frames(old);
+    let new_frames = index_frames(new);
+    let mut rounds: BTreeMap<(usize, u32), RoundDiff> = BTreeMap::new();
+
+    for (&frame_number, &(key, new_fd)) in &new_frames {
+        let diff = round_diff(&mut rounds, key);
+        let Some(&(_, old_fd)) = old_frames.get(&frame_number) else {
+            diff.only_new += 1;
+            continue;
+        };
+        diff.compared += 1;
+        for (reading, value) in READINGS {
+            let (old, new) = (value(old_fd), value(new_fd));
+            let changed = match (old, new) {
+                (Some(a), Some(b)) => (a - b).abs() > tolerance,
+                (None, None) => false,
+                _ => true,
+            };
+            if changed {
+                diff.changes.push(ReadingChange {
+                    frame_number,
+                    timestamp_seconds: new_fd.timestamp_seconds,
+                    reading,
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+    for (frame_number, &(key, _)) in &old_frames {
+        if !new_frames.contains_key(frame_number) {
+            round_diff(&mut rounds, key).only_old += 1;
+        }
+    }
+
+    let mut diffs: Vec<RoundDiff> = rounds.into_values().collect();
+    for diff in &mut diffs {
+        diff.changes.sort_by_key(|c| c.frame_number);
+    }
+    info!(
+        rounds = diffs.len(),
+        changes = diffs.iter().map(|d| d.changes.len()).sum::<usize>(),
+        "runs compared"
+    );
+    diffs
+}
+
+fn round_diff(
+    rounds: &mut BTreeMap<(usize, u32), RoundDiff>,
+    (match_index, round_index): (usize, u32),
+) -> &mut RoundDiff {
+    rounds
+        .entry((match_index, round_index))
+        .or_insert_with(|| RoundDiff {
+            match_index,
+            round_index,
+            ..Default::default()
+        })
+}
+
+/// Frames of every round by frame number, with their (match index, round index).
+fn index_frames(matches: &[Match]) -> HashMap<u32, ((usize, u32), &FrameData)> {
+    let mut frames = HashMap::new();
+    for (match_index, m) in matches.iter().enumerate() {
+        for round in &m.rounds {
+            for fd in &round.frames {
+                frames.insert(fd.frame_number, ((match_index, round.round_index), fd));
+            }
+        }
+    }
+    frames
+}
+
+/// Print one line per round with the number of changed readings by kind, followed by
+/// each change when `details` is set. Returns the total number of changes.
+pub fn write_diff(
+    diffs: &[RoundDiff],
+    details: bool,
+    out: &mut impl Write,
+) -> Result<usize, PipelineError> {
+    let mut total = 0usize;
+    for diff in diffs {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for change in &diff.changes {
+            match counts
+                .iter_mut()
+                .find(|(reading, _)| *reading == change.reading)
+            {
+                Some((_, n)) => *n += 1,
+                None => counts.push((change.reading, 1)),
+            }
+        }
+        let by_reading = if counts.is_empty() {
+            String::new()
+        } else {
+            let parts: Vec<String> = counts.iter().map(|(r, n)| format!("{r} {n}")).collect();
+            format!(" ({})", parts.join(", "))
+        };
+        writeln!(
+            out,
+            "match {} round {}: {} frames compared, {} readings changed{by_reading}, {} frames only in old, {} only in new",
+            diff.match_index + 1,
+            diff.round_index + 1,
+            diff.compared,
+            diff.changes.len(),
+            diff.only_old,
+            diff.only_new,
+        )
+        .map_err(PipelineError::Output)?;
+        if details {
+            let value = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{v:.3}"));
+            for c in &diff.changes {
+                writeln!(
+                    out,
+                    "  frame {} ({:.2}s) {}: {} -> {}",
+                    c.frame_number,
+                    c.timestamp_seconds,
+                    c.reading,
+                    value(c.old),
+                    value(c.new)
+                )
+                .map_err(PipelineError::Output)?;
+            }
+        }
+        total += diff.changes.len();
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::Round;
+
+    use super::*;
+    use crate::test_support::{drive, frame, health};
+
+    fn run(rounds: Vec<Vec<FrameData>>) -> Vec<Match> {
+        vec![Match {
+            rounds: rounds
+                .into_iter()
+                .enumerate()
+                .map(|(i, frames)| Round {
+                    round_index: i as u32,
+                    frames,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn changes_beyond_tolerance_are_reported_per_round() {
+        let [old_first, old_second, new_first, new_second] = [
+            [(0, Some(1.0), Some(6.0)), (10, Some(0.8), Some(5.0))],
+            [(100, Some(1.0), Some(6.0)), (110, Some(0.5), None)],
+            [(0, Some(1.01), Some(6.0)), (10, Some(0.7), Some(5.0))],
+            [(110, Some(0.5), Some(4.0)), (120, Some(0.4), None)],
+        ]
+        .map(|round| {
+            round
+                .map(|(n, p1_hp, p2_od)| FrameData {
+                    player1: Some(health(p1_hp)),
+                    player2: Some(drive(p2_od, None)),
+                    ..frame(n)
+                })
+                .to_vec()
+        });
+        let old = run(vec![old_first, old_second]);
+        let new = run(vec![new_first, new_second]);
+        let diffs = diff_matches(&old, &new, 0.02);
+        assert_eq!(diffs.len(), 2);
+
+        let first = &diffs[0];
+        assert_eq!((first.compared, first.only_old, first.only_new), (2, 0, 0));
+        assert_eq!(first.changes.len(), 1);
+        assert_eq!(first.changes[0].frame_number, 10);
+        assert_eq!(first.changes[0].reading, "P1 HP");
+        assert_eq!(
+            (first.changes[0].old, first.changes[0].new),
+            (Some(0.8), Some(0.7))
+        );
+
+        // Frame 100 is gone, 120 is new, and the OD read only by the new run changed.
+        let second = &diffs[1];
+        assert_eq!(
+            (second.compared, second.only_old, second.only_new),
+            (1, 1, 1)
+        );
+        assert_eq!(second.changes.len(), 1);
+        assert_eq!(second.changes[0].reading, "P2 OD");
+        assert_eq!(second.changes[0].old, None);
+
+        let mut text = Vec::new();
+        assert_eq!(write_diff(&diffs, true, &mut text).unwrap(), 2);
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("match 1 round 1: 2 frames compared, 1 readings changed (P1 HP 1)"));
+        assert!(text.contains("  frame 110 (1.83s) P2 OD: - -> 4.000"));
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+use std::io::Write;
+
+use tracing::info;
+
+use crate::error::PipelineError;
+use recmari_proto::proto::{source_metadata::Source, EventType, FrameData, Match, Round};
+
+use crate::damage;
+use crate::links::VideoLink;
+
+/// A Drive Impact or Drive Rush is credited with damage starting this soon after it.
+const FOLLOW_UP_SECONDS: f64 = 1.5;
+/// Health drops this close together belong to one combo.
+const COMBO_MAX_GAP_SECONDS: f64 = 1.0;
+/// A Drive Rush combo dealing less than this was most likely dropped.
+const DROPPED_COMBO_MAX_DAMAGE: f64 = 0.08;
+
+/// Kind of a moment worth practicing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrillKind {
+    /// The player was hit by a Drive Impact.
+    HitByDriveImpact,
+    /// The player's Drive gauge ran out.
+    Burnout,
+    /// The player's Drive Rush led to only a little damage.
+    DroppedCombo,
+}
+
+impl fmt::Display for DrillKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrillKind::HitByDriveImpact => write!(f, "hit_by_drive_impact"),
+            DrillKind::Burnout => write!(f, "burnout"),
+            DrillKind::DroppedCombo => write!(f, "dropped_combo"),
+        }
+    }
+}
+
+/// A flagged moment for a player to review.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drill {
+    pub kind: DrillKind,
+    pub round_index: u32,
+    /// The player who should practice the situation (1 or 2).
+    pub player: u8,
+    /// Time as stored in the match (game time when the playback speed is not 1.0).
+    pub timestamp_seconds: f64,
+    /// Seconds into the source video (playback speed undone).
+    pub video_seconds: f64,
+    /// Health dealt by a dropped combo.
+    pub damage: Option<f64>,
+}
+
+/// Find the Drive Impact hits, burnouts and dropped combos of a match, in
+/// chronological order.
+pub fn find_drills(m: &Match) -> Vec<Drill> {
+    let speed = match m.source.as_ref().and_then(|s| s.source.as_ref()) {
+        Some(Source::VideoFile(v)) if v.playback_speed > 0.0 => v.playback_speed,
+        _ => 1.0,
+    };
+    let mut drills = Vec::new();
+    for round in &m.rounds {
+        let mut push = |kind, player, ts: f64, damage| {
+            drills.push(Drill {
+                kind,
+                round_index: round.round_index,
+                player,
+                timestamp_seconds: ts,
+                video_seconds: ts / speed,
+                damage,
+            })
+        };
+        for (ts, victim) in follow_ups(round, EventType::DriveImpact) {
+            push(DrillKind::HitByDriveImpact, victim, ts, None);
+        }
+        for (ts, victim) in follow_ups(round, EventType::DriveRush) {
+            let damage = combo_damage(&round.frames, victim, ts);
+            if damage < DROPPED_COMBO_MAX_DAMAGE {
+                push(DrillKind::DroppedCombo, 3 - victim, ts, Some(damage));
+            }
+        }
+        for b in &round.burnouts {
+            push(DrillKind::Burnout, b.player as u8, b.start_seconds, None);
+        }
+    }
+    drills.sort_by(|a, b| a.video_seconds.total_cmp(&b.video_seconds));
+    info!(drills = drills.len(), "drills found");
+    drills
+}
+
+/// `(event time, player hit)` for each event of `kind` followed by a hit (see
+/// [`damage::hits`]) within [`FOLLOW_UP_SECONDS`]. The first player hit is the victim.
+fn follow_ups(round: &Round, kind: EventType) -> Vec<(f64, u8)> {
+    let hits = [1u8, 2].map(|player| damage::hits(&round.frames, player.into()));
+    round
+        .events
+        .iter()
+        .filter(|e| e.r#type() == kind)
+        .filter_map(|e| {
+            let ts = e.timestamp_seconds;
+            let first_hit = |player: u8| {
+                hits[usize::from(player - 1)]
+                    .iter()
+                    .map(|hit| hit.timestamp_seconds)
+                    .find(|&t| t > ts && t <= ts + FOLLOW_UP_SECONDS)
+                    .map(|t| (t, player))
+            };
+            let victim = [first_hit(1), first_hit(2)]
+                .into_iter()
+                .flatten()
+                .min_by(|a, b| a.0.total_cmp(&b.0))?;
+            Some((ts, victim.1))
+        })
+        .collect()
+}
+
+/// Health `victim` lost in the run of hits starting after `from`; the run ends at
+/// the first gap longer than [`COMBO_MAX_GAP_SECONDS`] between hits.
+fn combo_damage(frames: &[FrameData], victim: u8, from: f64) -> f64 {
+    let mut damage = 0.0;
+    let mut last_hit = from;
+    let hits = damage::hits(frames, victim.into());
+    for hit in hits.iter().filter(|hit| hit.timestamp_seconds > from) {
+        if hit.timestamp_seconds - last_hit > COMBO_MAX_GAP_SECONDS {
+            break;
+        }
+        damage += hit.amount;
+        last_hit = hit.timestamp_seconds;
+    }
+    damage
+}
+
+const DRILL_CSV_HEADER: &str = "match,round,video_seconds,player,drill,description,link";
+
+/// Write one CSV row per drill (importable into Anki or a spreadsheet). Match and
+/// round numbers are 1-based. Returns the number of rows written.
+pub fn write_drill_csv(
+    matches: impl Iterator<Item = Result<Match, PipelineError>>,
+    out: &mut impl Write,
+) -> Result<usize, PipelineError> {
+    writeln!(out, "{DRILL_CSV_HEADER}").map_err(PipelineError::Output)?;
+
+    let mut rows = 0usize;
+    for (match_index, m) in matches.enumerate() {
+        let m = m?;
+        let link = VideoLink::for_match(&m);
+        for drill in find_drills(&m) {
+            writeln!(
+                out,
+                "{},{},{:.1},P{},{},{},{}",
+                match_index + 1,
+                drill.round_index + 1,
+                drill.video_seconds,
+                drill.player,
+                drill.kind,
+                csv_field(&description(&drill)),
+                csv_field(
+                    &link
+                        .as_ref()
+                        .map(|l| l.at(drill.timestamp_seconds))
+                        .unwrap_or_default()
+                ),
+            )
+            .map_err(PipelineError::Output)?;
+            rows += 1;
+        }
+    }
+
+    info!(rows, "drill export complete");
+    Ok(rows)
+}
+
+/// One-line prompt for the drill, e.g. "P1 burned out at 1:23".
+fn description(drill: &Drill) -> String {
+    let secs = drill.video_seconds.max(0.0) as u64;
+    let at = format!("{}:{:02}", secs / 60, secs % 60);
+    let player = drill.player;
+    match drill.kind {
+        DrillKind::HitByDriveImpact => format!("P{player} was hit by Drive Impact at {at}"),
+        DrillKind::Burnout => format!("P{player} burned out at {at}"),
+        DrillKind::DroppedCombo => format!(
+            "P{player} dropped a Drive Rush combo at {at} ({:.0}% damage)",
+            drill.damage.unwrap_or(0.0) * 100.0
+        ),
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{Burnout, PlayerState, RoundEvent};
+
+    use super::*;
+    use crate::test_support::{drive, frame_at};
+
+    fn event(kind: EventType, ts: f64) -> RoundEvent {
+        RoundEvent {
+            r#type: kind.into(),
+            timestamp_seconds: ts,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_drive_impact_hits_burnouts_and_dropped_combos() {
+        let m = Match {
+            rounds: vec![Round {
+                round_index: 1,
+                frames: [
+                    (10.0, 1.0, 1.0, Some(3.0)),
+                    (11.0, 0.9, 1.0, Some(3.0)), // DI at 10.5 hits P1
+                    (20.0, 0.9, 1.0, Some(1.0)),
+                    (21.0, 0.9, 0.97, Some(0.5)), // P1 rush at 20.5: 3% only
+                    (22.0, 0.9, 0.97, None),      // P1 burns out
+                    (30.0, 0.9, 0.97, None),
+                    (31.0, 0.9, 0.8, None), // P1 rush at 30.5: full combo
+                    (32.0, 0.9, 0.6, None),
+                    (40.0, 0.9, 0.6, None), // rush at 39.5 hits nobody
+                ]
+                .map(|(ts, p1, p2, p1_od)| FrameData {
+                    player1: Some(PlayerState {
+                        health_ratio: Some(p1),
+                        ..drive(p1_od, p1_od.is_none().then_some(0.0))
+                    }),
+                    player2: Some(PlayerState {
+                        health_ratio: Some(p2),
+                        ..drive(Some(6.0), None)
+                    }),
+                    ..frame_at(ts)
+                })
+                .to_vec(),
+                events: vec![
+                    event(EventType::DriveImpact, 10.5),
+                    event(EventType::DriveRush, 20.5),
+                    event(EventType::DriveRush, 30.5),
+                    event(EventType::DriveRush, 39.5),
+                ],
+                burnouts: vec![Burnout {
+                    player: 1,
+                    start_seconds: 22.0,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let drills: Vec<(DrillKind, u8, f64)> = find_drills(&m)
+            .iter()
+            .map(|d| (d.kind, d.player, d.timestamp_seconds))
+            .collect();
+        assert_eq!(
+            drills,
+            vec![
+                (DrillKind::HitByDriveImpact, 1, 10.5),
+                (DrillKind::DroppedCombo, 1, 20.5),
+                (DrillKind::Burnout, 1, 22.0),
+            ]
+        );
+
+        let mut out = Vec::new();
+        assert_eq!(write_drill_csv([Ok(m)].into_iter(), &mut out).unwrap(), 3);
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], DRILL_CSV_HEADER);
+        assert_eq!(
+            lines[2],
+            "1,2,20.5,P1,dropped_combo,P1 dropped a Drive Rush combo at 0:20 (3% damage),"
+        );
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,104 @@
+//! Typed errors, so callers can tell a missing ffmpeg from a corrupt video from a
+//! recording without a HUD and react programmatically.
+
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Failures of the external tools (ffmpeg, ffprobe, yt-dlp) and the streams they produce.
+#[derive(Debug, Error)]
+pub enum DecoderError {
+    /// The tool could not be started: it is not installed or not on PATH.
+    #[error("failed to run {program} — is it installed?")]
+    ToolMissing {
+        program: &'static str,
+        #[source]
+        source: io::Error,
+    },
+    /// The tool ran and failed, e.g. on a corrupt or unsupported video.
+    #[error("{program} failed: {stderr}")]
+    ToolFailed {
+        program: &'static str,
+        stderr: String,
+    },
+    /// The video is not what the tool reported or was asked for: unreadable metadata,
+    /// a stream ending mid-frame, a start frame past the end or a crop outside it.
+    #[error("invalid video: {0}")]
+    InvalidVideo(String),
+    /// Reading from or writing to a tool's pipe failed.
+    #[error("ffmpeg pipe I/O failed")]
+    Pipe(#[from] io::Error),
+}
+
+/// HUD detection, configuration and calibration failures.
+#[derive(Debug, Error)]
+pub enum HudError {
+    /// No sampled frame showed a supported HUD.
+    #[error("no HUD detected in {frames} sampled frames")]
+    NeverDetected { frames: usize },
+    /// HUD regions are only known at 1920x1080.
+    #[error(
+        "unsupported video resolution {width}x{height} (only 1920x1080 is supported; \
+         use --crop for letterboxed recordings)"
+    )]
+    UnsupportedResolution { width: u32, height: u32 },
+    /// No HUD implementation has the requested name.
+    #[error("unknown HUD '{name}' (available: {available})")]
+    UnknownHud { name: String, available: String },
+    /// A HUD config is malformed or out of range.
+    #[error("invalid HUD config: {0}")]
+    InvalidConfig(String),
+    /// Reference images do not show what calibration needs.
+    #[error("calibration failed: {0}")]
+    Calibration(String),
+}
+
+/// Failures of an analysis run and of the files around it: output, checkpoints,
+/// streams, markers, chat logs and labeled frames.
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    #[error(transparent)]
+    Decoder(#[from] DecoderError),
+    #[error(transparent)]
+    Hud(#[from] HudError),
+    #[error("input video does not exist: {}", .0.display())]
+    InputNotFound(PathBuf),
+    /// An option is out of range or contradicts another.
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+    /// A file could not be read or written.
+    #[error("failed to {action} {}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// Writing to a caller-supplied writer failed.
+    #[error("failed to write output")]
+    Output(#[source] io::Error),
+    /// A file's contents are malformed.
+    #[error("{0}")]
+    Parse(String),
+    /// An image could not be decoded or encoded.
+    #[error("failed to {action} image {}", path.display())]
+    Image {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+}
+
+impl PipelineError {
+    /// An [`Io`](Self::Io) error for `action` on `path`.
+    pub fn io(action: &'static str, path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.into();
+        move |source| Self::Io {
+            action,
+            path,
+            source,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use tracing::info;
+
+use crate::error::PipelineError;
+use recmari_proto::proto::{FrameData, FrameStatus, Match, PlayerState};
+
+/// Tabular export format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    /// Flagged moments to practice, one CSV row each (see [`crate::drills`]).
+    Drills,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "drills" => Ok(ExportFormat::Drills),
+            _ => Err(format!(
+                "unknown export format '{s}' (expected csv or drills)"
+            )),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "match,round,frame_number,timestamp_seconds,\
+p1_hp,p1_sa,p1_od,p1_burnout,p2_hp,p2_sa,p2_od,p2_burnout,status";
+
+/// Write one CSV row per sampled frame. Match and round numbers are 1-based;
+/// unreadable values are empty cells. Returns the number of rows written.
+pub fn write_csv(
+    matches: impl Iterator<Item = Result<Match, PipelineError>>,
+    out: &mut impl Write,
+) -> Result<usize, PipelineError> {
+    writeln!(out, "{CSV_HEADER}").map_err(PipelineError::Output)?;
+
+    let mut rows = 0usize;
+    for (match_index, m) in matches.enumerate() {
+        let m = m?;
+        for round in &m.rounds {
+            for fd in &round.frames {
+                writeln!(
+                    out,
+                    "{},{},{}",
+                    match_index + 1,
+                    round.round_index + 1,
+                    frame_cells(fd)
+                )
+                .map_err(PipelineError::Output)?;
+                rows += 1;
+            }
+        }
+    }
+
+    info!(rows, "CSV export complete");
+    Ok(rows)
+}
+
+fn frame_cells(fd: &FrameData) -> String {
+    format!(
+        "{},{},{},{},{}",
+        fd.frame_number,
+        fd.timestamp_seconds,
+        player_cells(fd.player1.as_ref()),
+        player_cells(fd.player2.as_ref()),
+        status_name(fd.status())
+    )
+}
+
+/// Snake-case status name; empty for frames written before the status existed.
+pub(crate) fn status_name(status: FrameStatus) -> &'static str {
+    match status {
+        FrameStatus::Unknown => "",
+        FrameStatus::Ok => "ok",
+        FrameStatus::HudAbsent => "hud_absent",
+        FrameStatus::Cinematic => "cinematic",
+        FrameStatus::Occluded => "occluded",
+        FrameStatus::Stale => "stale",
+    }
+}
+
+fn player_cells(player: Option<&PlayerState>) -> String {
+    let cell = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+    let Some(p) = player else {
+        return ",,,".to_string();
+    };
+    [p.health_ratio, p.sa_gauge, p.od_gauge, p.burnout_gauge]
+        .map(cell)
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::Round;
+
+    use super::*;
+
+    #[test]
+    fn csv_has_row_per_frame_with_empty_unreadable_cells() {
+        let frame = FrameData {
+            frame_number: 120,
+            timestamp_seconds: 2.0,
+            player1: Some(PlayerState {
+                health_ratio: Some(0.5),
+                sa_gauge: Some(1.25),
+                burnout_gauge: Some(0.3),
+                ..Default::default()
+            }),
+            player2: None,
+            status: FrameStatus::Stale.into(),
+            ko_slowdown: false,
+            interpolated: false,
+            custom_metrics: Vec::new(),
+        };
+        let m = Match {
+            rounds: vec![Round {
+                round_index: 1,
+                frames: vec![frame],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut out = Vec::new();
+        let rows = write_csv([Ok(m)].into_iter(), &mut out).unwrap();
+        assert_eq!(rows, 1);
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], "1,2,120,2,0.5,1.25,,0.3,,,,,stale");
+    }
+}
@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+/// Consecutive frames `start..start + count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSpan {
+    pub start: u32,
+    pub count: u32,
+}
+
+/// Frames picked for spot checks, parsed from a comma-separated list of frame numbers
+/// and inclusive ranges such as `1200,3600,5000-5060`. Spans are sorted, and
+/// overlapping or adjacent entries are merged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameList {
+    spans: Vec<FrameSpan>,
+}
+
+impl FrameList {
+    pub fn spans(&self) -> &[FrameSpan] {
+        &self.spans
+    }
+
+    /// Total number of frames over all spans.
+    pub fn frame_count(&self) -> u32 {
+        self.spans.iter().map(|s| s.count).sum()
+    }
+}
+
+impl FromStr for FrameList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for entry in s.split(',').map(str::trim) {
+            let number = |n: &str| {
+                n.trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid frame '{n}' in '{s}'"))
+            };
+            let (first, last) = match entry.split_once('-') {
+                Some((first, last)) => (number(first)?, number(last)?),
+                None => {
+                    let frame = number(entry)?;
+                    (frame, frame)
+                }
+            };
+            if first > last {
+                return Err(format!("frame range '{entry}' ends before it starts"));
+            }
+            ranges.push((first, last));
+        }
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(u32, u32)> = Vec::new();
+        for (first, last) in ranges {
+            match merged.last_mut() {
+                Some((_, prev_last)) if first <= prev_last.saturating_add(1) => {
+                    *prev_last = (*prev_last).max(last);
+                }
+                _ => merged.push((first, last)),
+            }
+        }
+        let spans = merged
+            .into_iter()
+            .map(|(first, last)| FrameSpan {
+                start: first,
+                count: last - first + 1,
+            })
+            .collect();
+        Ok(FrameList { spans })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: u32, count: u32) -> FrameSpan {
+        FrameSpan { start, count }
+    }
+
+    #[test]
+    fn parses_frames_and_ranges() {
+        let list: FrameList = "5000-5060, 1200,3600".parse().unwrap();
+        assert_eq!(list.spans(), [span(1200, 1), span(3600, 1), span(5000, 61)]);
+        assert_eq!(list.frame_count(), 63);
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_entries() {
+        let list: FrameList = "10-20,15-30,31,40,40".parse().unwrap();
+        assert_eq!(list.spans(), [span(10, 22), span(40, 1)]);
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        for bad in ["", "12,", "a", "20-10", "1-2-3", "-5"] {
+            assert!(bad.parse::<FrameList>().is_err(), "{bad:?} was accepted");
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use tracing::info;
+
+use recmari_proto::proto::{source_metadata::Source, EventType, FrameData, Match, Round, Winner};
+
+use crate::players::player_state;
+
+/// The round winner's health at the end counts as untouched above this.
+const PERFECT_MIN_HEALTH: f64 = 0.99;
+/// The eventual winner must have trailed by at least this much health for a comeback.
+const COMEBACK_MIN_DEFICIT: f64 = 0.4;
+
+/// Kind of a highlight moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    /// A player was knocked out.
+    Ko,
+    /// The round was won without taking damage.
+    Perfect,
+    /// The winner retook the health lead after trailing far behind.
+    Comeback,
+}
+
+impl fmt::Display for HighlightKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HighlightKind::Ko => write!(f, "ko"),
+            HighlightKind::Perfect => write!(f, "perfect"),
+            HighlightKind::Comeback => write!(f, "comeback"),
+        }
+    }
+}
+
+/// A moment worth clipping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub kind: HighlightKind,
+    pub round_index: u32,
+    /// The KO'd player for KOs, the round winner otherwise (1 or 2).
+    pub player: u8,
+    /// Seconds into the source video (playback speed undone).
+    pub video_seconds: f64,
+}
+
+/// Find the KOs, perfect rounds and comebacks of a match, in chronological order.
+pub fn find_highlights(m: &Match) -> Vec<Highlight> {
+    let speed = match m.source.as_ref().and_then(|s| s.source.as_ref()) {
+        Some(Source::VideoFile(v)) if v.playback_speed > 0.0 => v.playback_speed,
+        _ => 1.0,
+    };
+    let mut highlights = Vec::new();
+    for round in &m.rounds {
+        let mut push = |kind, player, ts: f64| {
+            highlights.push(Highlight {
+                kind,
+                round_index: round.round_index,
+                player,
+                video_seconds: ts / speed,
+            })
+        };
+        for ko in round.events.iter().filter(|e| e.r#type() == EventType::Ko) {
+            if ko.player > 0 {
+                push(HighlightKind::Ko, ko.player as u8, ko.timestamp_seconds);
+            }
+        }
+        let Some(winner) = winner_player(round.winner()) else {
+            continue;
+        };
+        if let Some(ts) = perfect_at(round, winner) {
+            push(HighlightKind::Perfect, winner, ts);
+        }
+        if let Some(ts) = comeback_at(&round.frames, winner) {
+            push(HighlightKind::Comeback, winner, ts);
+        }
+    }
+    highlights.sort_by(|a, b| a.video_seconds.total_cmp(&b.video_seconds));
+    info!(highlights = highlights.len(), "highlights found");
+    highlights
+}
+
+fn winner_player(winner: Winner) -> Option<u8> {
+    match winner {
+        Winner::P1 => Some(1),
+        Winner::P2 => Some(2),
+        Winner::Unknown => None,
+    }
+}
+
+/// Time of the finishing KO if the winner ended the round untouched.
+fn perfect_at(round: &Round, winner: u8) -> Option<f64> {
+    let final_health = round
+        .frames
+        .iter()
+        .rev()
+        .find_map(|fd| player_state(fd, winner.into())?.health_ratio)?;
+    if final_health < PERFECT_MIN_HEALTH {
+        return None;
+    }
+    let ko = round
+        .events
+        .iter()
+        .find(|e| e.r#type() == EventType::Ko && e.player != u32::from(winner));
+    ko.map(|e| e.timestamp_seconds)
+        .or_else(|| round.frames.last().map(|fd| fd.timestamp_seconds))
+}
+
+/// First frame where the winner is no longer behind after trailing by at least
+/// [`COMEBACK_MIN_DEFICIT`].
+fn comeback_at(frames: &[FrameData], winner: u8) -> Option<f64> {
+    let loser = 3 - winner;
+    let mut max_deficit = 0.0f64;
+    for fd in frames {
+        let [w, l] =
+            [winner, loser].map(|p| player_state(fd, p.into()).and_then(|p| p.health_ratio));
+        let (Some(w), Some(l)) = (w, l) else {
+            continue;
+        };
+        let deficit = l - w;
+        if max_deficit >= COMEBACK_MIN_DEFICIT && deficit <= 0.0 {
+            return Some(fd.timestamp_seconds);
+        }
+        max_deficit = max_deficit.max(deficit);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{RoundEvent, SourceMetadata, VideoFileSource};
+
+    use super::*;
+    use crate::test_support::hp_frame;
+
+    fn ko(ts: f64, player: u32) -> RoundEvent {
+        RoundEvent {
+            r#type: EventType::Ko.into(),
+            timestamp_seconds: ts,
+            player,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_kos_perfects_and_comebacks() {
+        let m = Match {
+            source: Some(SourceMetadata {
+                source: Some(Source::VideoFile(VideoFileSource {
+                    playback_speed: 2.0,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            rounds: vec![
+                Round {
+                    round_index: 0,
+                    frames: vec![hp_frame(10.0, 1.0, 1.0), hp_frame(20.0, 1.0, 0.0)],
+                    winner: Winner::P1.into(),
+                    events: vec![ko(20.0, 2)],
+                    ..Default::default()
+                },
+                Round {
+                    round_index: 1,
+                    frames: vec![
+                        hp_frame(30.0, 1.0, 1.0),
+                        hp_frame(40.0, 0.9, 0.3), // P2 trails by 0.6
+                        hp_frame(50.0, 0.3, 0.35),
+                        hp_frame(60.0, 0.0, 0.35),
+                    ],
+                    winner: Winner::P2.into(),
+                    events: vec![ko(60.0, 1)],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let found: Vec<(HighlightKind, u32, u8, f64)> = find_highlights(&m)
+            .into_iter()
+            .map(|h| (h.kind, h.round_index, h.player, h.video_seconds))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (HighlightKind::Ko, 0, 2, 10.0),
+                (HighlightKind::Perfect, 0, 1, 10.0),
+                (HighlightKind::Comeback, 1, 2, 25.0),
+                (HighlightKind::Ko, 1, 1, 30.0),
+            ]
+        );
+    }
+}
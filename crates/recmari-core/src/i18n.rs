@@ -0,0 +1,121 @@
+use std::str::FromStr;
+
+/// Language of human-readable text such as the summary. Machine-readable outputs
+/// (protobuf, CSV, JSON) are not localized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    Ja,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "en" => Ok(Lang::En),
+            "ja" => Ok(Lang::Ja),
+            _ => Err(format!("unknown language '{s}' (expected en or ja)")),
+        }
+    }
+}
+
+/// Columns of the summary's round table, in order.
+pub(crate) const ROUND_COLUMNS: usize = 10;
+
+impl Lang {
+    /// Summary line introducing a match.
+    pub(crate) fn match_line(
+        self,
+        number: usize,
+        winner: &str,
+        rounds: usize,
+        duration: &str,
+    ) -> String {
+        match self {
+            Lang::En => {
+                format!("Match {number}: winner {winner}, {rounds} round(s), {duration}")
+            }
+            Lang::Ja => {
+                format!("第{number}試合: 勝者 {winner}、{rounds}ラウンド、{duration}")
+            }
+        }
+    }
+
+    /// Headers of the summary's round table.
+    pub(crate) fn round_columns(self) -> [&'static str; ROUND_COLUMNS] {
+        match self {
+            Lang::En => [
+                "round",
+                "duration",
+                "winner",
+                "p1_damage",
+                "p2_damage",
+                "p1_dmg/s",
+                "p2_dmg/s",
+                "p1_sa",
+                "p2_sa",
+                "leads",
+            ],
+            Lang::Ja => [
+                "ラウンド",
+                "時間",
+                "勝者",
+                "1Pダメージ",
+                "2Pダメージ",
+                "1P毎秒",
+                "2P毎秒",
+                "1P SA",
+                "2P SA",
+                "リード変化",
+            ],
+        }
+    }
+
+    /// Header of the summary's link column.
+    pub(crate) fn link_column(self) -> &'static str {
+        match self {
+            Lang::En => "link",
+            Lang::Ja => "リンク",
+        }
+    }
+}
+
+/// Terminal columns taken by `s`: East Asian wide characters take two.
+pub(crate) fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+/// `s` right-aligned to `width` terminal columns.
+pub(crate) fn pad_left(s: &str, width: usize) -> String {
+    let pad = width.saturating_sub(display_width(s));
+    format!("{}{s}", " ".repeat(pad))
+}
+
+/// Wide ranges used by Japanese text: CJK, kana and full-width forms.
+fn is_wide(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{115F}'
+        | '\u{2E80}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        assert_eq!(display_width("p1_sa"), 5);
+        assert_eq!(display_width("1Pダメージ"), 10);
+        assert_eq!(pad_left("勝者", 6), "  勝者");
+        assert_eq!(pad_left("winner", 4), "winner");
+        assert_eq!("JA".parse::<Lang>(), Ok(Lang::Ja));
+        assert!("fr".parse::<Lang>().is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use tracing::info;
+
+use recmari_proto::proto::{FrameData, Match, PlayerState, Round};
+
+use crate::players::{player_state, player_state_mut};
+
+/// How [`interpolate_round`] fills missing gauge values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationMode {
+    /// A straight line between the readings on either side of the gap.
+    Linear,
+    /// The last reading before the gap.
+    Hold,
+}
+
+impl std::str::FromStr for InterpolationMode {
+    type Err = String;
+
+    /// `linear` or `hold`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "linear" => Ok(InterpolationMode::Linear),
+            "hold" => Ok(InterpolationMode::Hold),
+            _ => Err(format!(
+                "invalid interpolation '{s}' (expected linear or hold)"
+            )),
+        }
+    }
+}
+
+/// Settings of the interpolation pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interpolation {
+    pub mode: InterpolationMode,
+    /// Longest gap filled, in seconds of the frame clock (game time once the playback
+    /// speed is applied): between the readings around it for `Linear`, since the last
+    /// reading for `Hold`. Longer gaps stay missing.
+    pub max_gap_seconds: f64,
+}
+
+/// A gauge of [`PlayerState`] filled by the pass.
+struct Gauge {
+    get: fn(&PlayerState) -> Option<f64>,
+    set: fn(&mut PlayerState, f64),
+    /// Whether the gauge is expected to be absent, so the gap is real (OD in burnout).
+    absent_by_design: fn(&PlayerState) -> bool,
+}
+
+const GAUGES: [Gauge; 3] = [
+    Gauge {
+        get: |p| p.health_ratio,
+        set: |p, v| p.health_ratio = Some(v),
+        absent_by_design: |_| false,
+    },
+    Gauge {
+        get: |p| p.sa_gauge,
+        set: |p, v| p.sa_gauge = Some(v),
+        absent_by_design: |_| false,
+    },
+    Gauge {
+        get: |p| p.od_gauge,
+        set: |p, v| p.od_gauge = Some(v),
+        absent_by_design: |p| p.burnout_gauge.is_some(),
+    },
+];
+
+/// Fill missing HP, SA and OD values of both players in `round` so each gauge forms a
+/// continuous series, and mark the filled frames `interpolated`. Values read or carried
+/// over are kept; OD stays absent during burnout. Returns the number of filled frames.
+pub fn interpolate_round(round: &mut Round, interpolation: &Interpolation) -> usize {
+    let mut filled = vec![false; round.frames.len()];
+    for player in [1, 2] {
+        for gauge in &GAUGES {
+            for (i, value) in fill_values(&round.frames, player, gauge, interpolation) {
+                (gauge.set)(player_state_mut(&mut round.frames[i], player), value);
+                filled[i] = true;
+            }
+        }
+    }
+    for (fd, filled) in round.frames.iter_mut().zip(&filled) {
+        fd.interpolated |= filled;
+    }
+    filled.iter().filter(|&&f| f).count()
+}
+
+/// Values to fill for one gauge of one player, by frame index.
+fn fill_values(
+    frames: &[FrameData],
+    player: u32,
+    gauge: &Gauge,
+    interpolation: &Interpolation,
+) -> Vec<(usize, f64)> {
+    let value = |i: usize| player_state(&frames[i], player).and_then(gauge.get);
+    let fillable = |i: usize| !player_state(&frames[i], player).is_some_and(gauge.absent_by_design);
+    let time = |i: usize| frames[i].timestamp_seconds;
+
+    let mut fills = Vec::new();
+    let mut last: Option<usize> = None;
+    let mut i = 0;
+    while i < frames.len() {
+        if value(i).is_some() {
+            last = Some(i);
+            i += 1;
+            continue;
+        }
+        if !fillable(i) {
+            last = None;
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < frames.len() && value(i).is_none() && fillable(i) {
+            i += 1;
+        }
+        let Some(before) = last else { continue };
+        let from = value(before).unwrap_or_default();
+        match interpolation.mode {
+            InterpolationMode::Hold => fills.extend(
+                (start..i)
+                    .take_while(|&h| time(h) - time(before) <= interpolation.max_gap_seconds)
+                    .map(|h| (h, from)),
+            ),
+            InterpolationMode::Linear => {
+                let Some(to) = (i < frames.len()).then(|| value(i)).flatten() else {
+                    continue;
+                };
+                let span = time(i) - time(before);
+                if span > interpolation.max_gap_seconds || span <= 0.0 {
+                    continue;
+                }
+                fills.extend((start..i).map(|h| {
+                    let t = (time(h) - time(before)) / span;
+                    (h, from + (to - from) * t)
+                }));
+            }
+        }
+    }
+    fills
+}
+
+/// Interpolate the rounds of every match.
+pub fn interpolate_matches(matches: &mut [Match], interpolation: &Interpolation) {
+    let mut frames = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        frames += interpolate_round(round, interpolation);
+    }
+    info!(frames, mode = ?interpolation.mode, "missing readings interpolated");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{drive, frame_at};
+
+    fn round() -> Round {
+        Round {
+            frames: [
+                (0.0, Some(1.0), Some(6.0), None),
+                (1.0, None, None, None),
+                (2.0, None, None, Some(0.0)),
+                (3.0, Some(0.4), None, Some(0.5)),
+                (4.0, None, Some(0.0), None),
+                (9.0, None, None, None),
+                (10.0, Some(0.2), Some(1.0), None),
+            ]
+            .map(|(ts, health_ratio, od, burnout)| FrameData {
+                player1: Some(PlayerState {
+                    health_ratio,
+                    ..drive(od, burnout)
+                }),
+                ..frame_at(ts)
+            })
+            .to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn hp(round: &Round) -> Vec<Option<f64>> {
+        round
+            .frames
+            .iter()
+            .map(|fd| fd.player1.as_ref().unwrap().health_ratio)
+            .collect()
+    }
+
+    #[test]
+    fn linear_fills_gaps_between_readings() {
+        let mut round = round();
+        let interpolation = Interpolation {
+            mode: InterpolationMode::Linear,
+            max_gap_seconds: 3.0,
+        };
+        assert_eq!(interpolate_round(&mut round, &interpolation), 2);
+        let hp = hp(&round);
+        assert!((hp[1].unwrap() - 0.8).abs() < 1e-9);
+        assert!((hp[2].unwrap() - 0.6).abs() < 1e-9);
+        // The 7s gap from 3s to 10s exceeds the limit.
+        assert_eq!((hp[4], hp[5]), (None, None));
+        // A gap ending in burnout has no reading to interpolate towards, and OD stays
+        // absent during burnout.
+        let od = |i: usize| round.frames[i].player1.as_ref().unwrap().od_gauge;
+        assert_eq!(od(1), None);
+        assert_eq!((od(2), od(3)), (None, None));
+        assert_eq!(od(5), None);
+        assert_eq!(
+            round
+                .frames
+                .iter()
+                .map(|fd| fd.interpolated)
+                .collect::<Vec<_>>(),
+            [false, true, true, false, false, false, false]
+        );
+        // Player 2 was never read, so nothing is filled for them.
+        assert!(round.frames.iter().all(|fd| fd.player2.is_none()));
+    }
+
+    #[test]
+    fn hold_carries_the_last_reading_up_to_the_limit() {
+        let mut round = round();
+        let interpolation = Interpolation {
+            mode: InterpolationMode::Hold,
+            max_gap_seconds: 2.0,
+        };
+        interpolate_round(&mut round, &interpolation);
+        assert_eq!(
+            hp(&round),
+            [
+                Some(1.0),
+                Some(1.0),
+                Some(1.0),
+                Some(0.4),
+                Some(0.4),
+                None,
+                Some(0.2)
+            ]
+        );
+        let od = |i: usize| round.frames[i].player1.as_ref().unwrap().od_gauge;
+        assert_eq!(od(1), Some(6.0));
+        assert_eq!(od(5), None);
+    }
+
+    #[test]
+    fn parses_mode() {
+        assert_eq!(
+            " Linear ".parse::<InterpolationMode>(),
+            Ok(InterpolationMode::Linear)
+        );
+        assert_eq!("hold".parse(), Ok(InterpolationMode::Hold));
+        assert!("cubic".parse::<InterpolationMode>().is_err());
+    }
+}
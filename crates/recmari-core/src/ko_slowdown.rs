@@ -0,0 +1,92 @@
+use tracing::info;
+
+use recmari_proto::proto::{EventType, FrameData, Match, Round};
+
+/// Length of the slow motion after a KO, in seconds of the frame clock.
+/// TODO: Calibrate against fixtures; the slowdown varies with the finishing move.
+pub const KO_SLOWDOWN_SECONDS: f64 = 3.0;
+
+/// Mark the frames of `round` that fall in a KO slowdown: after each KO event, up to
+/// [`KO_SLOWDOWN_SECONDS`] later. The KO frame itself is not marked, so the finishing
+/// hit still counts. Returns the number of marked frames.
+pub fn mark_round(round: &mut Round) -> usize {
+    let kos: Vec<f64> = round
+        .events
+        .iter()
+        .filter(|e| e.r#type() == EventType::Ko)
+        .map(|e| e.timestamp_seconds)
+        .collect();
+    let mut marked = 0;
+    for fd in &mut round.frames {
+        fd.ko_slowdown = kos.iter().any(|&ko| {
+            fd.timestamp_seconds > ko && fd.timestamp_seconds <= ko + KO_SLOWDOWN_SECONDS
+        });
+        marked += usize::from(fd.ko_slowdown);
+    }
+    marked
+}
+
+/// Mark the KO slowdown frames of every round. The pipeline does this only in
+/// experimental mode, since [`KO_SLOWDOWN_SECONDS`] is not calibrated.
+pub fn mark_ko_slowdown(matches: &mut [Match]) {
+    let mut frames = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        frames += mark_round(round);
+    }
+    info!(frames, "KO slowdown frames marked");
+}
+
+/// The frames of a round before its first KO slowdown frame, over which durations are
+/// in game time.
+pub fn before_slowdown(frames: &[FrameData]) -> &[FrameData] {
+    let end = frames
+        .iter()
+        .position(|fd| fd.ko_slowdown)
+        .unwrap_or(frames.len());
+    &frames[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::RoundEvent;
+
+    use super::*;
+
+    #[test]
+    fn marks_frames_after_the_ko() {
+        let mut round = Round {
+            frames: (0..10)
+                .map(|i| FrameData {
+                    timestamp_seconds: 20.0 + i as f64,
+                    ..Default::default()
+                })
+                .collect(),
+            events: vec![RoundEvent {
+                r#type: EventType::Ko.into(),
+                timestamp_seconds: 24.0,
+                player: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(mark_round(&mut round), 3);
+        let marked: Vec<f64> = round
+            .frames
+            .iter()
+            .filter(|fd| fd.ko_slowdown)
+            .map(|fd| fd.timestamp_seconds)
+            .collect();
+        assert_eq!(marked, [25.0, 26.0, 27.0]);
+        assert_eq!(before_slowdown(&round.frames).len(), 5);
+    }
+
+    #[test]
+    fn round_without_ko_is_untouched() {
+        let mut round = Round {
+            frames: vec![FrameData::default(); 3],
+            ..Default::default()
+        };
+        assert_eq!(mark_round(&mut round), 0);
+        assert_eq!(before_slowdown(&round.frames).len(), 3);
+    }
+}
@@ -1,5 +1,69 @@
+//! Street Fighter 6 match analysis: HUD readings, round/match segmentation and the
+//! protobuf output format.
+//!
+//! [`prelude`] is the supported public API; items there follow semver. The remaining
+//! modules are public for the `recmari` CLI and may change in minor releases.
+//!
+//! Cargo features (all on by default): `video` (ffmpeg decoding/encoding),
+//! `debug-render` (debug overlays), `pipeline` (whole-video analysis) and `chart`
+//! (SVG charts, HTML reports and stream overlays). With `default-features = false` only frame-level
+//! analysis and the output format remain.
+
+pub mod absolute_health;
 pub mod analysis;
+pub mod analyzer;
+pub mod anomaly;
+pub mod audio_cues;
+pub mod burnout;
+pub mod calibrate;
+#[cfg(feature = "chart")]
+pub mod chart;
+pub mod chat;
+pub mod checkpoint;
+pub mod config;
+pub mod damage;
+#[cfg(feature = "debug-render")]
 pub mod debug;
+pub mod defense;
+pub mod diff;
+pub mod drills;
+pub mod error;
+pub mod export;
+pub mod frame_list;
+pub mod highlights;
+pub mod i18n;
+pub mod interpolate;
+pub mod ko_slowdown;
+pub mod links;
+pub mod markers;
+pub mod match_timeline;
+pub mod output;
+#[cfg(feature = "chart")]
+pub mod overlay;
+#[cfg(feature = "pipeline")]
 pub mod pipeline;
+#[cfg(feature = "pipeline")]
+pub mod playback;
+pub mod players;
+pub mod prelude;
+#[cfg(feature = "pipeline")]
+pub mod progress;
+pub mod quality;
 pub mod rect;
+#[cfg(feature = "chart")]
+pub mod report;
+pub mod round_finish;
+pub mod selftest;
+pub mod stats;
+pub mod stream;
+pub mod summary;
+#[cfg(test)]
+mod test_support;
+pub mod testing;
+pub mod timeline;
+pub mod tune;
 pub mod video;
+pub mod winner;
+
+/// Generated protobuf types of the output format.
+pub use recmari_proto::proto;
@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use recmari_proto::proto::{source_metadata::Source, Match};
+
+/// Opens a match's source video at a given time: `?t=` for YouTube, a `#t=` media
+/// fragment for other URLs and `file://` paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoLink {
+    base: String,
+    kind: LinkKind,
+    /// Game seconds per video second.
+    speed: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkKind {
+    YouTube,
+    Url,
+    File,
+}
+
+impl VideoLink {
+    /// Link builder for the match's source video, or None if it has no file or URL.
+    pub fn for_match(m: &Match) -> Option<Self> {
+        let Some(Source::VideoFile(source)) = m.source.as_ref()?.source.as_ref() else {
+            return None;
+        };
+        let path = source.file_path.as_str();
+        if path.is_empty() {
+            return None;
+        }
+        let speed = if source.playback_speed > 0.0 {
+            source.playback_speed
+        } else {
+            1.0
+        };
+        let (base, kind) = if path.starts_with("http://") || path.starts_with("https://") {
+            let base = path.split('#').next().unwrap_or(path).to_string();
+            let kind = if base.contains("youtube.com/") || base.contains("youtu.be/") {
+                LinkKind::YouTube
+            } else {
+                LinkKind::Url
+            };
+            (base, kind)
+        } else {
+            (file_url(path), LinkKind::File)
+        };
+        Some(Self { base, kind, speed })
+    }
+
+    /// Link to `seconds` as stored in the match (game time when the playback speed
+    /// is not 1.0).
+    pub fn at(&self, seconds: f64) -> String {
+        let video_seconds = (seconds / self.speed).max(0.0);
+        match self.kind {
+            LinkKind::YouTube => {
+                let sep = if self.base.contains('?') { '&' } else { '?' };
+                format!("{}{sep}t={}s", self.base, video_seconds.floor() as u64)
+            }
+            LinkKind::Url | LinkKind::File => format!("{}#t={video_seconds:.1}", self.base),
+        }
+    }
+}
+
+/// `file://` URL of a local path; relative paths are resolved against the current
+/// directory.
+fn file_url(path: &str) -> String {
+    let absolute = if is_windows_absolute(path) || path.starts_with('/') {
+        path.to_string()
+    } else {
+        std::path::absolute(Path::new(path))
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| path.to_string())
+    };
+    let slashed = absolute.replace('\\', "/");
+    let prefix = if slashed.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    };
+    format!("{prefix}{}", percent_encode(&slashed))
+}
+
+/// `C:/...` or `C:\...`.
+fn is_windows_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'/' || bytes[2] == b'\\')
+}
+
+fn percent_encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for &b in path.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/:-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{SourceMetadata, VideoFileSource};
+
+    use super::*;
+
+    fn link(path: &str, speed: f64) -> VideoLink {
+        let m = Match {
+            source: Some(SourceMetadata {
+                source: Some(Source::VideoFile(VideoFileSource {
+                    file_path: path.to_string(),
+                    playback_speed: speed,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        VideoLink::for_match(&m).unwrap()
+    }
+
+    #[test]
+    fn links_per_source_kind() {
+        assert_eq!(
+            link("https://www.youtube.com/watch?v=abc", 0.0).at(83.9),
+            "https://www.youtube.com/watch?v=abc&t=83s"
+        );
+        assert_eq!(
+            link("https://youtu.be/abc", 2.0).at(100.0),
+            "https://youtu.be/abc?t=50s"
+        );
+        assert_eq!(
+            link("https://example.com/vod.mp4#x", 1.0).at(12.34),
+            "https://example.com/vod.mp4#t=12.3"
+        );
+        assert_eq!(
+            link("/videos/my replay.mp4", 1.0).at(5.0),
+            "file:///videos/my%20replay.mp4#t=5.0"
+        );
+        assert_eq!(
+            link("C:\\replays\\session01.mp4", 1.0).at(180.0),
+            "file:///C:/replays/session01.mp4#t=180.0"
+        );
+    }
+
+    #[test]
+    fn no_link_without_source() {
+        assert_eq!(VideoLink::for_match(&Match::default()), None);
+    }
+}
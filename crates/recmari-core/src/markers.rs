@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use tracing::{error, info};
+
+use crate::error::PipelineError;
+
+/// What a manual marker starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    /// A new match (and therefore also a new round).
+    Match,
+    /// A new round within the current match.
+    Round,
+}
+
+/// How manual markers combine with automatic segmentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkerMode {
+    /// Markers add boundaries on top of the automatically detected ones.
+    #[default]
+    Seed,
+    /// Markers are the only boundaries; automatic detection is ignored.
+    Override,
+}
+
+/// A user-provided boundary, e.g. from a stream marker export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    /// Seconds from the start of the source.
+    pub timestamp_seconds: f64,
+    pub kind: MarkerKind,
+    /// Free-form text after the kind, if any.
+    pub label: Option<String>,
+}
+
+/// Load markers from a text file. See [`parse_markers`] for the format.
+pub fn load_markers(path: &Path) -> Result<Vec<Marker>, PipelineError> {
+    info!(?path, "loading markers");
+    let text =
+        std::fs::read_to_string(path).map_err(PipelineError::io("read markers file", path))?;
+    let markers = parse_markers(&text)?;
+    info!(?path, count = markers.len(), "markers loaded");
+    Ok(markers)
+}
+
+/// Parse one marker per line: `<time> [match|round] [label]`.
+///
+/// `<time>` is `HH:MM:SS`, `MM:SS` or plain seconds, each with optional fraction.
+/// The kind defaults to `round`. Blank lines and lines starting with `#` are ignored.
+/// Fields may be separated by whitespace or commas. Markers are returned sorted by time.
+pub fn parse_markers(text: &str) -> Result<Vec<Marker>, PipelineError> {
+    let mut markers = Vec::new();
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty());
+        let time = fields.next().unwrap_or_default();
+        let Some(timestamp_seconds) = parse_timestamp(time) else {
+            error!(line = line_no + 1, time, "invalid marker timestamp");
+            return Err(PipelineError::Parse(format!(
+                "line {}: invalid timestamp '{time}'",
+                line_no + 1
+            )));
+        };
+
+        let rest: Vec<&str> = fields.collect();
+        let (kind, label_fields) = match rest.first().map(|k| k.to_ascii_lowercase()) {
+            Some(k) if k == "match" => (MarkerKind::Match, &rest[1..]),
+            Some(k) if k == "round" => (MarkerKind::Round, &rest[1..]),
+            _ => (MarkerKind::Round, &rest[..]),
+        };
+        let label = (!label_fields.is_empty()).then(|| label_fields.join(" "));
+
+        markers.push(Marker {
+            timestamp_seconds,
+            kind,
+            label,
+        });
+    }
+
+    markers.sort_by(|a, b| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+    Ok(markers)
+}
+
+/// Parse `HH:MM:SS(.f)`, `MM:SS(.f)` or `S(.f)` into seconds.
+fn parse_timestamp(text: &str) -> Option<f64> {
+    let parts: Vec<&str> = text.split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: f64 = part.parse().ok()?;
+        let is_last = i == parts.len() - 1;
+        if value < 0.0 || !value.is_finite() || (!is_last && value.fract() != 0.0) {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Some(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timestamp_formats() {
+        assert_eq!(parse_timestamp("95.5"), Some(95.5));
+        assert_eq!(parse_timestamp("01:35"), Some(95.0));
+        assert_eq!(parse_timestamp("1:00:00.25"), Some(3600.25));
+        assert_eq!(parse_timestamp("1.5:00"), None);
+        assert_eq!(parse_timestamp("abc"), None);
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+    }
+
+    #[test]
+    fn parse_markers_kinds_and_labels() {
+        let text = "\
+# stream markers
+0:10:00 match grand finals
+0:03:00,round
+0:05:30 comeback?
+";
+        let markers = parse_markers(text).unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                Marker {
+                    timestamp_seconds: 180.0,
+                    kind: MarkerKind::Round,
+                    label: None,
+                },
+                Marker {
+                    timestamp_seconds: 330.0,
+                    kind: MarkerKind::Round,
+                    label: Some("comeback?".to_string()),
+                },
+                Marker {
+                    timestamp_seconds: 600.0,
+                    kind: MarkerKind::Match,
+                    label: Some("grand finals".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_markers_rejects_bad_time() {
+        assert!(parse_markers("soon match").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use tracing::info;
+
+use recmari_proto::proto::{EventType, Match, Round, TimelineEvent, TimelineEventType, Winner};
+
+/// Chronological timeline of `m`: round boundaries, the round events worth listing and
+/// burnouts. Call after round events, winners and burnouts are attached.
+pub fn match_timeline(m: &Match) -> Vec<TimelineEvent> {
+    let mut timeline = Vec::new();
+    for round in &m.rounds {
+        round_timeline(round, &mut timeline);
+    }
+    // Stable, so a round's start stays ahead of events on the same frame.
+    timeline.sort_by(|a, b| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+    timeline
+}
+
+fn round_timeline(round: &Round, timeline: &mut Vec<TimelineEvent>) {
+    let (Some(first), Some(last)) = (round.frames.first(), round.frames.last()) else {
+        return;
+    };
+    let event =
+        |r#type: TimelineEventType, frame_number, timestamp_seconds, player| TimelineEvent {
+            r#type: r#type.into(),
+            round_index: round.round_index,
+            frame_number,
+            timestamp_seconds,
+            player,
+            duration_seconds: 0.0,
+        };
+
+    timeline.push(event(
+        TimelineEventType::RoundStart,
+        first.frame_number,
+        round.start_timestamp_seconds,
+        0,
+    ));
+    for e in &round.events {
+        let r#type = match e.r#type() {
+            EventType::Ko => TimelineEventType::Ko,
+            EventType::SaUse => TimelineEventType::SaUse,
+            EventType::DriveImpact => TimelineEventType::DriveImpact,
+            _ => continue,
+        };
+        timeline.push(event(r#type, e.frame_number, e.timestamp_seconds, e.player));
+    }
+    for b in &round.burnouts {
+        timeline.push(event(
+            TimelineEventType::BurnoutEnter,
+            b.start_frame_number,
+            b.start_seconds,
+            b.player,
+        ));
+        if let (Some(frame_number), Some(seconds)) = (b.end_frame_number, b.end_seconds) {
+            timeline.push(TimelineEvent {
+                duration_seconds: b.duration_seconds,
+                ..event(
+                    TimelineEventType::BurnoutExit,
+                    frame_number,
+                    seconds,
+                    b.player,
+                )
+            });
+        }
+    }
+    timeline.push(event(
+        TimelineEventType::RoundEnd,
+        last.frame_number,
+        last.timestamp_seconds,
+        match round.winner() {
+            Winner::P1 => 1,
+            Winner::P2 => 2,
+            Winner::Unknown => 0,
+        },
+    ));
+}
+
+/// Fill in the timeline of every match.
+pub fn attach_timelines(matches: &mut [Match]) {
+    let mut events = 0usize;
+    for m in matches.iter_mut() {
+        m.timeline = match_timeline(m);
+        events += m.timeline.len();
+    }
+    info!(matches = matches.len(), events, "match timelines built");
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{FrameData, RoundEvent};
+
+    use super::*;
+    use crate::burnout::round_burnouts;
+    use crate::test_support::{drive, frame};
+
+    #[test]
+    fn merges_round_events_and_burnout_transitions() {
+        let ko = RoundEvent {
+            r#type: EventType::Ko.into(),
+            frame_number: 300,
+            timestamp_seconds: 5.0,
+            player: 2,
+            ..Default::default()
+        };
+        let banner = RoundEvent {
+            r#type: EventType::FightBanner.into(),
+            ..Default::default()
+        };
+        let mut m = Match {
+            rounds: vec![Round {
+                round_index: 1,
+                start_timestamp_seconds: 0.0,
+                frames: [
+                    (0, Some(6.0), None),
+                    (60, Some(0.5), None),
+                    (120, None, None),
+                    (180, None, Some(0.0)),
+                    (240, Some(6.0), None),
+                    (360, Some(6.0), None),
+                ]
+                .map(|(n, od, burnout)| FrameData {
+                    player1: Some(drive(od, burnout)),
+                    ..frame(n)
+                })
+                .to_vec(),
+                events: vec![banner, ko],
+                winner: Winner::P1.into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        m.rounds[0].burnouts = round_burnouts(&m.rounds[0]);
+
+        let got: Vec<(TimelineEventType, u32, u32)> = match_timeline(&m)
+            .iter()
+            .map(|e| (e.r#type(), e.frame_number, e.player))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                (TimelineEventType::RoundStart, 0, 0),
+                (TimelineEventType::BurnoutEnter, 180, 1),
+                (TimelineEventType::BurnoutExit, 240, 1),
+                (TimelineEventType::Ko, 300, 2),
+                (TimelineEventType::RoundEnd, 360, 1),
+            ]
+        );
+        assert!(match_timeline(&m).iter().all(|e| e.round_index == 1));
+        let exit = &match_timeline(&m)[2];
+        assert!((exit.duration_seconds - 1.0).abs() < 1e-9);
+    }
+}
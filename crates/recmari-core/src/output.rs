@@ -0,0 +1,369 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+use prost::Message;
+use tracing::{error, info};
+
+use recmari_proto::proto::Match;
+
+use crate::error::PipelineError;
+
+/// Serialize matches as length-delimited protobuf and write to file.
+pub fn write_matches(matches: &[Match], output: &Path) -> Result<(), PipelineError> {
+    info!(
+        ?output,
+        match_count = matches.len(),
+        "writing protobuf output"
+    );
+
+    let mut buf = Vec::new();
+    for m in matches {
+        m.encode_length_delimited(&mut buf)
+            .expect("encoding into a Vec never runs out of space");
+    }
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(PipelineError::io("create output directory", parent))?;
+    }
+
+    write_atomic(output, &buf)?;
+
+    info!(?output, bytes = buf.len(), "protobuf output written");
+    Ok(())
+}
+
+/// A file written under a temporary name next to `path` and renamed over it by
+/// [`AtomicFile::commit`], so an interrupted run never leaves a truncated file behind.
+/// Dropping it without committing removes the temporary file.
+pub struct AtomicFile {
+    path: PathBuf,
+    temp: PathBuf,
+    file: Option<BufWriter<File>>,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> Result<Self, PipelineError> {
+        let Some(name) = path.file_name() else {
+            error!(?path, "output path has no file name");
+            return Err(PipelineError::InvalidConfig(format!(
+                "not a file path: {}",
+                path.display()
+            )));
+        };
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".tmp{}", std::process::id()));
+        let temp = path.with_file_name(temp_name);
+        let file = File::create(&temp).map_err(PipelineError::io("create", &temp))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp,
+            file: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Flush and fsync the data, then rename it into place.
+    pub fn commit(mut self) -> Result<(), PipelineError> {
+        let file = self.file.take().expect("AtomicFile committed twice");
+        let result = finish(file, &self.temp, &self.path);
+        if result.is_err() {
+            let _ = std::fs::remove_file(&self.temp);
+            error!(path = ?self.path, "failed to commit output file");
+        }
+        result
+    }
+}
+
+fn finish(file: BufWriter<File>, temp: &Path, path: &Path) -> Result<(), PipelineError> {
+    let file = file
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .map_err(PipelineError::io("write", temp))?;
+    file.sync_all().map_err(PipelineError::io("sync", temp))?;
+    std::fs::rename(temp, path).map_err(PipelineError::io("rename into place", path))?;
+    // Persist the rename itself; directories cannot be opened for syncing on Windows.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        File::open(dir)
+            .and_then(|d| d.sync_all())
+            .map_err(PipelineError::io("sync", dir))?;
+    }
+    Ok(())
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file
+            .as_mut()
+            .expect("AtomicFile already committed")
+            .write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file
+            .as_mut()
+            .expect("AtomicFile already committed")
+            .flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = std::fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Write `bytes` to `path` through an [`AtomicFile`].
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), PipelineError> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(bytes)
+        .map_err(PipelineError::io("write", path))?;
+    file.commit()
+}
+
+/// Read every length-delimited `Match` from an analysis output file.
+pub fn read_matches(input: &Path) -> Result<Vec<Match>, PipelineError> {
+    let reader = MatchReader::open(input)?;
+    let matches = reader.iter().collect::<Result<Vec<_>, _>>()?;
+    info!(?input, match_count = matches.len(), "protobuf output read");
+    Ok(matches)
+}
+
+/// Decode a buffer of length-delimited `Match` messages.
+pub fn decode_matches(bytes: &[u8]) -> Result<Vec<Match>, PipelineError> {
+    index_messages(bytes)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, range)| decode_at(bytes, i, range))
+        .collect()
+}
+
+/// Random access to the matches of a (possibly multi-gigabyte) output file.
+///
+/// The file is memory-mapped and only the length prefixes are scanned on open;
+/// each `Match` is decoded when requested.
+pub struct MatchReader {
+    /// None for an empty file, which cannot be mapped.
+    mmap: Option<Mmap>,
+    /// Byte range of each encoded `Match` (without its length prefix).
+    index: Vec<Range<usize>>,
+}
+
+impl MatchReader {
+    pub fn open(input: &Path) -> Result<Self, PipelineError> {
+        info!(?input, "opening protobuf output");
+        let file = File::open(input).map_err(PipelineError::io("open", input))?;
+        let len = file
+            .metadata()
+            .map_err(PipelineError::io("stat", input))?
+            .len();
+
+        let mmap = if len == 0 {
+            None
+        } else {
+            // SAFETY: output files are written once by `write_matches` and not modified
+            // while being read.
+            let mmap = unsafe { Mmap::map(&file) }.map_err(PipelineError::io("map", input))?;
+            Some(mmap)
+        };
+        let index = index_messages(mmap.as_deref().unwrap_or_default()).map_err(|e| {
+            PipelineError::Parse(format!("failed to index {}: {e}", input.display()))
+        })?;
+
+        info!(
+            ?input,
+            bytes = len,
+            match_count = index.len(),
+            "protobuf output indexed"
+        );
+        Ok(Self { mmap, index })
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Decode the match at `index` (0-based).
+    pub fn get(&self, index: usize) -> Result<Match, PipelineError> {
+        assert!(
+            index < self.index.len(),
+            "match index {index} out of range (len {})",
+            self.index.len()
+        );
+        decode_at(self.bytes(), index, self.index[index].clone())
+    }
+
+    /// Decode matches in file order.
+    pub fn iter(&self) -> impl Iterator<Item = Result<Match, PipelineError>> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.mmap.as_deref().unwrap_or_default()
+    }
+}
+
+/// Find the byte range of every length-delimited message without decoding them.
+fn index_messages(bytes: &[u8]) -> Result<Vec<Range<usize>>, PipelineError> {
+    let mut index = Vec::new();
+    let mut pos = 0usize;
+    while pos < bytes.len() {
+        let mut rest = &bytes[pos..];
+        let len = match prost::encoding::decode_varint(&mut rest) {
+            Ok(len) => len as usize,
+            Err(e) => {
+                error!(offset = pos, index = index.len(), %e, "invalid length prefix");
+                return Err(PipelineError::Parse(format!(
+                    "invalid length prefix at byte {pos}: {e}"
+                )));
+            }
+        };
+        let start = bytes.len() - rest.len();
+        let end = start.saturating_add(len);
+        if end > bytes.len() {
+            error!(
+                offset = pos,
+                len,
+                file_len = bytes.len(),
+                "truncated message"
+            );
+            return Err(PipelineError::Parse(format!(
+                "message {} at byte {pos} is truncated",
+                index.len()
+            )));
+        }
+        index.push(start..end);
+        pos = end;
+    }
+    Ok(index)
+}
+
+fn decode_at(bytes: &[u8], index: usize, range: Range<usize>) -> Result<Match, PipelineError> {
+    match Match::decode(&bytes[range]) {
+        Ok(m) => Ok(m),
+        Err(e) => {
+            error!(index, %e, "failed to decode Match");
+            Err(PipelineError::Parse(format!(
+                "failed to decode match {index}: {e}"
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::Round;
+
+    use super::*;
+
+    #[test]
+    fn decode_round_trips_length_delimited() {
+        let matches: Vec<Match> = (0..3)
+            .map(|i| Match {
+                rounds: vec![Round {
+                    round_index: i,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+            .collect();
+        let mut buf = Vec::new();
+        for m in &matches {
+            m.encode_length_delimited(&mut buf).unwrap();
+        }
+        assert_eq!(decode_matches(&buf).unwrap(), matches);
+    }
+
+    #[test]
+    fn decode_rejects_truncated_input() {
+        let m = Match {
+            rounds: vec![Round::default()],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        m.encode_length_delimited(&mut buf).unwrap();
+        buf.pop();
+        assert!(decode_matches(&buf).is_err());
+    }
+
+    #[test]
+    fn reader_decodes_lazily_by_index() {
+        let dir = std::env::temp_dir().join(format!("recmari-output-{}", std::process::id()));
+        let path = dir.join("matches.pb");
+        let matches: Vec<Match> = (0..4)
+            .map(|i| Match {
+                winner: i % 3,
+                ..Default::default()
+            })
+            .collect();
+        write_matches(&matches, &path).unwrap();
+
+        let reader = MatchReader::open(&path).unwrap();
+        assert_eq!(reader.len(), 4);
+        assert_eq!(reader.get(2).unwrap(), matches[2]);
+        assert_eq!(read_matches(&path).unwrap(), matches);
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(MatchReader::open(&path).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_file_appears_only_on_commit() {
+        let dir = std::env::temp_dir().join(format!("recmari-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.csv");
+        std::fs::write(&path, b"old").unwrap();
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"new").unwrap();
+        drop(file);
+        assert_eq!(std::fs::read(&path).unwrap(), b"old");
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"new").unwrap();
+        file.commit().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Quick in-tree counterpart of the `output_reader` fuzz target.
+    proptest::proptest! {
+        #[test]
+        fn decode_never_panics_on_arbitrary_bytes(
+            bytes in proptest::collection::vec(proptest::num::u8::ANY, 0..512),
+        ) {
+            let _ = decode_matches(&bytes);
+        }
+
+        #[test]
+        fn decode_never_panics_on_corrupted_output(
+            cut in 0usize..200,
+            flip in 0usize..200,
+            bit in 0u8..8,
+        ) {
+            let m = Match {
+                rounds: vec![Round::default(); 3],
+                ..Default::default()
+            };
+            let mut buf = Vec::new();
+            m.encode_length_delimited(&mut buf).unwrap();
+            m.encode_length_delimited(&mut buf).unwrap();
+            let flip = flip % buf.len();
+            buf[flip] ^= 1 << bit;
+            buf.truncate(cut.min(buf.len()));
+            let _ = decode_matches(&buf);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use image::{Rgba, RgbaImage};
+use tracing::info;
+
+use recmari_proto::proto::FrameData;
+
+use crate::chart::{Metric, Side};
+
+/// Size of the overlay image: P1 on the left half, P2 on the right, one row per gauge.
+pub const OVERLAY_WIDTH: u32 = 480;
+pub const OVERLAY_HEIGHT: u32 = 180;
+const CELL_PADDING: f64 = 6.0;
+const ROWS: [Metric; 3] = [Metric::Hp, Metric::Sa, Metric::Od];
+const BACKDROP: Rgba<u8> = Rgba([0, 0, 0, 96]);
+/// Player colors of the viewer.
+const P1_COLOR: Rgba<u8> = Rgba([0x42, 0xa5, 0xf5, 255]);
+const P2_COLOR: Rgba<u8> = Rgba([0xef, 0x53, 0x50, 255]);
+
+/// One gauge history line in overlay pixel coordinates. Unreadable frames split it
+/// into separate segments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sparkline {
+    pub side: Side,
+    pub metric: Metric,
+    pub segments: Vec<Vec<(f64, f64)>>,
+}
+
+/// HP, SA and OD sparklines of both players over `frames`, stretched to the overlay
+/// width.
+pub fn sparklines(frames: &[FrameData]) -> Vec<Sparkline> {
+    let t0 = frames.first().map_or(0.0, |f| f.timestamp_seconds);
+    let t1 = frames.last().map_or(0.0, |f| f.timestamp_seconds);
+    let span = (t1 - t0).max(1.0);
+    let frames: Vec<&FrameData> = frames.iter().collect();
+
+    let mut lines = Vec::new();
+    for (column, side) in [Side::P1, Side::P2].into_iter().enumerate() {
+        for (row, metric) in ROWS.into_iter().enumerate() {
+            let (x, y, w, h) = cell(column, row);
+            let segments = crate::chart::segments(&frames, side, metric, t0)
+                .into_iter()
+                .map(|segment| {
+                    segment
+                        .into_iter()
+                        .map(|(t, v)| {
+                            let ratio = v.clamp(0.0, metric.max()) / metric.max();
+                            (x + t / span * w, y + (1.0 - ratio) * h)
+                        })
+                        .collect()
+                })
+                .collect();
+            lines.push(Sparkline {
+                side,
+                metric,
+                segments,
+            });
+        }
+    }
+    lines
+}
+
+/// `x, y, width, height` of the plot area of one cell.
+fn cell(column: usize, row: usize) -> (f64, f64, f64, f64) {
+    let cell_w = OVERLAY_WIDTH as f64 / 2.0;
+    let cell_h = OVERLAY_HEIGHT as f64 / ROWS.len() as f64;
+    (
+        column as f64 * cell_w + CELL_PADDING,
+        row as f64 * cell_h + CELL_PADDING,
+        cell_w - 2.0 * CELL_PADDING,
+        cell_h - 2.0 * CELL_PADDING,
+    )
+}
+
+/// Render the sparklines of `frames` on a transparent image, for an OBS image source.
+pub fn render_png(frames: &[FrameData]) -> RgbaImage {
+    let mut img = RgbaImage::new(OVERLAY_WIDTH, OVERLAY_HEIGHT);
+    for column in 0..2 {
+        for row in 0..ROWS.len() {
+            let (x, y, w, h) = cell(column, row);
+            for py in (y - 2.0) as u32..(y + h + 2.0) as u32 {
+                for px in (x - 2.0) as u32..(x + w + 2.0) as u32 {
+                    img.put_pixel(px, py, BACKDROP);
+                }
+            }
+        }
+    }
+    let lines = sparklines(frames);
+    for line in &lines {
+        let color = match line.side {
+            Side::P1 => P1_COLOR,
+            Side::P2 => P2_COLOR,
+        };
+        for segment in &line.segments {
+            for pair in segment.windows(2) {
+                draw_line(&mut img, pair[0], pair[1], color);
+            }
+        }
+    }
+    info!(
+        frames = frames.len(),
+        sparklines = lines.len(),
+        "overlay rendered"
+    );
+    img
+}
+
+/// Draw a 2-px wide line by stamping along it every half pixel.
+fn draw_line(img: &mut RgbaImage, from: (f64, f64), to: (f64, f64), color: Rgba<u8>) {
+    let steps = ((to.0 - from.0).hypot(to.1 - from.1) * 2.0).ceil().max(1.0) as u32;
+    for i in 0..=steps {
+        let t = i as f64 / steps as f64;
+        let x = from.0 + (to.0 - from.0) * t;
+        let y = from.1 + (to.1 - from.1) * t;
+        for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            let (px, py) = ((x + dx) as u32, (y + dy) as u32);
+            if px < img.width() && py < img.height() {
+                img.put_pixel(px, py, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_support::{frame_at, health};
+
+    #[test]
+    fn sparklines_fill_their_cell() {
+        let frames =
+            [(10.0, Some(1.0)), (15.0, None), (20.0, Some(0.0))].map(|(ts, hp)| FrameData {
+                player1: Some(health(hp)),
+                ..frame_at(ts)
+            });
+        let lines = sparklines(&frames);
+        assert_eq!(lines.len(), 6);
+        let hp = &lines[0];
+        assert_eq!((hp.side, hp.metric), (Side::P1, Metric::Hp));
+        let (x, y, w, h) = cell(0, 0);
+        assert_eq!(hp.segments, vec![vec![(x, y)], vec![(x + w, y + h)]]);
+        assert!(lines[3..].iter().all(|l| l.segments.is_empty()));
+    }
+
+    #[test]
+    fn png_is_transparent_outside_the_cells() {
+        let frames = [(0.0, 1.0), (5.0, 0.5)].map(|(ts, hp)| FrameData {
+            player1: Some(health(Some(hp))),
+            ..frame_at(ts)
+        });
+        let img = render_png(&frames);
+        assert_eq!(img.dimensions(), (OVERLAY_WIDTH, OVERLAY_HEIGHT));
+        assert_eq!(img.get_pixel(OVERLAY_WIDTH / 2, 0)[3], 0);
+        assert!(img.pixels().any(|p| *p == P1_COLOR));
+        assert!(!img.pixels().any(|p| *p == P2_COLOR));
+    }
+}
@@ -1,27 +1,76 @@
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{bail, Context, Result};
-use tracing::info;
+use tracing::{debug, error, info, warn};
 
 use recmari_proto::proto::{
-    source_metadata::Source, FrameData, Match, PlayerState, Round, SourceMetadata, VideoFileSource,
-    Winner,
+    source_metadata::Source, CustomEvent, FrameData, FrameStatus, Match, PlayerState, Round,
+    SourceMetadata, VideoFileSource, Winner,
 };
 
-use crate::analysis::huds::manemon::ManemonHud;
-use crate::analysis::{Hud, OdValue};
-use crate::debug::DebugRenderer;
-use crate::video::decoder::VideoDecoder;
-use crate::video::frame::Frame;
+use crate::absolute_health::{self, Characters};
+use crate::analysis::detector::{HudDetector, DEFAULT_RECHECK_INTERVAL};
+use crate::analysis::events::{
+    self, fight_banner, gauges, screen_effects, super_arts, EventDebounce, EventKind, GameEvent,
+};
+use crate::analysis::huds::{
+    self,
+    manemon::{self, ManemonHud},
+};
+use crate::analysis::{HpReading, Hud, OdReading, OdValue, SaReading, SaValue};
+use crate::analyzer::{self, AnalyzerRegistry, FrameAnalyzer};
+use crate::anomaly::{AnomalyCause, AnomalyLog};
+use crate::audio_cues::{self, AudioCue, CueKind, CueTemplate};
+use crate::burnout;
+use crate::chat;
+use crate::checkpoint::{self, Checkpoint, CheckpointWriter};
+use crate::config::{HudConfig, HudElements};
+use crate::damage;
+use crate::debug::{DebugOutput, DebugRenderer};
+use crate::defense;
+use crate::error::{HudError, PipelineError};
+use crate::frame_list::FrameList;
+use crate::interpolate::{self, Interpolation};
+use crate::ko_slowdown;
+use crate::markers::{self, Marker, MarkerKind, MarkerMode};
+use crate::match_timeline;
+use crate::playback::{self, PlaybackSpeed};
+use crate::progress::Progress;
+use crate::round_finish;
+use crate::stats;
+use crate::stream::FrameStream;
+use crate::video::decoder::{self, ContentCrop, CropMode, DecoderConfig, VideoDecoder};
+use crate::video::dedup::FrameDeduplicator;
+use crate::video::frame::{Frame, FrameSource};
+use crate::video::{self, audio, refine};
+use crate::winner;
 
 /// Both players' health must be at or above this to count as "full".
 const ROUND_RESET_THRESHOLD: f64 = 0.95;
 /// At least one player's health must drop below this to arm round detection.
 const DAMAGE_THRESHOLD: f64 = 0.5;
-/// Number of round wins required to win a match.
-const ROUNDS_TO_WIN: u32 = 2;
+/// HUD-less gaps between HUD frames up to this long are labeled as cinematics
+/// (Critical Arts and similar hide the HUD for a few seconds).
+const CINEMATIC_MAX_SECONDS: f64 = 8.0;
+/// Both bars emptying within this many seconds of each other is a double KO.
+const DOUBLE_KO_WINDOW_SECONDS: f64 = 1.0;
+/// An audio cue whose boundary lies within this many seconds of an existing one
+/// confirms it instead of adding another.
+const AUDIO_CUE_TOLERANCE_SECONDS: f64 = 5.0;
+/// The round-start announcer plays at most this long after both bars refill.
+const ROUND_START_CUE_LEAD_SECONDS: f64 = 8.0;
+/// After a KO cue, both bars must refill within this long for a next round to start
+/// (otherwise the KO ended the match).
+const KO_CUE_NEXT_ROUND_SECONDS: f64 = 15.0;
+/// Reference clips looked up in the audio cue directory.
+const AUDIO_CUE_CLIPS: [(CueKind, &str); 2] = [
+    (CueKind::RoundStart, "round_start.wav"),
+    (CueKind::Ko, "ko.wav"),
+];
 
 /// Parameters for the analysis pipeline.
+#[derive(Clone)]
 pub struct PipelineConfig {
     /// Analyze every Nth decoded frame (1 = every frame).
     pub sample_rate: u32,
@@ -29,8 +78,101 @@ pub struct PipelineConfig {
     pub start_frame: u32,
     /// Maximum number of frames to process, or None for the entire video.
     pub max_frames: Option<u32>,
+    /// Debug overlay outputs.
+    pub debug: DebugConfig,
+    /// How frames are decoded before sampling.
+    pub decode: DecodeOptions,
+    /// JSON chat log of the stream VOD; activity spikes are attached to rounds.
+    pub chat_log: Option<PathBuf>,
+    /// Round boundaries known from outside the HUD.
+    pub boundaries: BoundaryHints,
+    /// HUD settings, e.g. elements hidden by a custom overlay.
+    pub hud_config: HudConfig,
+    /// Only look for this HUD (see [`huds::hud_names`]) instead of choosing among all
+    /// of them per frame. Frames where it is not detected are still HUD-absent. None
+    /// tries every HUD.
+    pub hud: Option<String>,
+    /// Output each frame's own readings: unreadable values stay absent (status
+    /// `Occluded`) instead of carrying the last-known value forward.
+    pub keep_unreadable: bool,
+    /// Fill missing HP/SA/OD values within rounds after segmentation (see
+    /// [`interpolate::interpolate_round`]). None leaves the gaps. Implies
+    /// `keep_unreadable`, so that there are gaps to fill, and runs after the round
+    /// statistics, which only count read values.
+    pub interpolation: Option<Interpolation>,
+    /// Checkpointing for resuming a crashed run.
+    pub checkpoint: CheckpointConfig,
+    /// Write each sampled frame here as soon as it is analyzed (JSONL for `.jsonl`,
+    /// length-delimited `FrameData` otherwise).
+    pub stream_file: Option<PathBuf>,
+    /// Report percentage, throughput and ETA on stderr while decoding.
+    pub progress: bool,
+    /// Playback speed of the capture; output timestamps are converted to game time.
+    pub playback_speed: PlaybackSpeed,
+    /// Re-decode the skipped frames before each event to report its exact frame
+    /// instead of the sample it was seen on. Only applies when `sample_rate` > 1.
+    pub precise_events: bool,
+    /// Discard a frame's readings (gap-filling instead) when its analysis took at
+    /// least this long, as slow frames tend to be misreads. This is not a timeout: the
+    /// analysis always runs to completion and is only judged afterwards.
+    pub slow_frame_limit: Option<Duration>,
+    /// Custom analyzers run on every sampled frame after the HUD analysis. A panicking
+    /// analyzer loses its outputs for that frame and records an anomaly.
+    pub analyzers: AnalyzerRegistry,
+    /// Also run the detectors whose screen positions are not calibrated against
+    /// fixtures yet: the round-intro FIGHT banner and the KO slowdown length.
+    pub experimental: bool,
+    /// Characters of player 1 and player 2 in every match of the video, with the
+    /// maximum health used for the absolute health and damage estimates. None leaves
+    /// them empty.
+    pub characters: Option<Characters>,
+}
+
+/// Debug overlay outputs of [`PipelineConfig`].
+#[derive(Clone, Default)]
+pub struct DebugConfig {
     /// Directory to write debug frame images, or None to skip.
-    pub debug_frames_dir: Option<PathBuf>,
+    pub frames_dir: Option<PathBuf>,
+    /// Encode debug overlays into this video instead of PNG frames.
+    pub video: Option<PathBuf>,
+    /// Font for debug overlay text instead of the bundled one.
+    pub font: Option<PathBuf>,
+}
+
+/// Decoding settings of [`PipelineConfig`].
+#[derive(Clone, Default)]
+pub struct DecodeOptions {
+    /// Decode only the rows containing HUD elements. Much less data crosses the ffmpeg
+    /// pipe, but stage center-line detection is disabled.
+    pub hud_rows_only: bool,
+    /// Game picture of a letterboxed or pillarboxed recording; it is cropped and
+    /// scaled to 1920x1080 so HUD regions line up. None analyzes the full frame.
+    pub crop: Option<CropMode>,
+    /// Skip frames that repeat the previous one (e.g. 30fps footage encoded at 60fps);
+    /// `sample_rate` then counts distinct frames only.
+    pub dedup_frames: bool,
+}
+
+/// Round boundaries of [`PipelineConfig`] known from outside the HUD.
+#[derive(Clone, Default)]
+pub struct BoundaryHints {
+    /// Text file of manual match/round markers (see [`markers::parse_markers`]).
+    pub markers_file: Option<PathBuf>,
+    /// Whether markers add to or replace automatic round/match detection.
+    pub marker_mode: MarkerMode,
+    /// Directory of reference clips (`round_start.wav`, `ko.wav`) to find in the
+    /// soundtrack; round boundaries the HUD missed are added where they occur.
+    pub audio_cues_dir: Option<PathBuf>,
+}
+
+/// Checkpointing of [`PipelineConfig`].
+#[derive(Clone, Default)]
+pub struct CheckpointConfig {
+    /// Append-only file of analyzed frames, for resuming a crashed run.
+    /// Ignored when `max_frames` is set.
+    pub file: Option<PathBuf>,
+    /// Continue from the frames already in `file` instead of starting over.
+    pub resume: bool,
 }
 
 impl Default for PipelineConfig {
@@ -39,18 +181,34 @@ impl Default for PipelineConfig {
             sample_rate: 60,
             start_frame: 0,
             max_frames: None,
-            debug_frames_dir: None,
+            debug: DebugConfig::default(),
+            decode: DecodeOptions::default(),
+            chat_log: None,
+            boundaries: BoundaryHints::default(),
+            hud_config: HudConfig::default(),
+            hud: None,
+            keep_unreadable: false,
+            interpolation: None,
+            checkpoint: CheckpointConfig::default(),
+            stream_file: None,
+            progress: false,
+            playback_speed: PlaybackSpeed::default(),
+            precise_events: false,
+            slow_frame_limit: None,
+            analyzers: AnalyzerRegistry::default(),
+            experimental: false,
+            characters: None,
         }
     }
 }
 
 /// Carries forward last-known gauge values across frames when a reading is temporarily unavailable.
-#[derive(Default)]
+#[derive(Default, Clone)]
 struct GapFillState {
     p1_hp: Option<f64>,
     p2_hp: Option<f64>,
-    p1_sa: Option<f64>,
-    p2_sa: Option<f64>,
+    p1_sa: Option<SaValue>,
+    p2_sa: Option<SaValue>,
     p1_od: Option<OdValue>,
     p2_od: Option<OdValue>,
 }
@@ -59,21 +217,77 @@ impl GapFillState {
     fn clear(&mut self) {
         *self = Self::default();
     }
+
+    /// This frame's readings, with the unreadable ones carried over from `self`.
+    fn fill(&self, hp: &HpReading, sa: &SaReading, od: &OdReading) -> Self {
+        Self {
+            p1_hp: hp.p1.or(self.p1_hp),
+            p2_hp: hp.p2.or(self.p2_hp),
+            p1_sa: sa.p1.or(self.p1_sa),
+            p2_sa: sa.p2.or(self.p2_sa),
+            p1_od: od.p1.or(self.p1_od),
+            p2_od: od.p2.or(self.p2_od),
+        }
+    }
+
+    /// Which values are known, per element (HP, SA, OD) and player (P1 first).
+    fn known(&self) -> [[bool; 2]; 3] {
+        [
+            [self.p1_hp.is_some(), self.p2_hp.is_some()],
+            [self.p1_sa.is_some(), self.p2_sa.is_some()],
+            [self.p1_od.is_some(), self.p2_od.is_some()],
+        ]
+    }
+
+    fn player_states(&self) -> [PlayerState; 2] {
+        [
+            od_to_player_state(self.p1_hp, self.p1_sa, self.p1_od),
+            od_to_player_state(self.p2_hp, self.p2_sa, self.p2_od),
+        ]
+    }
+}
+
+/// Content crop for the configured [`CropMode`], scaled to the HUD reference size.
+/// Auto-detection that finds no bars analyzes the full frame.
+fn resolve_crop(
+    input: &Path,
+    info: &decoder::ProbeResult,
+    mode: Option<CropMode>,
+) -> Result<Option<ContentCrop>, PipelineError> {
+    let rect = match mode {
+        None => return Ok(None),
+        Some(CropMode::Fixed(rect)) => rect,
+        Some(CropMode::Auto) => match decoder::detect_content_rect(input, info)? {
+            Some(rect) => rect,
+            None => return Ok(None),
+        },
+    };
+    let (width, height) = ManemonHud::REFERENCE_SIZE;
+    info!(?rect, width, height, "cropping video to the game picture");
+    Ok(Some(ContentCrop {
+        rect,
+        width,
+        height,
+    }))
+}
+
+/// Result of a pipeline run.
+pub struct PipelineOutput {
+    pub matches: Vec<Match>,
+    /// Every sampled frame that could not be fully analyzed.
+    pub anomalies: AnomalyLog,
 }
 
 /// Run the analysis pipeline on a video file.
 ///
 /// When `max_frames` is set, collects up to that many frames (skipping sample_rate filtering),
-/// saves debug overlays if configured, and returns an empty Vec.
+/// saves debug overlays if configured, and returns no matches.
 /// Otherwise, processes the full video and returns detected matches.
-pub fn run_pipeline(input: &Path, config: &PipelineConfig) -> Result<Vec<Match>> {
-    if !input.exists() {
-        bail!("input video does not exist: {}", input.display());
-    }
-    if config.sample_rate < 1 {
-        bail!("sample_rate must be >= 1, got {}", config.sample_rate);
-    }
-
+pub fn run_pipeline(
+    input: &Path,
+    config: &PipelineConfig,
+) -> Result<PipelineOutput, PipelineError> {
+    validate_input(input, config)?;
     info!(
         ?input,
         start_frame = config.start_frame,
@@ -81,140 +295,1091 @@ pub fn run_pipeline(input: &Path, config: &PipelineConfig) -> Result<Vec<Match>>
         sample_rate = config.sample_rate,
         "pipeline starting"
     );
+    let markers = match &config.boundaries.markers_file {
+        Some(path) => markers::load_markers(path)?,
+        None => Vec::new(),
+    };
 
-    let mut decoder =
-        VideoDecoder::open_at_frame(input, config.start_frame).context("failed to open video")?;
+    let info = decoder::probe(input)?;
+    let (mut collected, mut detector, decoder_config) = collect_video(input, &info, config)?;
+    let refiner = Refiner {
+        input,
+        decoder_config: &decoder_config,
+        elements: &config.hud_config.elements,
+    };
+    add_gauge_events(
+        &refiner,
+        &mut detector,
+        config,
+        &mut collected.frames,
+        &mut collected.events,
+    )?;
+    let markers = add_audio_cue_boundaries(input, config, &collected.frames, markers)?;
+    let mut matches = segment_into_matches(
+        &collected.frames,
+        input,
+        &markers,
+        config.boundaries.marker_mode,
+    );
+    finish_matches(&mut matches, config, &info, &collected)?;
+    collected.anomalies.log_summary();
+    info!(match_count = matches.len(), "pipeline complete");
+
+    Ok(PipelineOutput {
+        matches,
+        anomalies: collected.anomalies,
+    })
+}
 
-    let debug_renderer = config.debug_frames_dir.as_ref().map(|dir| {
-        std::fs::create_dir_all(dir).expect("failed to create debug frames directory");
-        info!(?dir, "debug frames directory ready");
-        DebugRenderer::new()
-    });
+/// Reject a missing input or an unusable sample rate before any decoding.
+fn validate_input(input: &Path, config: &PipelineConfig) -> Result<(), PipelineError> {
+    if !input.exists() && !video::is_url(input) {
+        error!(?input, "input video does not exist");
+        return Err(PipelineError::InputNotFound(input.to_path_buf()));
+    }
+    if config.sample_rate < 1 {
+        error!(sample_rate = config.sample_rate, "invalid sample rate");
+        return Err(PipelineError::InvalidConfig(format!(
+            "sample_rate must be >= 1, got {}",
+            config.sample_rate
+        )));
+    }
+    Ok(())
+}
 
-    let frame_data = collect_frame_data(&mut decoder, config, &debug_renderer)?;
+/// Decode and analyze the sampled frames of `input`, after the frames resumed from the
+/// checkpoint. Fails when no sampled frame shows a HUD. Returns the frames with
+/// cinematics labeled, the detector and the decoder settings for re-decoding.
+fn collect_video(
+    input: &Path,
+    info: &decoder::ProbeResult,
+    config: &PipelineConfig,
+) -> Result<(CollectedFrames, HudDetector, DecoderConfig), PipelineError> {
+    let (resumed, mut checkpoint, start_frame) = prepare_checkpoint(config)?;
+    let mut stream = open_stream(config, &resumed.frames)?;
+    let (mut detector, decoder_config) = prepare_decoding(input, info, config, start_frame)?;
+    let mut decoder = VideoDecoder::open_with(input, &decoder_config)?;
+    let mut debug_output = open_debug_output(config, info.fps)?;
+    let mut progress = config
+        .progress
+        .then(|| Progress::new(info.frame_count, start_frame));
+
+    let sinks = FrameSinks {
+        debug: debug_output.as_mut(),
+        checkpoint: checkpoint.as_mut(),
+        stream: stream.as_mut(),
+        progress: progress.as_mut(),
+    };
+    let mut collected = collect_frame_data(&mut decoder, &mut detector, config, sinks)?;
+    if let Some(debug) = debug_output {
+        debug.finish()?;
+    }
+    collected.prepend(resumed);
+    if config.max_frames.is_none() {
+        ensure_hud_seen(&collected.frames)?;
+    }
+    label_cinematics(&mut collected.frames);
     info!(
-        total_sampled_frames = frame_data.len(),
+        total_sampled_frames = collected.frames.len(),
         "frame collection complete"
     );
+    Ok((collected, detector, decoder_config))
+}
+
+/// Frame stream for `stream_file`, starting with the frames resumed from the checkpoint.
+fn open_stream(
+    config: &PipelineConfig,
+    resumed: &[FrameData],
+) -> Result<Option<FrameStream>, PipelineError> {
+    let Some(path) = config.stream_file.as_deref() else {
+        return Ok(None);
+    };
+    let mut stream = FrameStream::create(path)?;
+    for fd in resumed {
+        stream.append(fd)?;
+    }
+    Ok(Some(stream))
+}
 
-    let matches = segment_into_matches(&frame_data, input);
+/// HUD detector and decoder settings for the video described by `info`.
+fn prepare_decoding(
+    input: &Path,
+    info: &decoder::ProbeResult,
+    config: &PipelineConfig,
+    start_frame: u32,
+) -> Result<(HudDetector, DecoderConfig), PipelineError> {
+    let crop = resolve_crop(input, info, config.decode.crop)?;
+    let (width, height) = crop.map_or((info.width, info.height), |c| (c.width, c.height));
+    if !ManemonHud::supports_resolution(width, height) {
+        error!(width, height, "unsupported video resolution");
+        return Err(HudError::UnsupportedResolution { width, height }.into());
+    }
+    let detector = match &config.hud {
+        Some(name) => {
+            let hud = huds::create(name, width, height, &config.hud_config)?;
+            HudDetector::new(vec![hud], DEFAULT_RECHECK_INTERVAL)
+        }
+        None => HudDetector::with_all_huds(width, height, &config.hud_config),
+    };
+    let decoder_config = DecoderConfig {
+        start_frame,
+        row_bands: if config.decode.hud_rows_only {
+            detector.hud_rows()
+        } else {
+            Vec::new()
+        },
+        crop,
+        // Deduplication needs every frame, and max_frames ignores the sample rate.
+        frame_step: if config.decode.dedup_frames || config.max_frames.is_some() {
+            1
+        } else {
+            config.sample_rate
+        },
+    };
+    Ok((detector, decoder_config))
+}
+
+/// Fail when not one of the sampled `frames` showed a HUD: the video is probably not
+/// SF6 footage, or the capture needs a HUD profile.
+fn ensure_hud_seen(frames: &[FrameData]) -> Result<(), HudError> {
+    if frames.is_empty() || !frames.iter().all(is_hud_absent) {
+        return Ok(());
+    }
+    error!(
+        frames = frames.len(),
+        "no HUD detected in any sampled frame"
+    );
+    Err(HudError::NeverDetected {
+        frames: frames.len(),
+    })
+}
+
+/// Add round boundaries heard in the soundtrack to `markers`, unless markers override
+/// round detection.
+fn add_audio_cue_boundaries(
+    input: &Path,
+    config: &PipelineConfig,
+    frames: &[FrameData],
+    markers: Vec<Marker>,
+) -> Result<Vec<Marker>, PipelineError> {
+    let Some(dir) = &config.boundaries.audio_cues_dir else {
+        return Ok(markers);
+    };
+    if config.boundaries.marker_mode == MarkerMode::Override {
+        warn!("audio cues are ignored when markers override round detection");
+        return Ok(markers);
+    }
+    let cues = detect_audio_cues(input, dir)?;
+    Ok(add_audio_cue_markers(frames, markers, &cues))
+}
+
+/// Attach everything known about the segmented `matches` besides their frames:
+/// characters, round details and analysis metadata.
+fn finish_matches(
+    matches: &mut [Match],
+    config: &PipelineConfig,
+    info: &decoder::ProbeResult,
+    collected: &CollectedFrames,
+) -> Result<(), PipelineError> {
+    if let Some(characters) = &config.characters {
+        absolute_health::attach_characters(matches, characters);
+    }
+    attach_round_details(matches, config, &collected.events, &collected.custom_events)?;
+    let sample_rate = if config.max_frames.is_some() {
+        1
+    } else {
+        config.sample_rate
+    };
+    attach_analysis_metadata(matches, info, sample_rate, SystemTime::now());
     for (i, m) in matches.iter().enumerate() {
         log_match_summary(i + 1, m);
     }
-    info!(match_count = matches.len(), "pipeline complete");
+    Ok(())
+}
 
-    Ok(matches)
+/// Add the events read from gauge changes to `game_events` and sort them, re-decoding
+/// skipped frames for exact burnout entries and (with `precise_events`) event times.
+fn add_gauge_events(
+    refiner: &Refiner,
+    detector: &mut HudDetector,
+    config: &PipelineConfig,
+    frame_data: &mut Vec<FrameData>,
+    game_events: &mut Vec<GameEvent>,
+) -> Result<(), PipelineError> {
+    let refine = config.max_frames.is_none() && config.sample_rate > 1;
+    if refine && config.hud_config.elements.od {
+        refiner.refine_burnout_entries(detector, frame_data)?;
+    }
+    game_events.extend(gauges::detect_gauge_events(frame_data));
+    if refine && config.precise_events {
+        refiner.refine_events(detector, frame_data, game_events)?;
+    }
+    super_arts::attribute_super_flashes(frame_data, game_events);
+    game_events.sort_by(|a, b| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+    Ok(())
 }
 
-fn collect_frame_data(
-    decoder: &mut VideoDecoder,
+/// Attach everything derived from the segmented rounds, in dependency order: events,
+/// winners, statistics, interpolation and timelines.
+fn attach_round_details(
+    matches: &mut [Match],
     config: &PipelineConfig,
-    debug_renderer: &Option<DebugRenderer>,
-) -> Result<Vec<FrameData>> {
-    let hud = ManemonHud::new(decoder.width(), decoder.height());
-    let mut results: Vec<FrameData> = Vec::new();
-    let mut gap = GapFillState::default();
-    let mut frames_examined = 0u32;
-
-    loop {
-        let Some(frame) = decoder.next_frame()? else {
-            break;
+    game_events: &[GameEvent],
+    custom_events: &[CustomEvent],
+) -> Result<(), PipelineError> {
+    events::assign_round_starts(matches, game_events);
+    events::attach_round_events(matches, game_events);
+    analyzer::attach_custom_events(matches, custom_events);
+    winner::attach_winner_arbitration(matches);
+    events::lead::attach_lead_changes(matches);
+    let disabled = config.hud_config.elements.disabled();
+    for m in matches.iter_mut() {
+        m.disabled_elements = disabled.iter().map(|&e| e.into()).collect();
+    }
+    if let Some(path) = &config.chat_log {
+        let timestamps = chat::load_chat_log(path)?;
+        let spikes = chat::find_spikes(&timestamps);
+        chat::attach_chat_spikes(matches, &spikes);
+    }
+    playback::apply_playback_speed(matches, config.playback_speed.0);
+    if config.experimental {
+        ko_slowdown::mark_ko_slowdown(matches);
+    }
+    burnout::attach_burnouts(matches);
+    stats::attach_round_stats(matches);
+    round_finish::attach_finishes(matches);
+    defense::attach_pressure(matches);
+    damage::attach_damage(matches);
+    // Filled values are for charting only; the statistics above are from read values.
+    if let Some(interpolation) = &config.interpolation {
+        interpolate::interpolate_matches(matches, interpolation);
+    }
+    absolute_health::attach_absolute_health(matches);
+    match_timeline::attach_timelines(matches);
+    Ok(())
+}
+
+/// Run the pipeline on each span of `frames` (every frame of a span, like
+/// `max_frames`), for spot checks with debug overlays. Matches and anomalies of all
+/// spans are combined in frame order.
+pub fn run_frames(
+    input: &Path,
+    config: &PipelineConfig,
+    frames: &FrameList,
+) -> Result<PipelineOutput, PipelineError> {
+    let spans = frames.spans();
+    assert!(!spans.is_empty(), "frame list has no frames");
+    if spans.len() > 1 && (config.debug.video.is_some() || config.stream_file.is_some()) {
+        error!(
+            spans = spans.len(),
+            "debug video and frame stream would be overwritten per span"
+        );
+        return Err(PipelineError::InvalidConfig(
+            "a debug video or frame stream needs consecutive frames".into(),
+        ));
+    }
+
+    let mut output = PipelineOutput {
+        matches: Vec::new(),
+        anomalies: AnomalyLog::default(),
+    };
+    for span in spans {
+        let span_config = PipelineConfig {
+            start_frame: span.start,
+            max_frames: Some(span.count),
+            ..config.clone()
         };
+        let PipelineOutput { matches, anomalies } = run_pipeline(input, &span_config).inspect_err(
+            |e| error!(start = span.start, count = span.count, %e, "frame span failed"),
+        )?;
+        output.matches.extend(matches);
+        output.anomalies.merge(anomalies);
+    }
+    info!(
+        spans = spans.len(),
+        frames = frames.frame_count(),
+        anomalies = output.anomalies.anomalies.len(),
+        "frame list analyzed"
+    );
+    Ok(output)
+}
 
-        if config.max_frames.is_none() && frame.frame_number % config.sample_rate != 0 {
-            continue;
+/// Record the video properties and analysis settings in every match's source, so frame
+/// numbers can be converted to time and results reproduced.
+fn attach_analysis_metadata(
+    matches: &mut [Match],
+    info: &decoder::ProbeResult,
+    sample_rate: u32,
+    analyzed_at: SystemTime,
+) {
+    let analyzed_at = rfc3339_utc(analyzed_at);
+    for source in matches.iter_mut().filter_map(|m| m.source.as_mut()) {
+        source.recmari_version = env!("CARGO_PKG_VERSION").to_string();
+        source.analyzed_at = analyzed_at.clone();
+        if let Some(Source::VideoFile(video)) = source.source.as_mut() {
+            video.fps = info.fps;
+            video.width = info.width;
+            video.height = info.height;
+            video.sample_rate = sample_rate;
         }
+    }
+    info!(
+        matches = matches.len(),
+        fps = info.fps,
+        sample_rate,
+        %analyzed_at,
+        "analysis metadata recorded"
+    );
+}
 
-        let detected = hud.detect_hud(&frame);
-        info!(
-            frame_number = frame.frame_number,
-            hud_detected = detected,
-            "processing frame"
-        );
+/// `time` as RFC 3339 in UTC with second precision.
+fn rfc3339_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before 1970")
+        .as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
 
-        let fd = if detected {
-            Some(analyze_frame(&hud, &frame, &mut gap))
+/// Debug overlay output: a video when `debug_video` is set, else PNG frames.
+/// The video plays the sampled frames in real time.
+fn open_debug_output(
+    config: &PipelineConfig,
+    fps: f64,
+) -> Result<Option<DebugOutput>, PipelineError> {
+    if config.debug.video.is_none() && config.debug.frames_dir.is_none() {
+        return Ok(None);
+    }
+    let renderer = match &config.debug.font {
+        Some(path) => DebugRenderer::with_font(path)?,
+        None => DebugRenderer::new(),
+    };
+    if let Some(path) = &config.debug.video {
+        let step = if config.max_frames.is_some() {
+            1
         } else {
-            gap.clear();
-            None
+            config.sample_rate
         };
+        let video_fps = if fps > 0.0 { fps / step as f64 } else { 1.0 };
+        return Ok(Some(DebugOutput::video(path, video_fps, renderer)));
+    }
+    config
+        .debug
+        .frames_dir
+        .as_deref()
+        .map(|dir| DebugOutput::frames(dir, renderer))
+        .transpose()
+}
 
-        let any_ko = fd.as_ref().map_or(false, |fd| {
-            let p1 = fd.player1.as_ref().and_then(|p| p.health_ratio);
-            let p2 = fd.player2.as_ref().and_then(|p| p.health_ratio);
-            matches!(p1, Some(hp) if hp < 0.01) || matches!(p2, Some(hp) if hp < 0.01)
-        });
-        let center_x = if detected && !any_ko {
-            hud.detect_center_line(&frame)
-        } else {
-            None
+/// Load previously analyzed frames when resuming and open the checkpoint for appending.
+/// Returns the resumed frames and custom events, the writer, and the frame to start
+/// decoding from.
+fn prepare_checkpoint(
+    config: &PipelineConfig,
+) -> Result<(Checkpoint, Option<CheckpointWriter>, u32), PipelineError> {
+    let Some(path) = config
+        .checkpoint
+        .file
+        .as_deref()
+        .filter(|_| config.max_frames.is_none())
+    else {
+        return Ok((Checkpoint::default(), None, config.start_frame));
+    };
+
+    let resumed = if !config.checkpoint.resume {
+        Checkpoint::default()
+    } else if path.exists() {
+        checkpoint::load_checkpoint(path)?
+    } else {
+        warn!(?path, "no checkpoint to resume from, starting over");
+        Checkpoint::default()
+    };
+
+    let start_frame = match resumed.frames.last() {
+        // Continue at the next sampled frame after the last checkpointed one.
+        Some(last) => (last.frame_number / config.sample_rate + 1) * config.sample_rate,
+        None => config.start_frame,
+    };
+    let writer = CheckpointWriter::open(path, !resumed.frames.is_empty())?;
+    info!(
+        ?path,
+        resumed = resumed.frames.len(),
+        start_frame,
+        "checkpointing enabled"
+    );
+    Ok((resumed, Some(writer), start_frame.max(config.start_frame)))
+}
+
+/// Everything gathered from the sampled frames of a video.
+#[derive(Default)]
+struct CollectedFrames {
+    /// Frames with a detected HUD.
+    frames: Vec<FrameData>,
+    anomalies: AnomalyLog,
+    events: Vec<GameEvent>,
+    /// Events of the custom analyzers.
+    custom_events: Vec<CustomEvent>,
+}
+
+impl CollectedFrames {
+    /// Put the frames and custom events of an earlier, checkpointed run first.
+    fn prepend(&mut self, resumed: Checkpoint) {
+        if resumed.frames.is_empty() {
+            return;
+        }
+        info!(
+            resumed = resumed.frames.len(),
+            custom_events = resumed.custom_events.len(),
+            "prepending frames from checkpoint"
+        );
+        self.frames.splice(0..0, resumed.frames);
+        self.custom_events.splice(0..0, resumed.custom_events);
+    }
+}
+
+/// Outputs fed with every sampled frame as soon as it is analyzed.
+#[derive(Default)]
+struct FrameSinks<'a> {
+    debug: Option<&'a mut DebugOutput>,
+    checkpoint: Option<&'a mut CheckpointWriter>,
+    stream: Option<&'a mut FrameStream>,
+    progress: Option<&'a mut Progress>,
+}
+
+impl FrameSinks<'_> {
+    /// Record an analyzed frame and the custom events reported on it.
+    fn append(
+        &mut self,
+        fd: &FrameData,
+        custom_events: &[CustomEvent],
+    ) -> Result<(), PipelineError> {
+        if let Some(writer) = self.checkpoint.as_deref_mut() {
+            writer.append(fd, custom_events)?;
+        }
+        if let Some(stream) = self.stream.as_deref_mut() {
+            stream.append(fd)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the checkpoint and close the progress report once `decoded_until` frames
+    /// were decoded.
+    fn finish(self, decoded_until: u64) -> Result<(), PipelineError> {
+        if let Some(writer) = self.checkpoint {
+            writer.flush()?;
+        }
+        if let Some(stream) = self.stream {
+            info!(frames = stream.frames(), "frame stream complete");
+        }
+        if let Some(progress) = self.progress {
+            progress.finish(decoded_until);
+        }
+        Ok(())
+    }
+}
+
+/// Picks the frames to analyze out of the decoded ones: every `sample_rate`th frame
+/// (or distinct frame with `dedup_frames`), or every frame up to `max_frames`.
+struct Sampler {
+    dedup: Option<FrameDeduplicator>,
+    sample_rate: u32,
+    max_frames: Option<u32>,
+    sampled: u32,
+    /// Frame number after the last decoded frame.
+    decoded_until: u64,
+}
+
+impl Sampler {
+    fn new(config: &PipelineConfig) -> Self {
+        Self {
+            dedup: config.decode.dedup_frames.then(FrameDeduplicator::default),
+            sample_rate: config.sample_rate,
+            max_frames: config.max_frames,
+            sampled: 0,
+            decoded_until: 0,
+        }
+    }
+
+    /// Decode up to the next frame to analyze. None at the end of the video or once
+    /// `max_frames` frames were sampled.
+    fn next_sample(
+        &mut self,
+        decoder: &mut dyn FrameSource,
+        mut progress: Option<&mut Progress>,
+    ) -> Result<Option<Frame>, PipelineError> {
+        if self.max_frames.is_some_and(|max| self.sampled >= max) {
+            return Ok(None);
+        }
+        while let Some(frame) = decoder.next_frame()? {
+            self.decoded_until = frame.frame_number as u64 + 1;
+            if let Some(progress) = progress.as_deref_mut() {
+                progress.update(frame.frame_number);
+            }
+            if self.is_sample(&frame) {
+                self.sampled += 1;
+                return Ok(Some(frame));
+            }
+            decoder.recycle(frame);
+        }
+        Ok(None)
+    }
+
+    fn is_sample(&mut self, frame: &Frame) -> bool {
+        let index = match self.dedup.as_mut() {
+            Some(dedup) => {
+                if dedup.is_duplicate(frame) {
+                    return false;
+                }
+                dedup.unique_frames() - 1
+            }
+            None => frame.frame_number,
         };
+        self.max_frames.is_some() || index % self.sample_rate == 0
+    }
+
+    fn log_summary(&self) {
+        if let Some(dedup) = &self.dedup {
+            info!(
+                unique = dedup.unique_frames(),
+                duplicates = dedup.duplicate_frames(),
+                "duplicate frames skipped"
+            );
+        }
+    }
+}
+
+/// Analyzes sampled frames one after another, carrying state between them.
+struct Collector<'a> {
+    config: &'a PipelineConfig,
+    collected: CollectedFrames,
+    debounce: EventDebounce,
+    /// None when values must not be carried over (keep_unreadable, or left to the
+    /// interpolation pass).
+    gap: Option<GapFillState>,
+    analyzers: Vec<Box<dyn FrameAnalyzer>>,
+}
 
-        if let (Some(renderer), Some(dir)) = (debug_renderer, &config.debug_frames_dir) {
-            renderer
-                .save_frame(&frame, &hud, fd.as_ref(), center_x, dir)
-                .context("failed to save debug frame")?;
+impl<'a> Collector<'a> {
+    fn new(config: &'a PipelineConfig) -> Self {
+        let carry_over = !config.keep_unreadable && config.interpolation.is_none();
+        Self {
+            config,
+            collected: CollectedFrames::default(),
+            debounce: EventDebounce::default(),
+            gap: carry_over.then(GapFillState::default),
+            analyzers: config.analyzers.create(),
         }
+    }
 
-        if let Some(fd) = fd {
-            results.push(fd);
+    /// Analyze one sampled frame: HUD readings, screen events and custom analyzers.
+    fn add_sample(
+        &mut self,
+        frame: &Frame,
+        detector: &mut HudDetector,
+        sinks: &mut FrameSinks,
+    ) -> Result<(), PipelineError> {
+        self.collected.anomalies.note_frame(frame);
+        let detected = self.detect_hud(frame, detector);
+        let hud = detector.current_or_default();
+        info!(
+            frame_number = frame.frame_number,
+            hud_detected = detected,
+            "processing frame"
+        );
+        let fd = detected.then(|| self.read_hud(hud, frame));
+        if let Some(debug) = sinks.debug.as_deref_mut() {
+            let center_x = self.center_line(frame, fd.as_ref());
+            debug.write(frame, hud, fd.as_ref(), center_x)?;
         }
+        if let Some(fd) = fd.as_ref().filter(|_| !self.config.decode.hud_rows_only) {
+            let banner_window = self.config.experimental && is_round_intro(fd);
+            let events = &mut self.collected.events;
+            events::detect_frame_events(frame, banner_window, &mut self.debounce, events);
+        }
+
+        let mut fd = fd.unwrap_or_else(|| hud_absent_frame(frame));
+        let events_before = self.collected.custom_events.len();
+        analyzer::run_analyzers(
+            &mut self.analyzers,
+            frame,
+            detected.then(|| hud.hud_type()),
+            &mut fd,
+            &mut self.collected.custom_events,
+            &mut self.collected.anomalies,
+        );
+        sinks.append(&fd, &self.collected.custom_events[events_before..])?;
+        self.collected.frames.push(fd);
+        Ok(())
+    }
 
-        frames_examined += 1;
-        if let Some(max) = config.max_frames {
-            if frames_examined >= max {
-                break;
+    /// Whether a HUD is on `frame`. Carried values are dropped when there is none or
+    /// its layout changed, as they must not fill gaps in another layout.
+    fn detect_hud(&mut self, frame: &Frame, detector: &mut HudDetector) -> bool {
+        let previous_type = detector.current_type();
+        let detected = detector.detect(frame).is_some();
+        if !detected {
+            self.collected.anomalies.record(frame, AnomalyCause::NoHud);
+        }
+        if !detected || detector.current_type() != previous_type {
+            if let Some(gap) = self.gap.as_mut() {
+                gap.clear();
             }
         }
+        detected
+    }
+
+    fn read_hud(&mut self, hud: &dyn Hud, frame: &Frame) -> FrameData {
+        analyze_isolated(
+            hud,
+            frame,
+            &self.config.hud_config.elements,
+            self.config.slow_frame_limit,
+            self.gap.as_mut(),
+            &mut self.collected.anomalies,
+        )
+    }
+
+    /// Stage center line for the debug overlay. Not looked for on KO frames or when
+    /// only the HUD rows are decoded.
+    fn center_line(&self, frame: &Frame, fd: Option<&FrameData>) -> Option<u32> {
+        let fd = fd?;
+        let any_ko = players(fd)
+            .into_iter()
+            .any(|p| matches!(p.and_then(|p| p.health_ratio), Some(hp) if hp < 0.01));
+        if any_ko || self.config.decode.hud_rows_only {
+            return None;
+        }
+        manemon::detect_center_line(&frame.image)
+    }
+}
+
+/// Decode and analyze every sampled frame of `decoder`, feeding `sinks` as it goes.
+fn collect_frame_data(
+    decoder: &mut dyn FrameSource,
+    detector: &mut HudDetector,
+    config: &PipelineConfig,
+    mut sinks: FrameSinks,
+) -> Result<CollectedFrames, PipelineError> {
+    let mut sampler = Sampler::new(config);
+    let mut collector = Collector::new(config);
+    while let Some(frame) = sampler.next_sample(decoder, sinks.progress.as_deref_mut())? {
+        collector.add_sample(&frame, detector, &mut sinks)?;
+        decoder.recycle(frame);
     }
+    sinks.finish(sampler.decoded_until)?;
+    sampler.log_summary();
+    Ok(collector.collected)
+}
 
-    Ok(results)
+/// Re-decodes skipped frames around state changes seen between two samples.
+struct Refiner<'a> {
+    input: &'a Path,
+    decoder_config: &'a DecoderConfig,
+    elements: &'a HudElements,
 }
 
-/// Read HP, SA, and OD from a detected HUD frame, applying gap-fill from previous readings.
-fn analyze_frame(hud: &dyn Hud, frame: &Frame, gap: &mut GapFillState) -> FrameData {
-    let hp = hud.analyze_hp(frame);
-    let p1 = hp.p1.or(gap.p1_hp);
-    let p2 = hp.p2.or(gap.p2_hp);
+impl Refiner<'_> {
+    /// Insert the exact frame where each player enters burnout, found by decoding the
+    /// skipped frames between the last sample before and the first sample in burnout.
+    fn refine_burnout_entries(
+        &self,
+        detector: &mut HudDetector,
+        frames: &mut Vec<FrameData>,
+    ) -> Result<(), PipelineError> {
+        let gaps = burnout_entry_gaps(frames);
+        let mut refined: Vec<FrameData> = Vec::with_capacity(gaps.len());
+
+        for &(i, player) in &gaps {
+            let (after, until) = (frames[i - 1].frame_number, frames[i].frame_number - 1);
+            let found = refine::first_matching_frame(
+                self.input,
+                self.decoder_config,
+                after,
+                until,
+                |frame| {
+                    detector.detect(frame).is_some_and(|hud| {
+                        let od = hud.analyze_od(frame);
+                        let od = if player == 1 { od.p1 } else { od.p2 };
+                        matches!(od, Some(OdValue::Burnout(_)))
+                    })
+                },
+            )?;
+            let Some(frame) = found else {
+                continue;
+            };
+
+            // Refined frames are not samples, so their anomalies are not reported.
+            let mut scratch = AnomalyLog::default();
+            let hud = detector.current_or_default();
+            refined.push(analyze_frame(
+                hud,
+                &frame,
+                self.elements,
+                None,
+                &mut scratch,
+            ));
+        }
 
-    if hp.p1.is_some() {
-        gap.p1_hp = p1;
+        info!(
+            candidates = gaps.len(),
+            refined = refined.len(),
+            "burnout entry refinement complete"
+        );
+        frames.extend(refined);
+        frames.sort_by_key(|fd| fd.frame_number);
+        frames.dedup_by_key(|fd| fd.frame_number);
+        Ok(())
     }
-    if hp.p2.is_some() {
-        gap.p2_hp = p2;
+
+    /// Move each event to the first frame where it is visible, found by decoding the
+    /// skipped frames between the previous sample and the sample it was detected on.
+    fn refine_events(
+        &self,
+        detector: &mut HudDetector,
+        frames: &[FrameData],
+        events: &mut [GameEvent],
+    ) -> Result<(), PipelineError> {
+        let mut refined = 0usize;
+        for event in events.iter_mut() {
+            let i = frames.partition_point(|fd| fd.frame_number < event.frame_number);
+            let Some(before) = i.checked_sub(1).map(|i| &frames[i]) else {
+                continue;
+            };
+            let (after, until) = (before.frame_number, event.frame_number - 1);
+            if after >= until {
+                continue;
+            }
+            let player = usize::from(event.player.unwrap_or(1) - 1);
+            let sa_before = players(before)[player].and_then(|p| p.sa_gauge);
+            let found = refine::first_matching_frame(
+                self.input,
+                self.decoder_config,
+                after,
+                until,
+                |frame| shows_event(detector, frame, event.kind, player, sa_before),
+            )?;
+            if let Some(frame) = found {
+                event.frame_number = frame.frame_number;
+                event.timestamp_seconds = frame.timestamp_seconds;
+                refined += 1;
+            }
+        }
+
+        info!(
+            events = events.len(),
+            refined, "event timing refinement complete"
+        );
+        Ok(())
     }
+}
 
-    let sa = hud.analyze_sa(frame);
-    let p1_sa = sa.p1.or(gap.p1_sa);
-    let p2_sa = sa.p2.or(gap.p2_sa);
+/// Whether `kind` is visible on `frame`. `player` is the event's player (0 for P1) and
+/// `sa_before` their SA gauge on the sample before the event.
+fn shows_event(
+    detector: &mut HudDetector,
+    frame: &Frame,
+    kind: EventKind,
+    player: usize,
+    sa_before: Option<f64>,
+) -> bool {
+    match kind {
+        EventKind::FightBanner => fight_banner::detect_fight_banner(frame),
+        EventKind::DriveImpact | EventKind::DriveRush | EventKind::Stun | EventKind::SuperFlash => {
+            screen_effects::classify(frame) == Some(kind)
+        }
+        EventKind::Ko => detector.detect(frame).is_some_and(|hud| {
+            let hp = hud.analyze_hp(frame);
+            gauges::is_ko([hp.p1, hp.p2][player])
+        }),
+        EventKind::SaUse => detector.detect(frame).is_some_and(|hud| {
+            let sa = hud.analyze_sa(frame);
+            gauges::is_sa_use(sa_before, [sa.p1, sa.p2][player].map(SaValue::gauge))
+        }),
+        // Derived from round frames after segmentation; never refined.
+        EventKind::LeadChange => false,
+    }
+}
 
-    if sa.p1.is_some() {
-        gap.p1_sa = p1_sa;
+/// Indices `i` where a player (1 or 2) is in burnout at `frames[i]` but not at
+/// `frames[i - 1]`, with skipped frames in between.
+fn burnout_entry_gaps(frames: &[FrameData]) -> Vec<(usize, u8)> {
+    let in_burnout = |p: Option<&PlayerState>| p.is_some_and(|p| p.burnout_gauge.is_some());
+    let mut gaps = Vec::new();
+    for i in 1..frames.len() {
+        let (prev, cur) = (&frames[i - 1], &frames[i]);
+        if cur.frame_number <= prev.frame_number + 1 {
+            continue;
+        }
+        for (k, (p, c)) in players(prev).into_iter().zip(players(cur)).enumerate() {
+            if !in_burnout(p) && in_burnout(c) {
+                gaps.push((i, k as u8 + 1));
+            }
+        }
     }
-    if sa.p2.is_some() {
-        gap.p2_sa = p2_sa;
+    gaps
+}
+
+fn players(fd: &FrameData) -> [Option<&PlayerState>; 2] {
+    [fd.player1.as_ref(), fd.player2.as_ref()]
+}
+
+/// A sampled frame without a detected HUD. Kept so that gaps inside rounds are explicit;
+/// see [`label_cinematics`] and [`trim_hud_absent`].
+fn hud_absent_frame(frame: &Frame) -> FrameData {
+    FrameData {
+        frame_number: frame.frame_number,
+        timestamp_seconds: frame.timestamp_seconds,
+        status: FrameStatus::HudAbsent.into(),
+        ..Default::default()
     }
+}
+
+fn is_hud_absent(fd: &FrameData) -> bool {
+    fd.status() == FrameStatus::HudAbsent
+}
 
-    let od = hud.analyze_od(frame);
-    let p1_od = od.p1.or(gap.p1_od);
-    let p2_od = od.p2.or(gap.p2_od);
+/// Analyze one frame so that a bug triggered by one odd frame cannot abort an
+/// hours-long run: a panic, or taking at least `slow_limit`, yields [`failed_frame`]
+/// instead and leaves the gap-fill state and `anomalies` as they were before the frame.
+fn analyze_isolated(
+    hud: &dyn Hud,
+    frame: &Frame,
+    elements: &HudElements,
+    slow_limit: Option<Duration>,
+    mut gap: Option<&mut GapFillState>,
+    anomalies: &mut AnomalyLog,
+) -> FrameData {
+    let before = gap.as_deref().cloned();
+    let mut frame_anomalies = AnomalyLog::default();
+    let started = Instant::now();
+    let analyzed = panic::catch_unwind(AssertUnwindSafe(|| {
+        analyze_frame(
+            hud,
+            frame,
+            elements,
+            gap.as_deref_mut(),
+            &mut frame_anomalies,
+        )
+    }));
+    let elapsed = started.elapsed();
+    let failure = match analyzed {
+        Ok(_) if slow_limit.is_some_and(|limit| elapsed >= limit) => "frame analysis too slow",
+        Ok(fd) => {
+            anomalies.anomalies.extend(frame_anomalies.anomalies);
+            return fd;
+        }
+        Err(_) => "frame analysis panicked",
+    };
+    error!(
+        frame_number = frame.frame_number,
+        elapsed_ms = elapsed.as_millis() as u64,
+        "{failure}; using gap-filled values"
+    );
+    let before = before.unwrap_or_default();
+    if let Some(gap) = gap {
+        *gap = before.clone();
+    }
+    anomalies.record(frame, AnomalyCause::AnalysisFailed);
+    failed_frame(frame, elements, &before)
+}
 
-    if od.p1.is_some() {
-        gap.p1_od = p1_od;
+/// Frame whose analysis failed: every value is carried over from earlier frames.
+fn failed_frame(frame: &Frame, elements: &HudElements, gap: &GapFillState) -> FrameData {
+    let [player1, player2] = gap.player_states();
+    FrameData {
+        frame_number: frame.frame_number,
+        timestamp_seconds: frame.timestamp_seconds,
+        player1: Some(player1),
+        player2: Some(player2),
+        status: element_status(elements, [[false; 2]; 3], gap.known()).into(),
+        // Marked once the round's KO is known.
+        ko_slowdown: false,
+        interpolated: false,
+        custom_metrics: Vec::new(),
     }
-    if od.p2.is_some() {
-        gap.p2_od = p2_od;
+}
+
+/// Relabel short runs of HUD-less frames between two HUD frames as cinematics.
+fn label_cinematics(frames: &mut [FrameData]) {
+    let mut labeled = 0usize;
+    let mut i = 0;
+    while i < frames.len() {
+        if !is_hud_absent(&frames[i]) {
+            i += 1;
+            continue;
+        }
+        let run_end = frames[i..]
+            .iter()
+            .position(|fd| !is_hud_absent(fd))
+            .map_or(frames.len(), |n| i + n);
+        // Runs touching either end of the video are not between two HUD frames.
+        if i > 0 && run_end < frames.len() {
+            let duration = frames[run_end].timestamp_seconds - frames[i - 1].timestamp_seconds;
+            if duration <= CINEMATIC_MAX_SECONDS {
+                for fd in &mut frames[i..run_end] {
+                    fd.set_status(FrameStatus::Cinematic);
+                }
+                labeled += run_end - i;
+            }
+        }
+        i = run_end;
     }
+    info!(labeled, "cinematic frames labeled");
+}
+
+/// Both players at full health, as during the round intro. Events are never detected
+/// in HUD-rows-only mode, where the stage is not decoded.
+fn is_round_intro(fd: &FrameData) -> bool {
+    let p1 = fd.player1.as_ref().and_then(|p| p.health_ratio);
+    let p2 = fd.player2.as_ref().and_then(|p| p.health_ratio);
+    matches!((p1, p2), (Some(p1), Some(p2)) if p1 >= ROUND_RESET_THRESHOLD && p2 >= ROUND_RESET_THRESHOLD)
+}
 
+/// Read HP, SA, and OD from a detected HUD frame. With `gap`, unreadable values are
+/// filled from previous readings and this frame's values are remembered; without it,
+/// only this frame's own readings are used.
+fn analyze_frame(
+    hud: &dyn Hud,
+    frame: &Frame,
+    elements: &HudElements,
+    gap: Option<&mut GapFillState>,
+    anomalies: &mut AnomalyLog,
+) -> FrameData {
+    let (hp, sa, od) = read_enabled_elements(hud, frame, elements);
+    record_unreadable(anomalies, frame, elements, (&hp, &sa, &od));
+
+    let filled = gap
+        .as_deref()
+        .cloned()
+        .unwrap_or_default()
+        .fill(&hp, &sa, &od);
+    if let Some(gap) = gap {
+        *gap = filled.clone();
+    }
+    let read = [
+        [hp.p1.is_some(), hp.p2.is_some()],
+        [sa.p1.is_some(), sa.p2.is_some()],
+        [od.p1.is_some(), od.p2.is_some()],
+    ];
+    let [player1, player2] = filled.player_states();
     FrameData {
         frame_number: frame.frame_number,
         timestamp_seconds: frame.timestamp_seconds,
-        player1: Some(od_to_player_state(p1, p1_sa, p1_od)),
-        player2: Some(od_to_player_state(p2, p2_sa, p2_od)),
+        player1: Some(with_confidence(
+            player1,
+            [hp.p1_confidence, sa.p1_confidence, od.p1_confidence],
+        )),
+        player2: Some(with_confidence(
+            player2,
+            [hp.p2_confidence, sa.p2_confidence, od.p2_confidence],
+        )),
+        status: element_status(elements, read, filled.known()).into(),
+        ko_slowdown: false,
+        interpolated: false,
+        custom_metrics: Vec::new(),
+    }
+}
+
+/// Status of a HUD frame from which values were read and which are known after
+/// gap-fill, per element (HP, SA, OD) and player.
+fn element_status(
+    elements: &HudElements,
+    read: [[bool; 2]; 3],
+    known: [[bool; 2]; 3],
+) -> FrameStatus {
+    let enabled = [elements.hp, elements.sa, elements.od];
+    frame_status(&[0, 1, 2].map(|i| (enabled[i], read[i], known[i])))
+}
+
+/// Status of a HUD frame from `(enabled, read per player, value per player after gap-fill)`
+/// of each element.
+fn frame_status(elements: &[(bool, [bool; 2], [bool; 2])]) -> FrameStatus {
+    let mut status = FrameStatus::Ok;
+    for &(_, read, filled) in elements.iter().filter(|(enabled, ..)| *enabled) {
+        for (read, filled) in read.into_iter().zip(filled) {
+            if !filled {
+                return FrameStatus::Occluded;
+            }
+            if !read {
+                status = FrameStatus::Stale;
+            }
+        }
+    }
+    status
+}
+
+/// Read every enabled HUD element. Disabled elements read as None for both players.
+fn read_enabled_elements(
+    hud: &dyn Hud,
+    frame: &Frame,
+    elements: &HudElements,
+) -> (HpReading, SaReading, OdReading) {
+    let hp = if elements.hp {
+        hud.analyze_hp(frame)
+    } else {
+        HpReading::default()
+    };
+    let sa = if elements.sa {
+        hud.analyze_sa(frame)
+    } else {
+        SaReading::default()
+    };
+    let od = if elements.od {
+        hud.analyze_od(frame)
+    } else {
+        OdReading::default()
+    };
+    (hp, sa, od)
+}
+
+/// Record an anomaly for every enabled gauge kind that was unreadable for either player.
+fn record_unreadable(
+    anomalies: &mut AnomalyLog,
+    frame: &Frame,
+    elements: &HudElements,
+    (hp, sa, od): (&HpReading, &SaReading, &OdReading),
+) {
+    let gauges = [
+        (
+            elements.hp,
+            AnomalyCause::HpUnreadable,
+            [hp.p1.is_none(), hp.p2.is_none()],
+        ),
+        (
+            elements.sa,
+            AnomalyCause::SaUnreadable,
+            [sa.p1.is_none(), sa.p2.is_none()],
+        ),
+        (
+            elements.od,
+            AnomalyCause::OdUnreadable,
+            [od.p1.is_none(), od.p2.is_none()],
+        ),
+    ];
+    for (enabled, cause, players) in gauges {
+        if enabled && players.contains(&true) {
+            anomalies.record_players(frame, cause, players);
+        }
     }
 }
 
-fn od_to_player_state(hp: Option<f64>, sa: Option<f64>, od: Option<OdValue>) -> PlayerState {
+fn od_to_player_state(hp: Option<f64>, sa: Option<SaValue>, od: Option<OdValue>) -> PlayerState {
     let (od_gauge, burnout_gauge) = match od {
         Some(OdValue::Normal(v)) => (Some(v), None),
         Some(OdValue::Burnout(v)) => (None, Some(v)),
@@ -222,10 +1387,27 @@ fn od_to_player_state(hp: Option<f64>, sa: Option<f64>, od: Option<OdValue>) ->
     };
     PlayerState {
         health_ratio: hp,
-        sa_gauge: sa,
+        sa_gauge: sa.map(SaValue::gauge),
         od_gauge,
         burnout_gauge,
         at_stage_corner: None,
+        ca_ready: sa.map(|v| v == SaValue::CaReady),
+        health_confidence: None,
+        sa_confidence: None,
+        od_confidence: None,
+        // Needs the character, known only once the match is segmented.
+        health: None,
+    }
+}
+
+/// Attach the HP, SA and OD confidences of the values read from this frame. Carried
+/// values have none, since their readings were unavailable.
+fn with_confidence(state: PlayerState, [hp, sa, od]: [Option<f64>; 3]) -> PlayerState {
+    PlayerState {
+        health_confidence: hp,
+        sa_confidence: sa,
+        od_confidence: od,
+        ..state
     }
 }
 
@@ -236,12 +1418,15 @@ struct RoundResult {
 }
 
 fn round_result(frames: &[FrameData]) -> RoundResult {
+    let double_ko = is_double_ko(frames);
     for fd in frames.iter().rev() {
         let p1_hp = fd.player1.as_ref().and_then(|p| p.health_ratio);
         let p2_hp = fd.player2.as_ref().and_then(|p| p.health_ratio);
         match (p1_hp, p2_hp) {
             (Some(p1), Some(p2)) => {
-                let winner = if p1 > p2 {
+                let winner = if double_ko {
+                    Winner::Unknown
+                } else if p1 > p2 {
                     Winner::P1
                 } else if p2 > p1 {
                     Winner::P2
@@ -264,38 +1449,38 @@ fn round_result(frames: &[FrameData]) -> RoundResult {
     }
 }
 
-fn segment_into_matches(frames: &[FrameData], input: &Path) -> Vec<Match> {
-    let all_rounds = split_into_rounds(frames);
+fn segment_into_matches(
+    frames: &[FrameData],
+    input: &Path,
+    markers: &[Marker],
+    mode: MarkerMode,
+) -> Vec<Match> {
+    let all_rounds = rounds_with_markers(frames, markers, mode);
     let file_path = input.to_string_lossy().into_owned();
+    // Match markers are authoritative in override mode, so win counts no longer end matches.
+    let end_on_wins =
+        mode == MarkerMode::Seed || !markers.iter().any(|m| m.kind == MarkerKind::Match);
 
     let mut matches: Vec<Match> = Vec::new();
-    let mut current_rounds: Vec<Vec<FrameData>> = Vec::new();
-    let mut p1_wins = 0u32;
-    let mut p2_wins = 0u32;
-
-    for round_frames in all_rounds {
-        match round_result(&round_frames).winner {
-            Winner::P1 => p1_wins += 1,
-            Winner::P2 => p2_wins += 1,
-            Winner::Unknown => {}
-        }
-        current_rounds.push(round_frames);
-
-        if p1_wins >= ROUNDS_TO_WIN || p2_wins >= ROUNDS_TO_WIN {
-            let m = build_match(
-                &file_path,
-                current_rounds.drain(..).collect(),
-                p1_wins,
-                p2_wins,
-            );
+    let mut current_rounds: Vec<Round> = Vec::new();
+
+    for (round_frames, starts_match) in all_rounds {
+        if starts_match && !current_rounds.is_empty() {
+            info!("match boundary from marker");
+            let m = build_match(&file_path, std::mem::take(&mut current_rounds));
+            matches.push(m);
+        }
+
+        current_rounds.push(make_round(current_rounds.len() as u32, round_frames));
+
+        if end_on_wins && winner::match_winner(&current_rounds) != Winner::Unknown {
+            let m = build_match(&file_path, std::mem::take(&mut current_rounds));
             matches.push(m);
-            p1_wins = 0;
-            p2_wins = 0;
         }
     }
 
     if !current_rounds.is_empty() {
-        let m = build_match(&file_path, current_rounds, p1_wins, p2_wins);
+        let m = build_match(&file_path, current_rounds);
         matches.push(m);
     }
 
@@ -303,41 +1488,27 @@ fn segment_into_matches(frames: &[FrameData], input: &Path) -> Vec<Match> {
     matches
 }
 
-fn build_match(
-    file_path: &str,
-    round_frames: Vec<Vec<FrameData>>,
-    p1_wins: u32,
-    p2_wins: u32,
-) -> Match {
-    let start_seconds = round_frames
+fn build_match(file_path: &str, rounds: Vec<Round>) -> Match {
+    assert!(!rounds.is_empty(), "a match has at least one round");
+    let start_seconds = rounds[0]
+        .frames
         .first()
-        .and_then(|r| r.first())
         .map(|f| f.timestamp_seconds)
         .unwrap_or(0.0);
-
-    let rounds = round_frames
-        .into_iter()
-        .enumerate()
-        .map(|(i, f)| make_round(i as u32, f))
-        .collect();
-
-    let winner = if p1_wins >= ROUNDS_TO_WIN {
-        Winner::P1
-    } else if p2_wins >= ROUNDS_TO_WIN {
-        Winner::P2
-    } else {
-        Winner::Unknown
-    };
+    let winner = winner::match_winner(&rounds);
 
     Match {
         source: Some(SourceMetadata {
             source: Some(Source::VideoFile(VideoFileSource {
                 file_path: file_path.to_owned(),
                 start_seconds,
+                ..Default::default()
             })),
+            ..Default::default()
         }),
         rounds,
         winner: winner.into(),
+        ..Default::default()
     }
 }
 
@@ -379,6 +1550,196 @@ fn split_into_rounds(frames: &[FrameData]) -> Vec<Vec<FrameData>> {
     rounds
 }
 
+/// Split frames into rounds, applying manual markers per `mode`.
+fn rounds_with_markers(
+    frames: &[FrameData],
+    markers: &[Marker],
+    mode: MarkerMode,
+) -> Vec<(Vec<FrameData>, bool)> {
+    let mut rounds = if mode == MarkerMode::Override && !markers.is_empty() {
+        let mut rounds = split_at_markers(vec![frames.to_vec()], markers);
+        // Frames before the first marker belong to no round.
+        let first_marker = markers[0].timestamp_seconds;
+        if rounds
+            .first()
+            .is_some_and(|(r, _)| r[0].timestamp_seconds < first_marker)
+        {
+            let (dropped, _) = rounds.remove(0);
+            info!(
+                frames = dropped.len(),
+                "dropped frames before the first marker"
+            );
+        }
+        rounds
+    } else {
+        split_at_markers(split_into_rounds(frames), markers)
+    };
+    for (round, _) in &mut rounds {
+        trim_hud_absent(round);
+    }
+    rounds.retain(|(round, _)| !round.is_empty());
+    rounds
+}
+
+/// Drop HUD-less frames at the edges of a round (menus and loading screens between
+/// rounds and matches). HUD-less frames inside the round are kept.
+fn trim_hud_absent(frames: &mut Vec<FrameData>) {
+    let end = frames
+        .iter()
+        .rposition(|fd| !is_hud_absent(fd))
+        .map_or(0, |i| i + 1);
+    frames.truncate(end);
+    let start = frames.iter().position(|fd| !is_hud_absent(fd)).unwrap_or(0);
+    frames.drain(..start);
+}
+
+/// Split rounds further at manual marker timestamps.
+///
+/// Returns each round with a flag telling whether it starts a new match.
+fn split_at_markers(
+    rounds: Vec<Vec<FrameData>>,
+    markers: &[Marker],
+) -> Vec<(Vec<FrameData>, bool)> {
+    let mut result: Vec<(Vec<FrameData>, bool)> = Vec::new();
+    let mut next_marker = 0;
+
+    for round in rounds {
+        result.push((Vec::new(), false));
+        for fd in round {
+            while next_marker < markers.len()
+                && markers[next_marker].timestamp_seconds <= fd.timestamp_seconds
+            {
+                let marker = &markers[next_marker];
+                info!(
+                    at_frame = fd.frame_number,
+                    marker_seconds = marker.timestamp_seconds,
+                    kind = ?marker.kind,
+                    "boundary from marker"
+                );
+                if !result.last().unwrap().0.is_empty() {
+                    result.push((Vec::new(), false));
+                }
+                result.last_mut().unwrap().1 |= marker.kind == MarkerKind::Match;
+                next_marker += 1;
+            }
+            result.last_mut().unwrap().0.push(fd);
+        }
+    }
+
+    result.retain(|(frames, _)| !frames.is_empty());
+    result
+}
+
+/// Find the reference clips of `dir` in the soundtrack of `input`.
+fn detect_audio_cues(input: &Path, dir: &Path) -> Result<Vec<AudioCue>, PipelineError> {
+    let mut templates = Vec::new();
+    for (kind, name) in AUDIO_CUE_CLIPS {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        let envelope = audio::decode_envelope(&path)?;
+        if envelope.values.len() < audio_cues::MIN_TEMPLATE_HOPS {
+            error!(?path, "audio cue clip too short");
+            return Err(PipelineError::InvalidConfig(format!(
+                "audio cue clip {} is too short",
+                path.display()
+            )));
+        }
+        templates.push(CueTemplate { kind, envelope });
+    }
+    if templates.is_empty() {
+        error!(?dir, "no audio cue clips found");
+        return Err(PipelineError::InvalidConfig(format!(
+            "{} contains neither round_start.wav nor ko.wav",
+            dir.display()
+        )));
+    }
+
+    let track = audio::decode_envelope(input)?;
+    let mut cues: Vec<AudioCue> = templates
+        .iter()
+        .flat_map(|t| audio_cues::find_cues(&track, t))
+        .collect();
+    cues.sort_by(|a, b| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+    Ok(cues)
+}
+
+/// Cross-check audio cues against the visual round boundaries and the markers. A cue
+/// whose boundary none of them explains (a time over with healthy bars, a double KO)
+/// becomes a round marker at the first frame of the refilled bars.
+fn add_audio_cue_markers(
+    frames: &[FrameData],
+    mut markers: Vec<Marker>,
+    cues: &[AudioCue],
+) -> Vec<Marker> {
+    let mut boundaries: Vec<f64> = split_into_rounds(frames)
+        .iter()
+        .map(|r| r[0].timestamp_seconds)
+        .chain(markers.iter().map(|m| m.timestamp_seconds))
+        .collect();
+    let (mut confirmed, mut added) = (0, 0);
+
+    for cue in cues {
+        let Some(boundary) = audio_cue_boundary(frames, cue) else {
+            debug!(?cue, "audio cue without refilled bars");
+            continue;
+        };
+        if boundaries
+            .iter()
+            .any(|b| (b - boundary).abs() <= AUDIO_CUE_TOLERANCE_SECONDS)
+        {
+            confirmed += 1;
+            continue;
+        }
+        info!(
+            cue_seconds = cue.timestamp_seconds,
+            kind = ?cue.kind,
+            boundary,
+            "round boundary from audio cue"
+        );
+        boundaries.push(boundary);
+        markers.push(Marker {
+            timestamp_seconds: boundary,
+            kind: MarkerKind::Round,
+            label: Some(format!("audio {:?}", cue.kind)),
+        });
+        added += 1;
+    }
+
+    markers.sort_by(|a, b| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+    info!(
+        cues = cues.len(),
+        confirmed, added, "audio cues cross-checked"
+    );
+    markers
+}
+
+/// Timestamp of the frame where the round a cue belongs to starts: the refill of both
+/// bars before the announcer, or after the KO. None when the bars never read full.
+fn audio_cue_boundary(frames: &[FrameData], cue: &AudioCue) -> Option<f64> {
+    let at = cue.timestamp_seconds;
+    match cue.kind {
+        CueKind::RoundStart => {
+            let end = frames.partition_point(|fd| fd.timestamp_seconds <= at);
+            let start = frames[..end]
+                .iter()
+                .rposition(|fd| !is_round_intro(fd))
+                .map_or(0, |i| i + 1);
+            frames[start..end]
+                .first()
+                .map(|fd| fd.timestamp_seconds)
+                .filter(|&t| at - t <= ROUND_START_CUE_LEAD_SECONDS)
+        }
+        CueKind::Ko => frames
+            .iter()
+            .skip_while(|fd| fd.timestamp_seconds <= at)
+            .take_while(|fd| fd.timestamp_seconds <= at + KO_CUE_NEXT_ROUND_SECONDS)
+            .find(|fd| is_round_intro(fd))
+            .map(|fd| fd.timestamp_seconds),
+    }
+}
+
 /// Returns true if every frame with readable HP shows both players near full health.
 /// These rounds are artifacts from match-to-match transitions (HP reset visible briefly
 /// before HUD disappears for the rematch screen).
@@ -395,13 +1756,35 @@ fn is_reset_only(frames: &[FrameData]) -> bool {
 
 fn make_round(round_index: u32, frames: Vec<FrameData>) -> Round {
     let result = round_result(&frames);
+    let draw = is_double_ko(&frames);
+    if draw {
+        info!(round_index, "draw round");
+    }
     Round {
         round_index,
         frames,
         winner: result.winner.into(),
+        draw,
+        ..Default::default()
     }
 }
 
+/// Whether both players were knocked out within [`DOUBLE_KO_WINDOW_SECONDS`] of each
+/// other. Bars read slightly apart at 0, so comparing the final health would pick an
+/// arbitrary winner.
+fn is_double_ko(frames: &[FrameData]) -> bool {
+    let ko_at = |player: usize| {
+        frames
+            .iter()
+            .find(|fd| gauges::is_ko(players(fd)[player].and_then(|p| p.health_ratio)))
+            .map(|fd| fd.timestamp_seconds)
+    };
+    matches!(
+        (ko_at(0), ko_at(1)),
+        (Some(p1), Some(p2)) if (p1 - p2).abs() <= DOUBLE_KO_WINDOW_SECONDS
+    )
+}
+
 fn log_match_summary(match_number: usize, m: &Match) {
     info!("* Match number {}", match_number);
     for round in &m.rounds {
@@ -409,9 +1792,16 @@ fn log_match_summary(match_number: usize, m: &Match) {
         let round_index = round.round_index;
         let p1_hp = result.p1_hp;
         let p2_hp = result.p2_hp;
+        let disputed = if round.draw {
+            " (draw)"
+        } else if round.winner_disputed {
+            " (disputed)"
+        } else {
+            ""
+        };
         info!(
-            "  round {round_index}: winner: {:?}, final HP: P1={p1_hp:.2?}, P2={p2_hp:.2?}",
-            result.winner,
+            "  round {round_index}: winner: {:?}{disputed}, final HP: P1={p1_hp:.2?}, P2={p2_hp:.2?}",
+            round.winner(),
         );
     }
 
@@ -422,28 +1812,53 @@ fn log_match_summary(match_number: usize, m: &Match) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{drive, frame, frame_at, hp_frame, video_frame, StubHud};
 
     fn fd(frame_number: u32, ts: f64, p1: f64, p2: f64) -> FrameData {
         FrameData {
             frame_number,
-            timestamp_seconds: ts,
-            player1: Some(PlayerState {
-                health_ratio: Some(p1),
-                sa_gauge: None,
-                od_gauge: None,
-                burnout_gauge: None,
-                at_stage_corner: None,
-            }),
-            player2: Some(PlayerState {
-                health_ratio: Some(p2),
-                sa_gauge: None,
-                od_gauge: None,
-                burnout_gauge: None,
-                at_stage_corner: None,
-            }),
+            status: FrameStatus::Ok.into(),
+            ..hp_frame(ts, p1, p2)
         }
     }
 
+    #[test]
+    fn rfc3339_formats_utc_dates() {
+        let at = |secs| rfc3339_utc(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_868_799), "2000-02-29T23:59:59Z");
+        assert_eq!(at(1_771_590_600), "2026-02-20T12:30:00Z");
+    }
+
+    #[test]
+    fn analysis_metadata_is_recorded_on_video_sources() {
+        let mut matches = vec![Match {
+            source: Some(SourceMetadata {
+                source: Some(Source::VideoFile(VideoFileSource::default())),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }];
+        let info = decoder::ProbeResult {
+            width: 1920,
+            height: 1080,
+            fps: 59.94,
+            frame_count: None,
+        };
+        attach_analysis_metadata(&mut matches, &info, 2, UNIX_EPOCH);
+        let source = matches[0].source.as_ref().unwrap();
+        assert_eq!(source.recmari_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(source.analyzed_at, "1970-01-01T00:00:00Z");
+        let Some(Source::VideoFile(video)) = &source.source else {
+            panic!("not a video source");
+        };
+        assert_eq!(
+            (video.width, video.height, video.sample_rate),
+            (1920, 1080, 2)
+        );
+        assert_eq!(video.fps, 59.94);
+    }
+
     #[test]
     fn split_no_damage_yields_one_round() {
         let frames = vec![fd(0, 0.0, 1.0, 1.0), fd(1, 0.5, 0.9, 0.9)];
@@ -482,7 +1897,7 @@ mod tests {
             fd(4, 2.0, 0.7, 0.0), // P1 wins round 2 → match complete
         ];
         let input = Path::new("test.mp4");
-        let matches = segment_into_matches(&frames, input);
+        let matches = segment_into_matches(&frames, input, &[], MarkerMode::Seed);
         assert_eq!(matches.len(), 1);
         assert_eq!(matches[0].rounds.len(), 2);
         assert_eq!(matches[0].winner, Winner::P1 as i32);
@@ -490,6 +1905,31 @@ mod tests {
         assert_eq!(matches[0].rounds[1].winner, Winner::P1 as i32);
     }
 
+    #[test]
+    fn double_ko_is_a_draw_that_counts_for_neither_player() {
+        let frames = vec![
+            fd(0, 0.0, 1.0, 1.0),
+            fd(1, 1.0, 0.7, 0.0), // P1 wins round 1
+            fd(2, 2.0, 1.0, 1.0),
+            fd(3, 3.0, 0.3, 0.2),
+            fd(4, 3.5, 0.005, 0.0), // double KO, bars read slightly apart
+            fd(5, 4.0, 1.0, 1.0),   // round replayed
+            fd(6, 5.0, 0.2, 0.6),
+            fd(7, 6.0, 0.0, 0.5), // P2 wins
+            fd(8, 7.0, 1.0, 1.0),
+            fd(9, 8.0, 0.8, 0.0), // P1 wins the match
+        ];
+        let input = Path::new("test.mp4");
+        let matches = segment_into_matches(&frames, input, &[], MarkerMode::Seed);
+        assert_eq!(matches.len(), 1);
+        let rounds = &matches[0].rounds;
+        assert_eq!(rounds.len(), 4);
+        assert!(rounds[1].draw);
+        assert_eq!(rounds[1].winner(), Winner::Unknown);
+        assert!(!rounds[2].draw && !rounds[3].draw);
+        assert_eq!(matches[0].winner(), Winner::P1);
+    }
+
     #[test]
     fn segment_six_rounds_into_multiple_matches() {
         let frames = vec![
@@ -510,7 +1950,7 @@ mod tests {
             fd(11, 5.5, 0.8, 0.0), // P1 wins R1
         ];
         let input = Path::new("test.mp4");
-        let matches = segment_into_matches(&frames, input);
+        let matches = segment_into_matches(&frames, input, &[], MarkerMode::Seed);
         assert_eq!(matches.len(), 3);
 
         assert_eq!(matches[0].rounds.len(), 2);
@@ -540,6 +1980,11 @@ mod tests {
                     od_gauge: None,
                     burnout_gauge: None,
                     at_stage_corner: None,
+                    ca_ready: None,
+                    health_confidence: None,
+                    sa_confidence: None,
+                    od_confidence: None,
+                    health: None,
                 }),
                 player2: Some(PlayerState {
                     health_ratio: None,
@@ -547,9 +1992,344 @@ mod tests {
                     od_gauge: None,
                     burnout_gauge: None,
                     at_stage_corner: None,
+                    ca_ready: None,
+                    health_confidence: None,
+                    sa_confidence: None,
+                    od_confidence: None,
+                    health: None,
                 }),
+                status: FrameStatus::Occluded.into(),
+                ko_slowdown: false,
+                interpolated: false,
+                custom_metrics: Vec::new(),
             },
         ];
         assert_eq!(round_result(&frames).winner, Winner::P1);
     }
+
+    fn marker(ts: f64, kind: MarkerKind) -> Marker {
+        Marker {
+            timestamp_seconds: ts,
+            kind,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn seed_markers_add_round_boundary() {
+        let frames = vec![
+            fd(0, 0.0, 1.0, 1.0),
+            fd(1, 1.0, 0.9, 0.8),
+            fd(2, 2.0, 0.9, 0.7),
+            fd(3, 3.0, 0.8, 0.7),
+        ];
+        let markers = [marker(2.0, MarkerKind::Round)];
+        let rounds = split_at_markers(split_into_rounds(&frames), &markers);
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].0.len(), 2);
+        assert_eq!(rounds[1].0[0].frame_number, 2);
+        assert!(!rounds[1].1);
+    }
+
+    #[test]
+    fn match_marker_starts_new_match() {
+        let frames = vec![
+            fd(0, 0.0, 1.0, 1.0),
+            fd(1, 0.5, 0.5, 0.0), // P1 wins R1
+            fd(2, 1.0, 1.0, 1.0), // reset
+            fd(3, 1.5, 0.0, 0.6), // P2 wins R2
+            fd(4, 2.0, 1.0, 1.0), // reset, but a new match per the marker
+            fd(5, 2.5, 0.2, 0.0),
+        ];
+        let markers = [marker(2.0, MarkerKind::Match)];
+        let input = Path::new("test.mp4");
+        let matches = segment_into_matches(&frames, input, &markers, MarkerMode::Seed);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].rounds.len(), 2);
+        assert_eq!(matches[0].winner, Winner::Unknown as i32);
+        assert_eq!(matches[1].rounds.len(), 1);
+    }
+
+    #[test]
+    fn override_markers_replace_detection() {
+        let frames = vec![
+            fd(0, 0.0, 1.0, 1.0),
+            fd(1, 1.0, 0.5, 0.0),
+            fd(2, 2.0, 1.0, 1.0), // HP reset ignored in override mode
+            fd(3, 3.0, 0.4, 0.0),
+            fd(4, 4.0, 1.0, 1.0),
+            fd(5, 5.0, 0.0, 0.3),
+        ];
+        let markers = [
+            marker(1.0, MarkerKind::Match),
+            marker(4.0, MarkerKind::Round),
+        ];
+        let input = Path::new("test.mp4");
+        let matches = segment_into_matches(&frames, input, &markers, MarkerMode::Override);
+        assert_eq!(matches.len(), 1);
+        let rounds = &matches[0].rounds;
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].frames[0].frame_number, 1);
+        assert_eq!(rounds[0].frames.len(), 3);
+        assert_eq!(rounds[1].frames[0].frame_number, 4);
+    }
+
+    fn cue(timestamp_seconds: f64, kind: CueKind) -> AudioCue {
+        AudioCue {
+            timestamp_seconds,
+            kind,
+            score: 0.9,
+        }
+    }
+
+    #[test]
+    fn audio_cues_add_boundary_of_time_over_with_healthy_bars() {
+        let frames = vec![
+            fd(0, 0.0, 1.0, 1.0),
+            fd(1, 10.0, 0.8, 0.9),
+            fd(2, 20.0, 0.8, 0.9), // time over, no bar below DAMAGE_THRESHOLD
+            fd(3, 22.0, 1.0, 1.0), // refill
+            fd(4, 24.0, 1.0, 1.0),
+            fd(5, 30.0, 0.7, 0.9),
+        ];
+        assert_eq!(split_into_rounds(&frames).len(), 1);
+
+        let cues = [cue(20.5, CueKind::Ko), cue(25.0, CueKind::RoundStart)];
+        let markers = add_audio_cue_markers(&frames, Vec::new(), &cues);
+        assert_eq!(markers.len(), 1, "the second cue confirms the first");
+        assert_eq!(markers[0].timestamp_seconds, 22.0);
+
+        let input = Path::new("test.mp4");
+        let matches = segment_into_matches(&frames, input, &markers, MarkerMode::Seed);
+        assert_eq!(matches[0].rounds.len(), 2);
+    }
+
+    #[test]
+    fn audio_cues_confirm_visual_boundaries() {
+        let frames = vec![
+            fd(0, 0.0, 1.0, 1.0),
+            fd(1, 10.0, 0.6, 0.0), // KO
+            fd(2, 14.0, 1.0, 1.0), // reset
+            fd(3, 20.0, 0.9, 0.8),
+            fd(4, 30.0, 0.0, 0.8), // match-ending KO
+        ];
+        let cues = [
+            cue(10.0, CueKind::Ko),
+            cue(16.0, CueKind::RoundStart),
+            cue(30.0, CueKind::Ko),
+        ];
+        assert!(add_audio_cue_markers(&frames, Vec::new(), &cues).is_empty());
+    }
+
+    #[test]
+    fn burnout_entry_gaps_finds_transitions() {
+        let frames = [
+            (0, false, false),
+            (60, true, false), // P1 enters
+            (120, true, true), // P2 enters
+            (121, false, false),
+            (122, true, false), // adjacent frames: nothing to refine
+        ]
+        .map(|(n, p1_burnout, p2_burnout)| {
+            let [player1, player2] = [p1_burnout, p2_burnout]
+                .map(|burnout| Some(drive((!burnout).then_some(2.0), burnout.then_some(0.0))));
+            FrameData {
+                player1,
+                player2,
+                ..frame(n)
+            }
+        });
+        assert_eq!(burnout_entry_gaps(&frames), vec![(1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn hud_gaps_are_labeled_and_trimmed() {
+        let mut frames = [
+            (0, 0.0, None), // before the first HUD frame
+            (1, 1.0, Some((1.0, 1.0))),
+            (2, 2.0, None), // short gap: cinematic
+            (3, 3.0, Some((0.4, 0.8))),
+            (4, 4.0, None), // long gap: menu
+            (5, 30.0, None),
+            (6, 31.0, Some((0.0, 0.8))),
+            (7, 32.0, None), // after the last HUD frame
+        ]
+        .map(|(n, ts, hp)| match hp {
+            Some((p1, p2)) => fd(n, ts, p1, p2),
+            None => FrameData {
+                frame_number: n,
+                status: FrameStatus::HudAbsent.into(),
+                ..frame_at(ts)
+            },
+        })
+        .to_vec();
+        label_cinematics(&mut frames);
+        let statuses: Vec<FrameStatus> = frames.iter().map(|fd| fd.status()).collect();
+        assert_eq!(
+            statuses,
+            [
+                FrameStatus::HudAbsent,
+                FrameStatus::Ok,
+                FrameStatus::Cinematic,
+                FrameStatus::Ok,
+                FrameStatus::HudAbsent,
+                FrameStatus::HudAbsent,
+                FrameStatus::Ok,
+                FrameStatus::HudAbsent,
+            ]
+        );
+
+        let input = Path::new("test.mp4");
+        let matches = segment_into_matches(&frames, input, &[], MarkerMode::Seed);
+        let round = &matches[0].rounds[0];
+        // Edge frames are trimmed, interior gaps kept.
+        let numbers: Vec<u32> = round.frames.iter().map(|fd| fd.frame_number).collect();
+        assert_eq!(numbers, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(round.winner(), Winner::P2);
+    }
+
+    #[test]
+    fn failed_frame_carries_gap_values() {
+        let frame = Frame {
+            image: image::RgbImage::new(1, 1),
+            frame_number: 42,
+            timestamp_seconds: 0.7,
+        };
+        let gap = GapFillState {
+            p1_hp: Some(0.6),
+            p2_hp: Some(0.4),
+            ..Default::default()
+        };
+        let elements = HudElements {
+            sa: false,
+            od: false,
+            ..Default::default()
+        };
+        let fd = failed_frame(&frame, &elements, &gap);
+        assert_eq!(fd.status(), FrameStatus::Stale);
+        assert_eq!(fd.player1.unwrap().health_ratio, Some(0.6));
+        assert_eq!(
+            failed_frame(&frame, &HudElements::default(), &gap).status(),
+            FrameStatus::Occluded
+        );
+    }
+
+    #[test]
+    fn slow_or_panicking_frames_are_skipped() {
+        let frame = Frame {
+            image: image::RgbImage::new(1, 1),
+            frame_number: 7,
+            timestamp_seconds: 0.1,
+        };
+        let elements = HudElements {
+            sa: false,
+            od: false,
+            ..Default::default()
+        };
+        // Every analysis takes at least zero time, so a zero limit always trips.
+        let too_slow = Some(Duration::ZERO);
+        let mut gap = GapFillState::default();
+        let mut anomalies = AnomalyLog::default();
+        let mut analyze = |hp, panic, limit, gap: &mut GapFillState| {
+            let hud = if panic {
+                StubHud::panicking()
+            } else {
+                StubHud::reading(hp)
+            };
+            analyze_isolated(&hud, &frame, &elements, limit, Some(gap), &mut anomalies)
+        };
+
+        let fd = analyze(Some(0.6), false, None, &mut gap);
+        assert_eq!(fd.status(), FrameStatus::Ok);
+        // The unreadable HP of a discarded frame is not recorded on top of the failure.
+        let fd = analyze(None, false, too_slow, &mut gap);
+        assert_eq!(fd.status(), FrameStatus::Stale);
+        assert_eq!(fd.player1.unwrap().health_ratio, Some(0.6));
+        assert_eq!(gap.p1_hp, Some(0.6));
+        let fd = analyze(Some(0.2), true, None, &mut gap);
+        assert_eq!(fd.player2.unwrap().health_ratio, Some(0.6));
+        let counts = anomalies.counts();
+        assert_eq!(counts[&AnomalyCause::AnalysisFailed], 2);
+        assert_eq!(counts[&AnomalyCause::HpUnreadable], 0);
+    }
+
+    struct Frames(std::vec::IntoIter<Frame>);
+
+    impl FrameSource for Frames {
+        fn next_frame(&mut self) -> Result<Option<Frame>, crate::error::DecoderError> {
+            Ok(self.0.next())
+        }
+    }
+
+    #[test]
+    fn keep_unreadable_leaves_gaps_absent() {
+        let hp_of_last_frame = |keep_unreadable, interpolation| {
+            let frames: Vec<Frame> = (0..3)
+                .map(|n| video_frame(n, image::RgbImage::new(1, 1)))
+                .collect();
+            let mut config = PipelineConfig {
+                sample_rate: 1,
+                decode: DecodeOptions {
+                    hud_rows_only: true,
+                    ..Default::default()
+                },
+                keep_unreadable,
+                interpolation,
+                ..Default::default()
+            };
+            config.hud_config.elements.sa = false;
+            config.hud_config.elements.od = false;
+            let mut detector = HudDetector::new(
+                vec![Box::new(StubHud::with_hp(|frame| {
+                    (frame.frame_number == 0).then_some(0.6)
+                }))],
+                DEFAULT_RECHECK_INTERVAL,
+            );
+            let collected = collect_frame_data(
+                &mut Frames(frames.into_iter()),
+                &mut detector,
+                &config,
+                FrameSinks::default(),
+            )
+            .unwrap();
+            assert_eq!(collected.frames.len(), 3);
+            let last = collected.frames[2].clone();
+            (last.player1.as_ref().unwrap().health_ratio, last.status())
+        };
+        assert_eq!(
+            hp_of_last_frame(false, None),
+            (Some(0.6), FrameStatus::Stale)
+        );
+        assert_eq!(hp_of_last_frame(true, None), (None, FrameStatus::Occluded));
+        // Gaps are left for the interpolation pass.
+        let linear = Interpolation {
+            mode: interpolate::InterpolationMode::Linear,
+            max_gap_seconds: 2.0,
+        };
+        assert_eq!(
+            hp_of_last_frame(false, Some(linear)),
+            (None, FrameStatus::Occluded)
+        );
+    }
+
+    #[test]
+    fn ca_ready_is_kept_alongside_full_gauge() {
+        let ca = od_to_player_state(None, Some(SaValue::CaReady), None);
+        assert_eq!((ca.sa_gauge, ca.ca_ready), (Some(3.0), Some(true)));
+        let full = od_to_player_state(None, Some(SaValue::Stocks(3.0)), None);
+        assert_eq!((full.sa_gauge, full.ca_ready), (Some(3.0), Some(false)));
+        let unread = od_to_player_state(None, None, None);
+        assert_eq!((unread.sa_gauge, unread.ca_ready), (None, None));
+    }
+
+    #[test]
+    fn frame_status_prefers_occluded_over_stale() {
+        let ok = (true, [true, true], [true, true]);
+        let carried = (true, [true, false], [true, true]);
+        let missing = (true, [false, true], [false, true]);
+        let disabled = (false, [false, false], [false, false]);
+        assert_eq!(frame_status(&[ok, disabled]), FrameStatus::Ok);
+        assert_eq!(frame_status(&[ok, carried]), FrameStatus::Stale);
+        assert_eq!(frame_status(&[carried, missing]), FrameStatus::Occluded);
+    }
 }
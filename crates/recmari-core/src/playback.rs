@@ -0,0 +1,103 @@
+use std::str::FromStr;
+
+use tracing::info;
+
+use recmari_proto::proto::{source_metadata::Source, Match};
+
+/// How fast the capture plays relative to game time: game seconds per video second.
+/// Supplied by the user; nothing in the video is read to detect it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackSpeed(pub f64);
+
+impl Default for PlaybackSpeed {
+    fn default() -> Self {
+        PlaybackSpeed(1.0)
+    }
+}
+
+impl FromStr for PlaybackSpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_end_matches(['x', 'X']).parse::<f64>() {
+            Ok(v) if v > 0.0 && v.is_finite() => Ok(PlaybackSpeed(v)),
+            _ => Err(format!(
+                "invalid playback speed '{s}' (expected a positive factor)"
+            )),
+        }
+    }
+}
+
+/// Convert every timestamp of the matches to game seconds and record the speed.
+/// The match start in the video is kept in video seconds.
+pub fn apply_playback_speed(matches: &mut [Match], speed: f64) {
+    assert!(speed > 0.0, "playback speed must be positive, got {speed}");
+    for m in matches.iter_mut() {
+        if let Some(Source::VideoFile(source)) = m.source.as_mut().and_then(|s| s.source.as_mut()) {
+            source.playback_speed = speed;
+        }
+        if speed == 1.0 {
+            continue;
+        }
+        for round in &mut m.rounds {
+            round.start_timestamp_seconds *= speed;
+            for fd in &mut round.frames {
+                fd.timestamp_seconds *= speed;
+            }
+            for event in &mut round.events {
+                event.timestamp_seconds *= speed;
+            }
+            for spike in &mut round.chat_spikes {
+                spike.start_seconds *= speed;
+                spike.end_seconds *= speed;
+            }
+        }
+    }
+    info!(speed, matches = matches.len(), "playback speed applied");
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{FrameData, Round, SourceMetadata, VideoFileSource};
+
+    use super::*;
+
+    #[test]
+    fn parse_playback_speed() {
+        assert_eq!("2x".parse(), Ok(PlaybackSpeed(2.0)));
+        assert_eq!("1.5".parse(), Ok(PlaybackSpeed(1.5)));
+        assert!("0".parse::<PlaybackSpeed>().is_err());
+        assert!("auto".parse::<PlaybackSpeed>().is_err());
+    }
+
+    #[test]
+    fn timestamps_are_scaled_to_game_time() {
+        let mut matches = vec![Match {
+            source: Some(SourceMetadata {
+                source: Some(Source::VideoFile(VideoFileSource {
+                    start_seconds: 10.0,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            rounds: vec![Round {
+                start_timestamp_seconds: 10.0,
+                frames: vec![FrameData {
+                    timestamp_seconds: 12.0,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        apply_playback_speed(&mut matches, 2.0);
+
+        let Some(Source::VideoFile(source)) = &matches[0].source.as_ref().unwrap().source else {
+            panic!("video source expected");
+        };
+        assert_eq!((source.start_seconds, source.playback_speed), (10.0, 2.0));
+        let round = &matches[0].rounds[0];
+        assert_eq!(round.start_timestamp_seconds, 20.0);
+        assert_eq!(round.frames[0].timestamp_seconds, 24.0);
+    }
+}
@@ -0,0 +1,33 @@
+use recmari_proto::proto::{FrameData, PlayerState};
+
+/// State of player 1 or 2 on a frame.
+pub fn player_state(fd: &FrameData, player: u32) -> Option<&PlayerState> {
+    if player == 1 {
+        fd.player1.as_ref()
+    } else {
+        fd.player2.as_ref()
+    }
+}
+
+/// Mutable state of player 1 or 2 on a frame, inserted empty if the frame has none.
+pub fn player_state_mut(fd: &mut FrameData, player: u32) -> &mut PlayerState {
+    let state = if player == 1 {
+        &mut fd.player1
+    } else {
+        &mut fd.player2
+    };
+    state.get_or_insert_with(Default::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutable_state_is_inserted_for_the_requested_player() {
+        let mut fd = FrameData::default();
+        player_state_mut(&mut fd, 2).health_ratio = Some(0.25);
+        assert!(player_state(&fd, 1).is_none());
+        assert_eq!(player_state(&fd, 2).unwrap().health_ratio, Some(0.25));
+    }
+}
@@ -0,0 +1,36 @@
+//! Stable API for tools built on recmari-core.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use recmari_core::prelude::*;
+//!
+//! fn main() -> Result<(), PipelineError> {
+//!     let output = run_pipeline(Path::new("match.mp4"), &PipelineConfig::default())?;
+//!     write_matches(&output.matches, Path::new("match.pb"))?;
+//!     for m in MatchReader::open(Path::new("match.pb"))?.iter() {
+//!         println!("{} rounds", m?.rounds.len());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::analysis::detector::HudDetector;
+pub use crate::analysis::huds::{all_huds, create as create_hud, hud_names};
+pub use crate::analysis::{HpReading, Hud, HudType, OdReading, OdValue, SaReading, SaValue};
+pub use crate::analyzer::{AnalyzerOutput, AnalyzerRegistry, FrameAnalyzer, FrameContext};
+pub use crate::anomaly::AnomalyLog;
+pub use crate::config::{load_hud_config, HudConfig, HudElements, HudProfile};
+pub use crate::error::{DecoderError, HudError, PipelineError};
+pub use crate::output::{read_matches, write_matches, MatchReader};
+#[cfg(feature = "pipeline")]
+pub use crate::pipeline::{
+    run_pipeline, BoundaryHints, CheckpointConfig, DebugConfig, DecodeOptions, PipelineConfig,
+    PipelineOutput,
+};
+#[cfg(feature = "pipeline")]
+pub use crate::playback::PlaybackSpeed;
+pub use crate::proto::{FrameData, FrameStatus, Match, PlayerState, Round, Winner};
+#[cfg(feature = "video")]
+pub use crate::video::decoder::VideoDecoder;
+pub use crate::video::frame::{Frame, FrameSource};
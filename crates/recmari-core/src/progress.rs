@@ -0,0 +1,109 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+/// Minimum time between two progress lines.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reports decoding progress (percentage, throughput and ETA) on stderr.
+pub struct Progress {
+    /// Total frames of the video, when known from probing.
+    total_frames: Option<u64>,
+    /// First decoded frame; frames before it are not counted toward throughput.
+    start_frame: u64,
+    started: Instant,
+    last_report: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(total_frames: Option<u64>, start_frame: u32) -> Self {
+        info!(?total_frames, start_frame, "progress reporting enabled");
+        Self {
+            total_frames,
+            start_frame: start_frame as u64,
+            started: Instant::now(),
+            last_report: None,
+        }
+    }
+
+    /// Note that `frame_number` was decoded; prints a line at most every [`REPORT_INTERVAL`].
+    pub fn update(&mut self, frame_number: u32) {
+        let now = Instant::now();
+        if self
+            .last_report
+            .is_some_and(|last| now - last < REPORT_INTERVAL)
+        {
+            return;
+        }
+        self.last_report = Some(now);
+        let line = self.report(frame_number as u64 + 1, now - self.started);
+        let mut stderr = std::io::stderr().lock();
+        // Progress is best-effort; a closed stderr must not fail the analysis.
+        let _ = write!(stderr, "\r{line}");
+        let _ = stderr.flush();
+    }
+
+    /// Print the final line and end it with a newline.
+    pub fn finish(&mut self, frames_done: u64) {
+        let elapsed = self.started.elapsed();
+        let line = self.report(frames_done, elapsed);
+        eprintln!("\r{line}");
+        info!(frames_done, ?elapsed, "decoding finished");
+    }
+
+    /// Progress line after `done` frames from the start of the video.
+    fn report(&self, done: u64, elapsed: Duration) -> String {
+        let decoded = done.saturating_sub(self.start_frame);
+        let secs = elapsed.as_secs_f64();
+        let fps = if secs > 0.0 {
+            decoded as f64 / secs
+        } else {
+            0.0
+        };
+
+        let Some(total) = self.total_frames.filter(|&t| t > 0) else {
+            return format!("frame {done}  {fps:.1} fps");
+        };
+        let done = done.min(total);
+        let percent = done as f64 / total as f64 * 100.0;
+        let eta = if fps > 0.0 {
+            format_duration((total - done) as f64 / fps)
+        } else {
+            "--:--".to_string()
+        };
+        format!("[{percent:5.1}%] frame {done}/{total}  {fps:.1} fps  ETA {eta}")
+    }
+}
+
+/// `m:ss`, or `h:mm:ss` from one hour.
+fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (h, m, s) = (total / 3600, total / 60 % 60, total % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_includes_percentage_and_eta() {
+        let progress = Progress::new(Some(6000), 1000);
+        // 1200 frames decoded in 10 s: 120 fps, 3800 frames left.
+        let line = progress.report(2200, Duration::from_secs(10));
+        assert_eq!(line, "[ 36.7%] frame 2200/6000  120.0 fps  ETA 0:32");
+    }
+
+    #[test]
+    fn report_without_total_has_no_eta() {
+        let progress = Progress::new(None, 0);
+        let line = progress.report(600, Duration::from_secs(5));
+        assert_eq!(line, "frame 600  120.0 fps");
+        assert_eq!(format_duration(3725.0), "1:02:05");
+    }
+}
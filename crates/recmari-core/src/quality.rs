@@ -0,0 +1,414 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use tracing::{info, warn};
+
+use recmari_proto::proto::{FrameStatus, Match, Winner};
+
+use crate::anomaly::{AnomalyCause, AnomalyLog};
+
+/// Gauges tallied by [`ReadabilityReport`], in column order.
+pub const GAUGES: [(AnomalyCause, &str); 3] = [
+    (AnomalyCause::HpUnreadable, "HP"),
+    (AnomalyCause::SaUnreadable, "SA"),
+    (AnomalyCause::OdUnreadable, "OD"),
+];
+/// Worst frames closer together than this show the same problem; only the worst is kept.
+const WORST_FRAME_SPACING_SECONDS: f64 = 5.0;
+
+/// Limits an analysis must meet in strict mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityThresholds {
+    /// Maximum fraction of HUD frames with at least one unreadable gauge.
+    pub max_unreadable_ratio: f64,
+    /// Minimum fraction of matches whose match and round winners are all known.
+    pub min_segmentation_confidence: f64,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            max_unreadable_ratio: 0.2,
+            min_segmentation_confidence: 0.8,
+        }
+    }
+}
+
+/// Quality metrics of one analysis run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityReport {
+    /// Frames with a detected HUD.
+    pub hud_frames: u32,
+    /// Fraction of HUD frames with at least one unreadable gauge (0.0 when there are none).
+    pub unreadable_ratio: f64,
+    pub match_count: usize,
+    /// Fraction of matches whose match and round winners are all known
+    /// (0.0 when no match was found).
+    pub segmentation_confidence: f64,
+    /// Rounds whose winner signals disagree.
+    pub disputed_rounds: usize,
+}
+
+impl QualityReport {
+    pub fn evaluate(matches: &[Match], anomalies: &AnomalyLog) -> Self {
+        let no_hud = anomalies.counts()[&AnomalyCause::NoHud];
+        let hud_frames = anomalies.sampled_frames.saturating_sub(no_hud);
+        let unreadable: BTreeSet<u32> = anomalies
+            .anomalies
+            .iter()
+            .filter(|a| !matches!(a.cause, AnomalyCause::NoHud | AnomalyCause::AnalyzerFailed))
+            .map(|a| a.frame_number)
+            .collect();
+        let unreadable_ratio = if hud_frames == 0 {
+            0.0
+        } else {
+            unreadable.len() as f64 / hud_frames as f64
+        };
+
+        let complete = matches.iter().filter(|m| is_fully_decided(m)).count();
+        let segmentation_confidence = if matches.is_empty() {
+            0.0
+        } else {
+            complete as f64 / matches.len() as f64
+        };
+
+        let report = Self {
+            hud_frames,
+            unreadable_ratio,
+            match_count: matches.len(),
+            segmentation_confidence,
+            disputed_rounds: matches
+                .iter()
+                .flat_map(|m| &m.rounds)
+                .filter(|r| r.winner_disputed)
+                .count(),
+        };
+        info!(?report, "quality report");
+        report
+    }
+
+    /// Describe every threshold this report violates (empty when it passes).
+    pub fn violations(&self, thresholds: &QualityThresholds) -> Vec<String> {
+        let mut violations = Vec::new();
+        if self.unreadable_ratio > thresholds.max_unreadable_ratio {
+            violations.push(format!(
+                "unreadable frames {:.1}% exceed {:.1}%",
+                self.unreadable_ratio * 100.0,
+                thresholds.max_unreadable_ratio * 100.0
+            ));
+        }
+        if self.segmentation_confidence < thresholds.min_segmentation_confidence {
+            violations.push(format!(
+                "segmentation confidence {:.2} is below {:.2} ({} matches)",
+                self.segmentation_confidence,
+                thresholds.min_segmentation_confidence,
+                self.match_count
+            ));
+        }
+        for v in &violations {
+            warn!(violation = %v, "quality threshold violated");
+        }
+        violations
+    }
+}
+
+/// Unreadable-gauge tallies of both players over a span of HUD frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadabilityRow {
+    /// `all`, or `M<match> R<round>` (1-based).
+    pub label: String,
+    pub hud_frames: u32,
+    /// Frames with an unreadable gauge, indexed `[player - 1][gauge]` in [`GAUGES`] order.
+    pub unreadable: [[u32; 3]; 2],
+}
+
+impl ReadabilityRow {
+    /// Percentage of HUD frames where `gauge` of `player` (1 or 2) was unreadable.
+    pub fn percent(&self, player: usize, gauge: usize) -> f64 {
+        assert!(player == 1 || player == 2, "invalid player {player}");
+        if self.hud_frames == 0 {
+            return 0.0;
+        }
+        self.unreadable[player - 1][gauge] as f64 * 100.0 / self.hud_frames as f64
+    }
+}
+
+/// A frame with unreadable gauges, for dumping as an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorstFrame {
+    pub frame_number: u32,
+    pub timestamp_seconds: f64,
+    /// Unreadable gauges, indexed like [`ReadabilityRow::unreadable`].
+    pub unreadable: [[bool; 3]; 2],
+}
+
+impl WorstFrame {
+    fn unreadable_count(&self) -> usize {
+        self.unreadable.iter().flatten().filter(|&&u| u).count()
+    }
+
+    /// One caption per unreadable gauge, e.g. `P2 SA unreadable`.
+    pub fn captions(&self) -> Vec<String> {
+        let mut captions = Vec::new();
+        for (player, gauges) in self.unreadable.iter().enumerate() {
+            for (gauge, &unreadable) in gauges.iter().enumerate() {
+                if unreadable {
+                    captions.push(format!("P{} {} unreadable", player + 1, GAUGES[gauge].1));
+                }
+            }
+        }
+        captions
+    }
+}
+
+/// Per-player HP/SA/OD readability of one analysis run, overall and per round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadabilityReport {
+    pub total: ReadabilityRow,
+    pub rounds: Vec<ReadabilityRow>,
+    /// The frames with the most unreadable gauges, worst first, at least
+    /// `WORST_FRAME_SPACING_SECONDS` apart.
+    pub worst_frames: Vec<WorstFrame>,
+}
+
+impl ReadabilityReport {
+    /// Tally the unreadable gauges of `anomalies` and keep up to `worst_count` worst
+    /// frames. A failed analysis counts as every gauge unreadable.
+    pub fn evaluate(matches: &[Match], anomalies: &AnomalyLog, worst_count: usize) -> Self {
+        let mut frames: BTreeMap<u32, WorstFrame> = BTreeMap::new();
+        for a in &anomalies.anomalies {
+            let gauges: Vec<usize> = match a.cause {
+                AnomalyCause::NoHud | AnomalyCause::AnalyzerFailed => continue,
+                AnomalyCause::AnalysisFailed => (0..GAUGES.len()).collect(),
+                cause => vec![GAUGES.iter().position(|(c, _)| *c == cause).unwrap()],
+            };
+            let frame = frames.entry(a.frame_number).or_insert_with(|| WorstFrame {
+                frame_number: a.frame_number,
+                timestamp_seconds: a.timestamp_seconds,
+                unreadable: [[false; 3]; 2],
+            });
+            for (player, &affected) in a.players.iter().enumerate() {
+                for &gauge in &gauges {
+                    frame.unreadable[player][gauge] |= affected;
+                }
+            }
+        }
+
+        let no_hud = anomalies.counts()[&AnomalyCause::NoHud];
+        let total = tally(
+            "all".into(),
+            anomalies.sampled_frames.saturating_sub(no_hud),
+            frames.values(),
+        );
+        let mut rounds = Vec::new();
+        for (match_index, m) in matches.iter().enumerate() {
+            for round in &m.rounds {
+                let (Some(first), Some(last)) = (round.frames.first(), round.frames.last()) else {
+                    continue;
+                };
+                let hud_frames = round
+                    .frames
+                    .iter()
+                    .filter(|f| {
+                        !matches!(f.status(), FrameStatus::HudAbsent | FrameStatus::Cinematic)
+                    })
+                    .count() as u32;
+                rounds.push(tally(
+                    format!("M{} R{}", match_index + 1, round.round_index + 1),
+                    hud_frames,
+                    frames
+                        .range(first.frame_number..=last.frame_number)
+                        .map(|(_, f)| f),
+                ));
+            }
+        }
+
+        let mut candidates: Vec<&WorstFrame> = frames.values().collect();
+        candidates.sort_by_key(|f| (std::cmp::Reverse(f.unreadable_count()), f.frame_number));
+        let mut worst_frames: Vec<WorstFrame> = Vec::new();
+        for f in candidates {
+            if worst_frames.len() == worst_count {
+                break;
+            }
+            let near = worst_frames.iter().any(|w| {
+                (w.timestamp_seconds - f.timestamp_seconds).abs() < WORST_FRAME_SPACING_SECONDS
+            });
+            if !near {
+                worst_frames.push(f.clone());
+            }
+        }
+
+        info!(
+            hud_frames = total.hud_frames,
+            unreadable = ?total.unreadable,
+            rounds = rounds.len(),
+            worst_frames = worst_frames.len(),
+            "readability report"
+        );
+        Self {
+            total,
+            rounds,
+            worst_frames,
+        }
+    }
+
+    /// Plain-text table: one line per row with the unreadable percentage of every gauge.
+    pub fn table(&self) -> String {
+        let mut header = format!("{:<10} {:>7}", "", "frames");
+        for player in 1..=2 {
+            for (_, name) in GAUGES {
+                header.push_str(&format!(" {:>7}", format!("P{player} {name}")));
+            }
+        }
+        let mut lines = vec![header];
+        for row in std::iter::once(&self.total).chain(&self.rounds) {
+            let mut line = format!("{:<10} {:>7}", row.label, row.hud_frames);
+            for player in 1..=2 {
+                for gauge in 0..GAUGES.len() {
+                    line.push_str(&format!(" {:>6.1}%", row.percent(player, gauge)));
+                }
+            }
+            lines.push(line);
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+fn tally<'a>(
+    label: String,
+    hud_frames: u32,
+    frames: impl Iterator<Item = &'a WorstFrame>,
+) -> ReadabilityRow {
+    let mut unreadable = [[0u32; 3]; 2];
+    for f in frames {
+        for (player, gauges) in f.unreadable.iter().enumerate() {
+            for (gauge, &u) in gauges.iter().enumerate() {
+                unreadable[player][gauge] += u as u32;
+            }
+        }
+    }
+    ReadabilityRow {
+        label,
+        hud_frames,
+        unreadable,
+    }
+}
+
+fn is_fully_decided(m: &Match) -> bool {
+    m.winner() != Winner::Unknown && m.rounds.iter().all(|r| r.winner() != Winner::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbImage;
+    use recmari_proto::proto::{FrameData, Round};
+
+    use super::*;
+    use crate::video::frame::Frame;
+
+    fn frame(frame_number: u32) -> Frame {
+        Frame {
+            image: RgbImage::new(1, 1),
+            frame_number,
+            timestamp_seconds: frame_number as f64,
+        }
+    }
+
+    fn decided_match(round_winners: &[Winner]) -> Match {
+        Match {
+            rounds: round_winners
+                .iter()
+                .map(|&w| Round {
+                    winner: w.into(),
+                    ..Default::default()
+                })
+                .collect(),
+            winner: Winner::P1.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unreadable_ratio_ignores_frames_without_hud() {
+        let mut log = AnomalyLog::default();
+        for n in 0..5 {
+            log.note_frame(&frame(n));
+        }
+        log.record(&frame(0), AnomalyCause::NoHud);
+        log.record(&frame(1), AnomalyCause::HpUnreadable);
+        log.record(&frame(1), AnomalyCause::SaUnreadable);
+        let report = QualityReport::evaluate(&[], &log);
+        assert_eq!(report.hud_frames, 4);
+        assert_eq!(report.unreadable_ratio, 0.25);
+    }
+
+    #[test]
+    fn violations_report_each_threshold() {
+        let matches = [
+            decided_match(&[Winner::P1, Winner::P1]),
+            decided_match(&[Winner::P1, Winner::Unknown]),
+        ];
+        let report = QualityReport::evaluate(&matches, &AnomalyLog::default());
+        assert_eq!(report.segmentation_confidence, 0.5);
+
+        let thresholds = QualityThresholds::default();
+        assert_eq!(report.violations(&thresholds).len(), 1);
+        let lenient = QualityThresholds {
+            min_segmentation_confidence: 0.5,
+            ..thresholds
+        };
+        assert!(report.violations(&lenient).is_empty());
+    }
+
+    #[test]
+    fn readability_is_tallied_per_player_and_round() {
+        let mut log = AnomalyLog::default();
+        for n in 0..20 {
+            log.note_frame(&frame(n));
+        }
+        log.record(&frame(0), AnomalyCause::NoHud);
+        log.record_players(&frame(2), AnomalyCause::HpUnreadable, [false, true]);
+        log.record_players(&frame(2), AnomalyCause::SaUnreadable, [true, false]);
+        log.record(&frame(12), AnomalyCause::AnalysisFailed);
+        let round = |start: u32| Round {
+            frames: (start..start + 10)
+                .map(|n| FrameData {
+                    frame_number: n,
+                    status: FrameStatus::Ok.into(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let m = Match {
+            rounds: vec![round(0), round(10)],
+            ..Default::default()
+        };
+
+        let report = ReadabilityReport::evaluate(&[m], &log, 5);
+        assert_eq!(report.total.hud_frames, 19);
+        assert_eq!(report.total.unreadable, [[1, 2, 1], [2, 1, 1]]);
+        assert_eq!(report.rounds[0].label, "M1 R1");
+        assert_eq!(report.rounds[0].unreadable, [[0, 1, 0], [1, 0, 0]]);
+        assert_eq!(report.rounds[1].percent(2, 2), 10.0);
+        assert_eq!(report.table().lines().count(), 4);
+
+        // Frames 2 and 12 are 10 s apart; the failed analysis is worse.
+        let worst: Vec<u32> = report.worst_frames.iter().map(|f| f.frame_number).collect();
+        assert_eq!(worst, [12, 2]);
+        assert_eq!(
+            report.worst_frames[1].captions(),
+            ["P1 SA unreadable", "P2 HP unreadable"]
+        );
+    }
+
+    #[test]
+    fn worst_frames_are_spread_out() {
+        let mut log = AnomalyLog::default();
+        for n in [0, 1, 2, 30] {
+            log.record_players(&frame(n), AnomalyCause::OdUnreadable, [true, false]);
+        }
+        let report = ReadabilityReport::evaluate(&[], &log, 5);
+        let worst: Vec<u32> = report.worst_frames.iter().map(|f| f.frame_number).collect();
+        assert_eq!(worst, [0, 30]);
+    }
+}
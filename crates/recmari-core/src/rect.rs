@@ -1,3 +1,5 @@
+use serde::Deserialize;
+
 /// A rectangle defined in normalized coordinates (0.0 to 1.0),
 /// independent of the actual frame resolution.
 #[derive(Debug, Clone, Copy)]
@@ -9,7 +11,8 @@ pub struct NormalizedRect {
 }
 
 /// A rectangle in absolute pixel coordinates.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PixelRect {
     pub x: u32,
     pub y: u32,
@@ -29,6 +32,37 @@ impl NormalizedRect {
 }
 
 impl PixelRect {
+    /// The smallest rect containing every point of `points`, widened by `margin` on each
+    /// side. Panics on an empty iterator.
+    pub fn around(points: impl IntoIterator<Item = (u32, u32)>, margin: u32) -> PixelRect {
+        let mut points = points.into_iter();
+        let (x, y) = points.next().expect("rect around no points");
+        let (mut x0, mut y0, mut x1, mut y1) = (x, y, x, y);
+        for (x, y) in points {
+            (x0, y0) = (x0.min(x), y0.min(y));
+            (x1, y1) = (x1.max(x), y1.max(y));
+        }
+        let (x0, y0) = (x0.saturating_sub(margin), y0.saturating_sub(margin));
+        PixelRect {
+            x: x0,
+            y: y0,
+            w: x1 + margin + 1 - x0,
+            h: y1 + margin + 1 - y0,
+        }
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.y + self.h
+    }
+
+    /// Whether the two rects share at least one pixel.
+    pub fn intersects(&self, other: &PixelRect) -> bool {
+        self.x < other.x + other.w
+            && other.x < self.x + self.w
+            && self.y < other.y + other.h
+            && other.y < self.y + self.h
+    }
+
     /// Scale this rect from a reference resolution to a target resolution.
     pub fn scale_to(self, target_w: u32, target_h: u32, ref_w: u32, ref_h: u32) -> PixelRect {
         assert!(ref_w > 0 && ref_h > 0, "reference resolution must be > 0");
@@ -79,6 +113,32 @@ mod tests {
         assert_eq!(scaled.h, 10);
     }
 
+    #[test]
+    fn intersection_and_containment() {
+        let r = PixelRect {
+            x: 10,
+            y: 10,
+            w: 5,
+            h: 5,
+        };
+        assert!(r.contains(10, 14) && !r.contains(15, 10));
+        let touching = PixelRect { x: 15, ..r };
+        assert!(!r.intersects(&touching));
+        let overlapping = PixelRect { x: 14, y: 0, ..r };
+        assert!(!r.intersects(&overlapping));
+        let overlapping = PixelRect { x: 14, y: 6, ..r };
+        assert!(r.intersects(&overlapping) && overlapping.intersects(&r));
+        assert_eq!(
+            PixelRect::around([(5, 8), (2, 3)], 1),
+            PixelRect {
+                x: 1,
+                y: 2,
+                w: 6,
+                h: 8
+            }
+        );
+    }
+
     #[test]
     fn normalized_to_pixel() {
         let n = NormalizedRect {
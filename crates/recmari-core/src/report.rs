@@ -0,0 +1,218 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+use tracing::info;
+
+use recmari_proto::proto::{EventType, Match, Round, RoundEvent};
+
+use crate::chart::{self, escape, Marker, Metric};
+use crate::error::PipelineError;
+use crate::summary::{format_duration, winner_name, RoundSummary};
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:0.5em 0}\
+th,td{border:1px solid #ccc;padding:0.2em 0.6em;text-align:right}\
+.charts svg{display:block;margin:0.5em 0;max-width:100%;height:auto}\
+.events{font-size:0.9em;color:#555}";
+
+/// Write a standalone HTML page with, per match, a table of round summaries and, per
+/// round, HP/SA/OD charts of both players marked with the round's events. Returns the
+/// number of matches.
+pub fn write_report(
+    matches: impl Iterator<Item = Result<Match, PipelineError>>,
+    title: &str,
+    out: &mut impl Write,
+) -> Result<usize, PipelineError> {
+    let title = escape(title);
+    write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    )
+    .map_err(PipelineError::Output)?;
+    let mut count = 0usize;
+    for (index, m) in matches.enumerate() {
+        let m = m?;
+        out.write_all(match_section(index, &m).as_bytes())
+            .map_err(PipelineError::Output)?;
+        count += 1;
+    }
+    if count == 0 {
+        writeln!(out, "<p>No matches.</p>").map_err(PipelineError::Output)?;
+    }
+    writeln!(out, "</body>\n</html>").map_err(PipelineError::Output)?;
+    info!(matches = count, "report written");
+    Ok(count)
+}
+
+fn match_section(index: usize, m: &Match) -> String {
+    let summaries: Vec<RoundSummary> = m.rounds.iter().map(RoundSummary::from_round).collect();
+    let total: f64 = summaries.iter().map(|r| r.duration_seconds).sum();
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<section>\n<h2>Match {}: winner {}, {} round(s), {}</h2>",
+        index + 1,
+        winner_name(m.winner()),
+        m.rounds.len(),
+        format_duration(total)
+    )
+    .unwrap();
+
+    html.push_str(
+        "<table>\n<tr><th>round</th><th>duration</th><th>winner</th><th>P1 damage</th>\
+         <th>P2 damage</th><th>P1 SA</th><th>P2 SA</th><th>lead changes</th></tr>\n",
+    );
+    let percent = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.0}%", v * 100.0));
+    for (round, s) in m.rounds.iter().zip(&summaries) {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            round.round_index + 1,
+            format_duration(s.duration_seconds),
+            winner_name(s.winner),
+            percent(s.damage_dealt[0]),
+            percent(s.damage_dealt[1]),
+            s.sa_uses[0],
+            s.sa_uses[1],
+            s.lead_changes
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n");
+
+    for round in &m.rounds {
+        html.push_str(&round_section(index, round));
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+fn round_section(match_index: usize, round: &Round) -> String {
+    let markers: Vec<Marker> = round
+        .events
+        .iter()
+        .filter(|e| e.r#type() != EventType::Unknown)
+        .map(|e| Marker {
+            x: e.timestamp_seconds - round.start_timestamp_seconds,
+            label: event_label(e),
+        })
+        .collect();
+    let mut html = format!(
+        "<h3>Match {} round {}</h3>\n<div class=\"charts\">\n",
+        match_index + 1,
+        round.round_index + 1
+    );
+    for metric in [Metric::Hp, Metric::Sa, Metric::Od] {
+        let series = chart::round_series(round, metric);
+        let title = format!("Round {} {metric:?}", round.round_index + 1);
+        html.push_str(&chart::render_svg_with_markers(
+            &title,
+            "seconds since round start",
+            metric,
+            &series,
+            &markers,
+        ));
+    }
+    html.push_str("</div>\n");
+    if !round.events.is_empty() {
+        let events: Vec<String> = round
+            .events
+            .iter()
+            .map(|e| {
+                let seconds = e.timestamp_seconds - round.start_timestamp_seconds;
+                format!("{seconds:.1}s {}", escape(&event_label(e)))
+            })
+            .collect();
+        writeln!(html, "<p class=\"events\">{}</p>", events.join(" · ")).unwrap();
+    }
+    html
+}
+
+/// Short marker text of an event, with its player when it has one.
+fn event_label(e: &RoundEvent) -> String {
+    let name = match e.r#type() {
+        EventType::Unknown => "?".to_string(),
+        EventType::FightBanner => "FIGHT".to_string(),
+        EventType::DriveImpact => "DI".to_string(),
+        EventType::DriveRush => "DR".to_string(),
+        EventType::Stun => "stun".to_string(),
+        EventType::Ko => "KO".to_string(),
+        EventType::SaUse if e.sa_level > 0 => format!("SA{}", e.sa_level),
+        EventType::SaUse => "SA".to_string(),
+        EventType::SuperFlash => "flash".to_string(),
+        EventType::LeadChange => "lead".to_string(),
+    };
+    if e.player > 0 {
+        format!("{name} P{}", e.player)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{FrameData, PlayerState, Winner};
+
+    use super::*;
+    use crate::test_support::{frame_at, health};
+
+    #[test]
+    fn report_has_summary_charts_and_markers_per_round() {
+        let round = Round {
+            frames: [(10.0, 1.0, 1.0), (20.0, 0.6, 1.0), (30.0, 0.6, 0.0)]
+                .map(|(ts, p1, p2)| {
+                    let [player1, player2] = [p1, p2].map(|hp| {
+                        Some(PlayerState {
+                            sa_gauge: Some(1.0),
+                            od_gauge: Some(6.0),
+                            ..health(Some(hp))
+                        })
+                    });
+                    FrameData {
+                        player1,
+                        player2,
+                        ..frame_at(ts)
+                    }
+                })
+                .to_vec(),
+            start_timestamp_seconds: 10.0,
+            winner: Winner::P1.into(),
+            events: vec![RoundEvent {
+                r#type: EventType::SaUse.into(),
+                timestamp_seconds: 25.0,
+                player: 1,
+                sa_level: 3,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let m = Match {
+            rounds: vec![
+                round.clone(),
+                Round {
+                    round_index: 1,
+                    ..round
+                },
+            ],
+            winner: Winner::P1.into(),
+            ..Default::default()
+        };
+
+        let mut out = Vec::new();
+        assert_eq!(
+            write_report(std::iter::once(Ok(m)), "A & B", &mut out).unwrap(),
+            1
+        );
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<h2>Match 1: winner P1, 2 round(s), 0:40</h2>"));
+        assert!(html.contains("<h3>Match 1 round 2</h3>"));
+        // HP, SA and OD for each of the two rounds.
+        assert_eq!(html.matches("<svg").count(), 6);
+        assert_eq!(html.matches(">SA3 P1</text>").count(), 6);
+        assert!(html.contains("15.0s SA3 P1"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+}
@@ -0,0 +1,202 @@
+use tracing::info;
+
+use recmari_proto::proto::{FrameData, Match, Round, Winner};
+
+use crate::analysis::events::gauges;
+use crate::players::player_state;
+
+/// Winner health readings at or above this count as undamaged (bar edge noise).
+const PERFECT_MIN_HEALTH: f64 = 0.99;
+/// A winner whose health fell below this made a comeback.
+pub const COMEBACK_MAX_HEALTH: f64 = 0.2;
+
+/// Winner and loser (1 or 2) of a round decided by KO, or None for draws and rounds
+/// without a winner.
+fn ko_sides(round: &Round) -> Option<(u32, u32)> {
+    if round.draw {
+        return None;
+    }
+    match round.winner() {
+        Winner::P1 => Some((1, 2)),
+        Winner::P2 => Some((2, 1)),
+        Winner::Unknown => None,
+    }
+}
+
+/// Index of the first frame where `player`'s health reads as a KO.
+fn ko_frame(frames: &[FrameData], player: u32) -> Option<usize> {
+    frames
+        .iter()
+        .position(|fd| gauges::is_ko(player_state(fd, player).and_then(|p| p.health_ratio)))
+}
+
+/// Whether the round was won by KO without the winner losing any health. Needs at
+/// least one health reading of the winner.
+pub fn is_perfect(round: &Round) -> bool {
+    let Some((winner, loser)) = ko_sides(round) else {
+        return false;
+    };
+    if ko_frame(&round.frames, loser).is_none() {
+        return false;
+    }
+    let mut health = round
+        .frames
+        .iter()
+        .filter_map(|fd| player_state(fd, winner).and_then(|p| p.health_ratio))
+        .peekable();
+    health.peek().is_some() && health.all(|h| h >= PERFECT_MIN_HEALTH)
+}
+
+/// Whether the loser was KO'd while in burnout, where blocked attacks deal chip damage
+/// that can finish the round. The Drive gauge state is taken from the last frame up to
+/// the KO that shows it.
+pub fn is_burnout_ko(round: &Round) -> bool {
+    let Some((_, loser)) = ko_sides(round) else {
+        return false;
+    };
+    let Some(ko) = ko_frame(&round.frames, loser) else {
+        return false;
+    };
+    round.frames[..=ko]
+        .iter()
+        .rev()
+        .filter_map(|fd| player_state(fd, loser))
+        .find(|p| p.burnout_gauge.is_some() || p.od_gauge.is_some())
+        .is_some_and(|p| p.burnout_gauge.is_some())
+}
+
+/// Lowest health reading of the round winner and the frame it was read on. KO
+/// readings are misreads for the winner and skipped.
+pub fn winner_lowest_health(round: &Round) -> Option<(f64, &FrameData)> {
+    let winner = match round.winner() {
+        Winner::P1 => 1,
+        Winner::P2 => 2,
+        Winner::Unknown => return None,
+    };
+    round
+        .frames
+        .iter()
+        .filter_map(|fd| {
+            let health = player_state(fd, winner).and_then(|p| p.health_ratio)?;
+            (!gauges::is_ko(Some(health))).then_some((health, fd))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// Set the `perfect`, `burnout_ko` and comeback fields of every round. Call after
+/// winners are arbitrated.
+pub fn attach_finishes(matches: &mut [Match]) {
+    let (mut perfects, mut burnout_kos, mut comebacks) = (0usize, 0usize, 0usize);
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        round.perfect = is_perfect(round);
+        round.burnout_ko = is_burnout_ko(round);
+        let lowest = winner_lowest_health(round).map(|(h, fd)| (h, fd.timestamp_seconds));
+        round.winner_lowest_health = lowest.map(|(h, _)| h);
+        round.winner_lowest_health_seconds = lowest.map(|(_, ts)| ts);
+        round.comeback = lowest.is_some_and(|(h, _)| h < COMEBACK_MAX_HEALTH);
+        perfects += usize::from(round.perfect);
+        burnout_kos += usize::from(round.burnout_ko);
+        comebacks += usize::from(round.comeback);
+    }
+    info!(perfects, burnout_kos, comebacks, "round finishes marked");
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::PlayerState;
+
+    use super::*;
+    use crate::test_support::{drive, hp_frame};
+
+    fn round(frames: Vec<FrameData>) -> Round {
+        Round {
+            frames,
+            winner: Winner::P1.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn perfect_needs_an_untouched_winner_and_a_ko() {
+        let perfect = round(vec![
+            hp_frame(0.0, 1.0, 1.0),
+            hp_frame(0.0, 0.995, 0.4),
+            hp_frame(0.0, 1.0, 0.0),
+        ]);
+        assert!(is_perfect(&perfect));
+
+        let mut hit = perfect.clone();
+        hit.frames[1] = hp_frame(0.0, 0.9, 0.4);
+        assert!(!is_perfect(&hit));
+
+        let draw = Round {
+            draw: true,
+            ..perfect.clone()
+        };
+        assert!(!is_perfect(&draw));
+        let mut no_ko = perfect;
+        no_ko.frames.pop();
+        assert!(!is_perfect(&no_ko));
+    }
+
+    #[test]
+    fn comeback_from_the_winner_lowest_health() {
+        let frames = [
+            (0.0, 1.0, 1.0),
+            (1.0, 0.15, 0.6),
+            (2.0, 0.0, 0.6),
+            (3.0, 0.15, 0.0),
+        ]
+        .map(|(ts, p1, p2)| hp_frame(ts, p1, p2))
+        .to_vec();
+        let mut matches = vec![Match {
+            rounds: vec![round(frames)],
+            ..Default::default()
+        }];
+        attach_finishes(&mut matches);
+        let round = &matches[0].rounds[0];
+        assert!(round.comeback);
+        // The winner's KO reading on frame 2 is a misread.
+        assert_eq!(round.winner_lowest_health, Some(0.15));
+        assert_eq!(round.winner_lowest_health_seconds, Some(1.0));
+
+        let unknown = Round {
+            winner: Winner::Unknown.into(),
+            ..round.clone()
+        };
+        assert!(winner_lowest_health(&unknown).is_none());
+    }
+
+    #[test]
+    fn burnout_ko_uses_the_last_drive_state_before_the_ko() {
+        // P2's health and Drive gauges; the KO frame has no Drive reading.
+        let [burnout, recovered] = [
+            [
+                (0.3, Some(1.0), None),
+                (0.1, None, Some(0.3)),
+                (0.0, None, None),
+            ],
+            [
+                (0.1, None, Some(0.3)),
+                (0.05, Some(1.0), None),
+                (0.0, None, None),
+            ],
+        ]
+        .map(|states| {
+            round(
+                states
+                    .map(|(hp, od, burnout)| FrameData {
+                        player2: Some(PlayerState {
+                            health_ratio: Some(hp),
+                            ..drive(od, burnout)
+                        }),
+                        ..hp_frame(0.0, 0.5, 0.0)
+                    })
+                    .to_vec(),
+            )
+        });
+        assert!(is_burnout_ko(&burnout));
+        assert!(!is_perfect(&burnout));
+        assert!(!is_burnout_ko(&recovered));
+    }
+}
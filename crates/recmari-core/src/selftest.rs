@@ -0,0 +1,208 @@
+use image::{Rgb, RgbImage};
+use tracing::{info, warn};
+
+use crate::analysis::huds::manemon::{paint_reference_hud, ReferenceGauges};
+use crate::analysis::huds::{all_huds, select_hud};
+use crate::analysis::{HudType, OdValue, SaValue};
+use crate::config::HudConfig;
+use crate::video::frame::Frame;
+
+const REF_WIDTH: u32 = 1920;
+const REF_HEIGHT: u32 = 1080;
+/// Stage behind the reference HUDs.
+const BACKDROP: Rgb<u8> = Rgb([90, 110, 140]);
+/// Largest accepted difference between a drawn and a read value (drawn values are
+/// rounded to whole pixels).
+const TOLERANCE: f64 = 0.01;
+
+/// A HUD frame drawn from known gauge values.
+#[derive(Debug, Clone, Copy)]
+pub struct ReferenceCase {
+    pub name: &'static str,
+    pub hud: HudType,
+    pub players: [ReferenceGauges; 2],
+}
+
+const fn gauges(hp: f64, sa: SaValue, od: OdValue) -> ReferenceGauges {
+    ReferenceGauges { hp, sa, od }
+}
+
+/// Reference frames covering each gauge state the classifiers distinguish. They are
+/// drawn, not captured: no real HUD crops are bundled yet, so a passing self-test
+/// shows that the readers agree with the drawn layout and colors, not with the game.
+pub const REFERENCES: [ReferenceCase; 3] = [
+    ReferenceCase {
+        name: "round start",
+        hud: HudType::Manemon,
+        players: [
+            gauges(1.0, SaValue::Stocks(0.0), OdValue::Normal(6.0)),
+            gauges(1.0, SaValue::Stocks(0.0), OdValue::Normal(6.0)),
+        ],
+    },
+    ReferenceCase {
+        name: "mid round",
+        hud: HudType::Manemon,
+        players: [
+            gauges(0.62, SaValue::Stocks(1.4), OdValue::Normal(3.0)),
+            gauges(0.35, SaValue::Stocks(2.75), OdValue::Normal(5.0)),
+        ],
+    },
+    ReferenceCase {
+        name: "low health",
+        hud: HudType::Manemon,
+        players: [
+            gauges(0.12, SaValue::CaReady, OdValue::Burnout(0.4)),
+            gauges(0.0, SaValue::Stocks(3.0), OdValue::Normal(0.0)),
+        ],
+    },
+];
+
+/// One compared value of a reference frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub case: &'static str,
+    /// `HUD`, or the player and gauge such as `P2 SA`.
+    pub what: String,
+    pub expected: String,
+    pub actual: String,
+    pub passed: bool,
+}
+
+/// Draw the frame of a reference case.
+pub fn render_reference(case: &ReferenceCase) -> RgbImage {
+    let mut image = RgbImage::from_pixel(REF_WIDTH, REF_HEIGHT, BACKDROP);
+    paint_reference_hud(&mut image, &case.players);
+    image
+}
+
+/// Run HUD detection and every gauge classifier, configured by `config`, on each
+/// reference frame and compare the results with the drawn values.
+pub fn run_self_test(config: &HudConfig) -> Vec<Check> {
+    let huds = all_huds(REF_WIDTH, REF_HEIGHT, config);
+    let mut checks = Vec::new();
+    for case in &REFERENCES {
+        let frame = Frame {
+            image: render_reference(case),
+            frame_number: 0,
+            timestamp_seconds: 0.0,
+        };
+        let detected = select_hud(&huds, &frame).map(|i| huds[i].hud_type());
+        checks.push(Check {
+            case: case.name,
+            what: "HUD".into(),
+            expected: case.hud.to_string(),
+            actual: detected.map_or("none".into(), |hud| hud.to_string()),
+            passed: detected == Some(case.hud),
+        });
+
+        // Read with the expected HUD even when detection failed, to tell the gauges apart.
+        let hud = huds
+            .iter()
+            .find(|hud| hud.hud_type() == case.hud)
+            .expect("every HUD type is registered");
+        let hp = hud.analyze_hp(&frame);
+        let sa = hud.analyze_sa(&frame);
+        let od = hud.analyze_od(&frame);
+        let actual = [(hp.p1, sa.p1, od.p1), (hp.p2, sa.p2, od.p2)];
+        for (player, (expected, (hp, sa, od))) in case.players.iter().zip(actual).enumerate() {
+            let check =
+                |gauge: &str, expected: String, actual: Option<String>, passed: bool| Check {
+                    case: case.name,
+                    what: format!("P{} {gauge}", player + 1),
+                    expected,
+                    actual: actual.unwrap_or_else(|| "unreadable".into()),
+                    passed,
+                };
+            checks.push(check(
+                "HP",
+                format!("{:.3}", expected.hp),
+                hp.map(|v| format!("{v:.3}")),
+                hp.is_some_and(|v| (v - expected.hp).abs() <= TOLERANCE),
+            ));
+            checks.push(check(
+                "SA",
+                sa_text(expected.sa),
+                sa.map(sa_text),
+                sa.is_some_and(|v| sa_matches(v, expected.sa)),
+            ));
+            checks.push(check(
+                "OD",
+                od_text(expected.od),
+                od.map(od_text),
+                od.is_some_and(|v| od_matches(v, expected.od)),
+            ));
+        }
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    for c in checks.iter().filter(|c| !c.passed) {
+        warn!(case = c.case, what = %c.what, expected = %c.expected, actual = %c.actual, "self-test check failed");
+    }
+    info!(
+        references = REFERENCES.len(),
+        checks = checks.len(),
+        failed,
+        "self-test finished"
+    );
+    checks
+}
+
+pub(crate) fn sa_text(sa: SaValue) -> String {
+    match sa {
+        SaValue::Stocks(v) => format!("{v:.3}"),
+        SaValue::CaReady => "CA".into(),
+    }
+}
+
+fn sa_matches(actual: SaValue, expected: SaValue) -> bool {
+    match (actual, expected) {
+        (SaValue::Stocks(a), SaValue::Stocks(e)) => (a - e).abs() <= TOLERANCE,
+        (SaValue::CaReady, SaValue::CaReady) => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn od_text(od: OdValue) -> String {
+    match od {
+        OdValue::Normal(v) => format!("{v:.3}"),
+        OdValue::Burnout(v) => format!("burnout {v:.3}"),
+    }
+}
+
+fn od_matches(actual: OdValue, expected: OdValue) -> bool {
+    match (actual, expected) {
+        (OdValue::Normal(a), OdValue::Normal(e)) | (OdValue::Burnout(a), OdValue::Burnout(e)) => {
+            (a - e).abs() <= TOLERANCE
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_hud_config;
+
+    #[test]
+    fn references_read_back_with_the_default_profile() {
+        let checks = run_self_test(&HudConfig::default());
+        let failed: Vec<&Check> = checks.iter().filter(|c| !c.passed).collect();
+        assert!(failed.is_empty(), "failed checks: {failed:#?}");
+        assert_eq!(checks.len(), REFERENCES.len() * 7);
+    }
+
+    #[test]
+    fn a_misplaced_scanline_fails_its_checks() {
+        let config = parse_hud_config(
+            "[manemon.scanlines]\nhp_p1 = { x_start = 886, x_end = 187, y = 95 }\n",
+        )
+        .unwrap();
+        let failed: Vec<String> = run_self_test(&config)
+            .into_iter()
+            .filter(|c| !c.passed)
+            .map(|c| format!("{} {}", c.case, c.what))
+            .collect();
+        assert!(failed.contains(&"mid round P1 HP".to_string()));
+        assert!(failed.iter().all(|f| f.ends_with("P1 HP")));
+    }
+}
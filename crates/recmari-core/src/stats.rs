@@ -0,0 +1,313 @@
+use tracing::info;
+
+use recmari_proto::proto::{
+    Burnout, DriveStats, EventType, FrameData, Match, PacingStats, PlayerState, Round,
+};
+
+use crate::damage;
+use crate::ko_slowdown::before_slowdown;
+use crate::players::player_state;
+
+/// Drive gauge aggregates of `player` (1 or 2) over a round's frames. Depletions and
+/// burnout time come from the round's `burnouts` (see [`crate::burnout`]), cut off at
+/// the last of `frames`.
+///
+/// For the average, each frame's state holds until the next frame; frames without the
+/// player (HUD absent, cinematics) contribute nothing.
+pub fn drive_stats(frames: &[FrameData], burnouts: &[Burnout], player: u32) -> DriveStats {
+    let end = frames
+        .last()
+        .map_or(f64::NEG_INFINITY, |fd| fd.timestamp_seconds);
+    let mut stats = DriveStats::default();
+    for b in burnouts
+        .iter()
+        .filter(|b| b.player == player && b.start_seconds <= end)
+    {
+        stats.depletions += 1;
+        stats.burnout_seconds += (b.start_seconds + b.duration_seconds).min(end) - b.start_seconds;
+    }
+
+    let mut drive_time = 0.0;
+    let mut drive_sum = 0.0;
+    for (fd, next) in frames.iter().zip(frames.iter().skip(1)) {
+        let Some(state) = player_state(fd, player) else {
+            continue;
+        };
+        let drive = if state.burnout_gauge.is_some() {
+            Some(0.0)
+        } else {
+            state.od_gauge
+        };
+        if let Some(drive) = drive {
+            let dt = next.timestamp_seconds - fd.timestamp_seconds;
+            drive_time += dt;
+            drive_sum += drive * dt;
+        }
+    }
+
+    stats.average_drive = (drive_time > 0.0).then(|| drive_sum / drive_time);
+    stats
+}
+
+/// Drive gauge drops this close to a whole number of bars are spent on an action
+/// (passive regeneration between samples shaves a little off).
+const DRIVE_USE_TOLERANCE: f64 = 0.3;
+/// Readings further apart than this are not compared: blocking drain and regeneration
+/// in between would blur the drop.
+const DRIVE_USE_MAX_GAP_SECONDS: f64 = 0.5;
+/// A green Drive Rush tint this close to a drop belongs to it.
+const DRIVE_RUSH_TINT_WINDOW_SECONDS: f64 = 1.0;
+
+/// Drive Rushes and Drive Impacts/Parries of one player, from sudden drops of their
+/// Drive gauge: about 3 bars is a Drive Rush, about 1 bar is a Parry Drive Rush when
+/// one of `rush_tints` (timestamps of green screen tints) is nearby and a Drive Impact
+/// or Parry otherwise. Returns `(drive_rushes, drive_impacts_or_parries)`.
+pub fn drive_usage(
+    frames: &[FrameData],
+    player: impl Fn(&FrameData) -> Option<&PlayerState>,
+    rush_tints: &[f64],
+) -> (u32, u32) {
+    let (mut rushes, mut impacts) = (0, 0);
+    let mut last: Option<(f64, f64)> = None;
+    for fd in frames {
+        let state = player(fd);
+        let Some(od) = state
+            .filter(|p| p.burnout_gauge.is_none())
+            .and_then(|p| p.od_gauge)
+        else {
+            if state.is_some_and(|p| p.burnout_gauge.is_some()) {
+                last = None;
+            }
+            continue;
+        };
+        let ts = fd.timestamp_seconds;
+        if let Some((prev_ts, prev_od)) = last.filter(|&(t, _)| ts - t <= DRIVE_USE_MAX_GAP_SECONDS)
+        {
+            let drop = prev_od - od;
+            let near = |bars: f64| (drop - bars).abs() <= DRIVE_USE_TOLERANCE;
+            let tinted = rush_tints.iter().any(|&t| {
+                t >= prev_ts - DRIVE_RUSH_TINT_WINDOW_SECONDS
+                    && t <= ts + DRIVE_RUSH_TINT_WINDOW_SECONDS
+            });
+            if near(3.0) || (near(1.0) && tinted) {
+                rushes += 1;
+            } else if near(1.0) {
+                impacts += 1;
+            }
+        }
+        last = Some((ts, od));
+    }
+    (rushes, impacts)
+}
+
+/// Drive gauge aggregates and usage counts of player 1 or 2.
+fn player_drive(
+    frames: &[FrameData],
+    burnouts: &[Burnout],
+    player: u32,
+    rush_tints: &[f64],
+) -> DriveStats {
+    let (drive_rushes, drive_impacts_or_parries) =
+        drive_usage(frames, |fd| player_state(fd, player), rush_tints);
+    DriveStats {
+        drive_rushes,
+        drive_impacts_or_parries,
+        ..drive_stats(frames, burnouts, player)
+    }
+}
+
+/// Timestamps of the round's green Drive Rush tints.
+fn rush_tints(round: &Round) -> Vec<f64> {
+    round
+        .events
+        .iter()
+        .filter(|e| e.r#type() == EventType::DriveRush)
+        .map(|e| e.timestamp_seconds)
+        .collect()
+}
+
+/// Timestamps of frames where either player was hit (see [`damage::hits`]).
+fn damage_times(frames: &[FrameData]) -> Vec<f64> {
+    let mut times: Vec<f64> = [1, 2]
+        .into_iter()
+        .flat_map(|player| damage::hits(frames, player))
+        .map(|e| e.timestamp_seconds)
+        .collect();
+    times.sort_by(f64::total_cmp);
+    times.dedup();
+    times
+}
+
+/// Tempo of a round starting at `start` (seconds, same clock as the frames).
+pub fn pacing_stats(frames: &[FrameData], start: f64) -> PacingStats {
+    let hits = damage_times(frames);
+    let end = frames.last().map_or(start, |fd| fd.timestamp_seconds);
+    let gaps: Vec<f64> = hits.windows(2).map(|w| w[1] - w[0]).collect();
+    let longest_neutral = std::iter::once(start)
+        .chain(hits.iter().copied())
+        .chain(std::iter::once(end))
+        .collect::<Vec<_>>()
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .fold(0.0, f64::max);
+    PacingStats {
+        time_to_first_hit: hits.first().map(|t| (t - start).max(0.0)),
+        longest_neutral_seconds: longest_neutral,
+        average_seconds_between_damage: (!gaps.is_empty())
+            .then(|| gaps.iter().sum::<f64>() / gaps.len() as f64),
+        damage_events: hits.len() as u32,
+    }
+}
+
+/// Fill in the per-round Drive usage and pacing statistics of every round, leaving out KO
+/// slowdown frames (see [`crate::ko_slowdown`]). Call after the burnouts are attached.
+pub fn attach_round_stats(matches: &mut [Match]) {
+    let mut rounds = 0usize;
+    for round in matches.iter_mut().flat_map(|m| m.rounds.iter_mut()) {
+        // Slow motion after a KO would stretch durations beyond game time.
+        let frames = before_slowdown(&round.frames);
+        let tints = rush_tints(round);
+        round.player1_drive = Some(player_drive(frames, &round.burnouts, 1, &tints));
+        round.player2_drive = Some(player_drive(frames, &round.burnouts, 2, &tints));
+        round.pacing = Some(pacing_stats(frames, round.start_timestamp_seconds));
+        rounds += 1;
+    }
+    info!(rounds, "round statistics computed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::burnout::round_burnouts;
+    use crate::test_support::{drive, frame_at, health, hp_frame};
+
+    #[test]
+    fn burnout_time_depletions_and_average() {
+        let mut frames = [
+            (0.0, Some(6.0), None),
+            (1.0, Some(2.0), None),
+            (2.0, None, Some(0.0)), // depleted
+            (4.0, None, Some(0.9)),
+            (5.0, Some(1.0), None),
+            (6.0, None, None),      // cinematic
+            (7.0, None, Some(0.0)), // depleted again
+            (8.0, None, Some(0.1)),
+        ]
+        .map(|(ts, od, burnout)| FrameData {
+            player1: Some(drive(od, burnout)),
+            ..frame_at(ts)
+        });
+        frames[5].player1 = None;
+        let burnouts = round_burnouts(&Round {
+            frames: frames.to_vec(),
+            ..Default::default()
+        });
+        let stats = drive_stats(&frames, &burnouts, 1);
+        assert_eq!(stats.depletions, 2);
+        assert!((stats.burnout_seconds - 4.0).abs() < 1e-9);
+        // Drive 6, 2 for 1 s each, 0 for 4 s, 1 for 1 s: 9 / 7.
+        assert!((stats.average_drive.unwrap() - 9.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn burnouts_are_cut_off_at_the_last_frame() {
+        let frames = [
+            (0.0, Some(2.0), None),
+            (1.0, None, Some(0.0)),
+            (2.0, None, Some(0.5)),
+        ]
+        .map(|(ts, od, burnout)| FrameData {
+            player1: Some(drive(od, burnout)),
+            ..frame_at(ts)
+        });
+        let burnout = |start_seconds, duration_seconds| Burnout {
+            player: 1,
+            start_seconds,
+            duration_seconds,
+            ..Default::default()
+        };
+        // Running into the KO slowdown, and starting during it.
+        let burnouts = [burnout(1.0, 5.0), burnout(3.0, 1.0)];
+        let stats = drive_stats(&frames, &burnouts, 1);
+        assert_eq!(stats.depletions, 1);
+        assert!((stats.burnout_seconds - 1.0).abs() < 1e-9);
+        assert_eq!(drive_stats(&frames, &burnouts, 2).depletions, 0);
+    }
+
+    #[test]
+    fn unread_gauge_has_no_average() {
+        let frames = [0.0, 1.0].map(|ts| FrameData {
+            player1: Some(drive(None, None)),
+            ..frame_at(ts)
+        });
+        let stats = drive_stats(&frames, &[], 1);
+        assert_eq!(stats.average_drive, None);
+        assert_eq!(stats.depletions, 0);
+    }
+
+    #[test]
+    fn drive_gauge_drops_count_rushes_and_impacts() {
+        let frames = [
+            (0.0, Some(6.0), None),
+            (0.2, Some(3.1), None), // raw Drive Rush
+            (0.4, Some(2.2), None), // Drive Impact or Parry
+            (0.6, Some(2.1), None), // blocking drain
+            (2.0, Some(2.3), None),
+            (2.2, Some(1.3), None), // Parry Drive Rush: tinted
+            (5.0, Some(0.3), None), // too far apart to tell
+            (5.2, None, Some(0.0)),
+            (5.4, Some(0.5), None),
+        ]
+        .map(|(ts, od, burnout)| FrameData {
+            player1: Some(drive(od, burnout)),
+            ..frame_at(ts)
+        });
+        assert_eq!(
+            drive_usage(&frames, |fd| fd.player1.as_ref(), &[2.5]),
+            (2, 1)
+        );
+        assert_eq!(
+            drive_usage(&frames, |fd| fd.player2.as_ref(), &[2.5]),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn pacing_from_health_drops() {
+        let frames = [
+            (10.0, Some(1.0), 1.0),
+            (14.0, Some(1.0), 0.8), // first hit
+            (15.0, None, 0.8),
+            (16.0, Some(0.999), 0.8), // noise
+            (24.0, Some(0.7), 0.8),
+            (26.0, Some(0.7), 0.5),
+            (40.0, Some(0.7), 0.5),
+        ]
+        .map(|(ts, p1, p2)| FrameData {
+            player1: Some(health(p1)),
+            ..hp_frame(ts, 0.0, p2)
+        });
+        let pacing = pacing_stats(&frames, 12.0);
+        assert_eq!(pacing.damage_events, 3);
+        assert_eq!(pacing.time_to_first_hit, Some(2.0));
+        assert_eq!(pacing.longest_neutral_seconds, 14.0);
+        assert_eq!(pacing.average_seconds_between_damage, Some(6.0));
+
+        let quiet = pacing_stats(&frames[..1], 9.0);
+        assert_eq!(quiet.time_to_first_hit, None);
+        assert_eq!(quiet.average_seconds_between_damage, None);
+        assert_eq!(quiet.longest_neutral_seconds, 1.0);
+    }
+
+    #[test]
+    fn pacing_counts_the_hits_of_the_damage_events() {
+        // A slow drain: no single step is a hit, together they are one.
+        let frames = [(0.0, 1.0), (1.0, 0.997), (2.0, 0.994), (3.0, 0.991)]
+            .map(|(ts, p1)| hp_frame(ts, p1, 1.0));
+        let hits = damage::hits(&frames, 1);
+        assert_eq!(hits.len(), 1);
+        let pacing = pacing_stats(&frames, 0.0);
+        assert_eq!(pacing.damage_events, 1);
+        assert_eq!(pacing.time_to_first_hit, Some(hits[0].timestamp_seconds));
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use prost::Message;
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use recmari_proto::proto::{FrameData, PlayerState};
+
+use crate::error::PipelineError;
+use crate::export::status_name;
+
+/// Record format of a [`FrameStream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// Length-delimited `FrameData`, like the checkpoint file.
+    Protobuf,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl StreamFormat {
+    /// JSONL for `.jsonl` / `.ndjson` files, protobuf otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext)
+                if ext.eq_ignore_ascii_case("jsonl") || ext.eq_ignore_ascii_case("ndjson") =>
+            {
+                StreamFormat::Jsonl
+            }
+            _ => StreamFormat::Protobuf,
+        }
+    }
+}
+
+/// Writes every sampled frame to a file as soon as it is analyzed, so that partial
+/// results survive an interrupted run and other processes can tail the file.
+///
+/// Each record is flushed immediately. Frames inserted later by refinement are not
+/// streamed; the final output has them.
+pub struct FrameStream {
+    path: PathBuf,
+    format: StreamFormat,
+    file: File,
+    frames: usize,
+}
+
+impl FrameStream {
+    /// Create (or truncate) `path`; the format follows its extension.
+    pub fn create(path: &Path) -> Result<Self, PipelineError> {
+        let format = StreamFormat::from_path(path);
+        let file = File::create(path).map_err(PipelineError::io("create stream", path))?;
+        info!(?path, ?format, "frame stream opened");
+        Ok(Self {
+            path: path.to_path_buf(),
+            format,
+            file,
+            frames: 0,
+        })
+    }
+
+    pub fn append(&mut self, fd: &FrameData) -> Result<(), PipelineError> {
+        let record = match self.format {
+            StreamFormat::Protobuf => {
+                let mut buf = Vec::with_capacity(fd.encoded_len() + 4);
+                fd.encode_length_delimited(&mut buf)
+                    .expect("encoding into a Vec never runs out of space");
+                buf
+            }
+            StreamFormat::Jsonl => {
+                let mut line = frame_json(fd).to_string().into_bytes();
+                line.push(b'\n');
+                line
+            }
+        };
+        // One write per record, so readers never see a partial line from a buffer split.
+        if let Err(e) = self.file.write_all(&record) {
+            error!(path = ?self.path, %e, "failed to write frame stream");
+            return Err(PipelineError::io("write stream", &self.path)(e));
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+
+/// JSON record of one frame, as written to JSONL streams.
+pub fn frame_json(fd: &FrameData) -> Value {
+    json!({
+        "frame_number": fd.frame_number,
+        "timestamp_seconds": fd.timestamp_seconds,
+        "status": status_name(fd.status()),
+        "p1": player_json(fd.player1.as_ref()),
+        "p2": player_json(fd.player2.as_ref()),
+    })
+}
+
+pub(crate) fn player_json(player: Option<&PlayerState>) -> Value {
+    match player {
+        Some(p) => json!({
+            "hp": p.health_ratio,
+            "sa": p.sa_gauge,
+            "ca_ready": p.ca_ready,
+            "od": p.od_gauge,
+            "burnout": p.burnout_gauge,
+            "hp_confidence": p.health_confidence,
+            "sa_confidence": p.sa_confidence,
+            "od_confidence": p.od_confidence,
+        }),
+        None => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::FrameStatus;
+
+    use super::*;
+    use crate::test_support::{frame, health};
+
+    #[test]
+    fn streams_frames_in_either_format() {
+        let dir = std::env::temp_dir().join(format!("recmari-stream-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let [first, second, third] = [0, 60, 120].map(|n| FrameData {
+            player1: Some(health(Some(0.5))),
+            status: FrameStatus::Ok.into(),
+            ..frame(n)
+        });
+
+        let jsonl = dir.join("frames.jsonl");
+        let mut stream = FrameStream::create(&jsonl).unwrap();
+        stream.append(&first).unwrap();
+        stream.append(&second).unwrap();
+        // Readable without closing the stream, as a tailing process would.
+        let text = std::fs::read_to_string(&jsonl).unwrap();
+        let lines: Vec<Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["frame_number"], 60);
+        assert_eq!(lines[1]["status"], "ok");
+        assert_eq!(lines[1]["p1"]["hp"], 0.5);
+        assert!(lines[1]["p2"].is_null());
+
+        let pb = dir.join("frames.pb");
+        let mut stream = FrameStream::create(&pb).unwrap();
+        stream.append(&third).unwrap();
+        let bytes = std::fs::read(&pb).unwrap();
+        assert_eq!(
+            FrameData::decode_length_delimited(bytes.as_slice()).unwrap(),
+            third
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
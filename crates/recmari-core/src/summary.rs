@@ -0,0 +1,250 @@
+use std::io::Write;
+
+use tracing::info;
+
+use crate::error::PipelineError;
+use recmari_proto::proto::{EventType, FrameData, Match, PlayerState, Round, Winner};
+
+use crate::i18n::{self, Lang, ROUND_COLUMNS};
+use crate::links::VideoLink;
+
+/// Minimum width of each round table column; headers wider than this widen it.
+const ROUND_COLUMN_WIDTHS: [usize; ROUND_COLUMNS] = [5, 8, 6, 9, 9, 8, 8, 5, 5, 5];
+
+/// Per-round figures shown by [`write_summary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundSummary {
+    pub duration_seconds: f64,
+    pub winner: Winner,
+    /// Fraction of the opponent's health bar each player removed (P1, P2).
+    pub damage_dealt: [Option<f64>; 2],
+    /// SA uses of each player (P1, P2).
+    pub sa_uses: [usize; 2],
+    /// Times the health lead changed hands (including the first lead).
+    pub lead_changes: usize,
+}
+
+impl RoundSummary {
+    pub fn from_round(round: &Round) -> Self {
+        let end = round.frames.last().map_or(0.0, |fd| fd.timestamp_seconds);
+        let health = |player: fn(&FrameData) -> Option<&PlayerState>| {
+            let mut readings = round
+                .frames
+                .iter()
+                .filter_map(|fd| player(fd).and_then(|p| p.health_ratio));
+            let first = readings.next()?;
+            Some(first - readings.next_back().unwrap_or(first))
+        };
+        let sa_uses = |player: u32| {
+            round
+                .events
+                .iter()
+                .filter(|e| e.r#type() == EventType::SaUse && e.player == player)
+                .count()
+        };
+        Self {
+            duration_seconds: (end - round.start_timestamp_seconds).max(0.0),
+            winner: round.winner(),
+            // Damage dealt by P1 is health lost by P2 and vice versa.
+            damage_dealt: [
+                health(|fd| fd.player2.as_ref()),
+                health(|fd| fd.player1.as_ref()),
+            ],
+            sa_uses: [sa_uses(1), sa_uses(2)],
+            lead_changes: round
+                .events
+                .iter()
+                .filter(|e| e.r#type() == EventType::LeadChange)
+                .count(),
+        }
+    }
+}
+
+/// Print a human-readable table per match in `lang`: one row per round with duration,
+/// winner, damage dealt (total and per second), SA uses, lead changes and a link to the
+/// round in the source video. Returns the number of matches.
+pub fn write_summary(
+    matches: impl Iterator<Item = Result<Match, PipelineError>>,
+    lang: Lang,
+    out: &mut impl Write,
+) -> Result<usize, PipelineError> {
+    let headers = lang.round_columns();
+    let widths: [usize; ROUND_COLUMNS] =
+        std::array::from_fn(|i| ROUND_COLUMN_WIDTHS[i].max(i18n::display_width(headers[i])));
+    let round_header = table_row(&headers, &widths);
+    let mut count = 0usize;
+    for (index, m) in matches.enumerate() {
+        let m = m?;
+        let rounds: Vec<RoundSummary> = m.rounds.iter().map(RoundSummary::from_round).collect();
+        let total: f64 = rounds.iter().map(|r| r.duration_seconds).sum();
+        if index > 0 {
+            writeln!(out).map_err(PipelineError::Output)?;
+        }
+        let line = lang.match_line(
+            index + 1,
+            winner_name(m.winner()),
+            rounds.len(),
+            &format_duration(total),
+        );
+        writeln!(out, "{line}").map_err(PipelineError::Output)?;
+        // Links to each round's start, when the source video is known.
+        let link = VideoLink::for_match(&m);
+        let header = if link.is_some() {
+            format!("{round_header}  {}", lang.link_column())
+        } else {
+            round_header.clone()
+        };
+        writeln!(out, "{header}").map_err(PipelineError::Output)?;
+        for (round, summary) in m.rounds.iter().zip(&rounds) {
+            let mut row = round_row(round.round_index + 1, summary, &widths);
+            if let Some(link) = &link {
+                row = format!("{row}  {}", link.at(round.start_timestamp_seconds));
+            }
+            writeln!(out, "{row}").map_err(PipelineError::Output)?;
+        }
+        count += 1;
+    }
+    info!(matches = count, "summary written");
+    Ok(count)
+}
+
+fn round_row(number: u32, r: &RoundSummary, widths: &[usize; ROUND_COLUMNS]) -> String {
+    let percent = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.0}%", v * 100.0));
+    let rate = |v: Option<f64>| match v {
+        Some(v) if r.duration_seconds > 0.0 => {
+            format!("{:.1}%", v * 100.0 / r.duration_seconds)
+        }
+        _ => "-".to_string(),
+    };
+    let cells = [
+        number.to_string(),
+        format_duration(r.duration_seconds),
+        winner_name(r.winner).to_string(),
+        percent(r.damage_dealt[0]),
+        percent(r.damage_dealt[1]),
+        rate(r.damage_dealt[0]),
+        rate(r.damage_dealt[1]),
+        r.sa_uses[0].to_string(),
+        r.sa_uses[1].to_string(),
+        r.lead_changes.to_string(),
+    ];
+    table_row(&cells, widths)
+}
+
+/// Cells right-aligned to their column widths, each preceded by two spaces.
+fn table_row<S: AsRef<str>>(cells: &[S; ROUND_COLUMNS], widths: &[usize; ROUND_COLUMNS]) -> String {
+    cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, &width)| format!("  {}", i18n::pad_left(cell.as_ref(), width)))
+        .collect()
+}
+
+pub(crate) fn winner_name(winner: Winner) -> &'static str {
+    match winner {
+        Winner::P1 => "P1",
+        Winner::P2 => "P2",
+        Winner::Unknown => "?",
+    }
+}
+
+/// `m:ss`, rounded to whole seconds.
+pub(crate) fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    format!("{}:{:02}", total / 60, total % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{
+        source_metadata::Source, RoundEvent, SourceMetadata, VideoFileSource,
+    };
+
+    use super::*;
+    use crate::test_support::hp_frame;
+
+    #[test]
+    fn summary_lists_rounds_with_damage_and_sa_uses() {
+        let round = Round {
+            frames: vec![
+                hp_frame(10.0, 1.0, 1.0),
+                hp_frame(40.0, 0.8, 0.5),
+                hp_frame(50.0, 0.7, 0.0),
+            ],
+            winner: Winner::P1.into(),
+            start_timestamp_seconds: 10.0,
+            events: vec![RoundEvent {
+                r#type: EventType::SaUse.into(),
+                player: 1,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let summary = RoundSummary::from_round(&round);
+        assert_eq!(summary.duration_seconds, 40.0);
+        assert_eq!(summary.sa_uses, [1, 0]);
+        assert_eq!(summary.damage_dealt[0], Some(1.0));
+        assert!((summary.damage_dealt[1].unwrap() - 0.3).abs() < 1e-9);
+
+        let m = Match {
+            rounds: vec![round],
+            winner: Winner::P1.into(),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        assert_eq!(
+            write_summary(std::iter::once(Ok(m.clone())), Lang::En, &mut out).unwrap(),
+            1
+        );
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Match 1: winner P1, 1 round(s), 0:40");
+        assert_eq!(
+            lines[1],
+            "  round  duration  winner  p1_damage  p2_damage  p1_dmg/s  p2_dmg/s  p1_sa  p2_sa  leads"
+        );
+        assert_eq!(
+            lines[2],
+            "      1      0:40      P1       100%        30%      2.5%      0.8%      1      0      0"
+        );
+
+        let mut out = Vec::new();
+        write_summary(std::iter::once(Ok(m)), Lang::Ja, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "第1試合: 勝者 P1、1ラウンド、0:40");
+        assert_eq!(
+            lines[1],
+            "  ラウンド      時間    勝者  1Pダメージ  2Pダメージ    1P毎秒    2P毎秒  1P SA  2P SA  リード変化"
+        );
+        assert_eq!(
+            lines[2],
+            "         1      0:40      P1        100%         30%      2.5%      0.8%      1      0           0"
+        );
+    }
+
+    #[test]
+    fn rounds_link_to_source_video() {
+        let m = Match {
+            source: Some(SourceMetadata {
+                source: Some(Source::VideoFile(VideoFileSource {
+                    file_path: "https://youtu.be/abc".to_string(),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            rounds: vec![Round {
+                frames: vec![hp_frame(65.0, 1.0, 1.0), hp_frame(90.0, 1.0, 0.0)],
+                start_timestamp_seconds: 65.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_summary(std::iter::once(Ok(m)), Lang::En, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[1].ends_with("  link"));
+        assert!(lines[2].ends_with("  https://youtu.be/abc?t=65s"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Factories shared by the unit tests: frames of 60fps footage, player states and a
+//! stub HUD. Tests build their module-specific frames from these with struct update
+//! syntax, e.g. `FrameData { player1: Some(drive(Some(3.0), None)), ..frame_at(1.0) }`.
+
+use std::ops::Range;
+
+use image::RgbImage;
+use recmari_proto::proto::{FrameData, PlayerState};
+
+use crate::analysis::{DebugRegion, HpReading, Hud, HudType, OdReading, SaReading};
+use crate::video::frame::Frame;
+
+/// Frame `frame_number` of 60fps footage, with no readings.
+pub(crate) fn frame(frame_number: u32) -> FrameData {
+    FrameData {
+        frame_number,
+        timestamp_seconds: frame_number as f64 / 60.0,
+        ..Default::default()
+    }
+}
+
+/// Decoded frame `frame_number` of 60fps footage showing `image`.
+pub(crate) fn video_frame(frame_number: u32, image: RgbImage) -> Frame {
+    Frame {
+        image,
+        frame_number,
+        timestamp_seconds: frame_number as f64 / 60.0,
+    }
+}
+
+/// Frame at `ts` seconds of 60fps footage, with no readings.
+pub(crate) fn frame_at(ts: f64) -> FrameData {
+    FrameData {
+        frame_number: (ts * 60.0).round() as u32,
+        timestamp_seconds: ts,
+        ..Default::default()
+    }
+}
+
+/// Player state with only the health ratio.
+pub(crate) fn health(hp: Option<f64>) -> PlayerState {
+    PlayerState {
+        health_ratio: hp,
+        ..Default::default()
+    }
+}
+
+/// Player state with only the Drive and burnout gauges.
+pub(crate) fn drive(od: Option<f64>, burnout: Option<f64>) -> PlayerState {
+    PlayerState {
+        od_gauge: od,
+        burnout_gauge: burnout,
+        ..Default::default()
+    }
+}
+
+/// Frame at `ts` seconds where both players' health was read.
+pub(crate) fn hp_frame(ts: f64, p1: f64, p2: f64) -> FrameData {
+    FrameData {
+        player1: Some(health(Some(p1))),
+        player2: Some(health(Some(p2))),
+        ..frame_at(ts)
+    }
+}
+
+/// HP read from a frame by [`StubHud`].
+type HpFn = Box<dyn Fn(&Frame) -> Option<f64>>;
+/// Whether [`StubHud`] detects itself in a frame.
+type DetectFn = Box<dyn Fn(&Frame) -> bool>;
+
+/// HUD that detects the frames `detect` accepts (every frame unless built with
+/// [`StubHud::detecting`]) and reads both players' HP with `hp`; SA and OD are never
+/// read. A panicking HUD fails every HP reading with a panic.
+pub(crate) struct StubHud {
+    detect: DetectFn,
+    hp: HpFn,
+    panic: bool,
+}
+
+impl StubHud {
+    /// Reads `hp` on every frame.
+    pub(crate) fn reading(hp: Option<f64>) -> Self {
+        Self::with_hp(move |_| hp)
+    }
+
+    /// Reads the HP `hp` returns for each frame.
+    pub(crate) fn with_hp(hp: impl Fn(&Frame) -> Option<f64> + 'static) -> Self {
+        Self {
+            detect: Box::new(|_| true),
+            hp: Box::new(hp),
+            panic: false,
+        }
+    }
+
+    /// Detects only the frames `detect` accepts and reads `hp` on them.
+    pub(crate) fn detecting(detect: impl Fn(&Frame) -> bool + 'static, hp: Option<f64>) -> Self {
+        Self {
+            detect: Box::new(detect),
+            ..Self::reading(hp)
+        }
+    }
+
+    /// Panics on every HP reading.
+    #[cfg(feature = "pipeline")]
+    pub(crate) fn panicking() -> Self {
+        Self {
+            panic: true,
+            ..Self::reading(None)
+        }
+    }
+}
+
+impl Hud for StubHud {
+    fn hud_type(&self) -> HudType {
+        HudType::Manemon
+    }
+    fn detect_hud(&self, frame: &Frame) -> bool {
+        (self.detect)(frame)
+    }
+    fn analyze_hp(&self, frame: &Frame) -> HpReading {
+        assert!(!self.panic, "unreadable test frame");
+        let hp = (self.hp)(frame);
+        HpReading {
+            p1: hp,
+            p2: hp,
+            ..Default::default()
+        }
+    }
+    fn analyze_sa(&self, _frame: &Frame) -> SaReading {
+        SaReading::default()
+    }
+    fn analyze_od(&self, _frame: &Frame) -> OdReading {
+        OdReading::default()
+    }
+    fn debug_regions(&self) -> Vec<DebugRegion> {
+        Vec::new()
+    }
+    fn hud_rows(&self) -> Vec<Range<u32>> {
+        Vec::new()
+    }
+}
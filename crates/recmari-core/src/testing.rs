@@ -0,0 +1,356 @@
+//! Golden-fixture regression checks: read a directory of captured frames and compare
+//! HUD detection and gauge readings with an expectations manifest.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer};
+use tracing::{error, info, warn};
+
+use crate::analysis::huds::{all_huds, select_hud};
+use crate::analysis::{HudType, OdValue, SaValue};
+use crate::config::HudConfig;
+use crate::error::PipelineError;
+use crate::selftest::{od_text, sa_text};
+use crate::video::frame::Frame;
+
+/// File name of the manifest looked up in a fixture directory.
+pub const MANIFEST_NAME: &str = "expectations.toml";
+/// Default largest accepted difference between an expected and a read value.
+const DEFAULT_TOLERANCE: f64 = 0.05;
+
+/// Expected readings of the frames of a fixture directory.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureManifest {
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+    #[serde(rename = "frame")]
+    pub frames: Vec<FixtureFrame>,
+}
+
+fn default_tolerance() -> f64 {
+    DEFAULT_TOLERANCE
+}
+
+/// One fixture frame. Only the listed values are checked.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureFrame {
+    /// Image path relative to the fixture directory.
+    pub image: PathBuf,
+    /// HUD detection must pick; gauges are read with it (default manemon).
+    #[serde(default, deserialize_with = "deserialize_hud")]
+    pub hud: Option<HudType>,
+    #[serde(default)]
+    pub p1: PlayerExpectation,
+    #[serde(default)]
+    pub p2: PlayerExpectation,
+}
+
+/// Expected gauges of one player.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerExpectation {
+    pub hp: Option<f64>,
+    /// SA stocks, 0.0..=3.0.
+    pub sa: Option<f64>,
+    /// Critical Art ready (the SA gauge shows "CA").
+    #[serde(default)]
+    pub ca: bool,
+    /// Drive gauge, 0.0..=6.0.
+    pub od: Option<f64>,
+    /// Burnout recovery progress, 0.0..=1.0.
+    pub burnout: Option<f64>,
+    /// Gauges that must not be readable, e.g. a bar covered by a character.
+    #[serde(default)]
+    pub unreadable: Vec<Gauge>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Gauge {
+    Hp,
+    Sa,
+    Od,
+}
+
+fn deserialize_hud<'de, D: Deserializer<'de>>(d: D) -> Result<Option<HudType>, D::Error> {
+    Option::<String>::deserialize(d)?
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// One compared value of a fixture frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureCheck {
+    pub image: PathBuf,
+    /// `HUD`, or the player and gauge such as `P2 SA`.
+    pub what: String,
+    pub expected: String,
+    pub actual: String,
+    pub passed: bool,
+}
+
+/// Result of a fixture run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureReport {
+    pub frames: usize,
+    pub checks: Vec<FixtureCheck>,
+}
+
+impl FixtureReport {
+    pub fn failures(&self) -> impl Iterator<Item = &FixtureCheck> {
+        self.checks.iter().filter(|c| !c.passed)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+pub fn parse_fixture_manifest(text: &str) -> Result<FixtureManifest, PipelineError> {
+    let manifest: FixtureManifest = match toml::from_str(text) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!(%e, "failed to parse fixture manifest");
+            return Err(PipelineError::Parse(format!(
+                "failed to parse fixture manifest: {e}"
+            )));
+        }
+    };
+    if manifest.frames.is_empty() {
+        error!("fixture manifest has no frames");
+        return Err(PipelineError::Parse(
+            "fixture manifest has no [[frame]] entries".into(),
+        ));
+    }
+    if !manifest.tolerance.is_finite() || manifest.tolerance < 0.0 {
+        error!(tolerance = manifest.tolerance, "invalid tolerance");
+        return Err(PipelineError::Parse(format!(
+            "tolerance must not be negative, got {}",
+            manifest.tolerance
+        )));
+    }
+    for frame in &manifest.frames {
+        for (player, p) in [("p1", &frame.p1), ("p2", &frame.p2)] {
+            let conflicts = [
+                (p.sa.is_some() && p.ca, "sa and ca"),
+                (p.od.is_some() && p.burnout.is_some(), "od and burnout"),
+                (
+                    p.hp.is_some() && p.unreadable.contains(&Gauge::Hp),
+                    "hp and unreadable hp",
+                ),
+                (
+                    (p.sa.is_some() || p.ca) && p.unreadable.contains(&Gauge::Sa),
+                    "sa and unreadable sa",
+                ),
+                (
+                    (p.od.is_some() || p.burnout.is_some()) && p.unreadable.contains(&Gauge::Od),
+                    "od and unreadable od",
+                ),
+            ];
+            if let Some((_, what)) = conflicts.iter().find(|(conflict, _)| *conflict) {
+                error!(image = ?frame.image, player, what, "conflicting expectations");
+                return Err(PipelineError::Parse(format!(
+                    "{} {player}: expects both {what}",
+                    frame.image.display()
+                )));
+            }
+        }
+    }
+    Ok(manifest)
+}
+
+/// Read `dir`/[`MANIFEST_NAME`].
+pub fn load_fixture_manifest(dir: &Path) -> Result<FixtureManifest, PipelineError> {
+    let path = dir.join(MANIFEST_NAME);
+    let text = std::fs::read_to_string(&path).map_err(PipelineError::io("read", &path))?;
+    parse_fixture_manifest(&text)
+        .map_err(|e| PipelineError::Parse(format!("invalid manifest {}: {e}", path.display())))
+}
+
+/// Run HUD detection and every gauge classifier, configured by `config`, on each frame
+/// of `manifest` and compare the readings with the expected values. Detection covers
+/// every HUD, including those left out of automatic detection, so that they can be
+/// calibrated against fixtures.
+pub fn run_fixtures(
+    dir: &Path,
+    manifest: &FixtureManifest,
+    config: &HudConfig,
+) -> Result<FixtureReport, PipelineError> {
+    let tolerance = manifest.tolerance;
+    let mut checks = Vec::new();
+    for fixture in &manifest.frames {
+        let path = dir.join(&fixture.image);
+        let image = image::open(&path)
+            .map_err(|source| PipelineError::Image {
+                action: "open fixture",
+                path: path.clone(),
+                source,
+            })?
+            .into_rgb8();
+        let huds = all_huds(image.width(), image.height(), config);
+        let frame = Frame {
+            image,
+            frame_number: 0,
+            timestamp_seconds: 0.0,
+        };
+        let check =
+            |what: String, expected: String, actual: Option<String>, passed: bool| FixtureCheck {
+                image: fixture.image.clone(),
+                what,
+                expected,
+                actual: actual.unwrap_or_else(|| "unreadable".into()),
+                passed,
+            };
+
+        if let Some(expected) = fixture.hud {
+            let detected = select_hud(&huds, &frame).map(|i| huds[i].hud_type());
+            checks.push(check(
+                "HUD".into(),
+                expected.to_string(),
+                Some(detected.map_or("none".into(), |hud| hud.to_string())),
+                detected == Some(expected),
+            ));
+        }
+
+        let hud_type = fixture.hud.unwrap_or(HudType::Manemon);
+        let hud = huds
+            .iter()
+            .find(|hud| hud.hud_type() == hud_type)
+            .expect("every HUD type is registered");
+        let hp = hud.analyze_hp(&frame);
+        let sa = hud.analyze_sa(&frame);
+        let od = hud.analyze_od(&frame);
+        let actual = [(hp.p1, sa.p1, od.p1), (hp.p2, sa.p2, od.p2)];
+        for (player, (expected, (hp, sa, od))) in [&fixture.p1, &fixture.p2]
+            .into_iter()
+            .zip(actual)
+            .enumerate()
+        {
+            let what = |gauge: &str| format!("P{} {gauge}", player + 1);
+            let close = |a: f64, e: f64| (a - e).abs() <= tolerance;
+
+            if let Some(e) = expected.hp {
+                checks.push(check(
+                    what("HP"),
+                    format!("{e:.3}"),
+                    hp.map(|v| format!("{v:.3}")),
+                    hp.is_some_and(|a| close(a, e)),
+                ));
+            }
+            let sa_expected = match (expected.sa, expected.ca) {
+                (_, true) => Some(SaValue::CaReady),
+                (Some(v), false) => Some(SaValue::Stocks(v)),
+                (None, false) => None,
+            };
+            if let Some(e) = sa_expected {
+                let passed = match (sa, e) {
+                    (Some(SaValue::Stocks(a)), SaValue::Stocks(e)) => close(a, e),
+                    (Some(SaValue::CaReady), SaValue::CaReady) => true,
+                    _ => false,
+                };
+                checks.push(check(what("SA"), sa_text(e), sa.map(sa_text), passed));
+            }
+            let od_expected = match (expected.od, expected.burnout) {
+                (Some(v), _) => Some(OdValue::Normal(v)),
+                (None, Some(v)) => Some(OdValue::Burnout(v)),
+                (None, None) => None,
+            };
+            if let Some(e) = od_expected {
+                let passed = match (od, e) {
+                    (Some(OdValue::Normal(a)), OdValue::Normal(e))
+                    | (Some(OdValue::Burnout(a)), OdValue::Burnout(e)) => close(a, e),
+                    _ => false,
+                };
+                checks.push(check(what("OD"), od_text(e), od.map(od_text), passed));
+            }
+            for gauge in &expected.unreadable {
+                let (name, actual) = match gauge {
+                    Gauge::Hp => ("HP", hp.map(|v| format!("{v:.3}"))),
+                    Gauge::Sa => ("SA", sa.map(sa_text)),
+                    Gauge::Od => ("OD", od.map(od_text)),
+                };
+                let passed = actual.is_none();
+                checks.push(check(what(name), "unreadable".into(), actual, passed));
+            }
+        }
+    }
+
+    let report = FixtureReport {
+        frames: manifest.frames.len(),
+        checks,
+    };
+    let failed = report.failures().count();
+    for c in report.failures() {
+        warn!(image = ?c.image, what = %c.what, expected = %c.expected, actual = %c.actual, "fixture check failed");
+    }
+    info!(
+        frames = report.frames,
+        checks = report.checks.len(),
+        failed,
+        "fixtures verified"
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selftest::{render_reference, REFERENCES};
+
+    #[test]
+    fn the_shipped_manifest_parses() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/frames");
+        let manifest = load_fixture_manifest(&dir).unwrap();
+        for frame in &manifest.frames {
+            assert!(
+                dir.join(&frame.image).exists(),
+                "{:?} is missing",
+                frame.image
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_conflicting_expectations() {
+        for bad in [
+            "[[frame]]\nimage = \"a.png\"\np1 = { sa = 1.0, ca = true }\n",
+            "[[frame]]\nimage = \"a.png\"\np2 = { od = 1.0, burnout = 0.5 }\n",
+            "[[frame]]\nimage = \"a.png\"\np1 = { hp = 0.5, unreadable = [\"hp\"] }\n",
+            "[[frame]]\nimage = \"a.png\"\nhud = \"hud\"\n",
+            "tolerance = 0.1\n",
+        ] {
+            assert!(parse_fixture_manifest(bad).is_err(), "{bad:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn reports_matching_and_regressed_readings() {
+        let dir = std::env::temp_dir().join(format!("recmari-fixtures-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // The "low health" reference: P1 0.12 HP, CA, burnout 0.4; P2 at 0 HP.
+        render_reference(&REFERENCES[2])
+            .save(dir.join("low.png"))
+            .unwrap();
+        let manifest = parse_fixture_manifest(
+            r#"
+            [[frame]]
+            image = "low.png"
+            hud = "manemon"
+            p1 = { hp = 0.12, ca = true, burnout = 0.4 }
+            p2 = { hp = 0.5, sa = 3.0 }
+            "#,
+        )
+        .unwrap();
+
+        let report = run_fixtures(&dir, &manifest, &HudConfig::default()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.frames, 1);
+        assert_eq!(report.checks.len(), 6);
+        let failed: Vec<&str> = report.failures().map(|c| c.what.as_str()).collect();
+        assert_eq!(failed, ["P2 HP"]);
+        assert!(!report.passed());
+    }
+}
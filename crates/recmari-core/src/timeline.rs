@@ -0,0 +1,146 @@
+use serde_json::{json, Value};
+use tracing::info;
+
+use recmari_proto::proto::{source_metadata::Source, Match, Round, Winner};
+
+use crate::stream::player_json;
+
+/// Source video of a match, if it came from a file.
+pub fn source_video(m: &Match) -> Option<&str> {
+    match m.source.as_ref()?.source.as_ref()? {
+        Source::VideoFile(v) if !v.file_path.is_empty() => Some(&v.file_path),
+        _ => None,
+    }
+}
+
+/// Matches as JSON for the `serve-ui` viewer. Every time is in seconds into the source
+/// video (playback speed undone) so it can be compared with the video's current time.
+pub fn timeline_json(matches: &[Match]) -> Value {
+    let value = Value::Array(
+        matches
+            .iter()
+            .enumerate()
+            .map(|(i, m)| match_json(i + 1, m))
+            .collect(),
+    );
+    info!(matches = matches.len(), "timeline built");
+    value
+}
+
+fn match_json(number: usize, m: &Match) -> Value {
+    let speed = match m.source.as_ref().and_then(|s| s.source.as_ref()) {
+        Some(Source::VideoFile(v)) if v.playback_speed > 0.0 => v.playback_speed,
+        _ => 1.0,
+    };
+    let frames: Vec<Value> = m
+        .rounds
+        .iter()
+        .flat_map(|r| &r.frames)
+        .map(|fd| {
+            json!({
+                "t": fd.timestamp_seconds / speed,
+                "p1": player_json(fd.player1.as_ref()),
+                "p2": player_json(fd.player2.as_ref()),
+            })
+        })
+        .collect();
+    json!({
+        "number": number,
+        "winner": winner_name(m.winner()),
+        "rounds": m.rounds.iter().map(|r| round_json(r, speed)).collect::<Vec<_>>(),
+        "frames": frames,
+    })
+}
+
+fn round_json(round: &Round, speed: f64) -> Value {
+    let end = round
+        .frames
+        .last()
+        .map_or(round.start_timestamp_seconds, |fd| fd.timestamp_seconds);
+    let events: Vec<Value> = round
+        .events
+        .iter()
+        .map(|e| {
+            json!({
+                "type": e.r#type().as_str_name().trim_start_matches("EVENT_TYPE_"),
+                "t": e.timestamp_seconds / speed,
+                "player": (e.player > 0).then_some(e.player),
+                "sa_level": (e.sa_level > 0).then_some(e.sa_level),
+            })
+        })
+        .collect();
+    json!({
+        "number": round.round_index + 1,
+        "winner": winner_name(round.winner()),
+        "start": round.start_timestamp_seconds / speed,
+        "end": end / speed,
+        "events": events,
+    })
+}
+
+fn winner_name(winner: Winner) -> &'static str {
+    match winner {
+        Winner::P1 => "P1",
+        Winner::P2 => "P2",
+        Winner::Unknown => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{
+        EventType, FrameData, PlayerState, RoundEvent, SourceMetadata, VideoFileSource,
+    };
+
+    use super::*;
+
+    #[test]
+    fn times_are_in_video_seconds() {
+        let m = Match {
+            source: Some(SourceMetadata {
+                source: Some(Source::VideoFile(VideoFileSource {
+                    file_path: "replay.mp4".to_string(),
+                    playback_speed: 2.0,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            rounds: vec![Round {
+                frames: vec![FrameData {
+                    timestamp_seconds: 20.0,
+                    player1: Some(PlayerState {
+                        health_ratio: Some(0.5),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                start_timestamp_seconds: 10.0,
+                winner: Winner::P1.into(),
+                events: vec![RoundEvent {
+                    r#type: EventType::SuperFlash.into(),
+                    timestamp_seconds: 16.0,
+                    player: 2,
+                    sa_level: 3,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(source_video(&m), Some("replay.mp4"));
+
+        let timeline = timeline_json(&[m]);
+        let m = &timeline[0];
+        assert_eq!(m["frames"][0]["t"], 10.0);
+        assert_eq!(m["frames"][0]["p1"]["hp"], 0.5);
+        let round = &m["rounds"][0];
+        assert_eq!(
+            (&round["start"], &round["end"]),
+            (&json!(5.0), &json!(10.0))
+        );
+        assert_eq!(
+            round["events"][0],
+            json!({"type": "SUPER_FLASH", "t": 8.0, "player": 2, "sa_level": 3})
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use image::RgbImage;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::analysis::common::HsvRange;
+use crate::analysis::huds::manemon::ManemonHud;
+use crate::analysis::{Hud, HudType};
+use crate::config::HudProfile;
+use crate::error::{HudError, PipelineError};
+use crate::video::frame::Frame;
+
+/// Largest difference between a read and a labeled HP ratio that counts as correct.
+const HP_TOLERANCE: f64 = 0.02;
+/// Hue bounds are moved by up to this many degrees in `HUE_STEP` steps.
+const HUE_SWEEP: f32 = 10.0;
+const HUE_STEP: f32 = 2.0;
+/// Saturation and value minimums are moved by up to this much in `SV_STEP` steps.
+const SV_SWEEP: f32 = 0.2;
+const SV_STEP: f32 = 0.05;
+/// Sweeps over all bounds before giving up on further improvement.
+const MAX_PASSES: usize = 3;
+
+/// Labeled reference frames for `recmari tune`:
+///
+/// ```toml
+/// [[frame]]
+/// image = "frames/mid_round.png"  # relative to the manifest
+/// p1_hp = 0.62
+/// p2_hp = 0.35                    # omit a player whose value is unknown
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TuneManifest {
+    #[serde(rename = "frame")]
+    pub frames: Vec<LabeledFrame>,
+}
+
+/// A reference frame and the HP ratio each player shows.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LabeledFrame {
+    pub image: PathBuf,
+    pub p1_hp: Option<f64>,
+    pub p2_hp: Option<f64>,
+}
+
+pub fn parse_tune_manifest(text: &str) -> Result<TuneManifest, PipelineError> {
+    let manifest: TuneManifest = match toml::from_str(text) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!(%e, "failed to parse tune manifest");
+            return Err(PipelineError::Parse(format!(
+                "failed to parse tune manifest: {e}"
+            )));
+        }
+    };
+    if manifest.frames.is_empty() {
+        error!("tune manifest has no frames");
+        return Err(PipelineError::Parse(
+            "tune manifest has no [[frame]] entries".into(),
+        ));
+    }
+    for frame in &manifest.frames {
+        for hp in [frame.p1_hp, frame.p2_hp].into_iter().flatten() {
+            if !(0.0..=1.0).contains(&hp) {
+                error!(image = ?frame.image, hp, "labeled HP outside 0-1");
+                return Err(PipelineError::Parse(format!(
+                    "{}: HP {hp} is outside 0.0-1.0",
+                    frame.image.display()
+                )));
+            }
+        }
+    }
+    Ok(manifest)
+}
+
+/// Pixel classifier whose color range is swept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TuneTarget {
+    /// HP bar fill at normal health (`hp_fill`).
+    HpFill,
+    /// HP bar fill at low health (`hp_low_fill`).
+    HpLowFill,
+}
+
+impl TuneTarget {
+    /// Key of the range in a HUD profile's `colors` table.
+    pub fn config_key(self) -> &'static str {
+        match self {
+            TuneTarget::HpFill => "hp_fill",
+            TuneTarget::HpLowFill => "hp_low_fill",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            TuneTarget::HpFill => 0,
+            TuneTarget::HpLowFill => 1,
+        }
+    }
+}
+
+impl FromStr for TuneTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hp-fill" => Ok(TuneTarget::HpFill),
+            "hp-low-fill" => Ok(TuneTarget::HpLowFill),
+            _ => Err(format!(
+                "invalid classifier '{s}' (expected hp-fill or hp-low-fill)"
+            )),
+        }
+    }
+}
+
+/// Outcome of a sweep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuneResult {
+    pub range: HsvRange,
+    /// Labeled values read correctly with `range`.
+    pub correct: usize,
+    /// Labeled values read correctly with the range the sweep started from.
+    pub baseline_correct: usize,
+    pub labels: usize,
+}
+
+/// Sweep the color range of `target` around its current value in `profile` (hue
+/// bounds, saturation and value minimums, one at a time) and return the candidate reading the most
+/// labeled HP values within [`HP_TOLERANCE`]. Ties go to the candidate closest to the
+/// current range. Frames must be 1920x1080.
+pub fn sweep_hp_colors(
+    frames: &[(RgbImage, [Option<f64>; 2])],
+    target: TuneTarget,
+    profile: &HudProfile,
+) -> Result<TuneResult, HudError> {
+    assert!(!frames.is_empty(), "at least one labeled frame is required");
+    for (index, (image, _)) in frames.iter().enumerate() {
+        let (w, h) = image.dimensions();
+        if (w, h) != (1920, 1080) {
+            error!(index, w, h, "labeled frame is not 1920x1080");
+            return Err(HudError::Calibration(format!(
+                "labeled frame {} is {w}x{h}, expected 1920x1080",
+                index + 1
+            )));
+        }
+    }
+    let frames: Vec<(Frame, [Option<f64>; 2])> = frames
+        .iter()
+        .map(|(image, labels)| {
+            let frame = Frame {
+                image: image.clone(),
+                frame_number: 0,
+                timestamp_seconds: 0.0,
+            };
+            (frame, *labels)
+        })
+        .collect();
+    let labels = frames.iter().flat_map(|(_, l)| l.iter().flatten()).count();
+
+    let mut hud = ManemonHud::with_profile(1920, 1080, profile);
+    let base_ranges = hud.hp_fill_ranges();
+    let base = base_ranges[target.index()];
+    let mut score = |range: HsvRange| {
+        let mut ranges = base_ranges;
+        ranges[target.index()] = range;
+        hud.set_hp_fill_ranges(ranges);
+        frames
+            .iter()
+            .map(|(frame, expected)| {
+                let hp = hud.analyze_hp(frame);
+                [hp.p1, hp.p2]
+                    .into_iter()
+                    .zip(expected)
+                    .filter(|(read, expected)| match (read, expected) {
+                        (Some(read), Some(expected)) => (read - expected).abs() <= HP_TOLERANCE,
+                        _ => false,
+                    })
+                    .count()
+            })
+            .sum::<usize>()
+    };
+
+    // One bound at a time, keeping the others at the best values so far, until a
+    // pass over every bound finds nothing better.
+    let baseline_correct = score(base);
+    let mut best = (baseline_correct, 0.0f32, base);
+    let mut candidates = 0usize;
+    for _ in 0..MAX_PASSES {
+        let before = best;
+        for bound in Bound::ALL {
+            for value in bound.steps(base) {
+                let range = bound.with(best.2, value);
+                if !range.is_valid() {
+                    continue;
+                }
+                let correct = score(range);
+                candidates += 1;
+                let distance = distance(&range, &base);
+                if correct > best.0 || (correct == best.0 && distance < best.1) {
+                    best = (correct, distance, range);
+                }
+            }
+        }
+        if best.2 == before.2 {
+            break;
+        }
+    }
+
+    let (correct, _, range) = best;
+    info!(
+        ?target,
+        candidates,
+        labels,
+        baseline_correct,
+        correct,
+        ?range,
+        "color range swept"
+    );
+    Ok(TuneResult {
+        range,
+        correct,
+        baseline_correct,
+        labels,
+    })
+}
+
+/// A swept bound of an [`HsvRange`]; the saturation and value maximums stay as they are.
+#[derive(Debug, Clone, Copy)]
+enum Bound {
+    HueMin,
+    HueMax,
+    SaturationMin,
+    ValueMin,
+}
+
+impl Bound {
+    const ALL: [Bound; 4] = [
+        Bound::HueMin,
+        Bound::HueMax,
+        Bound::SaturationMin,
+        Bound::ValueMin,
+    ];
+
+    /// Candidate values around the bound of `base`.
+    fn steps(self, base: HsvRange) -> Vec<f32> {
+        match self {
+            Bound::HueMin => steps(base.h[0], HUE_SWEEP, HUE_STEP, [0.0, 360.0]),
+            Bound::HueMax => steps(base.h[1], HUE_SWEEP, HUE_STEP, [0.0, 360.0]),
+            Bound::SaturationMin => steps(base.s[0], SV_SWEEP, SV_STEP, [0.0, 1.0]),
+            Bound::ValueMin => steps(base.v[0], SV_SWEEP, SV_STEP, [0.0, 1.0]),
+        }
+    }
+
+    fn with(self, mut range: HsvRange, value: f32) -> HsvRange {
+        match self {
+            Bound::HueMin => range.h[0] = value,
+            Bound::HueMax => range.h[1] = value,
+            Bound::SaturationMin => range.s[0] = value,
+            Bound::ValueMin => range.v[0] = value,
+        }
+        range
+    }
+}
+
+/// How far `range` moved from `base`, in sweep steps.
+fn distance(range: &HsvRange, base: &HsvRange) -> f32 {
+    (range.h[0] - base.h[0]).abs() / HUE_STEP
+        + (range.h[1] - base.h[1]).abs() / HUE_STEP
+        + (range.s[0] - base.s[0]).abs() / SV_STEP
+        + (range.v[0] - base.v[0]).abs() / SV_STEP
+}
+
+/// `center` and the values `step` apart from it up to `sweep` away, within `bounds`.
+fn steps(center: f32, sweep: f32, step: f32, [lo, hi]: [f32; 2]) -> Vec<f32> {
+    let n = (sweep / step).round() as i32;
+    let mut values: Vec<f32> = (-n..=n)
+        .map(|i| (center + i as f32 * step).clamp(lo, hi))
+        .collect();
+    values.dedup();
+    values
+}
+
+/// A `--hud-config` snippet setting the swept range of `hud_type`.
+pub fn tuned_profile_toml(hud_type: HudType, target: TuneTarget, result: &TuneResult) -> String {
+    let range = &result.range;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# Tuned on {} labeled HP value(s): {} correct (built-in or given range: {}).",
+        result.labels, result.correct, result.baseline_correct
+    );
+    let _ = writeln!(out, "[{hud_type}.colors]");
+    let _ = writeln!(
+        out,
+        "{} = {{ h = [{:.1}, {:.1}], s = [{:.3}, {:.3}], v = [{:.3}, {:.3}] }}",
+        target.config_key(),
+        range.h[0],
+        range.h[1],
+        range.s[0],
+        range.s[1],
+        range.v[0],
+        range.v[1]
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+    use crate::analysis::common::rgb_to_hsv;
+    use crate::analysis::huds::manemon::{paint_reference_hud, ReferenceGauges};
+    use crate::analysis::{OdValue, SaValue};
+    use crate::config::parse_hud_config;
+
+    /// A battle frame with the given HP whose yellow fill is shifted towards orange the
+    /// way some capture cards do (H≈45°, inside the built-in low-health range).
+    fn shifted_frame(hp: [f64; 2]) -> RgbImage {
+        let mut image = RgbImage::from_pixel(1920, 1080, Rgb([90, 110, 140]));
+        let gauges = hp.map(|hp| ReferenceGauges {
+            hp,
+            sa: SaValue::Stocks(0.0),
+            od: OdValue::Normal(6.0),
+        });
+        paint_reference_hud(&mut image, &gauges);
+        for pixel in image.pixels_mut() {
+            if *pixel == Rgb([255, 230, 40]) {
+                *pixel = Rgb([247, 190, 25]);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn finds_a_range_for_shifted_bars() {
+        let frames = vec![
+            (shifted_frame([0.8, 0.6]), [Some(0.8), Some(0.6)]),
+            (shifted_frame([0.5, 1.0]), [Some(0.5), None]),
+        ];
+        let result = sweep_hp_colors(&frames, TuneTarget::HpFill, &HudProfile::default()).unwrap();
+        assert_eq!(result.labels, 3);
+        assert!(result.baseline_correct < 3);
+        assert_eq!(result.correct, 3);
+
+        let toml = tuned_profile_toml(HudType::Manemon, TuneTarget::HpFill, &result);
+        let parsed = parse_hud_config(&toml)
+            .unwrap()
+            .manemon
+            .colors
+            .hp_fill
+            .unwrap();
+        assert!((parsed.v[0] - result.range.v[0]).abs() < 1e-3);
+        assert!(parsed.contains(rgb_to_hsv(Rgb([247, 190, 25]))));
+    }
+
+    #[test]
+    fn manifest_paths_and_labels() {
+        let manifest = parse_tune_manifest(
+            "[[frame]]\nimage = \"a.png\"\np1_hp = 0.5\n\n[[frame]]\nimage = \"b.png\"\np2_hp = 1.0\n",
+        )
+        .unwrap();
+        assert_eq!(manifest.frames.len(), 2);
+        assert_eq!(manifest.frames[0].p2_hp, None);
+        assert!(parse_tune_manifest("[[frame]]\nimage = \"a.png\"\np1_hp = 1.5\n").is_err());
+        assert!(parse_tune_manifest("").is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use tracing::{error, info};
+
+use crate::audio_cues::{hop_rms, Envelope, AUDIO_SAMPLE_RATE, HOP_SAMPLES};
+use crate::error::DecoderError;
+
+/// Decode the first audio stream of `path` through an ffmpeg PCM pipe (mono 16-bit at
+/// [`AUDIO_SAMPLE_RATE`]) into its loudness envelope. Samples are reduced hop by hop,
+/// so a long VOD never sits in memory as PCM.
+pub fn decode_envelope(path: &Path) -> Result<Envelope, DecoderError> {
+    info!(?path, "decoding audio with ffmpeg");
+
+    let mut child = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-v", "error", "-i"])
+        .arg(path)
+        .args([
+            "-vn",
+            "-map",
+            "0:a:0",
+            "-ac",
+            "1",
+            "-ar",
+            &AUDIO_SAMPLE_RATE.to_string(),
+            "-f",
+            "s16le",
+            "pipe:1",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| DecoderError::ToolMissing {
+            program: "ffmpeg",
+            source,
+        })?;
+
+    let mut stdout = child.stdout.take().expect("ffmpeg stdout is piped");
+    let mut envelope = Envelope::default();
+    let mut bytes = vec![0u8; HOP_SAMPLES * 2];
+    let mut hop = vec![0i16; HOP_SAMPLES];
+    loop {
+        match stdout.read_exact(&mut bytes) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        for (sample, pair) in hop.iter_mut().zip(bytes.chunks_exact(2)) {
+            *sample = i16::from_le_bytes([pair[0], pair[1]]);
+        }
+        envelope.values.push(hop_rms(&hop));
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!(%stderr, ?path, "ffmpeg audio decoding failed");
+        return Err(DecoderError::ToolFailed {
+            program: "ffmpeg",
+            stderr: stderr.into_owned(),
+        });
+    }
+    if envelope.values.is_empty() {
+        error!(?path, "no audio decoded");
+        return Err(DecoderError::InvalidVideo(format!(
+            "no audio in {}",
+            path.display()
+        )));
+    }
+    info!(
+        ?path,
+        seconds = Envelope::seconds(envelope.values.len()),
+        "audio decoding complete"
+    );
+    Ok(envelope)
+}
@@ -0,0 +1,271 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tracing::{error, info};
+
+#[cfg(feature = "debug-render")]
+use recmari_proto::proto::{FrameData, Match};
+
+#[cfg(feature = "debug-render")]
+use crate::debug::DebugRenderer;
+use crate::error::DecoderError;
+use crate::highlights::Highlight;
+#[cfg(feature = "debug-render")]
+use crate::video::decoder::{self, VideoDecoder};
+#[cfg(feature = "debug-render")]
+use crate::video::encoder::VideoEncoder;
+
+/// Seconds an event stays captioned on annotated clips.
+#[cfg(feature = "debug-render")]
+const EVENT_CAPTION_SECONDS: f64 = 2.0;
+
+/// Seconds of video kept around each highlight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipWindow {
+    pub before: f64,
+    pub after: f64,
+}
+
+impl Default for ClipWindow {
+    fn default() -> Self {
+        Self {
+            before: 5.0,
+            after: 3.0,
+        }
+    }
+}
+
+/// File name of a highlight clip, e.g. `match01_round2_ko_p1.mp4`.
+pub fn clip_file_name(match_number: usize, highlight: &Highlight) -> String {
+    format!(
+        "match{match_number:02}_round{}_{}_p{}.mp4",
+        highlight.round_index + 1,
+        highlight.kind,
+        highlight.player
+    )
+}
+
+/// Cut the window around `highlight` out of `video` into `out_dir`, re-encoding so the
+/// clip starts exactly at the requested time instead of the previous keyframe.
+pub fn write_clip(
+    video: &Path,
+    out_dir: &Path,
+    match_number: usize,
+    highlight: &Highlight,
+    window: ClipWindow,
+) -> Result<PathBuf, DecoderError> {
+    let (start, duration) = clip_span(highlight, window);
+    let path = out_dir.join(clip_file_name(match_number, highlight));
+
+    info!(?path, start, duration, "cutting clip");
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-v", "error"])
+        .args(["-ss", &format!("{start:.3}")])
+        .arg("-i")
+        .arg(video)
+        .args(["-t", &format!("{duration:.3}")])
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:a", "aac"])
+        .arg(&path)
+        .output()
+        .map_err(|source| DecoderError::ToolMissing {
+            program: "ffmpeg",
+            source,
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!(?path, %stderr, "ffmpeg clip extraction failed");
+        return Err(DecoderError::ToolFailed {
+            program: "ffmpeg",
+            stderr: stderr.into_owned(),
+        });
+    }
+    Ok(path)
+}
+
+/// Start and length in seconds of the window around `highlight`.
+fn clip_span(highlight: &Highlight, window: ClipWindow) -> (f64, f64) {
+    let start = (highlight.video_seconds - window.before).max(0.0);
+    (start, highlight.video_seconds + window.after - start)
+}
+
+/// Like [`write_clip`], but with the analyzed HP/SA/OD values of `m` and its recent
+/// events burned into every frame. The frames are rendered with `renderer` and encoded
+/// first, then muxed with the source audio.
+#[cfg(feature = "debug-render")]
+pub fn write_annotated_clip(
+    video: &Path,
+    out_dir: &Path,
+    match_number: usize,
+    highlight: &Highlight,
+    window: ClipWindow,
+    m: &Match,
+    renderer: &DebugRenderer,
+) -> Result<PathBuf, DecoderError> {
+    let (start, duration) = clip_span(highlight, window);
+    let name = clip_file_name(match_number, highlight);
+    let path = out_dir.join(&name);
+    let video_only = out_dir.join(format!(".{name}.video.mp4"));
+
+    let fps = decoder::probe(video)?.fps;
+    let start_frame = (start * fps).round() as u32;
+    let frames = (duration * fps).round() as u32;
+    let caption_frames = (EVENT_CAPTION_SECONDS * fps).round() as u32;
+    info!(?path, start, duration, fps, "rendering annotated clip");
+
+    let mut decoder = VideoDecoder::open_at_frame(video, start_frame)?;
+    let mut encoder = VideoEncoder::create(&video_only, decoder.width(), decoder.height(), fps)?;
+    for _ in 0..frames {
+        let Some(frame) = decoder.next_frame()? else {
+            break;
+        };
+        let data = frame_data_at(m, frame.frame_number);
+        let captions = event_captions(m, frame.frame_number, caption_frames);
+        encoder.write_frame(&renderer.annotate(&frame, data, &captions))?;
+    }
+    encoder.finish()?;
+
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-v", "error"])
+        .arg("-i")
+        .arg(&video_only)
+        .args(["-ss", &format!("{start:.3}")])
+        .arg("-i")
+        .arg(video)
+        .args(["-t", &format!("{duration:.3}")])
+        // `?` keeps sources without an audio track working.
+        .args(["-map", "0:v", "-map", "1:a?", "-c:v", "copy", "-c:a", "aac"])
+        .arg(&path)
+        .output()
+        .map_err(|source| DecoderError::ToolMissing {
+            program: "ffmpeg",
+            source,
+        });
+    let _ = std::fs::remove_file(&video_only);
+    let output = output?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!(?path, %stderr, "ffmpeg audio mux failed");
+        return Err(DecoderError::ToolFailed {
+            program: "ffmpeg",
+            stderr: stderr.into_owned(),
+        });
+    }
+    Ok(path)
+}
+
+/// The analyzed frame shown at video frame `frame_number`: the latest sampled frame at
+/// or before it, or None outside every round.
+#[cfg(feature = "debug-render")]
+pub fn frame_data_at(m: &Match, frame_number: u32) -> Option<&FrameData> {
+    m.rounds.iter().find_map(|round| {
+        let first = round.frames.first()?;
+        let last = round.frames.last()?;
+        if frame_number < first.frame_number || frame_number > last.frame_number {
+            return None;
+        }
+        let i = round
+            .frames
+            .partition_point(|f| f.frame_number <= frame_number);
+        round.frames.get(i - 1)
+    })
+}
+
+/// Captions of the events of `m` detected in the `window_frames` frames up to
+/// `frame_number`, oldest first, e.g. `P1 DRIVE IMPACT`.
+#[cfg(feature = "debug-render")]
+pub fn event_captions(m: &Match, frame_number: u32, window_frames: u32) -> Vec<String> {
+    m.rounds
+        .iter()
+        .flat_map(|round| &round.events)
+        .filter(|e| e.frame_number <= frame_number && frame_number - e.frame_number < window_frames)
+        .map(|e| {
+            let name = e
+                .r#type()
+                .as_str_name()
+                .trim_start_matches("EVENT_TYPE_")
+                .replace('_', " ");
+            let caption = match e.player {
+                0 => name,
+                player => format!("P{player} {name}"),
+            };
+            match e.sa_level {
+                0 => caption,
+                level => format!("{caption} Lv{level}"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highlights::HighlightKind;
+
+    use super::*;
+
+    #[test]
+    fn clip_names_are_one_based() {
+        let highlight = Highlight {
+            kind: HighlightKind::Comeback,
+            round_index: 1,
+            player: 2,
+            video_seconds: 42.0,
+        };
+        assert_eq!(
+            clip_file_name(3, &highlight),
+            "match03_round2_comeback_p2.mp4"
+        );
+    }
+
+    #[cfg(feature = "debug-render")]
+    fn annotated_match() -> Match {
+        use recmari_proto::proto::{EventType, Round, RoundEvent};
+
+        let frame = |frame_number| FrameData {
+            frame_number,
+            ..Default::default()
+        };
+        let event = |r#type: EventType, frame_number, player, sa_level| RoundEvent {
+            r#type: r#type as i32,
+            frame_number,
+            player,
+            sa_level,
+            ..Default::default()
+        };
+        Match {
+            rounds: vec![Round {
+                frames: vec![frame(100), frame(110), frame(120)],
+                events: vec![
+                    event(EventType::DriveImpact, 105, 1, 0),
+                    event(EventType::SuperFlash, 112, 2, 3),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "debug-render")]
+    #[test]
+    fn frame_data_holds_the_last_sample_within_rounds() {
+        let m = annotated_match();
+        let at = |n| frame_data_at(&m, n).map(|fd| fd.frame_number);
+        assert_eq!(at(99), None);
+        assert_eq!(at(100), Some(100));
+        assert_eq!(at(115), Some(110));
+        assert_eq!(at(120), Some(120));
+        assert_eq!(at(121), None);
+    }
+
+    #[cfg(feature = "debug-render")]
+    #[test]
+    fn events_are_captioned_for_a_window() {
+        let m = annotated_match();
+        assert!(event_captions(&m, 104, 10).is_empty());
+        assert_eq!(event_captions(&m, 105, 10), ["P1 DRIVE IMPACT"]);
+        assert_eq!(
+            event_captions(&m, 114, 10),
+            ["P1 DRIVE IMPACT", "P2 SUPER FLASH Lv3"]
+        );
+        assert_eq!(event_captions(&m, 115, 10), ["P2 SUPER FLASH Lv3"]);
+    }
+}
@@ -1,21 +1,61 @@
+#[cfg(not(feature = "libav"))]
 use std::io::Read;
+use std::ops::Range;
 use std::path::Path;
-use std::process::{Child, Command, Stdio};
+#[cfg(not(feature = "libav"))]
+use std::process::Child;
+use std::process::{Command, Stdio};
+#[cfg(not(feature = "libav"))]
+use std::thread::JoinHandle;
 
-use anyhow::{bail, Context, Result};
+use image::imageops::{self, FilterType};
 use image::RgbImage;
 use tracing::{debug, error, info, warn};
 
-use super::frame::Frame;
+use super::frame::{Frame, FrameSource};
+use super::image_sequence::{self, ImageSequence};
+use crate::error::DecoderError;
+use crate::rect::PixelRect;
 
 /// Video metadata obtained by probing with ffprobe.
-struct ProbeResult {
-    width: u32,
-    height: u32,
-    fps: f64,
+#[derive(Debug, Clone, Copy)]
+pub struct ProbeResult {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    /// Total frames, from the stream's frame count or else duration × fps.
+    /// None when the container reports neither.
+    pub frame_count: Option<u64>,
 }
 
-fn probe(path: &Path) -> Result<ProbeResult> {
+/// Probe a video file's resolution, frame rate and length with ffprobe, or with
+/// libavformat when built with the `libav` feature.
+pub fn probe(path: &Path) -> Result<ProbeResult, DecoderError> {
+    let result = if image_sequence::is_image_sequence(path) {
+        image_sequence::probe(path)?
+    } else {
+        #[cfg(feature = "libav")]
+        let result = super::libav::probe(path)?;
+        #[cfg(not(feature = "libav"))]
+        let result = probe_ffprobe(path)?;
+        result
+    };
+
+    if result.fps <= 0.0 {
+        warn!(
+            fps = result.fps,
+            ?path,
+            "video has non-positive fps, timestamps will be 0.0"
+        );
+    }
+    if result.frame_count.is_none() {
+        warn!(?path, "video length unknown, progress will have no ETA");
+    }
+    Ok(result)
+}
+
+#[cfg(not(feature = "libav"))]
+fn probe_ffprobe(path: &Path) -> Result<ProbeResult, DecoderError> {
     info!(?path, "probing video metadata with ffprobe");
 
     let output = Command::new("ffprobe")
@@ -25,7 +65,7 @@ fn probe(path: &Path) -> Result<ProbeResult> {
             "-select_streams",
             "v:0",
             "-show_entries",
-            "stream=width,height,r_frame_rate",
+            "stream=width,height,r_frame_rate,nb_frames:format=duration",
             "-of",
             "csv=p=0",
         ])
@@ -33,69 +73,287 @@ fn probe(path: &Path) -> Result<ProbeResult> {
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .output()
-        .context("failed to run ffprobe — is ffmpeg installed?")?;
+        .map_err(|source| DecoderError::ToolMissing {
+            program: "ffprobe",
+            source,
+        })?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         error!(%stderr, ?path, "ffprobe failed");
-        bail!("ffprobe failed: {stderr}");
+        return Err(DecoderError::ToolFailed {
+            program: "ffprobe",
+            stderr: stderr.into_owned(),
+        });
+    }
+
+    let result = parse_probe_output(&String::from_utf8_lossy(&output.stdout))?;
+    info!(?result, "probe completed");
+    Ok(result)
+}
+
+/// Where the game picture is in a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CropMode {
+    /// Find black letterbox or pillarbox bars with ffmpeg's `cropdetect`.
+    Auto,
+    /// Use this rectangle of the source frame.
+    Fixed(PixelRect),
+}
+
+impl std::str::FromStr for CropMode {
+    type Err = String;
+
+    /// `auto` or `x:y:w:h` in source pixels.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(CropMode::Auto);
+        }
+        let parts: Vec<u32> = s
+            .split(':')
+            .map(|p| p.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid crop '{s}' (expected auto or x:y:w:h)"))?;
+        match parts[..] {
+            [x, y, w, h] if w > 0 && h > 0 => Ok(CropMode::Fixed(PixelRect { x, y, w, h })),
+            _ => Err(format!("invalid crop '{s}' (expected auto or x:y:w:h)")),
+        }
+    }
+}
+
+/// Seconds of video sampled by [`detect_content_rect`].
+const CROPDETECT_SECONDS: f64 = 20.0;
+
+/// Rectangle of the game picture inside black bars, or None when the picture fills
+/// the frame. Samples a window in the middle of the video, where gameplay is likely.
+pub fn detect_content_rect(
+    path: &Path,
+    info: &ProbeResult,
+) -> Result<Option<PixelRect>, DecoderError> {
+    if image_sequence::is_image_sequence(path) {
+        error!(?path, "letterbox detection needs a video file");
+        return Err(DecoderError::InvalidVideo(format!(
+            "cannot detect the letterbox of image sequence {}; pass the crop as x:y:w:h",
+            path.display()
+        )));
+    }
+    let start = match info.frame_count {
+        Some(frames) if info.fps > 0.0 => {
+            (frames as f64 / info.fps / 2.0 - CROPDETECT_SECONDS / 2.0).max(0.0)
+        }
+        _ => 0.0,
+    };
+    info!(?path, start, "detecting letterbox with ffmpeg cropdetect");
+
+    let output = Command::new("ffmpeg")
+        .args([
+            "-hide_banner",
+            "-nostats",
+            "-ss",
+            &format!("{start:.3}"),
+            "-i",
+        ])
+        .arg(path)
+        .args([
+            "-t",
+            &format!("{CROPDETECT_SECONDS}"),
+            "-vf",
+            "cropdetect=limit=24:round=2:reset=0",
+            "-an",
+            "-f",
+            "null",
+            "-",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|source| DecoderError::ToolMissing {
+            program: "ffmpeg",
+            source,
+        })?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        error!(%stderr, ?path, "ffmpeg cropdetect failed");
+        return Err(DecoderError::ToolFailed {
+            program: "ffmpeg",
+            stderr: stderr.into_owned(),
+        });
     }
+    let Some(rect) = parse_cropdetect(&stderr) else {
+        warn!(?path, "cropdetect reported no crop, using the full frame");
+        return Ok(None);
+    };
+    let full = PixelRect {
+        x: 0,
+        y: 0,
+        w: info.width,
+        h: info.height,
+    };
+    let content = (rect != full).then_some(rect);
+    info!(?content, "letterbox detection completed");
+    Ok(content)
+}
 
-    // Output format: "width,height,num/den"
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let parts: Vec<&str> = stdout.trim().split(',').collect();
+/// Last `crop=w:h:x:y` suggestion in cropdetect's log.
+fn parse_cropdetect(stderr: &str) -> Option<PixelRect> {
+    stderr.lines().rev().find_map(|line| {
+        let crop = line
+            .split_whitespace()
+            .find_map(|t| t.strip_prefix("crop="))?;
+        let parts: Vec<u32> = crop
+            .split(':')
+            .map(|p| p.parse().ok())
+            .collect::<Option<_>>()?;
+        match parts[..] {
+            [w, h, x, y] if w > 0 && h > 0 => Some(PixelRect { x, y, w, h }),
+            _ => None,
+        }
+    })
+}
+
+/// Parse ffprobe output: a "width,height,num/den,nb_frames" stream line followed by
+/// a "duration" format line. nb_frames and duration may be "N/A" or missing.
+#[cfg_attr(feature = "libav", allow(dead_code))]
+fn parse_probe_output(stdout: &str) -> Result<ProbeResult, DecoderError> {
+    let invalid = |what: &str| {
+        DecoderError::InvalidVideo(format!("unparsable {what} in ffprobe output: {stdout}"))
+    };
+    let mut lines = stdout.lines().map(str::trim).filter(|l| !l.is_empty());
+    let stream = lines.next().unwrap_or_default();
+    let parts: Vec<&str> = stream.split(',').collect();
     if parts.len() < 3 {
         error!(%stdout, "unexpected ffprobe output format, expected width,height,fps");
-        bail!("unexpected ffprobe output: {stdout}");
+        return Err(DecoderError::InvalidVideo(format!(
+            "unexpected ffprobe output: {stdout}"
+        )));
     }
 
-    let width: u32 = parts[0].parse().context("failed to parse width")?;
-    let height: u32 = parts[1].parse().context("failed to parse height")?;
+    let width: u32 = parts[0].parse().map_err(|_| invalid("width"))?;
+    let height: u32 = parts[1].parse().map_err(|_| invalid("height"))?;
 
     let fps = if let Some((num, den)) = parts[2].split_once('/') {
-        let num: f64 = num.parse().context("failed to parse fps numerator")?;
-        let den: f64 = den.parse().context("failed to parse fps denominator")?;
+        let num: f64 = num.parse().map_err(|_| invalid("fps numerator"))?;
+        let den: f64 = den.parse().map_err(|_| invalid("fps denominator"))?;
         if den > 0.0 {
             num / den
         } else {
             0.0
         }
     } else {
-        parts[2].parse().context("failed to parse fps")?
+        parts[2].parse().map_err(|_| invalid("fps"))?
     };
 
-    if fps <= 0.0 {
-        warn!(
-            fps,
-            ?path,
-            "video has non-positive fps, timestamps will be 0.0"
-        );
+    let nb_frames = parts.get(3).and_then(|v| v.parse::<u64>().ok());
+    let duration = lines.next().and_then(|v| v.parse::<f64>().ok());
+    let frame_count = nb_frames.or_else(|| {
+        duration
+            .filter(|&d| d > 0.0 && fps > 0.0)
+            .map(|d| (d * fps).round() as u64)
+    });
+
+    Ok(ProbeResult {
+        width,
+        height,
+        fps,
+        frame_count,
+    })
+}
+
+/// Options for opening a [`VideoDecoder`].
+#[derive(Debug, Clone, Default)]
+pub struct DecoderConfig {
+    /// Frame number to start decoding from.
+    pub start_frame: u32,
+    /// Pixel rows to decode, or empty for the full frame. When set, ffmpeg crops and
+    /// stacks only these rows, and the decoder places them back at their original
+    /// position in an otherwise black frame, so HUD coordinates stay valid.
+    pub row_bands: Vec<Range<u32>>,
+    /// Game picture inside a letterboxed or pillarboxed recording. When set, ffmpeg
+    /// crops it and scales it before any row bands are taken, and frames have the
+    /// crop's output size.
+    pub crop: Option<ContentCrop>,
+    /// Emit only frames whose number is a multiple of this (0 or 1 emits every frame).
+    /// ffmpeg's `select` filter drops the others before they cross the pipe.
+    pub frame_step: u32,
+}
+
+/// Part of the source frame holding the game picture, scaled to `width`x`height`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentCrop {
+    pub rect: PixelRect,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ContentCrop {
+    #[cfg_attr(feature = "libav", allow(dead_code))]
+    fn filter(&self) -> String {
+        let r = self.rect;
+        format!(
+            "crop={}:{}:{}:{},scale={}:{}",
+            r.w, r.h, r.x, r.y, self.width, self.height
+        )
     }
+}
 
-    info!(width, height, fps, "probe completed");
-    Ok(ProbeResult { width, height, fps })
+/// Frame buffers kept for reuse by [`VideoDecoder::recycle`]. A few cover a frame being
+/// analyzed while the next is decoded.
+const MAX_POOLED_BUFFERS: usize = 4;
+
+/// Where raw RGB24 frames come from.
+enum RawSource {
+    /// The stdout pipe of an ffmpeg process.
+    #[cfg(not(feature = "libav"))]
+    Pipe(FfmpegPipe),
+    /// libav decoding in this process.
+    #[cfg(feature = "libav")]
+    Libav(Box<super::libav::LibavStream>),
+    /// A directory of images.
+    Images(ImageSequence),
 }
 
-/// Decodes video frames by piping raw RGB24 data from the ffmpeg CLI.
+/// Decodes video frames by piping raw RGB24 data from the ffmpeg CLI, or with libav in
+/// process when built with the `libav` feature. A directory is read as an
+/// [image sequence](super::image_sequence) without either.
 pub struct VideoDecoder {
-    child: Child,
+    source: RawSource,
     width: u32,
     height: u32,
     fps: f64,
     frame_count: u32,
     frame_bytes: usize,
+    row_bands: Vec<Range<u32>>,
+    /// Distance between the numbers of emitted frames.
+    frame_step: u32,
+    /// Full-frame buffers of recycled frames.
+    pool: Vec<Vec<u8>>,
+    /// Stacked row bands as read from the pipe, reused for every frame.
+    packed: Vec<u8>,
 }
 
 impl VideoDecoder {
     /// Open a video file for decoding from the beginning.
-    pub fn open(path: &Path) -> Result<Self> {
+    pub fn open(path: &Path) -> Result<Self, DecoderError> {
         Self::open_at_frame(path, 0)
     }
 
     /// Open a video file and seek to a specific frame before decoding.
-    pub fn open_at_frame(path: &Path, start_frame: u32) -> Result<Self> {
+    pub fn open_at_frame(path: &Path, start_frame: u32) -> Result<Self, DecoderError> {
+        Self::open_with(
+            path,
+            &DecoderConfig {
+                start_frame,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Open a video file with the given decoder options.
+    pub fn open_with(path: &Path, config: &DecoderConfig) -> Result<Self, DecoderError> {
+        let start_frame = config.start_frame;
         assert!(
-            path.exists(),
+            path.exists() || super::is_url(path),
             "video file does not exist: {}",
             path.display()
         );
@@ -107,52 +365,114 @@ impl VideoDecoder {
             info.width,
             info.height
         );
-
-        let seek_seconds = if start_frame > 0 && info.fps > 0.0 {
-            start_frame as f64 / info.fps
-        } else {
-            0.0
+        let (width, height) = match config.crop {
+            Some(crop) => {
+                validate_crop(&crop.rect, &info)?;
+                (crop.width, crop.height)
+            }
+            None => (info.width, info.height),
         };
+        validate_row_bands(&config.row_bands, height);
 
-        info!(
-            ?path,
-            start_frame, seek_seconds, "spawning ffmpeg decoder process"
-        );
+        let seek_seconds = seek_seconds(start_frame, info.fps);
 
-        let mut cmd = Command::new("ffmpeg");
-        if seek_seconds > 0.0 {
-            cmd.args(["-ss", &format!("{seek_seconds:.3}")]);
-        }
-        let child = cmd
-            .args(["-i"])
-            .arg(path)
-            .args([
-                "-f", "rawvideo", "-pix_fmt", "rgb24", "-v", "error", "pipe:1",
-            ])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("failed to spawn ffmpeg — is ffmpeg installed?")?;
+        let frame_step = config.frame_step.max(1);
+        // The first decoded frame is `start_frame` after a seek, else frame 0.
+        let first_decoded = if seek_seconds > 0.0 { start_frame } else { 0 };
+        let source = if image_sequence::is_image_sequence(path) {
+            RawSource::Images(ImageSequence::open(
+                path,
+                (info.width, info.height),
+                first_decoded,
+                frame_step,
+                config.crop,
+                &config.row_bands,
+            )?)
+        } else {
+            #[cfg(not(feature = "libav"))]
+            let source = RawSource::Pipe(FfmpegPipe::spawn(
+                path,
+                config,
+                seek_seconds,
+                first_decoded,
+                width,
+            )?);
+            #[cfg(feature = "libav")]
+            let source = RawSource::Libav(Box::new(super::libav::LibavStream::open(
+                path,
+                seek_seconds,
+                first_decoded,
+                frame_step,
+                config.crop,
+                &config.row_bands,
+            )?));
+            source
+        };
 
-        let frame_bytes = (info.width as usize) * (info.height as usize) * 3;
+        let decoded_height = if config.row_bands.is_empty() {
+            height
+        } else {
+            config.row_bands.iter().map(|b| b.end - b.start).sum()
+        };
+        let frame_bytes = (width as usize) * (decoded_height as usize) * 3;
 
         info!(
-            width = info.width,
-            height = info.height,
+            width,
+            height,
             fps = info.fps,
             frame_bytes,
             start_frame,
+            frame_step,
             "video decoder opened"
         );
 
-        Ok(Self {
-            child,
-            width: info.width,
-            height: info.height,
+        let mut decoder = Self {
+            source,
+            width,
+            height,
             fps: info.fps,
-            frame_count: start_frame,
+            frame_count: first_decoded.next_multiple_of(frame_step),
             frame_bytes,
-        })
+            row_bands: config.row_bands.clone(),
+            frame_step,
+            pool: Vec::new(),
+            packed: Vec::new(),
+        };
+
+        if decoder.frame_count < start_frame {
+            warn!(
+                start_frame,
+                "cannot seek by timestamp, decoding and discarding frames up to start"
+            );
+            decoder.skip_frames(start_frame)?;
+        }
+        assert!(decoder.frame_count >= start_frame);
+        assert!(decoder.frame_count - start_frame < frame_step);
+
+        Ok(decoder)
+    }
+
+    /// Decode and discard frames until the next one is at or after `start_frame`. Used
+    /// when timestamp seeking is unavailable.
+    fn skip_frames(&mut self, start_frame: u32) -> Result<(), DecoderError> {
+        let mut buf = vec![0u8; self.frame_bytes];
+        let mut count = 0u32;
+        while self.frame_count < start_frame {
+            if !self.read_raw_frame(&mut buf)? {
+                error!(
+                    frame = self.frame_count,
+                    "video ended before reaching the start frame"
+                );
+                return Err(DecoderError::InvalidVideo(format!(
+                    "video ended at frame {} before start frame {start_frame}",
+                    self.frame_count
+                )));
+            }
+            self.frame_count += self.frame_step;
+            count += 1;
+        }
+        debug!(skipped = count, "skipped frames");
+        Ok(())
     }
 
     pub fn width(&self) -> u32 {
@@ -168,44 +488,32 @@ impl VideoDecoder {
     }
 
     /// Read the next frame from the ffmpeg pipe, or `None` if the video is finished.
-    pub fn next_frame(&mut self) -> Result<Option<Frame>> {
-        let stdout = self
-            .child
-            .stdout
-            .as_mut()
-            .context("ffmpeg stdout not available")?;
+    ///
+    /// The frame's buffer comes from the pool when frames were [recycled](Self::recycle).
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, DecoderError> {
+        let full_bytes = self.width as usize * self.height as usize * 3;
+        let mut buf = self.pool.pop().unwrap_or_else(|| vec![0u8; full_bytes]);
+        assert_eq!(buf.len(), full_bytes, "pooled buffer size mismatch");
 
-        let mut buf = vec![0u8; self.frame_bytes];
-        let mut read = 0;
-
-        while read < self.frame_bytes {
-            match stdout.read(&mut buf[read..]) {
-                Ok(0) => {
-                    if read == 0 {
-                        info!(total_frames = self.frame_count, "video stream ended");
-                        return Ok(None);
-                    }
-                    error!(
-                        read_bytes = read,
-                        expected_bytes = self.frame_bytes,
-                        frame = self.frame_count,
-                        "ffmpeg stream ended mid-frame"
-                    );
-                    bail!(
-                        "ffmpeg stream ended mid-frame (read {read}/{} bytes)",
-                        self.frame_bytes,
-                    );
-                }
-                Ok(n) => read += n,
-                Err(e) => {
-                    error!(frame = self.frame_count, %e, "failed to read from ffmpeg pipe");
-                    return Err(e).context("failed to read from ffmpeg pipe");
-                }
+        let read = if self.row_bands.is_empty() {
+            self.read_raw_frame(&mut buf)?
+        } else {
+            let mut packed = std::mem::take(&mut self.packed);
+            packed.resize(self.frame_bytes, 0);
+            let read = self.read_raw_frame(&mut packed)?;
+            if read {
+                expand_row_bands(&packed, self.width, &self.row_bands, &mut buf);
             }
+            self.packed = packed;
+            read
+        };
+        if !read {
+            self.pool.push(buf);
+            return Ok(None);
         }
 
         let image = RgbImage::from_raw(self.width, self.height, buf)
-            .context("failed to create RgbImage from raw frame data")?;
+            .expect("frame buffer matches the frame size");
 
         let frame_number = self.frame_count;
         let timestamp_seconds = if self.fps > 0.0 {
@@ -213,7 +521,7 @@ impl VideoDecoder {
         } else {
             0.0
         };
-        self.frame_count += 1;
+        self.frame_count += self.frame_step;
 
         debug!(frame_number, timestamp_seconds, "decoded frame");
 
@@ -223,12 +531,591 @@ impl VideoDecoder {
             timestamp_seconds,
         }))
     }
+
+    /// Return `frame`'s buffer to the pool for a later [`next_frame`](Self::next_frame).
+    /// Frames of another size are dropped.
+    pub fn recycle(&mut self, frame: Frame) {
+        if frame.image.dimensions() != (self.width, self.height)
+            || self.pool.len() >= MAX_POOLED_BUFFERS
+        {
+            return;
+        }
+        self.pool.push(frame.image.into_raw());
+    }
+
+    /// Fill `buf` with one raw RGB24 frame. Returns false on a clean end of stream.
+    fn read_raw_frame(&mut self, buf: &mut [u8]) -> Result<bool, DecoderError> {
+        assert_eq!(buf.len(), self.frame_bytes, "frame buffer size mismatch");
+        match &mut self.source {
+            #[cfg(not(feature = "libav"))]
+            RawSource::Pipe(pipe) => read_pipe_frame(pipe, buf, self.frame_count),
+            #[cfg(feature = "libav")]
+            RawSource::Libav(stream) => stream.read_frame(buf),
+            RawSource::Images(sequence) => sequence.read_frame(buf),
+        }
+    }
+}
+
+/// Fill `buf` with one raw frame from ffmpeg's stdout. Returns false on a clean end of
+/// stream; `frame` is the number of the frame being read, for logging.
+///
+/// When the stream ends, ffmpeg's exit status tells a finished video from a crash: a
+/// failed exit is reported with what ffmpeg logged.
+#[cfg(not(feature = "libav"))]
+fn read_pipe_frame(
+    pipe: &mut FfmpegPipe,
+    buf: &mut [u8],
+    frame: u32,
+) -> Result<bool, DecoderError> {
+    let stdout = pipe.child.stdout.as_mut().expect("ffmpeg stdout is piped");
+    let mut read = 0;
+
+    while read < buf.len() {
+        match stdout.read(&mut buf[read..]) {
+            Ok(0) => {
+                let stderr = pipe.finish()?;
+                if read == 0 {
+                    if !stderr.is_empty() {
+                        warn!(%stderr, "ffmpeg reported errors while decoding");
+                    }
+                    info!(total_frames = frame, "video stream ended");
+                    return Ok(false);
+                }
+                error!(
+                    read_bytes = read,
+                    expected_bytes = buf.len(),
+                    frame,
+                    %stderr,
+                    "ffmpeg stream ended mid-frame"
+                );
+                let detail = if stderr.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {stderr}")
+                };
+                return Err(DecoderError::InvalidVideo(format!(
+                    "ffmpeg stream ended mid-frame (read {read}/{} bytes){detail}",
+                    buf.len(),
+                )));
+            }
+            Ok(n) => read += n,
+            Err(e) => {
+                error!(frame, %e, "failed to read from ffmpeg pipe");
+                return Err(e.into());
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Bytes of ffmpeg's stderr kept for error messages. A corrupt file can log an error
+/// per frame; the last ones explain the failure.
+#[cfg(not(feature = "libav"))]
+const STDERR_TAIL_BYTES: usize = 4096;
+
+/// An ffmpeg process writing raw frames to its stdout. Its stderr is drained on a
+/// background thread, so a chatty ffmpeg never blocks on a full pipe, and is reported
+/// when the process fails.
+#[cfg(not(feature = "libav"))]
+struct FfmpegPipe {
+    child: Child,
+    stderr: Option<JoinHandle<String>>,
+}
+
+#[cfg(not(feature = "libav"))]
+impl FfmpegPipe {
+    /// Start ffmpeg (see [`spawn_ffmpeg`]) and begin draining its stderr.
+    fn spawn(
+        path: &Path,
+        config: &DecoderConfig,
+        seek_seconds: f64,
+        first_decoded: u32,
+        width: u32,
+    ) -> Result<Self, DecoderError> {
+        let mut child = spawn_ffmpeg(path, config, seek_seconds, first_decoded, width)?;
+        let stderr = child
+            .stderr
+            .take()
+            .map(|pipe| std::thread::spawn(move || read_tail(pipe, STDERR_TAIL_BYTES)));
+        Ok(Self { child, stderr })
+    }
+
+    /// Wait for ffmpeg to exit once its stdout has ended. Returns what it logged, or
+    /// [`DecoderError::ToolFailed`] with the log when it exited with an error.
+    fn finish(&mut self) -> Result<String, DecoderError> {
+        let status = self.child.wait()?;
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|thread| thread.join().ok())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        if status.success() {
+            return Ok(stderr);
+        }
+        error!(%status, %stderr, "ffmpeg decoder exited with an error");
+        let stderr = if stderr.is_empty() {
+            status.to_string()
+        } else {
+            format!("{stderr} ({status})")
+        };
+        Err(DecoderError::ToolFailed {
+            program: "ffmpeg",
+            stderr,
+        })
+    }
+}
+
+/// Read `reader` to its end, keeping its last `max` bytes as text.
+#[cfg(not(feature = "libav"))]
+fn read_tail(mut reader: impl Read, max: usize) -> String {
+    let mut tail = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => {
+                tail.extend_from_slice(&chunk[..n]);
+                if tail.len() > max {
+                    tail.drain(..tail.len() - max);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+    String::from_utf8_lossy(&tail).into_owned()
+}
+
+/// Spawn ffmpeg writing raw RGB24 frames of `width`-pixel rows to its stdout.
+#[cfg(not(feature = "libav"))]
+fn spawn_ffmpeg(
+    path: &Path,
+    config: &DecoderConfig,
+    seek_seconds: f64,
+    first_decoded: u32,
+    width: u32,
+) -> Result<Child, DecoderError> {
+    info!(
+        ?path,
+        start_frame = config.start_frame,
+        seek_seconds,
+        "spawning ffmpeg decoder process"
+    );
+
+    // Input seeking (-ss before -i) jumps to the nearest keyframe and then decodes
+    // and discards up to the target, so the first emitted frame is `start_frame`.
+    let mut cmd = Command::new("ffmpeg");
+    if seek_seconds > 0.0 {
+        cmd.args(["-accurate_seek", "-ss", &format!("{seek_seconds:.6}")]);
+    }
+    cmd.args(["-i"]).arg(path);
+    let frame_step = config.frame_step.max(1);
+    let filters: Vec<String> = select_filter(frame_step, first_decoded)
+        .into_iter()
+        .chain(config.crop.map(|crop| crop.filter()))
+        .chain(row_band_filter(&config.row_bands, width))
+        .collect();
+    if !filters.is_empty() {
+        let filter = filters.join(",");
+        info!(%filter, "decoding with video filter");
+        cmd.args(["-vf", &filter]);
+    }
+    if frame_step > 1 {
+        // Keep ffmpeg from duplicating frames to fill the gaps select leaves.
+        cmd.args(["-fps_mode", "passthrough"]);
+    }
+    cmd.args([
+        "-f", "rawvideo", "-pix_fmt", "rgb24", "-v", "error", "pipe:1",
+    ])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|source| DecoderError::ToolMissing {
+        program: "ffmpeg",
+        source,
+    })
+}
+
+/// Copy `image` into `buf` as ffmpeg's filters would emit it: the crop's picture scaled
+/// to its output size, then only the row bands (all rows when empty) stacked.
+pub(super) fn write_raw_frame(
+    image: RgbImage,
+    crop: Option<ContentCrop>,
+    bands: &[Range<u32>],
+    buf: &mut [u8],
+) {
+    let image = match crop {
+        Some(crop) => {
+            let r = crop.rect;
+            let picture = imageops::crop_imm(&image, r.x, r.y, r.w, r.h).to_image();
+            scale_crop(&picture, crop)
+        }
+        None => image,
+    };
+    let row_bytes = image.width() as usize * 3;
+    let raw = image.as_raw();
+    copy_row_bands(
+        |y| &raw[y as usize * row_bytes..][..row_bytes],
+        image.height(),
+        bands,
+        buf,
+    );
+}
+
+/// Scale the cropped `picture` to the crop's output size.
+pub(super) fn scale_crop(picture: &RgbImage, crop: ContentCrop) -> RgbImage {
+    imageops::resize(picture, crop.width, crop.height, FilterType::CatmullRom)
+}
+
+/// Copy the rows of a `height`-row picture into `buf`, only the row bands (all rows
+/// when empty) stacked. `row` returns the packed RGB24 pixels of a row.
+pub(super) fn copy_row_bands<'a>(
+    row: impl Fn(u32) -> &'a [u8],
+    height: u32,
+    bands: &[Range<u32>],
+    buf: &mut [u8],
+) {
+    let all = 0..height;
+    let bands = if bands.is_empty() {
+        std::slice::from_ref(&all)
+    } else {
+        bands
+    };
+    let mut offset = 0;
+    for y in bands.iter().flat_map(|band| band.clone()) {
+        let pixels = row(y);
+        buf[offset..offset + pixels.len()].copy_from_slice(pixels);
+        offset += pixels.len();
+    }
+    assert_eq!(offset, buf.len(), "packed frame size mismatch");
+}
+
+fn validate_crop(rect: &PixelRect, info: &ProbeResult) -> Result<(), DecoderError> {
+    // The crop comes from the command line, so its sums may overflow u32.
+    let fits =
+        |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|end| end <= size);
+    if rect.w == 0
+        || rect.h == 0
+        || !fits(rect.x, rect.w, info.width)
+        || !fits(rect.y, rect.h, info.height)
+    {
+        error!(
+            ?rect,
+            width = info.width,
+            height = info.height,
+            "crop outside the video frame"
+        );
+        return Err(DecoderError::InvalidVideo(format!(
+            "crop {}x{} at {},{} does not fit in the {}x{} video",
+            rect.w, rect.h, rect.x, rect.y, info.width, info.height
+        )));
+    }
+    Ok(())
+}
+
+fn validate_row_bands(bands: &[Range<u32>], height: u32) {
+    let mut prev_end = 0;
+    for band in bands {
+        assert!(band.start < band.end, "empty row band: {band:?}");
+        assert!(
+            band.end <= height,
+            "row band {band:?} exceeds height {height}"
+        );
+        assert!(
+            band.start >= prev_end,
+            "row bands must be sorted and disjoint"
+        );
+        prev_end = band.end;
+    }
+}
+
+/// Build an ffmpeg filter keeping only frames whose number is a multiple of `step`, for
+/// a stream whose first decoded frame is number `first_frame`.
+#[cfg_attr(feature = "libav", allow(dead_code))]
+fn select_filter(step: u32, first_frame: u32) -> Option<String> {
+    assert!(step > 0, "frame step must be > 0");
+    if step == 1 {
+        return None;
+    }
+    // `n` counts decoded frames from 0; commas inside the expression are escaped.
+    Some(format!(
+        "select=not(mod(n+{}\\,{step}))",
+        first_frame % step
+    ))
+}
+
+/// Build an ffmpeg filter that crops each row band and stacks them vertically.
+#[cfg_attr(feature = "libav", allow(dead_code))]
+fn row_band_filter(bands: &[Range<u32>], width: u32) -> Option<String> {
+    if bands.is_empty() {
+        return None;
+    }
+    let crop = |b: &Range<u32>| format!("crop={width}:{}:0:{}", b.end - b.start, b.start);
+    if bands.len() == 1 {
+        return Some(crop(&bands[0]));
+    }
+
+    let mut filter = format!("split={}", bands.len());
+    for i in 0..bands.len() {
+        filter.push_str(&format!("[s{i}]"));
+    }
+    for (i, band) in bands.iter().enumerate() {
+        filter.push_str(&format!(";[s{i}]{}[c{i}]", crop(band)));
+    }
+    filter.push(';');
+    for i in 0..bands.len() {
+        filter.push_str(&format!("[c{i}]"));
+    }
+    filter.push_str(&format!("vstack=inputs={}", bands.len()));
+    Some(filter)
+}
+
+/// Place stacked row bands back at their original rows of the full-size frame `full`
+/// and black out every other row (a reused buffer holds an earlier frame).
+fn expand_row_bands(packed: &[u8], width: u32, bands: &[Range<u32>], full: &mut [u8]) {
+    let stride = width as usize * 3;
+    assert_eq!(full.len() % stride, 0, "full frame size mismatch");
+    let mut offset = 0;
+    let mut row = 0usize;
+    for band in bands {
+        let (start, end) = (band.start as usize * stride, band.end as usize * stride);
+        full[row..start].fill(0);
+        full[start..end].copy_from_slice(&packed[offset..offset + end - start]);
+        offset += end - start;
+        row = end;
+    }
+    full[row..].fill(0);
+    assert_eq!(offset, packed.len(), "packed frame size mismatch");
+}
+
+/// Seek target for `start_frame`, or 0.0 when the stream must be decoded from the start.
+///
+/// Targets half a frame before the frame's presentation time so that rounding of the
+/// timestamp can neither drop `start_frame` nor include the frame before it.
+fn seek_seconds(start_frame: u32, fps: f64) -> f64 {
+    if start_frame == 0 || fps <= 0.0 {
+        return 0.0;
+    }
+    (start_frame as f64 - 0.5) / fps
+}
+
+impl FrameSource for VideoDecoder {
+    fn next_frame(&mut self) -> Result<Option<Frame>, DecoderError> {
+        VideoDecoder::next_frame(self)
+    }
+
+    fn recycle(&mut self, frame: Frame) {
+        VideoDecoder::recycle(self, frame)
+    }
 }
 
 impl Drop for VideoDecoder {
     fn drop(&mut self) {
         info!(total_frames = self.frame_count, "closing video decoder");
-        let _ = self.child.kill();
-        let _ = self.child.wait();
+        match &mut self.source {
+            #[cfg(not(feature = "libav"))]
+            RawSource::Pipe(pipe) => {
+                let _ = pipe.child.kill();
+                let _ = pipe.child.wait();
+            }
+            #[cfg(feature = "libav")]
+            RawSource::Libav(_) => {}
+            RawSource::Images(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "libav"))]
+    #[test]
+    fn stderr_tail_keeps_the_last_bytes() {
+        let log = "error while decoding MB 1\n".repeat(400) + "Invalid data found";
+        let tail = read_tail(log.as_bytes(), 64);
+        assert_eq!(tail.len(), 64);
+        assert!(tail.ends_with("MB 1\nInvalid data found"));
+        assert_eq!(read_tail("short".as_bytes(), 64), "short");
+    }
+
+    #[test]
+    fn crop_must_fit_in_the_frame() {
+        let info = ProbeResult {
+            width: 1920,
+            height: 1080,
+            fps: 60.0,
+            frame_count: None,
+        };
+        let rect = |x, y, w, h| PixelRect { x, y, w, h };
+        assert!(validate_crop(&rect(0, 0, 1920, 1080), &info).is_ok());
+        assert!(validate_crop(&rect(240, 0, 1440, 1080), &info).is_ok());
+        assert!(validate_crop(&rect(1, 0, 1920, 1080), &info).is_err());
+        assert!(validate_crop(&rect(0, 0, 0, 1080), &info).is_err());
+        assert!(validate_crop(&rect(u32::MAX, 0, 1, 1), &info).is_err());
+        assert!(validate_crop(&rect(0, u32::MAX, 1, 1), &info).is_err());
+    }
+
+    #[test]
+    fn row_bands_are_stacked_in_order() {
+        // 2x4 picture whose bytes are their own row number.
+        let rows: Vec<[u8; 6]> = (0..4).map(|y| [y; 6]).collect();
+        let mut buf = vec![0; 18];
+        copy_row_bands(|y| &rows[y as usize], 4, &[0..1, 2..4], &mut buf);
+        assert_eq!(buf, [[0; 6], [2; 6], [3; 6]].concat());
+        let mut all = vec![0; 24];
+        copy_row_bands(|y| &rows[y as usize], 4, &[], &mut all);
+        assert_eq!(all, rows.concat());
+    }
+
+    #[test]
+    fn row_band_filter_single_band() {
+        let bands = vec![Range { start: 10, end: 20 }];
+        assert_eq!(
+            row_band_filter(&bands, 1920).as_deref(),
+            Some("crop=1920:10:0:10")
+        );
+    }
+
+    #[test]
+    fn row_band_filter_stacks_bands() {
+        let filter = row_band_filter(&[0..4, 10..12], 8).unwrap();
+        assert_eq!(
+            filter,
+            "split=2[s0][s1];[s0]crop=8:4:0:0[c0];[s1]crop=8:2:0:10[c1];[c0][c1]vstack=inputs=2"
+        );
+    }
+
+    #[test]
+    fn select_filter_keeps_absolute_multiples() {
+        assert_eq!(select_filter(1, 0), None);
+        assert_eq!(
+            select_filter(60, 0).as_deref(),
+            Some("select=not(mod(n+0\\,60))")
+        );
+        // After seeking to frame 130, n = 0 is frame 130 and n = 50 is frame 180.
+        assert_eq!(
+            select_filter(60, 130).as_deref(),
+            Some("select=not(mod(n+10\\,60))")
+        );
+    }
+
+    #[test]
+    fn expand_row_bands_restores_positions() {
+        let (width, height) = (2u32, 6u32);
+        let stride = width as usize * 3;
+        // Two bands: rows 1..2 filled with 1, rows 4..6 filled with 2.
+        let mut packed = vec![1u8; stride];
+        packed.extend(vec![2u8; stride * 2]);
+
+        // A reused buffer: rows outside the bands must be cleared.
+        let mut full = vec![9u8; stride * height as usize];
+        expand_row_bands(&packed, width, &[1..2, 4..6], &mut full);
+        let row = |y: usize| &full[y * stride..(y + 1) * stride];
+        assert!(row(0).iter().all(|&b| b == 0));
+        assert!(row(1).iter().all(|&b| b == 1));
+        assert!(row(3).iter().all(|&b| b == 0));
+        assert!(row(4).iter().all(|&b| b == 2));
+        assert!(row(5).iter().all(|&b| b == 2));
+    }
+
+    #[test]
+    fn seek_seconds_from_start() {
+        assert_eq!(seek_seconds(0, 60.0), 0.0);
+    }
+
+    #[test]
+    fn seek_seconds_without_fps() {
+        assert_eq!(seek_seconds(120, 0.0), 0.0);
+    }
+
+    #[test]
+    fn seek_seconds_lands_between_frames() {
+        let fps = 60.0;
+        for frame in [1u32, 59, 54000] {
+            let t = seek_seconds(frame, fps);
+            assert!(t > (frame - 1) as f64 / fps, "frame {frame}: {t}");
+            assert!(t < frame as f64 / fps, "frame {frame}: {t}");
+        }
+    }
+
+    #[test]
+    fn seek_seconds_ntsc_rate() {
+        let fps = 60000.0 / 1001.0;
+        let t = seek_seconds(54000, fps);
+        let formatted: f64 = format!("{t:.6}").parse().unwrap();
+        assert!(formatted > 53999.0 / fps);
+        assert!(formatted < 54000.0 / fps);
+    }
+
+    #[test]
+    fn probe_output_with_frame_count() {
+        let result = parse_probe_output("1920,1080,60/1,3600\n60.000000\n").unwrap();
+        assert_eq!(
+            (result.width, result.height, result.fps),
+            (1920, 1080, 60.0)
+        );
+        assert_eq!(result.frame_count, Some(3600));
+    }
+
+    #[test]
+    fn probe_output_falls_back_to_duration() {
+        let result = parse_probe_output("1280,720,30/1,N/A\n10.5\n").unwrap();
+        assert_eq!(result.frame_count, Some(315));
+        let result = parse_probe_output("1280,720,30/1\n").unwrap();
+        assert_eq!(result.frame_count, None);
+    }
+
+    #[test]
+    fn crop_mode_parses_auto_and_rect() {
+        assert_eq!("auto".parse::<CropMode>(), Ok(CropMode::Auto));
+        assert_eq!(
+            "0:140:1920:800".parse::<CropMode>(),
+            Ok(CropMode::Fixed(PixelRect {
+                x: 0,
+                y: 140,
+                w: 1920,
+                h: 800
+            }))
+        );
+        assert!("1920:800".parse::<CropMode>().is_err());
+        assert!("0:0:0:800".parse::<CropMode>().is_err());
+    }
+
+    #[test]
+    fn cropdetect_last_suggestion_wins() {
+        let stderr = "\
+[Parsed_cropdetect_0 @ 0x1] x1:0 x2:1919 y1:140 y2:939 w:1920 h:800 x:0 y:140 pts:1 t:0.016 crop=1920:800:0:140
+[Parsed_cropdetect_0 @ 0x1] x1:240 x2:1679 y1:0 y2:1079 w:1440 h:1080 x:240 y:0 pts:2 t:0.033 crop=1440:1080:240:0
+frame=  600 fps=0.0 q=-0.0 Lsize=N/A time=00:00:10.00
+";
+        assert_eq!(
+            parse_cropdetect(stderr),
+            Some(PixelRect {
+                x: 240,
+                y: 0,
+                w: 1440,
+                h: 1080
+            })
+        );
+        assert_eq!(parse_cropdetect("no suggestions\n"), None);
+    }
+
+    #[test]
+    fn crop_filter_scales_to_output_size() {
+        let crop = ContentCrop {
+            rect: PixelRect {
+                x: 240,
+                y: 0,
+                w: 1440,
+                h: 810,
+            },
+            width: 1920,
+            height: 1080,
+        };
+        assert_eq!(crop.filter(), "crop=1440:810:240:0,scale=1920:1080");
     }
 }
@@ -0,0 +1,99 @@
+use tracing::debug;
+
+use super::frame::Frame;
+
+/// Pixels are compared on a grid with this spacing (both axes); duplicated frames
+/// match everywhere, so a sparse grid is enough.
+const GRID_STEP: u32 = 8;
+/// Frames whose grid pixels differ by at most this much on average (0-255 per
+/// channel) are duplicates. Re-encoded duplicates are not byte-identical.
+const MAX_MEAN_DIFF: f64 = 0.5;
+
+/// Detects frames repeated by the capture, e.g. 30fps footage encoded at 60fps.
+#[derive(Default)]
+pub struct FrameDeduplicator {
+    previous: Option<Vec<u8>>,
+    unique: u32,
+    duplicates: u32,
+}
+
+impl FrameDeduplicator {
+    /// Whether `frame` repeats the frame observed before it.
+    pub fn is_duplicate(&mut self, frame: &Frame) -> bool {
+        let grid = grid_pixels(frame);
+        let duplicate = self
+            .previous
+            .as_ref()
+            .is_some_and(|prev| mean_abs_diff(prev, &grid) <= MAX_MEAN_DIFF);
+        self.previous = Some(grid);
+        if duplicate {
+            self.duplicates += 1;
+            debug!(frame_number = frame.frame_number, "duplicate frame");
+        } else {
+            self.unique += 1;
+        }
+        duplicate
+    }
+
+    /// Frames observed that were not duplicates.
+    pub fn unique_frames(&self) -> u32 {
+        self.unique
+    }
+
+    /// Frames observed that repeated the previous one.
+    pub fn duplicate_frames(&self) -> u32 {
+        self.duplicates
+    }
+}
+
+fn grid_pixels(frame: &Frame) -> Vec<u8> {
+    let image = &frame.image;
+    let mut pixels = Vec::new();
+    for y in (0..image.height()).step_by(GRID_STEP as usize) {
+        for x in (0..image.width()).step_by(GRID_STEP as usize) {
+            pixels.extend_from_slice(&image.get_pixel(x, y).0);
+        }
+    }
+    pixels
+}
+
+/// Mean absolute difference of two grids, or infinity when their sizes differ.
+fn mean_abs_diff(a: &[u8], b: &[u8]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return f64::INFINITY;
+    }
+    let total: u64 = a
+        .iter()
+        .zip(b)
+        .map(|(&x, &y)| u64::from(x.abs_diff(y)))
+        .sum();
+    total as f64 / a.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::test_support::video_frame;
+
+    #[test]
+    fn repeated_and_near_identical_frames_are_duplicates() {
+        let base = RgbImage::from_pixel(64, 32, Rgb([100, 120, 140]));
+        let mut noisy = base.clone();
+        noisy.put_pixel(8, 8, Rgb([101, 121, 141]));
+        let mut moved = base.clone();
+        for x in 0..32 {
+            for y in 0..32 {
+                moved.put_pixel(x, y, Rgb([20, 20, 20]));
+            }
+        }
+
+        let mut dedup = FrameDeduplicator::default();
+        assert!(!dedup.is_duplicate(&video_frame(0, base.clone())));
+        assert!(dedup.is_duplicate(&video_frame(1, base)));
+        assert!(dedup.is_duplicate(&video_frame(2, noisy)));
+        assert!(!dedup.is_duplicate(&video_frame(3, moved)));
+        assert_eq!((dedup.unique_frames(), dedup.duplicate_frames()), (2, 2));
+    }
+}
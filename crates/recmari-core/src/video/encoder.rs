@@ -0,0 +1,103 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use image::RgbImage;
+use tracing::{error, info};
+
+use crate::error::DecoderError;
+
+/// Encodes RGB frames into a video file by piping them into an ffmpeg subprocess.
+pub struct VideoEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+}
+
+impl VideoEncoder {
+    /// Start encoding `width`x`height` frames played back at `fps` into an H.264 file.
+    pub fn create(path: &Path, width: u32, height: u32, fps: f64) -> Result<Self, DecoderError> {
+        assert!(
+            width > 0 && height > 0,
+            "invalid frame size {width}x{height}"
+        );
+        assert!(fps > 0.0, "fps must be positive, got {fps}");
+
+        info!(?path, width, height, fps, "spawning ffmpeg encoder process");
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-v", "error", "-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{width}x{height}")])
+            .args(["-r", &format!("{fps:.6}")])
+            .args(["-i", "pipe:0"])
+            // yuv420p needs even dimensions; pad odd ones by a pixel.
+            .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+            .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| DecoderError::ToolMissing {
+                program: "ffmpeg",
+                source,
+            })?;
+        let stdin = child.stdin.take();
+
+        Ok(Self {
+            child,
+            stdin,
+            path: path.to_path_buf(),
+            width,
+            height,
+            frame_count: 0,
+        })
+    }
+
+    pub fn write_frame(&mut self, image: &RgbImage) -> Result<(), DecoderError> {
+        assert_eq!(
+            image.dimensions(),
+            (self.width, self.height),
+            "frame size changed during encoding"
+        );
+        let stdin = self.stdin.as_mut().expect("encoder is not finished");
+        if let Err(e) = stdin.write_all(image.as_raw()) {
+            error!(path = ?self.path, %e, "failed to write frame to ffmpeg");
+            return Err(e.into());
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Close the input and wait for ffmpeg to finish writing the file.
+    pub fn finish(mut self) -> Result<(), DecoderError> {
+        drop(self.stdin.take());
+        let mut stderr = String::new();
+        if let Some(mut pipe) = self.child.stderr.take() {
+            // Reaches EOF when ffmpeg exits; errors are logged at `-v error` only.
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        let status = self.child.wait()?;
+        if !status.success() {
+            error!(path = ?self.path, %stderr, "ffmpeg encoder failed");
+            return Err(DecoderError::ToolFailed {
+                program: "ffmpeg",
+                stderr,
+            });
+        }
+        info!(path = ?self.path, frames = self.frame_count, "video encoded");
+        Ok(())
+    }
+}
+
+impl Drop for VideoEncoder {
+    fn drop(&mut self) {
+        if self.stdin.is_some() {
+            info!(path = ?self.path, frames = self.frame_count, "aborting video encoder");
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
@@ -1,5 +1,7 @@
 use image::RgbImage;
 
+use crate::error::DecoderError;
+
 /// A single decoded video frame with metadata.
 pub struct Frame {
     /// The frame's image data.
@@ -9,3 +11,13 @@ pub struct Frame {
     /// Elapsed seconds from the start of the source.
     pub timestamp_seconds: f64,
 }
+
+/// A sequence of decoded frames, e.g. a video file or a live capture.
+pub trait FrameSource {
+    /// The next frame, or `None` when the source is exhausted.
+    fn next_frame(&mut self) -> Result<Option<Frame>, DecoderError>;
+
+    /// Hand a frame that is no longer needed back, so its buffer can be reused for a
+    /// later frame. Sources without a buffer pool drop it.
+    fn recycle(&mut self, _frame: Frame) {}
+}
@@ -0,0 +1,218 @@
+//! A directory of numbered images as a video, read without ffmpeg. Frames are the
+//! images in order of the number in their file name, played at [`SEQUENCE_FPS`].
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, error, info};
+
+use super::decoder::{write_raw_frame, ContentCrop, ProbeResult};
+use crate::error::DecoderError;
+
+/// Frame rate of an image sequence, which has no timing of its own: the game's 60fps,
+/// so frame numbers map to game time.
+pub const SEQUENCE_FPS: f64 = 60.0;
+/// File extensions read as frames.
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "bmp"];
+
+/// Whether `input` is an image sequence (a directory) rather than a video file.
+pub fn is_image_sequence(input: &Path) -> bool {
+    input.is_dir()
+}
+
+/// Image files of `dir` in frame order: by the last number in the file name (so
+/// `frame2.png` comes before `frame10.png`), then by name.
+pub fn list_frames(dir: &Path) -> Result<Vec<PathBuf>, DecoderError> {
+    let unreadable = |e: std::io::Error| {
+        error!(?dir, %e, "failed to list image sequence");
+        DecoderError::InvalidVideo(format!("failed to list {}: {e}", dir.display()))
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(unreadable)? {
+        let path = entry.map_err(unreadable)?.path();
+        let is_image = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+        if is_image && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort_by_cached_key(|p| {
+        let name = p
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        (frame_index(&name), name)
+    });
+    if files.is_empty() {
+        error!(?dir, "no images in image sequence directory");
+        return Err(DecoderError::InvalidVideo(format!(
+            "no {} images in {}",
+            IMAGE_EXTENSIONS.join("/"),
+            dir.display()
+        )));
+    }
+    Ok(files)
+}
+
+/// Last run of digits in `name`'s stem, or 0 without one.
+fn frame_index(name: &str) -> u64 {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let digits: String = stem
+        .chars()
+        .rev()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits
+        .chars()
+        .rev()
+        .collect::<String>()
+        .parse()
+        .unwrap_or(0)
+}
+
+/// Size and length of the image sequence in `dir`, from its first image.
+pub fn probe(dir: &Path) -> Result<ProbeResult, DecoderError> {
+    info!(?dir, "probing image sequence");
+    let files = list_frames(dir)?;
+    let (width, height) = image::image_dimensions(&files[0]).map_err(|e| {
+        error!(path = ?files[0], %e, "failed to read image");
+        DecoderError::InvalidVideo(format!("failed to read {}: {e}", files[0].display()))
+    })?;
+    let result = ProbeResult {
+        width,
+        height,
+        fps: SEQUENCE_FPS,
+        frame_count: Some(files.len() as u64),
+    };
+    info!(?result, "probe completed");
+    Ok(result)
+}
+
+/// The images of a sequence decoded one by one into raw RGB24 frames.
+pub struct ImageSequence {
+    files: Vec<PathBuf>,
+    /// Index of the next image to decode.
+    next: usize,
+    frame_step: usize,
+    width: u32,
+    height: u32,
+    crop: Option<ContentCrop>,
+    row_bands: Vec<Range<u32>>,
+}
+
+impl ImageSequence {
+    /// Open the sequence in `dir` of `width`x`height` images at frame `start_frame`,
+    /// returning only frames whose number is a multiple of `frame_step`.
+    pub fn open(
+        dir: &Path,
+        (width, height): (u32, u32),
+        start_frame: u32,
+        frame_step: u32,
+        crop: Option<ContentCrop>,
+        row_bands: &[Range<u32>],
+    ) -> Result<Self, DecoderError> {
+        assert!(frame_step > 0, "frame step must be > 0");
+        let files = list_frames(dir)?;
+        let next = start_frame.next_multiple_of(frame_step) as usize;
+        info!(
+            ?dir,
+            images = files.len(),
+            start_frame,
+            frame_step,
+            "image sequence opened"
+        );
+        Ok(Self {
+            files,
+            next,
+            frame_step: frame_step as usize,
+            width,
+            height,
+            crop,
+            row_bands: row_bands.to_vec(),
+        })
+    }
+
+    /// Fill `buf` with the next selected image as packed RGB24 rows (only the row bands
+    /// when set). Returns false after the last image.
+    pub fn read_frame(&mut self, buf: &mut [u8]) -> Result<bool, DecoderError> {
+        let Some(path) = self.files.get(self.next) else {
+            info!(images = self.files.len(), "image sequence ended");
+            return Ok(false);
+        };
+        let image = image::open(path)
+            .map_err(|e| {
+                error!(?path, %e, "failed to decode image");
+                DecoderError::InvalidVideo(format!("failed to decode {}: {e}", path.display()))
+            })?
+            .into_rgb8();
+        if image.dimensions() != (self.width, self.height) {
+            error!(
+                ?path,
+                size = ?image.dimensions(),
+                expected = ?(self.width, self.height),
+                "image size differs from the sequence"
+            );
+            return Err(DecoderError::InvalidVideo(format!(
+                "{} is {}x{}, but the sequence is {}x{}",
+                path.display(),
+                image.width(),
+                image.height(),
+                self.width,
+                self.height
+            )));
+        }
+        debug!(?path, frame = self.next, "image decoded");
+        write_raw_frame(image, self.crop, &self.row_bands, buf);
+        self.next += self.frame_step;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn frames_are_ordered_by_number() {
+        let dir = std::env::temp_dir().join(format!("recmari-sequence-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, shade) in [("f10.png", 30), ("f2.png", 20), ("f1.png", 10)] {
+            RgbImage::from_pixel(4, 2, Rgb([shade, 0, 0]))
+                .save(dir.join(name))
+                .unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not a frame").unwrap();
+
+        let names: Vec<String> = list_frames(&dir)
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["f1.png", "f2.png", "f10.png"]);
+
+        let info = probe(&dir).unwrap();
+        assert_eq!((info.width, info.height, info.frame_count), (4, 2, Some(3)));
+
+        // Every second frame from frame 1: frames 2 (f10.png) only.
+        let mut sequence = ImageSequence::open(&dir, (4, 2), 1, 2, None, &[]).unwrap();
+        let mut buf = vec![0u8; 4 * 2 * 3];
+        assert!(sequence.read_frame(&mut buf).unwrap());
+        assert_eq!(buf[0], 30);
+        assert!(!sequence.read_frame(&mut buf).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn frame_index_is_the_last_number_of_the_stem() {
+        assert_eq!(frame_index("round2_0042.png"), 42);
+        assert_eq!(frame_index("0007.jpg"), 7);
+        assert_eq!(frame_index("cover.png"), 0);
+    }
+}
@@ -0,0 +1,315 @@
+//! In-process decoding through the libav libraries (ffmpeg-next), for systems where
+//! the ffmpeg and ffprobe binaries are not on PATH. Produces the same raw RGB24 frames
+//! as the ffmpeg pipe of [`VideoDecoder`](super::decoder::VideoDecoder).
+
+use std::ops::Range;
+use std::path::Path;
+
+use ffmpeg::format::context::Input;
+use ffmpeg::format::Pixel;
+use ffmpeg::software::scaling::{self, Flags};
+use ffmpeg::util::error::EAGAIN;
+use ffmpeg::{codec, decoder, frame, media, Packet, Rational};
+use ffmpeg_next as ffmpeg;
+use image::RgbImage;
+use tracing::{debug, error, info};
+
+use super::decoder::{copy_row_bands, scale_crop, write_raw_frame, ContentCrop, ProbeResult};
+use crate::error::DecoderError;
+
+/// Video metadata of `path`, read with libavformat instead of ffprobe.
+pub fn probe(path: &Path) -> Result<ProbeResult, DecoderError> {
+    info!(?path, "probing video metadata with libav");
+    let input = open_input(path)?;
+    let stream = video_stream(&input, path)?;
+    let decoder = video_decoder(&stream, path)?;
+
+    let fps = rational_to_f64(stream.rate());
+    let duration = input.duration() as f64 / ffmpeg::ffi::AV_TIME_BASE as f64;
+    let frame_count = u64::try_from(stream.frames())
+        .ok()
+        .filter(|&n| n > 0)
+        .or_else(|| (duration > 0.0 && fps > 0.0).then(|| (duration * fps).round() as u64));
+    let result = ProbeResult {
+        width: decoder.width(),
+        height: decoder.height(),
+        fps,
+        frame_count,
+    };
+    info!(?result, "probe completed");
+    Ok(result)
+}
+
+/// The video stream of an open input, decoded and converted to RGB24 frames of the
+/// requested crop and row bands.
+pub struct LibavStream {
+    input: Input,
+    stream_index: usize,
+    decoder: decoder::Video,
+    scaler: scaling::Context,
+    time_base: f64,
+    /// Presentation time of the stream's first frame, in `time_base` units.
+    start_pts: i64,
+    /// Frames before this many seconds are decoded and dropped (accurate seeking).
+    seek_seconds: f64,
+    crop: Option<ContentCrop>,
+    row_bands: Vec<Range<u32>>,
+    frame_step: u32,
+    /// Number of the next frame at or after `seek_seconds`.
+    next_number: u32,
+    decoded: frame::Video,
+    rgb: frame::Video,
+}
+
+impl LibavStream {
+    /// Open `path` positioned at `seek_seconds`, whose frame is number `first_frame`.
+    /// Like ffmpeg's `select` filter, only frames whose number is a multiple of
+    /// `frame_step` are returned.
+    pub fn open(
+        path: &Path,
+        seek_seconds: f64,
+        first_frame: u32,
+        frame_step: u32,
+        crop: Option<ContentCrop>,
+        row_bands: &[Range<u32>],
+    ) -> Result<Self, DecoderError> {
+        assert!(frame_step > 0, "frame step must be > 0");
+        let mut input = open_input(path)?;
+        let (stream_index, time_base, start_pts, decoder) = {
+            let stream = video_stream(&input, path)?;
+            let start = stream.start_time();
+            let start_pts = if start == ffmpeg::ffi::AV_NOPTS_VALUE {
+                0
+            } else {
+                start
+            };
+            let decoder = video_decoder(&stream, path)?;
+            (
+                stream.index(),
+                rational_to_f64(stream.time_base()),
+                start_pts,
+                decoder,
+            )
+        };
+        let scaler = scaling::Context::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            Pixel::RGB24,
+            decoder.width(),
+            decoder.height(),
+            Flags::BILINEAR,
+        )
+        .map_err(libav_error(path, "create scaler for"))?;
+
+        if seek_seconds > 0.0 {
+            let target = ((seek_seconds + start_pts as f64 * time_base)
+                * ffmpeg::ffi::AV_TIME_BASE as f64) as i64;
+            // Lands on the keyframe at or before the target; frames up to it are dropped
+            // by timestamp, so a failed seek only costs decoding time.
+            if let Err(e) = input.seek(target, ..target) {
+                info!(%e, "libav seek failed, decoding from the start");
+            }
+        }
+
+        info!(
+            ?path,
+            seek_seconds,
+            first_frame,
+            frame_step,
+            width = decoder.width(),
+            height = decoder.height(),
+            "libav decoder opened"
+        );
+        Ok(Self {
+            input,
+            stream_index,
+            decoder,
+            scaler,
+            time_base,
+            start_pts,
+            seek_seconds,
+            crop,
+            row_bands: row_bands.to_vec(),
+            frame_step,
+            next_number: first_frame,
+            decoded: frame::Video::empty(),
+            rgb: frame::Video::empty(),
+        })
+    }
+
+    /// Fill `buf` with the next selected frame as packed RGB24 rows (only the row bands
+    /// when set). Returns false at the end of the stream.
+    pub fn read_frame(&mut self, buf: &mut [u8]) -> Result<bool, DecoderError> {
+        while self.decode_next()? {
+            let seconds = self
+                .decoded
+                .timestamp()
+                .map(|ts| (ts - self.start_pts) as f64 * self.time_base);
+            if self.seek_seconds > 0.0 && seconds.is_some_and(|s| s < self.seek_seconds) {
+                continue;
+            }
+            let number = self.next_number;
+            self.next_number += 1;
+            if number % self.frame_step != 0 {
+                continue;
+            }
+            self.scaler
+                .run(&self.decoded, &mut self.rgb)
+                .map_err(|e| DecoderError::InvalidVideo(format!("libav scaling failed: {e}")))?;
+            self.write_rgb(buf);
+            debug!(number, ?seconds, "libav frame decoded");
+            return Ok(true);
+        }
+        info!(frames = self.next_number, "libav stream ended");
+        Ok(false)
+    }
+
+    /// Decode the next frame into `self.decoded`. Returns false at the end of the stream.
+    fn decode_next(&mut self) -> Result<bool, DecoderError> {
+        loop {
+            match self.decoder.receive_frame(&mut self.decoded) {
+                Ok(()) => return Ok(true),
+                Err(ffmpeg::Error::Eof) => return Ok(false),
+                Err(ffmpeg::Error::Other { errno }) if errno == EAGAIN => {}
+                Err(e) => return Err(decode_error(e)),
+            }
+            let mut packet = Packet::empty();
+            match packet.read(&mut self.input) {
+                Ok(()) if packet.stream() == self.stream_index => {
+                    self.decoder.send_packet(&packet).map_err(decode_error)?
+                }
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => self.decoder.send_eof().map_err(decode_error)?,
+                Err(e) => return Err(decode_error(e)),
+            }
+        }
+    }
+
+    /// Copy the converted frame into `buf`, cropped and scaled, keeping only the row
+    /// bands. Without a crop the rows go straight from the libav frame into `buf`.
+    fn write_rgb(&self, buf: &mut [u8]) {
+        let stride = self.rgb.stride(0);
+        let data = self.rgb.data(0);
+        let Some(crop) = self.crop else {
+            let row_bytes = self.rgb.width() as usize * 3;
+            let row = |y: u32| &data[y as usize * stride..][..row_bytes];
+            copy_row_bands(row, self.rgb.height(), &self.row_bands, buf);
+            return;
+        };
+        // Scaling needs the cropped picture as an image; only the crop is copied.
+        let r = crop.rect;
+        let row_bytes = r.w as usize * 3;
+        let mut picture = RgbImage::new(r.w, r.h);
+        for (y, row) in picture.chunks_exact_mut(row_bytes).enumerate() {
+            let start = (r.y as usize + y) * stride + r.x as usize * 3;
+            row.copy_from_slice(&data[start..start + row_bytes]);
+        }
+        write_raw_frame(scale_crop(&picture, crop), None, &self.row_bands, buf);
+    }
+}
+
+fn open_input(path: &Path) -> Result<Input, DecoderError> {
+    ffmpeg::init().map_err(libav_error(path, "initialize libav for"))?;
+    ffmpeg::format::input(path).map_err(libav_error(path, "open"))
+}
+
+fn video_stream<'a>(input: &'a Input, path: &Path) -> Result<ffmpeg::Stream<'a>, DecoderError> {
+    input.streams().best(media::Type::Video).ok_or_else(|| {
+        error!(?path, "no video stream");
+        DecoderError::InvalidVideo(format!("no video stream in {}", path.display()))
+    })
+}
+
+fn video_decoder(stream: &ffmpeg::Stream, path: &Path) -> Result<decoder::Video, DecoderError> {
+    codec::context::Context::from_parameters(stream.parameters())
+        .and_then(|context| context.decoder().video())
+        .map_err(libav_error(path, "open a video decoder for"))
+}
+
+/// A rational as a float; 0.0 when unset (zero denominator).
+fn rational_to_f64(value: Rational) -> f64 {
+    if value.denominator() == 0 {
+        0.0
+    } else {
+        f64::from(value)
+    }
+}
+
+fn libav_error(path: &Path, action: &'static str) -> impl FnOnce(ffmpeg::Error) -> DecoderError {
+    let path = path.to_path_buf();
+    move |e| {
+        error!(?path, %e, "failed to {action} video");
+        DecoderError::InvalidVideo(format!("failed to {action} {}: {e}", path.display()))
+    }
+}
+
+fn decode_error(e: ffmpeg::Error) -> DecoderError {
+    error!(%e, "libav decoding failed");
+    DecoderError::InvalidVideo(format!("libav decoding failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use image::Rgb;
+
+    use super::*;
+    use crate::video::decoder::{DecoderConfig, VideoDecoder};
+    use crate::video::encoder::VideoEncoder;
+
+    const FRAMES: u32 = 10;
+
+    /// Gray level of frame `n` of the test video.
+    fn level(n: u32) -> u8 {
+        20 + n as u8 * 20
+    }
+
+    /// A 64x32 video of [`FRAMES`] flat gray frames at 30fps, encoded with the ffmpeg
+    /// binary (installed alongside the libav development files).
+    fn test_video(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("recmari-libav-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let mut encoder = VideoEncoder::create(&path, 64, 32, 30.0).unwrap();
+        for n in 0..FRAMES {
+            let level = level(n);
+            encoder
+                .write_frame(&RgbImage::from_pixel(64, 32, Rgb([level; 3])))
+                .unwrap();
+        }
+        encoder.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn probes_size_rate_and_length() {
+        let path = test_video("probe.mp4");
+        let info = probe(&path).unwrap();
+        assert_eq!((info.width, info.height), (64, 32));
+        assert!((info.fps - 30.0).abs() < 1e-6);
+        assert_eq!(info.frame_count, Some(FRAMES as u64));
+    }
+
+    #[test]
+    fn decodes_selected_frames_and_row_bands() {
+        let path = test_video("decode.mp4");
+        let config = DecoderConfig {
+            start_frame: 0,
+            row_bands: vec![4..8],
+            crop: None,
+            frame_step: 2,
+        };
+        let mut decoder = VideoDecoder::open_with(&path, &config).unwrap();
+        let mut numbers = Vec::new();
+        while let Some(frame) = decoder.next_frame().unwrap() {
+            // H.264 is lossy; flat frames come back within a few levels.
+            let [band, ..] = frame.image.get_pixel(10, 5).0;
+            assert!(band.abs_diff(level(frame.frame_number)) <= 4, "{band}");
+            assert_eq!(frame.image.get_pixel(10, 20).0, [0; 3]);
+            numbers.push(frame.frame_number);
+        }
+        assert_eq!(numbers, [0, 2, 4, 6, 8]);
+    }
+}
@@ -1,2 +1,28 @@
+#[cfg(feature = "video")]
+pub mod audio;
+#[cfg(feature = "video")]
+pub mod clip;
+#[cfg(feature = "video")]
 pub mod decoder;
+pub mod dedup;
+#[cfg(feature = "video")]
+pub mod encoder;
 pub mod frame;
+#[cfg(feature = "video")]
+pub mod image_sequence;
+#[cfg(feature = "libav")]
+pub mod libav;
+#[cfg(feature = "video")]
+pub mod refine;
+#[cfg(feature = "url-input")]
+pub mod remote;
+
+use std::path::Path;
+
+/// Whether `input` is an http(s) URL rather than a local path. ffmpeg and ffprobe open
+/// such inputs directly.
+pub fn is_url(input: &Path) -> bool {
+    input
+        .to_str()
+        .is_some_and(|s| s.starts_with("https://") || s.starts_with("http://"))
+}
@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use tracing::info;
+
+use crate::error::DecoderError;
+use crate::video::decoder::{DecoderConfig, VideoDecoder};
+use crate::video::frame::Frame;
+
+/// Seek to `after + 1` and decode every frame up to `until` (inclusive), returning the
+/// first one for which `is_target` holds.
+///
+/// Used to refine a state change seen between two sampled frames to the exact frame.
+pub fn first_matching_frame(
+    path: &Path,
+    config: &DecoderConfig,
+    after: u32,
+    until: u32,
+    mut is_target: impl FnMut(&Frame) -> bool,
+) -> Result<Option<Frame>, DecoderError> {
+    assert!(after < until, "empty refinement range ({after}, {until}]");
+    let seek_config = DecoderConfig {
+        start_frame: after + 1,
+        frame_step: 1,
+        ..config.clone()
+    };
+    let mut decoder = VideoDecoder::open_with(path, &seek_config)?;
+
+    while let Some(frame) = decoder.next_frame()? {
+        if frame.frame_number > until {
+            break;
+        }
+        if is_target(&frame) {
+            info!(
+                after,
+                until,
+                found = frame.frame_number,
+                "refined transition frame"
+            );
+            return Ok(Some(frame));
+        }
+        decoder.recycle(frame);
+    }
+
+    info!(after, until, "no frame in refinement range matched");
+    Ok(None)
+}
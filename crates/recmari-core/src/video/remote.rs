@@ -0,0 +1,80 @@
+//! VOD page URLs (YouTube, Twitch) resolved to a media stream ffmpeg can read directly.
+
+use std::process::{Command, Stdio};
+
+use tracing::{error, info};
+
+use crate::error::DecoderError;
+
+/// yt-dlp format selector: 1080p video only, preferring 60 fps. Audio is never decoded.
+const FORMAT: &str = "bestvideo[height=1080][fps>50]/bestvideo[height=1080]/best[height=1080]";
+
+/// Resolve a VOD page URL to the direct URL of its 1080p video stream with yt-dlp.
+///
+/// The pipeline opens its input several times (probe, crop detection, decoding,
+/// event refinement), so it is given a seekable URL rather than a one-shot pipe.
+pub fn resolve_stream_url(page_url: &str) -> Result<String, DecoderError> {
+    info!(page_url, "resolving stream URL with yt-dlp");
+
+    let output = Command::new("yt-dlp")
+        .args(["--no-playlist", "--no-warnings", "--get-url", "-f", FORMAT])
+        .arg(page_url)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|source| DecoderError::ToolMissing {
+            program: "yt-dlp",
+            source,
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!(%stderr, page_url, "yt-dlp failed");
+        return Err(DecoderError::ToolFailed {
+            program: "yt-dlp",
+            stderr: stderr.into_owned(),
+        });
+    }
+
+    let stream_url = parse_stream_url(&String::from_utf8_lossy(&output.stdout))?;
+    info!(page_url, "resolved stream URL");
+    Ok(stream_url)
+}
+
+/// The video stream URL from yt-dlp's `--get-url` output: the first line, which is the
+/// video stream when a format selector yields separate video and audio streams.
+fn parse_stream_url(stdout: &str) -> Result<String, DecoderError> {
+    let Some(url) = stdout.lines().map(str::trim).find(|l| !l.is_empty()) else {
+        error!("yt-dlp printed no stream URL");
+        return Err(DecoderError::InvalidVideo(
+            "yt-dlp printed no stream URL".into(),
+        ));
+    };
+    if !super::is_url(std::path::Path::new(url)) {
+        error!(url, "yt-dlp printed something other than a URL");
+        return Err(DecoderError::InvalidVideo(format!(
+            "yt-dlp printed something other than a URL: {url}"
+        )));
+    }
+    Ok(url.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_the_video_stream_of_split_formats() {
+        let stdout = "https://rr1.example/videoplayback?itag=299\nhttps://rr1.example/videoplayback?itag=140\n";
+        assert_eq!(
+            parse_stream_url(stdout).unwrap(),
+            "https://rr1.example/videoplayback?itag=299"
+        );
+    }
+
+    #[test]
+    fn rejects_empty_or_non_url_output() {
+        assert!(parse_stream_url("\n").is_err());
+        assert!(parse_stream_url("ERROR: unavailable\n").is_err());
+    }
+}
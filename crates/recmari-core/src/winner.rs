@@ -0,0 +1,214 @@
+use tracing::{info, warn};
+
+use recmari_proto::proto::{
+    EventType, FrameData, FrameStatus, Match, Round, Winner, WinnerSignal, WinnerSignalSource,
+};
+
+/// Number of round wins required to win a match.
+pub const ROUNDS_TO_WIN: u32 = 2;
+/// A final health gap this large makes the health signal fully confident; smaller gaps
+/// scale down linearly, since a misread bar flips close calls.
+const HEALTH_CONFIDENT_GAP: f64 = 0.1;
+/// Confidence factor of a health comparison carried over from earlier frames.
+const STALE_HEALTH_FACTOR: f64 = 0.5;
+/// Confidence of a KO event, which needs a bar read as empty.
+const KO_CONFIDENCE: f64 = 0.9;
+
+/// Every winner signal available for `round`. Sources without an opinion (equal final
+/// health, no KO or a double KO) are left out.
+pub fn winner_signals(round: &Round) -> Vec<WinnerSignal> {
+    let mut signals = Vec::new();
+    if let Some(signal) = health_signal(&round.frames) {
+        signals.push(signal);
+    }
+    let mut knocked_out = round
+        .events
+        .iter()
+        .filter(|e| e.r#type() == EventType::Ko)
+        .map(|e| e.player);
+    if let Some(first) = knocked_out.next() {
+        let winner = match first {
+            1 => Winner::P2,
+            2 => Winner::P1,
+            _ => Winner::Unknown,
+        };
+        if winner != Winner::Unknown && knocked_out.all(|p| p == first) {
+            signals.push(signal(WinnerSignalSource::Ko, winner, KO_CONFIDENCE));
+        }
+    }
+    signals
+}
+
+fn health_signal(frames: &[FrameData]) -> Option<WinnerSignal> {
+    let (fd, p1, p2) = frames.iter().rev().find_map(|fd| {
+        let p1 = fd.player1.as_ref()?.health_ratio?;
+        let p2 = fd.player2.as_ref()?.health_ratio?;
+        Some((fd, p1, p2))
+    })?;
+    let winner = if p1 > p2 {
+        Winner::P1
+    } else if p2 > p1 {
+        Winner::P2
+    } else {
+        return None;
+    };
+    let mut confidence = ((p1 - p2).abs() / HEALTH_CONFIDENT_GAP).min(1.0);
+    if fd.status() == FrameStatus::Stale {
+        confidence *= STALE_HEALTH_FACTOR;
+    }
+    Some(signal(WinnerSignalSource::Health, winner, confidence))
+}
+
+fn signal(source: WinnerSignalSource, winner: Winner, confidence: f64) -> WinnerSignal {
+    WinnerSignal {
+        source: source.into(),
+        winner: winner.into(),
+        confidence,
+    }
+}
+
+/// The winner with the highest total confidence (Unknown on a tie or without
+/// signals), and whether the signals name different winners.
+pub fn arbitrate(signals: &[WinnerSignal]) -> (Winner, bool) {
+    let total = |winner: Winner| -> f64 {
+        signals
+            .iter()
+            .filter(|s| s.winner() == winner)
+            .map(|s| s.confidence)
+            .sum()
+    };
+    let (p1, p2) = (total(Winner::P1), total(Winner::P2));
+    let winner = if p1 > p2 {
+        Winner::P1
+    } else if p2 > p1 {
+        Winner::P2
+    } else {
+        Winner::Unknown
+    };
+    (winner, p1 > 0.0 && p2 > 0.0)
+}
+
+/// Record the winner signals of every round, replace round winners with the
+/// arbitration result and recount match winners. Call after the round events are
+/// attached.
+pub fn attach_winner_arbitration(matches: &mut [Match]) {
+    let mut disputed = 0usize;
+    let mut changed = 0usize;
+    for (match_index, m) in matches.iter_mut().enumerate() {
+        for round in &mut m.rounds {
+            round.winner_signals = winner_signals(round);
+            let (winner, is_disputed) = if round.draw {
+                (Winner::Unknown, false)
+            } else {
+                arbitrate(&round.winner_signals)
+            };
+            if is_disputed {
+                warn!(
+                    match_number = match_index + 1,
+                    round = round.round_index + 1,
+                    signals = ?round.winner_signals,
+                    "winner signals disagree"
+                );
+                disputed += 1;
+            }
+            if winner != round.winner() {
+                changed += 1;
+            }
+            round.winner = winner.into();
+            round.winner_disputed = is_disputed;
+        }
+        m.winner = match_winner(&m.rounds).into();
+    }
+    info!(
+        matches = matches.len(),
+        disputed, changed, "round winners arbitrated"
+    );
+}
+
+/// The first player to [`ROUNDS_TO_WIN`] round wins, if any.
+pub fn match_winner(rounds: &[Round]) -> Winner {
+    let wins = |winner: Winner| rounds.iter().filter(|r| r.winner() == winner).count() as u32;
+    if wins(Winner::P1) >= ROUNDS_TO_WIN {
+        Winner::P1
+    } else if wins(Winner::P2) >= ROUNDS_TO_WIN {
+        Winner::P2
+    } else {
+        Winner::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::RoundEvent;
+
+    use super::*;
+    use crate::test_support::hp_frame;
+
+    fn ko(player: u32) -> RoundEvent {
+        RoundEvent {
+            r#type: EventType::Ko.into(),
+            player,
+            ..Default::default()
+        }
+    }
+
+    fn round(frames: Vec<FrameData>, events: Vec<RoundEvent>) -> Round {
+        Round {
+            frames,
+            events,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn agreeing_signals_are_not_disputed() {
+        let r = round(vec![hp_frame(0.0, 0.4, 0.0)], vec![ko(2)]);
+        let signals = winner_signals(&r);
+        assert_eq!(signals.len(), 2);
+        assert_eq!(signals[0].source(), WinnerSignalSource::Health);
+        assert_eq!(signals[0].confidence, 1.0);
+        assert_eq!(signals[1].source(), WinnerSignalSource::Ko);
+        assert_eq!(arbitrate(&signals), (Winner::P1, false));
+    }
+
+    #[test]
+    fn disagreement_goes_to_the_more_confident_signal() {
+        // A stale, close health reading against a KO of player 1.
+        let stale = FrameData {
+            status: FrameStatus::Stale.into(),
+            ..hp_frame(0.0, 0.05, 0.02)
+        };
+        let r = round(vec![stale], vec![ko(1)]);
+        let signals = winner_signals(&r);
+        assert!((signals[0].confidence - 0.15).abs() < 1e-9);
+        assert_eq!(arbitrate(&signals), (Winner::P2, true));
+    }
+
+    #[test]
+    fn double_ko_and_equal_health_give_no_signal() {
+        let r = round(vec![hp_frame(0.0, 0.0, 0.0)], vec![ko(1), ko(2)]);
+        assert!(winner_signals(&r).is_empty());
+        assert_eq!(arbitrate(&[]), (Winner::Unknown, false));
+    }
+
+    #[test]
+    fn arbitration_recounts_the_match_winner() {
+        let mut matches = vec![Match {
+            rounds: vec![
+                round(vec![hp_frame(0.0, 0.5, 0.2)], vec![]),
+                // Health was misread as a P1 lead, but P1 was KO'd.
+                round(vec![hp_frame(0.0, 0.03, 0.0)], vec![ko(1)]),
+                round(vec![hp_frame(0.0, 0.0, 0.6)], vec![ko(1)]),
+            ],
+            winner: Winner::P1.into(),
+            ..Default::default()
+        }];
+        attach_winner_arbitration(&mut matches);
+        let m = &matches[0];
+        let winners: Vec<Winner> = m.rounds.iter().map(|r| r.winner()).collect();
+        assert_eq!(winners, [Winner::P1, Winner::P2, Winner::P2]);
+        assert!(m.rounds[1].winner_disputed);
+        assert!(!m.rounds[2].winner_disputed);
+        assert_eq!(m.winner(), Winner::P2);
+    }
+}
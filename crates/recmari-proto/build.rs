@@ -1,4 +1,5 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=../../proto/recmari.proto");
     prost_build::compile_protos(&["../../proto/recmari.proto"], &["../../proto/"])?;
     Ok(())
 }
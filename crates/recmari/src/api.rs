@@ -0,0 +1,593 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use recmari_core::output::MatchReader;
+
+use crate::batch::run_analyze;
+use crate::serve::{read_head, respond, READ_TIMEOUT};
+
+/// Largest JSON request body accepted (uploads are streamed to disk instead).
+const MAX_JSON_BODY: u64 = 64 * 1024;
+/// Connections handled at once; more are answered with 503 and closed.
+const MAX_CONNECTIONS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl JobState {
+    fn name(self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+        }
+    }
+}
+
+/// One submitted video and where its analysis goes.
+struct Job {
+    id: usize,
+    input: PathBuf,
+    output: PathBuf,
+    log: PathBuf,
+    state: JobState,
+    matches: Option<usize>,
+    error: Option<String>,
+}
+
+impl Job {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "input": self.input.display().to_string(),
+            "status": self.state.name(),
+            "matches": self.matches,
+            "error": self.error,
+        })
+    }
+}
+
+/// Shared state of the API server.
+pub struct ApiServer {
+    exe: PathBuf,
+    work_dir: PathBuf,
+    analyze_args: Vec<OsString>,
+    jobs: Mutex<Vec<Job>>,
+    queue: Sender<usize>,
+    /// Numbers upload files so concurrent uploads never share a path.
+    uploads: AtomicUsize,
+    /// Largest accepted upload in bytes.
+    max_upload: u64,
+    /// Connections being handled, capped at [`MAX_CONNECTIONS`].
+    connections: Arc<AtomicUsize>,
+}
+
+/// Counts a connection as handled until dropped.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    /// Take a slot, or None when [`MAX_CONNECTIONS`] are already handled.
+    fn acquire(connections: &Arc<AtomicUsize>) -> Option<Self> {
+        connections
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| ConnectionSlot(Arc::clone(connections)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Serve the job API until the process is killed. Videos are analyzed by `workers`
+/// background `recmari analyze` processes; outputs and logs go to `work_dir`.
+/// Uploads larger than `max_upload` bytes are refused.
+pub fn serve_api(
+    listener: TcpListener,
+    work_dir: &Path,
+    workers: usize,
+    max_upload: u64,
+    analyze_args: Vec<OsString>,
+) -> Result<()> {
+    std::fs::create_dir_all(work_dir.join("uploads"))
+        .with_context(|| format!("failed to create {}", work_dir.display()))?;
+    let (queue, pending) = mpsc::channel();
+    let server = Arc::new(ApiServer {
+        exe: std::env::current_exe().context("failed to locate the recmari executable")?,
+        work_dir: work_dir.to_path_buf(),
+        analyze_args,
+        jobs: Mutex::new(Vec::new()),
+        queue,
+        uploads: AtomicUsize::new(0),
+        max_upload,
+        connections: Arc::default(),
+    });
+    let pending = Arc::new(Mutex::new(pending));
+    for _ in 0..workers.max(1) {
+        let server = Arc::clone(&server);
+        let pending = Arc::clone(&pending);
+        thread::spawn(move || run_jobs(&server, &pending));
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(%e, "failed to accept connection");
+                continue;
+            }
+        };
+        let Some(slot) = ConnectionSlot::acquire(&server.connections) else {
+            warn!(max = MAX_CONNECTIONS, "too many connections, refusing");
+            refuse(stream);
+            continue;
+        };
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(e) = handle(stream, &server) {
+                debug!(%e, "connection closed");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Answer a connection over [`MAX_CONNECTIONS`] without reading its request.
+fn refuse(mut stream: TcpStream) {
+    let sent = stream
+        .set_write_timeout(Some(READ_TIMEOUT))
+        .and_then(|()| send_error(&mut stream, "503 Service Unavailable", "server busy"));
+    if let Err(e) = sent {
+        debug!(%e, "failed to refuse connection");
+    }
+}
+
+/// Analyze queued jobs one at a time until the server exits.
+fn run_jobs(server: &ApiServer, pending: &Mutex<Receiver<usize>>) {
+    loop {
+        let Ok(id) = pending.lock().unwrap().recv() else {
+            return;
+        };
+        let (input, output, log) = {
+            let mut jobs = server.jobs.lock().unwrap();
+            let job = &mut jobs[id];
+            job.state = JobState::Running;
+            (job.input.clone(), job.output.clone(), job.log.clone())
+        };
+        info!(id, ?input, "job started");
+        let result = run_analyze(&server.exe, &input, &output, &log, &server.analyze_args)
+            .and_then(|()| Ok(MatchReader::open(&output)?.len()));
+        let mut jobs = server.jobs.lock().unwrap();
+        let job = &mut jobs[id];
+        match result {
+            Ok(matches) => {
+                job.state = JobState::Done;
+                job.matches = Some(matches);
+                info!(id, matches, "job finished");
+            }
+            Err(e) => {
+                job.state = JobState::Failed;
+                job.error = Some(format!("{e:#}"));
+                warn!(id, error = %e, "job failed");
+            }
+        }
+    }
+}
+
+impl ApiServer {
+    /// Register a job for `input` and queue it.
+    fn submit(&self, input: PathBuf) -> Value {
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = jobs.len();
+        let job = Job {
+            id,
+            input,
+            output: self.work_dir.join(format!("job{id}.pb")),
+            log: self.work_dir.join(format!("job{id}.log")),
+            state: JobState::Queued,
+            matches: None,
+            error: None,
+        };
+        info!(id, input = ?job.input, "job queued");
+        let body = job.to_json();
+        jobs.push(job);
+        // Workers hold the receiver for the life of the process.
+        self.queue.send(id).expect("job workers stopped");
+        body
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    /// None when the request has no `Content-Length`.
+    content_length: Option<u64>,
+}
+
+fn handle(stream: TcpStream, server: &ApiServer) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut out = stream;
+    let request = match read_request(&mut reader) {
+        Ok(request) => request,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            warn!(%e, "malformed api request");
+            return send_error(&mut out, "400 Bad Request", &e.to_string());
+        }
+        Err(e) => return Err(e),
+    };
+    info!(method = %request.method, path = %request.path, "api request");
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["jobs"]) => {
+            let jobs: Vec<Value> = server
+                .jobs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(Job::to_json)
+                .collect();
+            send_json(&mut out, "200 OK", &Value::Array(jobs))
+        }
+        ("POST", ["jobs"]) => {
+            let length = request.content_length.unwrap_or(0);
+            if length > MAX_JSON_BODY {
+                return send_error(&mut out, "413 Payload Too Large", "request body too large");
+            }
+            let mut body = Vec::new();
+            reader.take(length).read_to_end(&mut body)?;
+            let path = serde_json::from_slice::<Value>(&body)
+                .ok()
+                .and_then(|v| v.get("path")?.as_str().map(PathBuf::from));
+            match path {
+                Some(path) if path.is_file() => {
+                    send_json(&mut out, "201 Created", &server.submit(path))
+                }
+                Some(path) => send_error(
+                    &mut out,
+                    "400 Bad Request",
+                    &format!("video not found: {}", path.display()),
+                ),
+                None => send_error(&mut out, "400 Bad Request", "expected {\"path\": \"...\"}"),
+            }
+        }
+        ("POST", ["jobs", "upload"]) => {
+            let Some(length) = request.content_length else {
+                return send_error(
+                    &mut out,
+                    "411 Length Required",
+                    "upload needs a Content-Length",
+                );
+            };
+            if length > server.max_upload {
+                warn!(length, max = server.max_upload, "upload too large");
+                return send_error(&mut out, "413 Payload Too Large", "upload too large");
+            }
+            let name = upload_name(&request.query);
+            let number = server.uploads.fetch_add(1, Ordering::Relaxed);
+            let path = server
+                .work_dir
+                .join("uploads")
+                .join(format!("{number}-{name}"));
+            if save_upload(&mut reader, &path, length)? < length {
+                return send_error(&mut out, "400 Bad Request", "upload ended early");
+            }
+            send_json(&mut out, "201 Created", &server.submit(path))
+        }
+        ("GET", ["jobs", id]) => match find_job(server, id, |job| job.to_json()) {
+            Some(body) => send_json(&mut out, "200 OK", &body),
+            None => send_error(&mut out, "404 Not Found", "no such job"),
+        },
+        ("GET", ["jobs", id, "result"]) => {
+            match find_job(server, id, |job| (job.state, job.output.clone())) {
+                Some((JobState::Done, output)) => {
+                    let bytes = std::fs::read(output)?;
+                    respond(&mut out, "200 OK", "application/x-protobuf", &bytes)
+                }
+                Some(_) => send_error(&mut out, "409 Conflict", "job has no result yet"),
+                None => send_error(&mut out, "404 Not Found", "no such job"),
+            }
+        }
+        _ => send_error(&mut out, "404 Not Found", "not found"),
+    }
+}
+
+/// Read the request head (see [`read_head`]). A malformed `Content-Length` is an
+/// `InvalidData` error too.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let head = read_head(reader)?;
+    let content_length = head
+        .header("content-length")
+        .map(|value| {
+            value.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed Content-Length: {value}"),
+                )
+            })
+        })
+        .transpose()?;
+    let (path, query) = head.target.split_once('?').unwrap_or((&head.target, ""));
+    Ok(Request {
+        path: path.to_string(),
+        query: query.to_string(),
+        method: head.method,
+        content_length,
+    })
+}
+
+/// Stream `length` bytes of an upload body to `path`; the number of bytes written.
+/// The partial file is removed when the copy fails or the body ends early.
+fn save_upload(reader: &mut impl Read, path: &Path, length: u64) -> io::Result<u64> {
+    let mut file = File::create(path)?;
+    let copied = io::copy(&mut reader.take(length), &mut file);
+    drop(file);
+    match copied {
+        Ok(copied) if copied == length => Ok(copied),
+        Ok(copied) => {
+            warn!(?path, copied, length, "upload ended early");
+            let _ = std::fs::remove_file(path);
+            Ok(copied)
+        }
+        Err(e) => {
+            warn!(?path, error = %e, "upload failed");
+            let _ = std::fs::remove_file(path);
+            Err(e)
+        }
+    }
+}
+
+/// File name for an upload from its `name=` query parameter, reduced to characters
+/// that are safe in a path.
+fn upload_name(query: &str) -> String {
+    let name = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("name="))
+        .unwrap_or("upload.mp4");
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if safe.trim_matches('.').is_empty() {
+        "upload.mp4".to_string()
+    } else {
+        safe
+    }
+}
+
+fn find_job<T>(server: &ApiServer, id: &str, f: impl Fn(&Job) -> T) -> Option<T> {
+    let id: usize = id.parse().ok()?;
+    server.jobs.lock().unwrap().get(id).map(f)
+}
+
+fn send_json(out: &mut TcpStream, status: &str, body: &Value) -> io::Result<()> {
+    respond(out, status, "application/json", body.to_string().as_bytes())
+}
+
+fn send_error(out: &mut TcpStream, status: &str, message: &str) -> io::Result<()> {
+    send_json(out, status, &json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn head(text: &str) -> io::Result<Request> {
+        read_request(&mut text.as_bytes())
+    }
+
+    #[test]
+    fn head_splits_target_and_reads_length() {
+        let request =
+            head("POST /jobs/upload?name=a.mp4 HTTP/1.1\r\ncontent-length: 42\r\n\r\nbody")
+                .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/jobs/upload");
+        assert_eq!(request.query, "name=a.mp4");
+        assert_eq!(request.content_length, Some(42));
+
+        let request = head("GET /jobs HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(
+            (request.path.as_str(), request.query.as_str()),
+            ("/jobs", "")
+        );
+        assert_eq!(request.content_length, None);
+    }
+
+    #[test]
+    fn malformed_length_is_invalid_data() {
+        for length in ["-1", "12abc", ""] {
+            let e = head(&format!(
+                "POST /jobs HTTP/1.1\r\nContent-Length: {length}\r\n\r\n"
+            ))
+            .err()
+            .unwrap();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{length:?}");
+        }
+    }
+
+    #[test]
+    fn connection_slots_are_capped_and_released() {
+        let connections = Arc::default();
+        let slots: Vec<ConnectionSlot> = (0..MAX_CONNECTIONS)
+            .map(|_| ConnectionSlot::acquire(&connections).unwrap())
+            .collect();
+        assert!(ConnectionSlot::acquire(&connections).is_none());
+        drop(slots);
+        assert_eq!(connections.load(Ordering::Acquire), 0);
+        assert!(ConnectionSlot::acquire(&connections).is_some());
+    }
+
+    #[test]
+    fn upload_name_keeps_only_safe_characters() {
+        assert_eq!(upload_name("name=match.mp4"), "match.mp4");
+        assert_eq!(upload_name("x=1&name=../../etc/passwd"), ".._.._etc_passwd");
+        assert_eq!(upload_name("name=a%20b.mp4"), "a_20b.mp4");
+        assert_eq!(upload_name("name=.."), "upload.mp4");
+        assert_eq!(upload_name(""), "upload.mp4");
+    }
+
+    /// A reader that yields its bytes and then fails.
+    struct FailingReader<'a>(&'a [u8]);
+
+    impl Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn save_upload_removes_partial_files() {
+        let dir = std::env::temp_dir().join(format!("recmari-api-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("upload.mp4");
+
+        assert_eq!(save_upload(&mut &b"video"[..], &path, 5).unwrap(), 5);
+        assert_eq!(std::fs::read(&path).unwrap(), b"video");
+
+        assert_eq!(save_upload(&mut &b"vid"[..], &path, 5).unwrap(), 3);
+        assert!(!path.exists());
+
+        let e = save_upload(&mut FailingReader(b"vid"), &path, 5).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::ConnectionReset);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A server without workers, so submitted jobs stay queued.
+    fn test_server(name: &str, max_upload: u64) -> (Arc<ApiServer>, Receiver<usize>) {
+        let work_dir =
+            std::env::temp_dir().join(format!("recmari-api-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(work_dir.join("uploads")).unwrap();
+        let (queue, pending) = mpsc::channel();
+        let server = ApiServer {
+            exe: PathBuf::from("recmari"),
+            work_dir,
+            analyze_args: Vec::new(),
+            jobs: Mutex::new(Vec::new()),
+            queue,
+            uploads: AtomicUsize::new(0),
+            max_upload,
+            connections: Arc::default(),
+        };
+        (Arc::new(server), pending)
+    }
+
+    /// Send `request` to a connection handled by `server`; the status line and body.
+    fn exchange(server: &Arc<ApiServer>, request: &[u8]) -> (String, String) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let handler = {
+            let server = Arc::clone(server);
+            thread::spawn(move || handle(stream, &server))
+        };
+        client.write_all(request).unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        handler.join().unwrap().unwrap();
+        let status = response.lines().next().unwrap_or_default().to_string();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn routes_job_requests() {
+        let (server, pending) = test_server("routes", 1024);
+        let status = |request: &str| exchange(&server, request.as_bytes()).0;
+
+        assert_eq!(status("GET /jobs HTTP/1.1\r\n\r\n"), "HTTP/1.1 200 OK");
+        assert_eq!(
+            status("GET /jobs/0 HTTP/1.1\r\n\r\n"),
+            "HTTP/1.1 404 Not Found"
+        );
+        assert_eq!(
+            status("GET /nope HTTP/1.1\r\n\r\n"),
+            "HTTP/1.1 404 Not Found"
+        );
+        assert_eq!(
+            status("POST /jobs HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}"),
+            "HTTP/1.1 400 Bad Request"
+        );
+        assert_eq!(
+            status("POST /jobs HTTP/1.1\r\nContent-Length: x\r\n\r\n"),
+            "HTTP/1.1 400 Bad Request"
+        );
+
+        let (status, body) = exchange(
+            &server,
+            b"POST /jobs/upload?name=m.mp4 HTTP/1.1\r\nContent-Length: 5\r\n\r\nvideo",
+        );
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        let job: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(job["status"], "queued");
+        assert_eq!(pending.try_recv(), Ok(0));
+        let input = PathBuf::from(job["input"].as_str().unwrap());
+        assert_eq!(std::fs::read(&input).unwrap(), b"video");
+
+        let (status, body) = exchange(&server, b"GET /jobs/0 HTTP/1.1\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["id"], 0);
+        assert_eq!(
+            exchange(&server, b"GET /jobs/0/result HTTP/1.1\r\n\r\n").0,
+            "HTTP/1.1 409 Conflict"
+        );
+        std::fs::remove_dir_all(&server.work_dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_uploads_over_the_limit_or_without_length() {
+        let (server, pending) = test_server("limit", 4);
+        let status = |request: &[u8]| exchange(&server, request).0;
+        assert_eq!(
+            status(b"POST /jobs/upload HTTP/1.1\r\nContent-Length: 5\r\n\r\nvideo"),
+            "HTTP/1.1 413 Payload Too Large"
+        );
+        assert_eq!(
+            status(b"POST /jobs/upload HTTP/1.1\r\n\r\nvideo"),
+            "HTTP/1.1 411 Length Required"
+        );
+        assert!(pending.try_recv().is_err());
+        assert!(std::fs::read_dir(server.work_dir.join("uploads"))
+            .unwrap()
+            .next()
+            .is_none());
+        std::fs::remove_dir_all(&server.work_dir).unwrap();
+    }
+}
@@ -0,0 +1,373 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use tracing::{debug, error, info, warn};
+
+use recmari_core::output::{write_atomic, MatchReader};
+
+/// File extensions treated as videos when scanning the input directory.
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "mov", "webm", "avi", "flv", "ts", "m4v"];
+
+/// Name of the consolidated index written to the output directory.
+const INDEX_FILE: &str = "index.json";
+/// How often a throttled worker re-checks the system load.
+const LOAD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of `analyze` processes run at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jobs {
+    Fixed(usize),
+    /// One per two CPUs (ffmpeg decodes with several threads), and no new video is
+    /// started while the system load exceeds the CPU count.
+    Auto,
+}
+
+impl FromStr for Jobs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Jobs::Auto);
+        }
+        match s.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Jobs::Fixed(n)),
+            _ => Err(format!(
+                "invalid job count '{s}' (expected auto or a positive number)"
+            )),
+        }
+    }
+}
+
+/// Outcome of analyzing one video.
+struct BatchEntry {
+    input: PathBuf,
+    output: PathBuf,
+    log: PathBuf,
+    seconds: f64,
+    result: Result<usize, String>,
+}
+
+/// Video files directly inside `dir`, sorted by name.
+pub fn find_videos(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+    let mut videos = Vec::new();
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("failed to read {}", dir.display()))?
+            .path();
+        let is_video = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+        if is_video && path.is_file() {
+            videos.push(path);
+        }
+    }
+    videos.sort();
+    Ok(videos)
+}
+
+/// Analyze every video in `input_dir` with `recmari analyze`, up to `jobs` processes
+/// at a time, writing `<name>.pb` and `<name>.log` per video (see [`output_names`]) and
+/// an `index.json` of all results to `output_dir`. `analyze_args` are passed to every `analyze` run.
+/// Fails after writing the index if any video failed.
+pub fn analyze_batch(
+    input_dir: &Path,
+    output_dir: &Path,
+    jobs: Jobs,
+    analyze_args: &[OsString],
+) -> Result<()> {
+    let videos = find_videos(input_dir)?;
+    if videos.is_empty() {
+        error!(?input_dir, "no video files found");
+        bail!("no video files found in {}", input_dir.display());
+    }
+    std::fs::create_dir_all(output_dir)
+        .with_context(|| format!("failed to create {}", output_dir.display()))?;
+    let exe = std::env::current_exe().context("failed to locate the recmari executable")?;
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let (max_jobs, throttle) = match jobs {
+        Jobs::Fixed(n) => (n, false),
+        Jobs::Auto => ((cpus / 2).max(1), true),
+    };
+    let max_jobs = max_jobs.clamp(1, videos.len());
+    let names = output_names(&videos);
+    info!(
+        videos = videos.len(),
+        max_jobs,
+        throttle,
+        cpus,
+        ?output_dir,
+        "starting batch analysis"
+    );
+
+    let started = Instant::now();
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let running = AtomicUsize::new(0);
+    let entries: Mutex<Vec<Option<BatchEntry>>> = Mutex::new(videos.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..max_jobs {
+            scope.spawn(|| loop {
+                // At least one video always runs so a busy machine slows the batch
+                // down instead of stalling it.
+                while throttle
+                    && running.load(Ordering::Relaxed) > 0
+                    && next.load(Ordering::Relaxed) < videos.len()
+                    && is_overloaded(cpus)
+                {
+                    std::thread::sleep(LOAD_POLL_INTERVAL);
+                }
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = videos.get(i) else {
+                    break;
+                };
+                running.fetch_add(1, Ordering::Relaxed);
+                let entry = analyze_one(&exe, input, &output_dir.join(&names[i]), analyze_args);
+                running.fetch_sub(1, Ordering::Relaxed);
+                let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+                let per_hour = finished as f64 * 3600.0 / started.elapsed().as_secs_f64();
+                match &entry.result {
+                    Ok(matches) => info!(
+                        progress = %format!("{finished}/{}", videos.len()),
+                        ?input,
+                        matches,
+                        seconds = entry.seconds,
+                        videos_per_hour = %format!("{per_hour:.1}"),
+                        "video analyzed"
+                    ),
+                    Err(e) => warn!(
+                        progress = %format!("{finished}/{}", videos.len()),
+                        ?input,
+                        error = %e,
+                        log = ?entry.log,
+                        "video failed"
+                    ),
+                }
+                entries.lock().unwrap()[i] = Some(entry);
+            });
+        }
+    });
+    let entries: Vec<BatchEntry> = entries
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let index_path = output_dir.join(INDEX_FILE);
+    let index = index_json(&entries, started.elapsed().as_secs_f64());
+    write_atomic(
+        &index_path,
+        serde_json::to_string_pretty(&index)?.as_bytes(),
+    )?;
+
+    let failed = entries.iter().filter(|e| e.result.is_err()).count();
+    if failed > 0 {
+        error!(
+            failed,
+            total = entries.len(),
+            ?index_path,
+            "batch analysis had failures"
+        );
+        bail!(
+            "{failed} of {} videos failed (see {})",
+            entries.len(),
+            index_path.display()
+        );
+    }
+    info!(
+        total = entries.len(),
+        ?index_path,
+        "batch analysis complete"
+    );
+    Ok(())
+}
+
+/// Whether the 1-minute load average exceeds the CPU count. Always false where the
+/// load average is unavailable (non-Linux).
+fn is_overloaded(cpus: usize) -> bool {
+    let Some(load) = std::fs::read_to_string("/proc/loadavg")
+        .ok()
+        .and_then(|text| parse_loadavg(&text))
+    else {
+        return false;
+    };
+    let overloaded = load > cpus as f64;
+    if overloaded {
+        debug!(load, cpus, "system overloaded, delaying the next video");
+    }
+    overloaded
+}
+
+/// 1-minute load average from the contents of `/proc/loadavg`.
+fn parse_loadavg(text: &str) -> Option<f64> {
+    text.split_whitespace().next()?.parse().ok()
+}
+
+/// Base name of each video's result and log: the file stem, or the whole file name
+/// where several videos share a stem (`game.mp4` and `game.mkv`), so that no two
+/// videos write the same files. Stems are compared case-insensitively for
+/// case-insensitive file systems.
+fn output_names(videos: &[PathBuf]) -> Vec<OsString> {
+    let stem = |video: &PathBuf| video.file_stem().unwrap_or(video.as_os_str()).to_owned();
+    let key = |video: &PathBuf| stem(video).to_string_lossy().to_lowercase();
+    let names: Vec<OsString> = videos
+        .iter()
+        .map(|video| {
+            let shared = videos
+                .iter()
+                .filter(|other| key(other) == key(video))
+                .count()
+                > 1;
+            if !shared {
+                return stem(video);
+            }
+            let name = video.file_name().unwrap_or(video.as_os_str()).to_owned();
+            info!(
+                ?video,
+                ?name,
+                "videos share a file stem, naming the output after the file"
+            );
+            name
+        })
+        .collect();
+    assert_eq!(names.len(), videos.len());
+    names
+}
+
+/// Run `recmari analyze` on one video, writing `<base>.pb` and logging its output to
+/// `<base>.log`.
+fn analyze_one(exe: &Path, input: &Path, base: &Path, analyze_args: &[OsString]) -> BatchEntry {
+    let output = with_suffix(base, "pb");
+    let log = with_suffix(base, "log");
+    let started = Instant::now();
+    let result = run_analyze(exe, input, &output, &log, analyze_args)
+        .and_then(|()| Ok(MatchReader::open(&output)?.len()))
+        .map_err(|e| format!("{e:#}"));
+    BatchEntry {
+        input: input.to_path_buf(),
+        output,
+        log,
+        seconds: started.elapsed().as_secs_f64(),
+        result,
+    }
+}
+
+/// `base` with `.suffix` appended, keeping any dots already in its file name
+/// (unlike [`Path::with_extension`], which would replace `a.v1` with `a.pb`).
+fn with_suffix(base: &Path, suffix: &str) -> PathBuf {
+    let mut name = base.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+pub(crate) fn run_analyze(
+    exe: &Path,
+    input: &Path,
+    output: &Path,
+    log: &Path,
+    analyze_args: &[OsString],
+) -> Result<()> {
+    let log_file =
+        File::create(log).with_context(|| format!("failed to create {}", log.display()))?;
+    let status = Command::new(exe)
+        .arg("analyze")
+        .arg("--input")
+        .arg(input)
+        .arg("--output")
+        .arg(output)
+        .args(analyze_args)
+        .stdin(Stdio::null())
+        .stdout(log_file.try_clone().context("failed to open log file")?)
+        .stderr(log_file)
+        .status()
+        .context("failed to start recmari analyze")?;
+    if !status.success() {
+        bail!("analyze exited with {status} (see {})", log.display());
+    }
+    Ok(())
+}
+
+fn index_json(entries: &[BatchEntry], seconds: f64) -> Value {
+    let videos: Vec<Value> = entries
+        .iter()
+        .map(|e| {
+            json!({
+                "input": e.input.display().to_string(),
+                "output": e.output.display().to_string(),
+                "log": e.log.display().to_string(),
+                "status": if e.result.is_ok() { "ok" } else { "failed" },
+                "matches": e.result.as_ref().ok(),
+                "error": e.result.as_ref().err(),
+                "seconds": e.seconds,
+            })
+        })
+        .collect();
+    json!({
+        "total": entries.len(),
+        "failed": entries.iter().filter(|e| e.result.is_err()).count(),
+        "seconds": seconds,
+        "videos": videos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_job_counts() {
+        assert_eq!("auto".parse(), Ok(Jobs::Auto));
+        assert_eq!("AUTO".parse(), Ok(Jobs::Auto));
+        assert_eq!("4".parse(), Ok(Jobs::Fixed(4)));
+        assert!("0".parse::<Jobs>().is_err());
+        assert!("-1".parse::<Jobs>().is_err());
+        assert!("many".parse::<Jobs>().is_err());
+    }
+
+    #[test]
+    fn parses_the_one_minute_load_average() {
+        assert_eq!(parse_loadavg("1.25 0.80 0.50 2/345 6789\n"), Some(1.25));
+        assert_eq!(parse_loadavg(""), None);
+        assert_eq!(parse_loadavg("busy"), None);
+    }
+
+    #[test]
+    fn finds_videos_sorted_by_name() {
+        let dir = std::env::temp_dir().join(format!("recmari-batch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested.mp4")).unwrap();
+        for name in ["b.mkv", "a.MP4", "notes.txt", "no_extension"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let videos = find_videos(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(videos, [dir.join("a.MP4"), dir.join("b.mkv")]);
+        assert!(find_videos(&dir).is_err());
+    }
+
+    #[test]
+    fn output_names_never_collide() {
+        let videos: Vec<PathBuf> = ["in/a.v1.mp4", "in/a.v2.mp4", "in/game.mp4", "in/Game.mkv"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(
+            output_names(&videos),
+            ["a.v1", "a.v2", "game.mp4", "Game.mkv"].map(OsString::from)
+        );
+        let base = Path::new("out").join("a.v1");
+        assert_eq!(with_suffix(&base, "pb"), Path::new("out").join("a.v1.pb"));
+        assert_eq!(with_suffix(&base, "log"), Path::new("out").join("a.v1.log"));
+    }
+}
@@ -1,6 +1,19 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use recmari_core::absolute_health::Characters;
+use recmari_core::analysis::HudType;
+use recmari_core::chart::{Metric, Side};
+use recmari_core::export::ExportFormat;
+use recmari_core::frame_list::FrameList;
+use recmari_core::i18n::Lang;
+use recmari_core::interpolate::InterpolationMode;
+use recmari_core::playback::PlaybackSpeed;
+use recmari_core::tune::TuneTarget;
+use recmari_core::video::decoder::CropMode;
+
+use crate::batch::Jobs;
+use crate::probe::ProbeTarget;
 
 #[derive(Parser)]
 #[command(name = "recmari", about = "SF6 gameplay analyzer")]
@@ -10,35 +23,530 @@ pub struct Cli {
 }
 
 #[derive(Subcommand)]
+// Parsed once per process; boxing `Analyze` would only complicate the match in main.
+#[allow(clippy::large_enum_variant)]
 pub enum Command {
     /// Analyze a recorded video file.
-    Analyze {
-        /// Path to the input video file (MP4, etc.).
+    Analyze(AnalyzeArgs),
+
+    /// Analyze every video file in a directory, one `analyze` process per video.
+    AnalyzeBatch {
+        /// Directory containing the videos (not searched recursively).
+        #[arg(long)]
+        input_dir: PathBuf,
+
+        /// Directory to write `<video>.pb`, `<video>.log` and `index.json` to.
+        #[arg(long)]
+        output_dir: PathBuf,
+
+        /// Number of videos analyzed in parallel, or `auto` to use half the CPUs and
+        /// hold back new videos while the system is overloaded.
+        #[arg(short, long, default_value = "1")]
+        jobs: Jobs,
+
+        /// Analyze every Nth frame.
+        #[arg(short, long, default_value_t = 60)]
+        sample_rate: u32,
+
+        /// HUD settings (TOML) used for every video.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
+
+        /// Also write one JSON file per round next to each output file.
+        #[arg(long)]
+        round_json: bool,
+    },
+
+    /// Render HP/gauge curves from an analysis output file as an SVG chart.
+    Chart {
+        /// Protobuf output file written by `analyze`.
         #[arg(short, long)]
         input: PathBuf,
 
-        /// Path to write the output protobuf file.
+        /// SVG file to write.
         #[arg(short, long)]
         output: PathBuf,
 
-        /// Analyze every Nth frame (default: 60, i.e. 60 samples/sec from 60fps).
+        /// Gauge to plot: hp, sa or od.
+        #[arg(long, default_value = "hp")]
+        metric: Metric,
+
+        /// Match to plot (1-based). In overlay mode, every match is used when omitted.
+        #[arg(long = "match")]
+        match_number: Option<usize>,
+
+        /// Overlay one player's curves from every round on one axis, aligned to round start.
+        #[arg(long)]
+        overlay: bool,
+
+        /// Player plotted in overlay mode: p1 or p2.
+        #[arg(long, default_value = "p1")]
+        player: Side,
+    },
+
+    /// Write a standalone HTML page with round summaries and per-round HP/SA/OD charts
+    /// marked with the round's events.
+    Report {
+        /// Protobuf output file written by `analyze`.
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// HTML file to write.
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Page title (default: the input file name).
+        #[arg(long)]
+        title: Option<String>,
+    },
+
+    /// Export the per-frame gauge time series of an analysis output file.
+    Export {
+        /// Protobuf output file written by `analyze`.
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// File to write (stdout when omitted).
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Export format: csv (per-frame gauges) or drills (moments to practice:
+        /// Drive Impact hits taken, burnouts, dropped Drive Rush combos).
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+    },
+
+    /// Print per-match and per-round statistics of an analysis output file as a table.
+    Summarize {
+        /// Protobuf output file written by `analyze`.
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Language of the summary text (`en` / `ja`).
+        #[arg(long, default_value = "en")]
+        lang: Lang,
+    },
+
+    /// Compare the frame readings of two analysis runs of the same video, aligned by
+    /// frame number, and print the changes per round.
+    Diff {
+        /// Protobuf output of the earlier run.
+        old: PathBuf,
+
+        /// Protobuf output of the run to check.
+        new: PathBuf,
+
+        /// Largest difference of a reading that is not reported as a change.
+        #[arg(long, default_value_t = 0.02)]
+        tolerance: f64,
+
+        /// List every changed reading, not only the counts per round.
+        #[arg(long)]
+        details: bool,
+    },
+
+    /// Run an HTTP API that queues videos for analysis and serves the results.
+    Serve {
+        /// Address to listen on. The API has no authentication; only expose it to
+        /// trusted networks.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on.
+        #[arg(long, default_value_t = 8090)]
+        port: u16,
+
+        /// Directory for uploaded videos, outputs and logs.
+        #[arg(long, default_value = "recmari-jobs")]
+        work_dir: PathBuf,
+
+        /// Number of videos analyzed at the same time.
+        #[arg(long, default_value_t = 1)]
+        workers: usize,
+
+        /// Largest accepted upload, in MiB.
+        #[arg(long, default_value_t = 16384)]
+        max_upload_mb: u64,
+
+        /// Analyze every Nth frame.
         #[arg(short, long, default_value_t = 60)]
         sample_rate: u32,
 
-        /// Directory to save debug frames with HUD region overlays.
+        /// HUD settings (TOML) used for every video.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
+    },
+
+    /// Broadcast frames of a JSONL frame stream (`analyze --stream x.jsonl`) to
+    /// WebSocket clients as they are written, e.g. for OBS browser-source overlays.
+    Push {
+        /// JSONL frame stream to follow; it may not exist yet.
+        #[arg(long)]
+        stream: PathBuf,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on.
+        #[arg(long, default_value_t = 8765)]
+        port: u16,
+    },
+
+    /// Write an OBS overlay with HP/SA/OD history sparklines: a transparent PNG or HTML
+    /// page of one analyzed round, or a live HTML page fed by `recmari push`.
+    Overlay {
+        /// Protobuf output file written by `analyze`. Omit for a live page.
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Overlay file to write: `.png` or `.html`.
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Match to draw (1-based; the last one when omitted).
+        #[arg(long = "match")]
+        match_number: Option<usize>,
+
+        /// Round to draw (1-based; the last one when omitted).
+        #[arg(long)]
+        round: Option<usize>,
+
+        /// WebSocket address of `recmari push` for the live page.
+        #[arg(long, default_value = "ws://127.0.0.1:8765")]
+        ws: String,
+
+        /// Seconds of history kept by the live page.
+        #[arg(long, default_value_t = 30.0)]
+        window: f64,
+    },
+
+    /// Cut highlight clips (KOs, perfect rounds, comebacks) out of the source video.
+    Clips {
+        /// Source video the analysis was run on.
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Protobuf output file written by `analyze`.
+        #[arg(long = "match")]
+        match_file: PathBuf,
+
+        /// Directory to write the clips to (created if missing).
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Seconds of video kept before each highlight.
+        #[arg(long, default_value_t = 5.0)]
+        before: f64,
+
+        /// Seconds of video kept after each highlight.
+        #[arg(long, default_value_t = 3.0)]
+        after: f64,
+
+        /// Burn the analyzed HP/SA/OD values and recent events into the clips.
+        #[arg(long)]
+        annotate: bool,
+    },
+
+    /// Serve a local web page with the source video and HP/SA/OD charts synced to it.
+    ServeUi {
+        /// Protobuf output file written by `analyze`.
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Source video (default: the path recorded in the output file).
         #[arg(long)]
-        debug_frames: Option<PathBuf>,
+        video: Option<PathBuf>,
+
+        /// Port to listen on (localhost only).
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
 
-        /// Analyze only this single frame (seek + analyze + debug overlay).
+    /// Tune HUD colors from reference frames and print a `--hud-config` profile.
+    Calibrate {
+        /// Reference frames (1920x1080) where both players have full health.
+        #[arg(long, required = true)]
+        image: Vec<PathBuf>,
+
+        /// HUD the profile is for (`manemon`).
+        #[arg(long, default_value = "manemon")]
+        hud: HudType,
+
+        /// Existing HUD config whose scanline overrides are used for sampling.
         #[arg(long)]
-        frame: Option<u32>,
+        hud_config: Option<PathBuf>,
+
+        /// Output TOML file (default: stdout).
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Instead of tuning, check each HUD element per player and report which side
+        /// fails (e.g. covered by a camera overlay). Exits with an error if any fails.
+        #[arg(long, conflicts_with = "output")]
+        check: bool,
+    },
+
+    /// Sweep a pixel classifier's color range on labeled frames and print the range
+    /// reading the most labeled values correctly as a `--hud-config` profile.
+    Tune {
+        /// TOML manifest of `[[frame]]` entries: `image` (relative to the manifest) and
+        /// the expected `p1_hp` / `p2_hp`.
+        #[arg(long)]
+        manifest: PathBuf,
+
+        /// Classifier to tune: `hp-fill` or `hp-low-fill`.
+        #[arg(long, default_value = "hp-fill")]
+        classifier: TuneTarget,
+
+        /// HUD the profile is for (`manemon`).
+        #[arg(long, default_value = "manemon")]
+        hud: HudType,
+
+        /// Existing HUD config to start the sweep from.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
+
+        /// Output TOML file (default: stdout).
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 
-    /// Scan SA digit bounding box for unique probe positions.
+    /// Run HUD detection and the HP/SA/OD classifiers on built-in reference frames and
+    /// compare the readings with the drawn values. Exits with an error if any differ.
+    /// The references are synthetic; use `verify-fixtures` to check real frames.
+    SelfTest {
+        /// HUD config to test instead of the built-in layout and colors.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
+    },
+
+    /// Read the HUD of one still image (1920x1080) and print the HP/SA/OD readings of
+    /// both players as JSON.
+    AnalyzeImage {
+        /// Screenshot or extracted frame to read.
+        #[arg(long)]
+        image: PathBuf,
+
+        /// HUD config to read with instead of the built-in layout and colors.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
+    },
+
+    /// Draw the HUD regions, SA digit probes and OD segment boundaries on an image to
+    /// check the coordinates against a capture, without running the analysis.
+    Regions {
+        /// Frame (1920x1080) to draw on.
+        #[arg(long)]
+        image: PathBuf,
+
+        /// Output PNG path.
+        #[arg(long)]
+        out: PathBuf,
+
+        /// HUD whose regions are drawn (default: the detected HUD, or `manemon` when
+        /// none is detected).
+        #[arg(long)]
+        hud: Option<HudType>,
+
+        /// HUD config whose layout overrides are drawn.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
+    },
+
+    /// Read the frames of a fixture directory and compare HUD detection and gauge
+    /// readings with its `expectations.toml`. Exits with an error if any differ.
+    VerifyFixtures {
+        /// Directory holding the frames and `expectations.toml`.
+        #[arg(long, default_value = "crates/recmari-core/tests/fixtures/frames")]
+        dir: PathBuf,
+
+        /// HUD config to test instead of the built-in layout and colors.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
+    },
+
+    /// Measure HUD positions on reference frames: SA digit probe positions, the OD
+    /// gauge layout or the HP bar scanlines.
     ProbeScan {
-        /// Image:digit pairs (e.g. "path/to/both_sa0.png:0").
-        /// Each image must show the specified digit for both P1 and P2.
+        /// What to measure: `sa-digit`, `od-segment` or `hp-bar`.
+        #[arg(long, default_value = "sa-digit")]
+        target: ProbeTarget,
+
+        /// sa-digit: image:digit pairs (e.g. "path/to/both_sa0.png:0"), each showing
+        /// the digit for both P1 and P2. od-segment / hp-bar: 1920x1080 frames where
+        /// both players have a full Drive gauge / full health.
         #[arg(long, required = true)]
         image: Vec<String>,
+
+        /// HUD config whose HP colors are used by `hp-bar`.
+        #[arg(long)]
+        hud_config: Option<PathBuf>,
     },
 }
+
+/// Options of the `analyze` command.
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// Path to the input video file (MP4, etc.), a directory of numbered frame images
+    /// (read as 60fps without ffmpeg), or a YouTube/Twitch VOD URL with the
+    /// `url-input` feature.
+    #[arg(short, long)]
+    pub input: PathBuf,
+
+    /// Path to write the output protobuf file.
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Analyze every Nth frame (default: 60, i.e. 60 samples/sec from 60fps).
+    #[arg(short, long, default_value_t = 60)]
+    pub sample_rate: u32,
+
+    /// Directory to save debug frames with HUD region overlays.
+    #[arg(long)]
+    pub debug_frames: Option<PathBuf>,
+
+    /// Encode debug overlays into a single video (e.g. debug.mp4) instead of PNG frames.
+    #[arg(long, conflicts_with = "debug_frames")]
+    pub debug_video: Option<PathBuf>,
+
+    /// TrueType/OpenType font for debug overlay text (default: bundled DejaVu Sans Mono).
+    #[arg(long)]
+    pub debug_font: Option<PathBuf>,
+
+    /// Analyze only this single frame (seek + analyze + debug overlay).
+    #[arg(long)]
+    pub frame: Option<u32>,
+
+    /// Analyze only these frames, e.g. `1200,3600,5000-5060` (ranges inclusive), each
+    /// with debug overlays.
+    #[arg(long, conflicts_with = "frame")]
+    pub frames: Option<FrameList>,
+
+    /// Also write one JSON file per round next to the output file.
+    #[arg(long)]
+    pub round_json: bool,
+
+    /// Also save a round-start thumbnail PNG per round next to the output file and
+    /// record its path in the round.
+    #[arg(long)]
+    pub thumbnails: bool,
+
+    /// Decode only the HUD rows of each frame (faster; disables center-line detection).
+    #[arg(long)]
+    pub hud_rows_only: bool,
+
+    /// JSON chat log (e.g. a TwitchDownloader export) to correlate with rounds.
+    #[arg(long)]
+    pub chat_log: Option<PathBuf>,
+
+    /// Text file of manual match/round markers (`<time> [match|round] [label]` per line).
+    #[arg(long)]
+    pub markers: Option<PathBuf>,
+
+    /// Use the markers as the only round boundaries instead of seeding detection.
+    #[arg(long, requires = "markers")]
+    pub markers_override: bool,
+
+    /// Directory with reference clips `round_start.wav` and/or `ko.wav`, found in the
+    /// soundtrack to add round boundaries the HUD readings missed.
+    #[arg(long, conflicts_with = "markers_override")]
+    pub audio_cues: Option<PathBuf>,
+
+    /// Write the anomaly timeline as JSON to this file (and as SVG next to it).
+    #[arg(long)]
+    pub anomaly_timeline: Option<PathBuf>,
+
+    /// Print per-player, per-round percentages of unreadable HP/SA/OD and save the
+    /// worst frames as annotated PNGs in this directory.
+    #[arg(long)]
+    pub report_quality: Option<PathBuf>,
+
+    /// TOML HUD config, e.g. to disable elements hidden by a custom overlay.
+    #[arg(long)]
+    pub hud_config: Option<PathBuf>,
+
+    /// Only look for this HUD (`manemon`) instead of choosing among
+    /// all of them. Frames where it is not detected still count as HUD-absent.
+    #[arg(long)]
+    pub hud: Option<String>,
+
+    /// Leave values absent on frames where they are unreadable instead of carrying
+    /// the last-known value forward, so gaps in the readings stay visible.
+    #[arg(long)]
+    pub keep_unreadable: bool,
+
+    /// Fill missing HP/SA/OD values within rounds (`linear` or `hold`) and mark
+    /// those frames `interpolated`, for continuous series in charting tools. Implies
+    /// --keep-unreadable; round statistics only count read values.
+    #[arg(long)]
+    pub interpolate: Option<InterpolationMode>,
+
+    /// Longest gap in seconds (of game time) filled by --interpolate; longer gaps
+    /// stay missing.
+    #[arg(long, default_value_t = 2.0, requires = "interpolate")]
+    pub max_gap: f64,
+
+    /// Exit with code 3 (after writing all outputs) when the quality thresholds are not met.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Strict mode: maximum percentage of HUD frames with an unreadable gauge.
+    #[arg(long, default_value_t = 20.0)]
+    pub max_unreadable_percent: f64,
+
+    /// Strict mode: minimum fraction (0-1) of matches with every winner determined.
+    #[arg(long, default_value_t = 0.8)]
+    pub min_segmentation_confidence: f64,
+
+    /// Continue an interrupted analysis from `<output>.checkpoint`.
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Show progress (percentage, decoding speed and ETA) on stderr.
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Playback speed the capture was recorded at (a factor such as `2`); timestamps
+    /// in the output are converted to game time. The speed is not detected from the
+    /// video, so a wrong factor silently skews every duration.
+    #[arg(long, default_value = "1")]
+    pub playback_speed: PlaybackSpeed,
+
+    /// Re-decode the skipped frames before each event (KO, SA use, screen effects)
+    /// to report frame-accurate event times when sample-rate > 1.
+    #[arg(long)]
+    pub precise_events: bool,
+
+    /// Append each analyzed frame to this file while the analysis runs (JSONL for
+    /// `.jsonl`, length-delimited protobuf `FrameData` otherwise).
+    #[arg(long)]
+    pub stream: Option<PathBuf>,
+
+    /// Skip a frame (keeping the previous readings) when its analysis took at least
+    /// this many seconds. Slow analysis is not interrupted, only discarded.
+    #[arg(long)]
+    pub slow_frame_limit: Option<f64>,
+
+    /// Game picture of a letterboxed or pillarboxed recording: `auto` to detect
+    /// the black bars, or `x:y:w:h` in source pixels. It is scaled to 1920x1080.
+    #[arg(long)]
+    pub crop: Option<CropMode>,
+
+    /// Skip frames that repeat the previous one (30fps footage encoded at 60fps);
+    /// sample-rate then counts distinct frames.
+    #[arg(long)]
+    pub dedup_frames: bool,
+
+    /// Also run the detectors that are not calibrated yet: the round-intro FIGHT
+    /// banner (round start timestamps) and the KO slowdown marking.
+    #[arg(long)]
+    pub experimental: bool,
+
+    /// Characters of player 1 and player 2 as `P1,P2` (e.g. `Ryu,Zangief`). Give a
+    /// side's maximum health as `NAME=MAX` (e.g. `Zangief=11000`) to also estimate
+    /// its absolute health and damage. They apply to every match of the video;
+    /// characters are not recognized on screen.
+    #[arg(long)]
+    pub characters: Option<Characters>,
+}
@@ -1,15 +1,64 @@
+mod api;
+mod batch;
 mod cli;
+mod overlay;
+mod probe;
+mod push;
+mod serve;
+mod sidecar;
 
-use std::path::Path;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{bail, Context, Result};
 use clap::Parser;
-use prost::Message;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
-use recmari_core::analysis::huds::manemon;
-use recmari_core::pipeline::{self, PipelineConfig};
-use recmari_proto::proto::Match;
+use recmari_core::analysis::detector::HudDetector;
+use recmari_core::analysis::huds::manemon::ManemonHud;
+use recmari_core::analysis::huds::{all_huds, select_hud};
+use recmari_core::analysis::{HudType, OdValue, SaValue};
+use recmari_core::anomaly::AnomalyLog;
+use recmari_core::calibrate;
+use recmari_core::chart;
+use recmari_core::config::{self, HudConfig, HudProfile};
+use recmari_core::debug::DebugRenderer;
+use recmari_core::diff;
+use recmari_core::drills;
+use recmari_core::export::{self, ExportFormat};
+use recmari_core::highlights;
+use recmari_core::interpolate::Interpolation;
+use recmari_core::markers::MarkerMode;
+use recmari_core::output::{write_atomic, write_matches, AtomicFile, MatchReader};
+use recmari_core::pipeline::{
+    self, BoundaryHints, CheckpointConfig, DebugConfig, DecodeOptions, PipelineConfig,
+    PipelineOutput,
+};
+use recmari_core::quality::{QualityReport, QualityThresholds, ReadabilityReport};
+use recmari_core::report;
+use recmari_core::selftest;
+use recmari_core::stream::StreamFormat;
+use recmari_core::summary;
+use recmari_core::testing;
+use recmari_core::timeline;
+use recmari_core::tune::{self, TuneTarget};
+use recmari_core::video;
+use recmari_core::video::clip::{self, ClipWindow};
+use recmari_core::video::decoder::VideoDecoder;
+use recmari_core::video::frame::Frame;
+#[cfg(feature = "url-input")]
+use recmari_core::video::remote;
+use recmari_proto::proto::{source_metadata::Source, FrameData, Match};
+use serde_json::json;
+
+/// Worst frames saved by `analyze --report-quality`.
+const WORST_FRAME_IMAGES: usize = 5;
+
+/// Exit code of a run that completed but failed `--strict` quality thresholds
+/// (distinct from 1, which anyhow uses for errors).
+const EXIT_QUALITY: i32 = 3;
 
 fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -22,139 +71,969 @@ fn main() -> Result<()> {
     let cli = cli::Cli::parse();
 
     match cli.command {
-        cli::Command::Analyze {
+        cli::Command::Analyze(args) => analyze(args),
+
+        cli::Command::Chart {
             input,
             output,
+            metric,
+            match_number,
+            overlay,
+            player,
+        } => {
+            info!(?input, ?output, ?metric, overlay, "rendering chart");
+            let args = ChartArgs {
+                metric,
+                match_number,
+                overlay,
+                player,
+            };
+            write_chart(args, &input, &output)
+        }
+
+        cli::Command::AnalyzeBatch {
+            input_dir,
+            output_dir,
+            jobs,
             sample_rate,
-            debug_frames,
-            frame,
+            hud_config,
+            round_json,
+        } => {
+            info!(?input_dir, ?output_dir, ?jobs, "starting batch analysis");
+            let analyze_args = analyze_args(sample_rate, hud_config, round_json);
+            batch::analyze_batch(&input_dir, &output_dir, jobs, &analyze_args)
+        }
+
+        cli::Command::Export {
+            input,
+            output,
+            format,
         } => {
-            info!(?input, ?output, sample_rate, ?frame, "starting analysis");
+            info!(?input, ?output, ?format, "exporting");
+            export_matches(&input, output.as_deref(), format)
+        }
+
+        cli::Command::Report { input, out, title } => {
+            info!(?input, ?out, "writing report");
+            let title = title.unwrap_or_else(|| {
+                input.file_name().map_or("recmari report".to_string(), |n| {
+                    n.to_string_lossy().into_owned()
+                })
+            });
+            let reader = MatchReader::open(&input)?;
+            let mut html = Vec::new();
+            let matches = report::write_report(reader.iter(), &title, &mut html)?;
+            write_atomic(&out, &html)?;
+            info!(?out, matches, "report written");
+            Ok(())
+        }
 
-            let config = PipelineConfig {
-                sample_rate,
-                start_frame: frame.unwrap_or(0),
-                max_frames: frame.map(|_| 1),
-                debug_frames_dir: debug_frames,
+        cli::Command::Summarize { input, lang } => {
+            info!(?input, ?lang, "summarizing");
+            let reader = MatchReader::open(&input)?;
+            summary::write_summary(reader.iter(), lang, &mut std::io::stdout().lock())?;
+            Ok(())
+        }
+
+        cli::Command::Diff {
+            old,
+            new,
+            tolerance,
+            details,
+        } => {
+            info!(?old, ?new, tolerance, "comparing runs");
+            if tolerance.is_nan() || tolerance < 0.0 {
+                error!(tolerance, "invalid tolerance");
+                bail!("--tolerance must be >= 0");
+            }
+            let load = |path: &Path| -> Result<Vec<Match>> {
+                Ok(MatchReader::open(path)?
+                    .iter()
+                    .collect::<Result<Vec<Match>, _>>()?)
             };
+            let diffs = diff::diff_matches(&load(&old)?, &load(&new)?, tolerance);
+            let changes = diff::write_diff(&diffs, details, &mut std::io::stdout().lock())?;
+            info!(rounds = diffs.len(), changes, "runs compared");
+            Ok(())
+        }
 
-            let matches = pipeline::run_pipeline(&input, &config).context("pipeline failed")?;
+        cli::Command::Serve {
+            host,
+            port,
+            work_dir,
+            workers,
+            max_upload_mb,
+            sample_rate,
+            hud_config,
+        } => {
+            info!(%host, port, ?work_dir, workers, "starting API server");
+            let listener = std::net::TcpListener::bind((host.as_str(), port))
+                .with_context(|| format!("failed to listen on {host}:{port}"))?;
+            info!(url = %format!("http://{host}:{port}/jobs"), "API listening");
+            let analyze_args = analyze_args(sample_rate, hud_config, false);
+            api::serve_api(
+                listener,
+                &work_dir,
+                workers,
+                max_upload_mb * 1024 * 1024,
+                analyze_args,
+            )
+        }
 
-            if matches.is_empty() {
-                warn!("no matches detected in video");
+        cli::Command::Push { stream, host, port } => {
+            info!(?stream, %host, port, "starting frame push");
+            if StreamFormat::from_path(&stream) != StreamFormat::Jsonl {
+                error!(?stream, "frame stream is not JSONL");
+                bail!("--stream must be a .jsonl frame stream");
             }
+            let listener = std::net::TcpListener::bind((host.as_str(), port))
+                .with_context(|| format!("failed to listen on {host}:{port}"))?;
+            info!(url = %format!("ws://{host}:{port}/"), "websocket listening");
+            push::push_stream(listener, &stream)
+        }
 
-            write_matches(&matches, &output)?;
+        cli::Command::Overlay {
+            input,
+            output,
+            match_number,
+            round,
+            ws,
+            window,
+        } => {
+            info!(?input, ?output, match_number, round, "writing overlay");
+            write_overlay(input.as_deref(), &output, match_number, round, &ws, window)
+        }
 
+        cli::Command::Clips {
+            input,
+            match_file,
+            out,
+            before,
+            after,
+            annotate,
+        } => {
             info!(
-                match_count = matches.len(),
-                total_rounds = matches.iter().map(|m| m.rounds.len()).sum::<usize>(),
-                ?output,
-                "analysis complete"
+                ?input,
+                ?match_file,
+                ?out,
+                before,
+                after,
+                annotate,
+                "extracting clips"
             );
+            extract_clips(
+                &input,
+                &match_file,
+                &out,
+                ClipWindow { before, after },
+                annotate,
+            )
+        }
 
-            Ok(())
+        cli::Command::ServeUi { input, video, port } => {
+            info!(?input, ?video, port, "starting viewer");
+            serve_ui(&input, video, port)
         }
 
-        cli::Command::ProbeScan { image } => {
-            let digit_images = parse_image_args(&image)?;
-            let entries = manemon::scan_sa_digit_probes(&digit_images);
-
-            // For each digit in the cascade, find the best probe position.
-            // Required: probe[i] must be foreground for digit i and background
-            // for all digits checked after i. Prefer fewer total foreground digits.
-            let cascade_masks: [(u8, u8); 4] = [
-                (0b0001, 0b1111), // digit 0: must be foreground, 1/2/3 must be background
-                (0b0010, 0b1110), // digit 1: must be foreground, 2/3 must be background
-                (0b0100, 0b1100), // digit 2: must be foreground, 3 must be background
-                (0b1000, 0b1000), // digit 3: must be foreground
-            ];
-
-            let cx = entries.iter().map(|e| e.x).min().unwrap()
-                + entries.iter().map(|e| e.x).max().unwrap();
-            let cx = cx / 2;
-            let cy = entries.iter().map(|e| e.y).min().unwrap()
-                + entries.iter().map(|e| e.y).max().unwrap();
-            let cy = cy / 2;
-
-            for (digit, (required, check_mask)) in cascade_masks.iter().enumerate() {
-                let best = entries
-                    .iter()
-                    .filter(|e| e.fg_mask & check_mask == *required)
-                    .min_by_key(|e| {
-                        let dist = e.x.abs_diff(cx) + e.y.abs_diff(cy);
-                        (e.fg_mask.count_ones(), dist)
-                    });
-
-                match best {
-                    Some(c) => println!(
-                        "Probe {{ x: {x}, y: {y} }}, // foreground for: {digits}",
-                        x = c.x,
-                        y = c.y,
-                        digits = (0..4u8)
-                            .filter(|d| c.fg_mask & (1 << d) != 0)
-                            .map(|d| d.to_string())
-                            .collect::<Vec<_>>()
-                            .join(", "),
-                    ),
-                    None => warn!(digit, "no valid probe position found"),
-                }
+        cli::Command::Calibrate {
+            image,
+            hud,
+            hud_config,
+            output,
+            check,
+        } => {
+            info!(images = image.len(), %hud, ?output, check, "calibrating");
+            let hud_config = load_hud_config(hud_config.as_deref())?;
+            let images = load_reference_images(&image)?;
+            if check {
+                check_hud_sides(&image, &images, hud_config.profile(hud))
+            } else {
+                write_calibration(&images, hud, &hud_config, output.as_deref())
             }
+        }
 
-            Ok(())
+        cli::Command::Tune {
+            manifest,
+            classifier,
+            hud,
+            hud_config,
+            output,
+        } => {
+            info!(?manifest, ?classifier, %hud, ?output, "tuning");
+            let hud_config = load_hud_config(hud_config.as_deref())?;
+            write_tuned_profile(&manifest, classifier, hud, &hud_config, output.as_deref())
+        }
+
+        cli::Command::SelfTest { hud_config } => {
+            info!(?hud_config, "running self-test");
+            let hud_config = load_hud_config(hud_config.as_deref())?;
+            run_self_test(&hud_config)
+        }
+
+        cli::Command::AnalyzeImage { image, hud_config } => {
+            info!(?image, ?hud_config, "analyzing image");
+            let hud_config = load_hud_config(hud_config.as_deref())?;
+            analyze_image(&image, &hud_config)
+        }
+
+        cli::Command::Regions {
+            image,
+            out,
+            hud,
+            hud_config,
+        } => {
+            info!(?image, ?out, ?hud, ?hud_config, "drawing HUD regions");
+            let hud_config = load_hud_config(hud_config.as_deref())?;
+            draw_regions(&image, &out, hud, &hud_config)
+        }
+
+        cli::Command::VerifyFixtures { dir, hud_config } => {
+            info!(?dir, ?hud_config, "verifying fixtures");
+            let hud_config = load_hud_config(hud_config.as_deref())?;
+            verify_fixtures(&dir, &hud_config)
+        }
+
+        cli::Command::ProbeScan {
+            target,
+            image,
+            hud_config,
+        } => {
+            info!(%target, images = image.len(), "scanning probes");
+            let hud_config = load_hud_config(hud_config.as_deref())?;
+            probe::probe_scan(target, &image, &hud_config)
         }
     }
 }
 
-/// Serialize matches as length-delimited protobuf and write to file.
-fn write_matches(matches: &[Match], output: &Path) -> Result<()> {
+/// HUD config from `--hud-config`, or the default config when it is not given.
+fn load_hud_config(path: Option<&Path>) -> Result<HudConfig> {
+    let Some(path) = path else {
+        info!("no HUD config given, using the defaults");
+        return Ok(HudConfig::default());
+    };
+    Ok(config::load_hud_config(path)?)
+}
+
+/// Run the `analyze` command: analyze the video and write the output with the
+/// sidecar files requested by `args`.
+fn analyze(args: cli::AnalyzeArgs) -> Result<()> {
+    info!(
+        input = ?args.input,
+        output = ?args.output,
+        sample_rate = args.sample_rate,
+        frame = ?args.frame,
+        frames = ?args.frames,
+        "starting analysis"
+    );
+    if let Some(path) = &args.anomaly_timeline {
+        sidecar::anomaly_svg_path(path)?;
+    }
+    let config = pipeline_config(&args)?;
+
+    let (input, page_url) = resolve_input(args.input.clone())?;
+    let PipelineOutput {
+        mut matches,
+        anomalies,
+    } = match &args.frames {
+        Some(frames) => pipeline::run_frames(&input, &config, frames),
+        None => pipeline::run_pipeline(&input, &config),
+    }
+    .context("pipeline failed")?;
+    if let Some(page_url) = &page_url {
+        record_page_url(&mut matches, page_url);
+    }
+    if matches.is_empty() {
+        warn!("no matches detected in video");
+    }
+    write_analysis(&args, &input, &mut matches, &anomalies)?;
     info!(
-        ?output,
         match_count = matches.len(),
-        "writing protobuf output"
+        total_rounds = matches.iter().map(|m| m.rounds.len()).sum::<usize>(),
+        output = ?args.output,
+        "analysis complete"
+    );
+
+    if args.strict {
+        let thresholds = QualityThresholds {
+            max_unreadable_ratio: args.max_unreadable_percent / 100.0,
+            min_segmentation_confidence: args.min_segmentation_confidence,
+        };
+        enforce_quality(&matches, &anomalies, &thresholds);
+    }
+    Ok(())
+}
+
+/// Pipeline configuration of the `analyze` command.
+fn pipeline_config(args: &cli::AnalyzeArgs) -> Result<PipelineConfig> {
+    let slow_frame_limit = args
+        .slow_frame_limit
+        .map(Duration::try_from_secs_f64)
+        .transpose()
+        .context("invalid --slow-frame-limit")?;
+    Ok(PipelineConfig {
+        sample_rate: args.sample_rate,
+        start_frame: args.frame.unwrap_or(0),
+        max_frames: args.frame.map(|_| 1),
+        debug: DebugConfig {
+            frames_dir: args.debug_frames.clone(),
+            video: args.debug_video.clone(),
+            font: args.debug_font.clone(),
+        },
+        decode: DecodeOptions {
+            hud_rows_only: args.hud_rows_only,
+            crop: args.crop,
+            dedup_frames: args.dedup_frames,
+        },
+        chat_log: args.chat_log.clone(),
+        boundaries: BoundaryHints {
+            markers_file: args.markers.clone(),
+            marker_mode: if args.markers_override {
+                MarkerMode::Override
+            } else {
+                MarkerMode::Seed
+            },
+            audio_cues_dir: args.audio_cues.clone(),
+        },
+        hud_config: load_hud_config(args.hud_config.as_deref())?,
+        hud: args.hud.clone(),
+        keep_unreadable: args.keep_unreadable,
+        interpolation: args.interpolate.map(|mode| Interpolation {
+            mode,
+            max_gap_seconds: args.max_gap,
+        }),
+        checkpoint: CheckpointConfig {
+            file: Some(checkpoint_path(&args.output)),
+            resume: args.resume,
+        },
+        stream_file: args.stream.clone(),
+        progress: args.progress,
+        playback_speed: args.playback_speed,
+        precise_events: args.precise_events,
+        slow_frame_limit,
+        experimental: args.experimental,
+        characters: args.characters.clone(),
+        ..Default::default()
+    })
+}
+
+/// Write the analysis output and the sidecar files requested by `args`, then drop the
+/// checkpoint of the finished run.
+fn write_analysis(
+    args: &cli::AnalyzeArgs,
+    input: &Path,
+    matches: &mut [Match],
+    anomalies: &AnomalyLog,
+) -> Result<()> {
+    let output = &args.output;
+    if args.thumbnails {
+        sidecar::write_round_thumbnails(input, matches, output)?;
+    }
+    write_matches(matches, output)?;
+    if args.round_json {
+        sidecar::write_round_sidecars(matches, output)?;
+    }
+    if let Some(path) = &args.anomaly_timeline {
+        sidecar::write_anomaly_timeline(anomalies, path)?;
+    }
+    if let Some(dir) = &args.report_quality {
+        report_readability(input, matches, anomalies, dir, args.debug_font.as_deref())?;
+    }
+    remove_checkpoint(&checkpoint_path(output));
+    Ok(())
+}
+
+fn load_reference_images(paths: &[PathBuf]) -> Result<Vec<image::RgbImage>> {
+    paths
+        .iter()
+        .map(|path| {
+            info!(?path, "loading reference frame");
+            Ok(image::open(path)
+                .with_context(|| format!("failed to open image {}", path.display()))?
+                .into_rgb8())
+        })
+        .collect()
+}
+
+/// Calibrate the HP fill color from reference frames and write the profile snippet.
+fn write_calibration(
+    images: &[image::RgbImage],
+    hud: HudType,
+    hud_config: &HudConfig,
+    output: Option<&Path>,
+) -> Result<()> {
+    let range = calibrate::calibrate_hp_fill(images, hud_config.profile(hud))?;
+    let toml = calibrate::hp_fill_profile_toml(hud, &range, images.len());
+    match output {
+        Some(path) => write_atomic(path, toml.as_bytes())?,
+        None => print!("{toml}"),
+    }
+    info!(?output, "calibration profile written");
+    Ok(())
+}
+
+fn write_tuned_profile(
+    manifest_path: &Path,
+    target: TuneTarget,
+    hud: HudType,
+    hud_config: &HudConfig,
+    output: Option<&Path>,
+) -> Result<()> {
+    let text = std::fs::read_to_string(manifest_path)
+        .with_context(|| format!("failed to read manifest {}", manifest_path.display()))?;
+    let manifest = tune::parse_tune_manifest(&text)
+        .with_context(|| format!("invalid manifest {}", manifest_path.display()))?;
+    let dir = manifest_path.parent().unwrap_or(Path::new(""));
+    let paths: Vec<PathBuf> = manifest.frames.iter().map(|f| dir.join(&f.image)).collect();
+    let labeled: Vec<(image::RgbImage, [Option<f64>; 2])> = load_reference_images(&paths)?
+        .into_iter()
+        .zip(&manifest.frames)
+        .map(|(image, frame)| (image, [frame.p1_hp, frame.p2_hp]))
+        .collect();
+
+    let result = tune::sweep_hp_colors(&labeled, target, hud_config.profile(hud))?;
+    let toml = tune::tuned_profile_toml(hud, target, &result);
+    match output {
+        Some(path) => write_atomic(path, toml.as_bytes())?,
+        None => print!("{toml}"),
+    }
+    info!(?output, "tuned profile written");
+    Ok(())
+}
+
+/// Print per-player readability of every HUD element; fail if any side is unreadable.
+fn check_hud_sides(
+    paths: &[PathBuf],
+    images: &[image::RgbImage],
+    profile: &HudProfile,
+) -> Result<()> {
+    let side = |ok: bool| if ok { "ok" } else { "FAILED" };
+    let mut failures = Vec::new();
+    for (path, image) in paths.iter().zip(images) {
+        println!("{}", path.display());
+        for check in calibrate::check_sides(image, profile)? {
+            println!(
+                "  {:<10} P1 {:<6} P2 {}",
+                check.element,
+                side(check.p1),
+                side(check.p2)
+            );
+            let failed_sides = [(check.p1, "P1"), (check.p2, "P2")]
+                .into_iter()
+                .filter(|(ok, _)| !ok)
+                .map(|(_, name)| format!("{} {name} {}", path.display(), check.element));
+            failures.extend(failed_sides);
+        }
+    }
+    if !failures.is_empty() {
+        error!(?failures, "HUD check failed");
+        bail!("unreadable HUD elements: {}", failures.join(", "));
+    }
+    info!(images = images.len(), "HUD check passed");
+    Ok(())
+}
+
+/// Print every self-test check; fail if any reading differs from its reference.
+fn run_self_test(hud_config: &HudConfig) -> Result<()> {
+    let checks = selftest::run_self_test(hud_config);
+    let mut failures = Vec::new();
+    for check in &checks {
+        println!(
+            "  {:<12} {:<6} expected {:<14} got {:<14} {}",
+            check.case,
+            check.what,
+            check.expected,
+            check.actual,
+            if check.passed { "ok" } else { "FAILED" }
+        );
+        if !check.passed {
+            failures.push(format!("{} {}", check.case, check.what));
+        }
+    }
+    if !failures.is_empty() {
+        error!(?failures, "self-test failed");
+        bail!(
+            "self-test readings differ from the references: {}",
+            failures.join(", ")
+        );
+    }
+    info!(checks = checks.len(), "self-test passed");
+    Ok(())
+}
+
+fn verify_fixtures(dir: &Path, hud_config: &HudConfig) -> Result<()> {
+    let manifest = testing::load_fixture_manifest(dir)?;
+    let report = testing::run_fixtures(dir, &manifest, hud_config)?;
+    for check in &report.checks {
+        println!(
+            "  {:<28} {:<6} expected {:<14} got {:<14} {}",
+            check.image.display(),
+            check.what,
+            check.expected,
+            check.actual,
+            if check.passed { "ok" } else { "FAILED" }
+        );
+    }
+    let failures: Vec<String> = report
+        .failures()
+        .map(|c| format!("{} {}", c.image.display(), c.what))
+        .collect();
+    if !failures.is_empty() {
+        error!(?failures, "fixture verification failed");
+        bail!(
+            "{} of {} fixture checks failed: {}",
+            failures.len(),
+            report.checks.len(),
+            failures.join(", ")
+        );
+    }
+    info!(
+        frames = report.frames,
+        checks = report.checks.len(),
+        "fixtures verified"
     );
+    Ok(())
+}
+
+/// Draw the regions of `hud` (or of the detected HUD) on the image at `path`.
+fn draw_regions(
+    path: &Path,
+    out: &Path,
+    hud: Option<HudType>,
+    hud_config: &HudConfig,
+) -> Result<()> {
+    let frame = load_still(path)?;
+    let (width, height) = frame.image.dimensions();
+    let huds = all_huds(width, height, hud_config);
+    let hud = hud.unwrap_or_else(|| {
+        select_hud(&huds, &frame).map_or_else(
+            || {
+                info!(?path, "no HUD detected, drawing the manemon HUD");
+                HudType::Manemon
+            },
+            |i| huds[i].hud_type(),
+        )
+    });
+    let index = huds
+        .iter()
+        .position(|h| h.hud_type() == hud)
+        .expect("every HUD type is registered");
+    let img = DebugRenderer::new().render_regions(&frame.image, huds[index].as_ref());
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageFormat::Png)
+        .context("failed to encode annotated image")?;
+    write_atomic(out, png.get_ref())?;
+    info!(?out, hud = %huds[index].hud_type(), "HUD regions drawn");
+    Ok(())
+}
+
+/// Load a still image as frame 0, checking that the HUD can be read at its size.
+fn load_still(path: &Path) -> Result<Frame> {
+    let image = image::open(path)
+        .with_context(|| format!("failed to open image {}", path.display()))?
+        .into_rgb8();
+    let (width, height) = image.dimensions();
+    if !ManemonHud::supports_resolution(width, height) {
+        error!(?path, width, height, "unsupported image resolution");
+        bail!(
+            "{} is {width}x{height}, but only 1920x1080 is supported",
+            path.display()
+        );
+    }
+    Ok(Frame {
+        image,
+        frame_number: 0,
+        timestamp_seconds: 0.0,
+    })
+}
+
+/// Detect the HUD of one still image and print its readings as JSON.
+fn analyze_image(path: &Path, hud_config: &HudConfig) -> Result<()> {
+    let frame = load_still(path)?;
+    let (width, height) = frame.image.dimensions();
+    let mut detector = HudDetector::with_all_huds(width, height, hud_config);
+    let Some(hud) = detector.detect(&frame) else {
+        info!(?path, "no HUD detected");
+        println!("{}", serde_json::to_string_pretty(&json!({ "hud": null }))?);
+        return Ok(());
+    };
+
+    let hp = hud.analyze_hp(&frame);
+    let sa = hud.analyze_sa(&frame);
+    let od = hud.analyze_od(&frame);
+    let player = |hp: Option<f64>, hp_conf, sa: Option<SaValue>, sa_conf, od, od_conf| {
+        let (drive, burnout) = match od {
+            Some(OdValue::Normal(v)) => (Some(v), None),
+            Some(OdValue::Burnout(v)) => (None, Some(v)),
+            None => (None, None),
+        };
+        json!({
+            "hp": hp,
+            "hp_confidence": hp_conf,
+            "sa": sa.map(SaValue::gauge),
+            "ca_ready": sa.map(|sa| matches!(sa, SaValue::CaReady)),
+            "sa_confidence": sa_conf,
+            "drive": drive,
+            "burnout_recovery": burnout,
+            "od_confidence": od_conf,
+        })
+    };
+    let readings = json!({
+        "hud": hud.hud_type().to_string(),
+        "player1": player(hp.p1, hp.p1_confidence, sa.p1, sa.p1_confidence, od.p1, od.p1_confidence),
+        "player2": player(hp.p2, hp.p2_confidence, sa.p2, sa.p2_confidence, od.p2, od.p2_confidence),
+    });
+    println!("{}", serde_json::to_string_pretty(&readings)?);
+    info!(?path, hud = %hud.hud_type(), "image analyzed");
+    Ok(())
+}
+
+/// A VOD page URL resolved to its stream with yt-dlp, and the page URL itself.
+#[cfg(feature = "url-input")]
+fn resolve_input(input: PathBuf) -> Result<(PathBuf, Option<String>)> {
+    if !video::is_url(&input) {
+        return Ok((input, None));
+    }
+    let page_url = input.to_string_lossy().into_owned();
+    let stream_url = remote::resolve_stream_url(&page_url)?;
+    Ok((PathBuf::from(stream_url), Some(page_url)))
+}
+
+/// Local input as is; URLs need the `url-input` feature.
+#[cfg(not(feature = "url-input"))]
+fn resolve_input(input: PathBuf) -> Result<(PathBuf, Option<String>)> {
+    if video::is_url(&input) {
+        error!(?input, "URL input without the url-input feature");
+        bail!("URL input needs a build with `--features url-input`");
+    }
+    Ok((input, None))
+}
 
-    let mut buf = Vec::new();
+/// Record the page URL as the source of each match instead of the short-lived stream
+/// URL, so links and the viewer point at the VOD.
+fn record_page_url(matches: &mut [Match], page_url: &str) {
     for m in matches {
-        m.encode_length_delimited(&mut buf)
-            .context("failed to encode Match")?;
+        if let Some(Source::VideoFile(file)) = m.source.as_mut().and_then(|s| s.source.as_mut()) {
+            file.file_path = page_url.to_owned();
+        }
+    }
+}
+
+/// Checkpoint of an in-progress analysis, kept next to its output.
+fn checkpoint_path(output: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.checkpoint", output.display()))
+}
+
+/// Remove the checkpoint once every output is written; it is only needed to resume.
+fn remove_checkpoint(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => info!(?path, "checkpoint removed"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!(?path, %e, "failed to remove checkpoint"),
+    }
+}
+
+/// Exit with [`EXIT_QUALITY`] when the analysis misses any quality threshold.
+fn enforce_quality(matches: &[Match], anomalies: &AnomalyLog, thresholds: &QualityThresholds) {
+    let report = QualityReport::evaluate(matches, anomalies);
+    let violations = report.violations(thresholds);
+    if violations.is_empty() {
+        info!(?thresholds, "strict mode: quality thresholds met");
+        return;
     }
+    error!(
+        ?violations,
+        exit_code = EXIT_QUALITY,
+        "strict mode: analysis failed quality thresholds"
+    );
+    std::process::exit(EXIT_QUALITY);
+}
+
+/// Print the readability table of an analysis and save its worst frames, decoded
+/// from `input` without any crop, with the analyzed values and unreadable gauges drawn.
+fn report_readability(
+    input: &Path,
+    matches: &[Match],
+    anomalies: &AnomalyLog,
+    dir: &Path,
+    debug_font: Option<&Path>,
+) -> Result<()> {
+    let report = ReadabilityReport::evaluate(matches, anomalies, WORST_FRAME_IMAGES);
+    print!("{}", report.table());
+
+    std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let renderer = match debug_font {
+        Some(path) => DebugRenderer::with_font(path)?,
+        None => DebugRenderer::new(),
+    };
+    for worst in &report.worst_frames {
+        let frame = VideoDecoder::open_at_frame(input, worst.frame_number)?
+            .next_frame()?
+            .with_context(|| {
+                format!("frame {} is past the end of the video", worst.frame_number)
+            })?;
+        let data = matches
+            .iter()
+            .find_map(|m| clip::frame_data_at(m, worst.frame_number));
+        let path = dir.join(format!("worst_{:08}.png", worst.frame_number));
+        renderer
+            .annotate(&frame, data, &worst.captions())
+            .save(&path)
+            .with_context(|| format!("failed to save {}", path.display()))?;
+        info!(?path, captions = ?worst.captions(), "worst frame saved");
+    }
+    info!(
+        ?dir,
+        frames = report.worst_frames.len(),
+        "quality report written"
+    );
+    Ok(())
+}
 
-    if let Some(parent) = output.parent() {
-        std::fs::create_dir_all(parent).context("failed to create output directory")?;
+/// `analyze` options shared by every video of `analyze-batch` and `serve`.
+fn analyze_args(sample_rate: u32, hud_config: Option<PathBuf>, round_json: bool) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["--sample-rate".into(), sample_rate.to_string().into()];
+    if let Some(path) = hud_config {
+        args.push("--hud-config".into());
+        args.push(path.into());
     }
+    if round_json {
+        args.push("--round-json".into());
+    }
+    args
+}
 
-    std::fs::write(output, &buf)
-        .with_context(|| format!("failed to write {}", output.display()))?;
+/// Export every frame of an analysis output file to `output` (stdout when None).
+fn export_matches(input: &Path, output: Option<&Path>, format: ExportFormat) -> Result<()> {
+    let reader = MatchReader::open(input)?;
+    let rows = match output {
+        Some(path) => {
+            let mut out = AtomicFile::create(path)?;
+            let rows = write_export(&reader, format, &mut out)?;
+            out.commit()?;
+            rows
+        }
+        None => write_export(&reader, format, &mut std::io::stdout().lock())?,
+    };
+    info!(?input, ?output, rows, "export complete");
+    Ok(())
+}
 
-    info!(?output, bytes = buf.len(), "protobuf output written");
+fn write_export(reader: &MatchReader, format: ExportFormat, out: &mut impl Write) -> Result<usize> {
+    match format {
+        ExportFormat::Csv => Ok(export::write_csv(reader.iter(), out)?),
+        ExportFormat::Drills => Ok(drills::write_drill_csv(reader.iter(), out)?),
+    }
+}
+
+/// Cut every highlight of every match in `match_file` out of `video` into `out_dir`.
+fn extract_clips(
+    video: &Path,
+    match_file: &Path,
+    out_dir: &Path,
+    window: ClipWindow,
+    annotate: bool,
+) -> Result<()> {
+    if !(window.before >= 0.0 && window.after >= 0.0 && window.before + window.after > 0.0) {
+        error!(?window, "invalid clip window");
+        bail!("--before and --after must be non-negative and not both zero");
+    }
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create {}", out_dir.display()))?;
+    let reader = MatchReader::open(match_file)?;
+    let renderer = annotate.then(DebugRenderer::new);
+    let mut clips = 0usize;
+    for (index, m) in reader.iter().enumerate() {
+        let m = m?;
+        for highlight in highlights::find_highlights(&m) {
+            let path = match &renderer {
+                Some(renderer) => clip::write_annotated_clip(
+                    video,
+                    out_dir,
+                    index + 1,
+                    &highlight,
+                    window,
+                    &m,
+                    renderer,
+                )?,
+                None => clip::write_clip(video, out_dir, index + 1, &highlight, window)?,
+            };
+            info!(?path, kind = %highlight.kind, "clip written");
+            clips += 1;
+        }
+    }
+    info!(?out_dir, clips, "clip extraction complete");
     Ok(())
 }
 
-/// Parse "--image path:digit" arguments into (RgbImage, digit) pairs.
-fn parse_image_args(args: &[String]) -> Result<Vec<(image::RgbImage, u8)>> {
-    let mut result = Vec::with_capacity(args.len());
+/// Serve the viewer for `input`, with `video` or the source recorded in the matches.
+fn serve_ui(input: &Path, video: Option<PathBuf>, port: u16) -> Result<()> {
+    let reader = MatchReader::open(input)?;
+    let matches = reader.iter().collect::<Result<Vec<Match>, _>>()?;
+    let video = match video.or_else(|| {
+        matches
+            .iter()
+            .find_map(timeline::source_video)
+            .map(PathBuf::from)
+    }) {
+        Some(video) => video,
+        None => {
+            error!(?input, "no source video recorded");
+            bail!("{} records no source video; pass --video", input.display());
+        }
+    };
+    if !video.is_file() {
+        error!(?video, "source video not found");
+        bail!("video not found: {}", video.display());
+    }
+    let site = serve::Site {
+        timeline: timeline::timeline_json(&matches).to_string(),
+        video,
+    };
+    let listener = serve::bind(port)?;
+    info!(url = %format!("http://127.0.0.1:{port}/"), "viewer listening");
+    serve::serve(listener, site)
+}
 
-    for arg in args {
-        let (path_str, digit_str) = arg
-            .rsplit_once(':')
-            .with_context(|| format!("expected 'path:digit' format, got '{arg}'"))?;
+/// Render an SVG chart from an analysis output file.
+fn write_chart(args: ChartArgs, input: &Path, output: &Path) -> Result<()> {
+    let loaded = load_chart_matches(input, args.match_number, args.overlay)?;
+    let selected: Vec<(usize, &Match)> = loaded.iter().map(|(i, m)| (*i, m)).collect();
 
-        let digit: u8 = digit_str
-            .parse()
-            .with_context(|| format!("invalid digit '{digit_str}' in '{arg}'"))?;
+    let svg = if args.overlay {
+        let series = chart::overlay_series(&selected, args.player, args.metric);
+        let title = format!("{:?} {:?} by round", args.player, args.metric);
+        chart::render_svg(&title, "seconds since round start", args.metric, &series)
+    } else {
+        let (index, m) = selected[0];
+        let series = chart::timeline_series(m, args.metric);
+        let title = format!("Match {} {:?}", index + 1, args.metric);
+        chart::render_svg(&title, "seconds since match start", args.metric, &series)
+    };
 
-        if digit > 3 {
-            bail!("digit must be 0–3, got {digit} in '{arg}'");
+    write_atomic(output, svg.as_bytes())?;
+    info!(?output, overlay = args.overlay, "chart written");
+    Ok(())
+}
+
+/// Write the overlay of one round of `input`, or the live page without `input`. The
+/// format follows the extension of `output`.
+fn write_overlay(
+    input: Option<&Path>,
+    output: &Path,
+    match_number: Option<usize>,
+    round: Option<usize>,
+    ws: &str,
+    window: f64,
+) -> Result<()> {
+    if window.is_nan() || window <= 0.0 {
+        error!(window, "invalid overlay window");
+        bail!("--window must be positive");
+    }
+    let png = output
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("png"));
+    let frames = match input {
+        Some(input) => Some(load_overlay_round(input, match_number, round)?),
+        None if png => {
+            error!(?output, "live overlay requested as PNG");
+            bail!("a live overlay must be an .html file; pass --input to draw a PNG");
         }
+        None => None,
+    };
+
+    let bytes = if png {
+        let img = recmari_core::overlay::render_png(frames.as_deref().unwrap_or_default());
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut bytes, image::ImageFormat::Png)
+            .context("failed to encode overlay PNG")?;
+        bytes.into_inner()
+    } else {
+        overlay::overlay_html(frames.as_deref(), ws, window).into_bytes()
+    };
+    write_atomic(output, &bytes)?;
+    info!(?output, live = input.is_none(), "overlay written");
+    Ok(())
+}
 
-        let img = image::open(path_str)
-            .with_context(|| format!("failed to open image '{path_str}'"))?
-            .into_rgb8();
+/// Frames of one round of `input`; the last match and round when not given.
+fn load_overlay_round(
+    input: &Path,
+    match_number: Option<usize>,
+    round: Option<usize>,
+) -> Result<Vec<FrameData>> {
+    let reader = MatchReader::open(input)?;
+    let index = match match_number {
+        Some(n) if n == 0 || n > reader.len() => {
+            error!(
+                match_number = n,
+                available = reader.len(),
+                "match not found"
+            );
+            bail!("match {n} not found ({} matches in file)", reader.len());
+        }
+        Some(n) => n - 1,
+        None if reader.is_empty() => {
+            error!(?input, "no matches to draw");
+            bail!("{} contains no matches", input.display());
+        }
+        None => reader.len() - 1,
+    };
+    let mut m = reader.get(index)?;
+    let round_index = match round {
+        Some(n) if n == 0 || n > m.rounds.len() => {
+            error!(round = n, available = m.rounds.len(), "round not found");
+            bail!(
+                "round {n} not found ({} rounds in match {})",
+                m.rounds.len(),
+                index + 1
+            );
+        }
+        Some(n) => n - 1,
+        None if m.rounds.is_empty() => {
+            error!(match_number = index + 1, "match has no rounds");
+            bail!("match {} has no rounds", index + 1);
+        }
+        None => m.rounds.len() - 1,
+    };
+    Ok(m.rounds.swap_remove(round_index).frames)
+}
 
-        info!(path = path_str, digit, "loaded image");
-        result.push((img, digit));
+/// Decode only the matches the chart needs: the requested one, every match for an
+/// overlay, or the first match otherwise.
+fn load_chart_matches(
+    input: &Path,
+    match_number: Option<usize>,
+    overlay: bool,
+) -> Result<Vec<(usize, Match)>> {
+    let reader = MatchReader::open(input)?;
+    if reader.is_empty() {
+        error!(?input, "no matches to chart");
+        bail!("{} contains no matches", input.display());
     }
 
-    Ok(result)
+    let indices: Vec<usize> = match match_number {
+        Some(n) if n == 0 || n > reader.len() => {
+            error!(
+                match_number = n,
+                available = reader.len(),
+                "match not found"
+            );
+            bail!("match {n} not found ({} matches in file)", reader.len());
+        }
+        Some(n) => vec![n - 1],
+        None if overlay => (0..reader.len()).collect(),
+        None => vec![0],
+    };
+    indices
+        .into_iter()
+        .map(|i| Ok(reader.get(i).map(|m| (i, m))?))
+        .collect()
+}
+
+/// Options of the `chart` command besides input/output.
+struct ChartArgs {
+    metric: chart::Metric,
+    match_number: Option<usize>,
+    overlay: bool,
+    player: chart::Side,
 }
@@ -0,0 +1,28 @@
+use serde_json::Value;
+
+use recmari_core::stream::frame_json;
+use recmari_proto::proto::FrameData;
+
+const OVERLAY_HTML: &str = include_str!("../assets/overlay.html");
+
+/// The overlay page: `frames` drawn once, or with None a live page following the
+/// `recmari push` server at `ws_url` and keeping `window_seconds` of history.
+pub fn overlay_html(frames: Option<&[FrameData]>, ws_url: &str, window_seconds: f64) -> String {
+    let frames = match frames {
+        Some(frames) => Value::Array(frames.iter().map(frame_json).collect()),
+        None => Value::Null,
+    };
+    OVERLAY_HTML
+        .replacen("/*FRAMES*/null", &script_json(&frames), 1)
+        .replacen(
+            r#"/*WS_URL*/"ws://127.0.0.1:8765""#,
+            &script_json(&Value::from(ws_url)),
+            1,
+        )
+        .replacen("/*WINDOW_SECONDS*/30", &window_seconds.to_string(), 1)
+}
+
+/// JSON that cannot close the surrounding `<script>` element.
+fn script_json(value: &Value) -> String {
+    value.to_string().replace("</", r"<\/")
+}
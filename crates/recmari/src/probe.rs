@@ -0,0 +1,188 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use tracing::{error, info, warn};
+
+use recmari_core::analysis::huds::manemon;
+use recmari_core::config::HudConfig;
+
+/// HUD part measured by `probe-scan`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeTarget {
+    /// SA stock digit probe positions, from one frame per digit.
+    SaDigit,
+    /// OD gauge scanline and segment spacing, from frames with full Drive gauges.
+    OdSegment,
+    /// HP bar scanlines, from frames with full health.
+    HpBar,
+}
+
+impl FromStr for ProbeTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sa-digit" => Ok(ProbeTarget::SaDigit),
+            "od-segment" => Ok(ProbeTarget::OdSegment),
+            "hp-bar" => Ok(ProbeTarget::HpBar),
+            _ => Err(format!(
+                "invalid probe target '{s}' (expected sa-digit, od-segment or hp-bar)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ProbeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProbeTarget::SaDigit => "sa-digit",
+            ProbeTarget::OdSegment => "od-segment",
+            ProbeTarget::HpBar => "hp-bar",
+        })
+    }
+}
+
+/// Measure `target` on the `--image` arguments and print the positions found.
+pub fn probe_scan(target: ProbeTarget, images: &[String], hud_config: &HudConfig) -> Result<()> {
+    match target {
+        ProbeTarget::SaDigit => scan_sa_digits(&parse_image_args(images)?),
+        ProbeTarget::OdSegment => {
+            for (path, image) in load_images(images)? {
+                println!("// {path}");
+                for (name, probe) in ["P1_OD_GAUGE", "P2_OD_GAUGE"]
+                    .into_iter()
+                    .zip(manemon::probe_od_gauges(&image))
+                {
+                    match probe {
+                        Some(p) => println!(
+                            "{name}: Scanline {{ x_start: {}, x_end: {}, y: {} }}, // segment {} px, gap {} px",
+                            p.gauge.x_start, p.gauge.x_end, p.gauge.y, p.segment_width, p.gap_width
+                        ),
+                        None => warn!(path, gauge = name, "no full OD gauge found"),
+                    }
+                }
+            }
+            Ok(())
+        }
+        ProbeTarget::HpBar => {
+            for (path, image) in load_images(images)? {
+                println!("# {path}\n[manemon.scanlines]");
+                for (key, scan) in ["hp_p1", "hp_p2"]
+                    .into_iter()
+                    .zip(manemon::probe_hp_bars(&image, &hud_config.manemon.colors))
+                {
+                    match scan {
+                        Some(s) => println!(
+                            "{key} = {{ x_start = {}, x_end = {}, y = {} }}",
+                            s.x_start, s.x_end, s.y
+                        ),
+                        None => warn!(path, scanline = key, "no HP bar found"),
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn load_images(paths: &[String]) -> Result<Vec<(&str, image::RgbImage)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let image = image::open(path)
+                .with_context(|| format!("failed to open image '{path}'"))?
+                .into_rgb8();
+            if image.dimensions() != (1920, 1080) {
+                error!(
+                    path,
+                    width = image.width(),
+                    height = image.height(),
+                    "not 1920x1080"
+                );
+                bail!(
+                    "'{path}' is {}x{}, expected 1920x1080",
+                    image.width(),
+                    image.height()
+                );
+            }
+            info!(path, "loaded image");
+            Ok((path.as_str(), image))
+        })
+        .collect()
+}
+
+fn scan_sa_digits(digit_images: &[(image::RgbImage, u8)]) -> Result<()> {
+    let entries = manemon::scan_sa_digit_probes(digit_images);
+
+    // For each digit in the cascade, find the best probe position.
+    // Required: probe[i] must be foreground for digit i and background
+    // for all digits checked after i. Prefer fewer total foreground digits.
+    let cascade_masks: [(u8, u8); 4] = [
+        (0b0001, 0b1111), // digit 0: must be foreground, 1/2/3 must be background
+        (0b0010, 0b1110), // digit 1: must be foreground, 2/3 must be background
+        (0b0100, 0b1100), // digit 2: must be foreground, 3 must be background
+        (0b1000, 0b1000), // digit 3: must be foreground
+    ];
+
+    let cx =
+        entries.iter().map(|e| e.x).min().unwrap() + entries.iter().map(|e| e.x).max().unwrap();
+    let cx = cx / 2;
+    let cy =
+        entries.iter().map(|e| e.y).min().unwrap() + entries.iter().map(|e| e.y).max().unwrap();
+    let cy = cy / 2;
+
+    for (digit, (required, check_mask)) in cascade_masks.iter().enumerate() {
+        let best = entries
+            .iter()
+            .filter(|e| e.fg_mask & check_mask == *required)
+            .min_by_key(|e| {
+                let dist = e.x.abs_diff(cx) + e.y.abs_diff(cy);
+                (e.fg_mask.count_ones(), dist)
+            });
+
+        match best {
+            Some(c) => println!(
+                "Probe {{ x: {x}, y: {y} }}, // foreground for: {digits}",
+                x = c.x,
+                y = c.y,
+                digits = (0..4u8)
+                    .filter(|d| c.fg_mask & (1 << d) != 0)
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            None => warn!(digit, "no valid probe position found"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse "--image path:digit" arguments into (RgbImage, digit) pairs.
+fn parse_image_args(args: &[String]) -> Result<Vec<(image::RgbImage, u8)>> {
+    let mut result = Vec::with_capacity(args.len());
+
+    for arg in args {
+        let (path_str, digit_str) = arg
+            .rsplit_once(':')
+            .with_context(|| format!("expected 'path:digit' format, got '{arg}'"))?;
+
+        let digit: u8 = digit_str
+            .parse()
+            .with_context(|| format!("invalid digit '{digit_str}' in '{arg}'"))?;
+
+        if digit > 3 {
+            bail!("digit must be 0–3, got {digit} in '{arg}'");
+        }
+
+        let img = image::open(path_str)
+            .with_context(|| format!("failed to open image '{path_str}'"))?
+            .into_rgb8();
+
+        info!(path = path_str, digit, "loaded image");
+        result.push((img, digit));
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,310 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+
+/// How often the stream file is checked for new frames.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Appended to the client key before hashing (RFC 6455).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// How long a connection may take to send its upgrade request.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest accepted upgrade request.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+/// How long one frame may take to reach a client before it is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Frames queued per client; a client this far behind is dropped.
+const CLIENT_QUEUE: usize = 256;
+
+/// Queues of the connected clients, each drained by the client's own thread.
+type Clients = Arc<Mutex<Vec<SyncSender<Arc<[u8]>>>>>;
+
+/// Follow the JSONL frame stream at `stream_path` (written by `analyze --stream`)
+/// and send every new line as a WebSocket text message to all connected clients.
+/// Frames already in the file when a client connects are not replayed.
+pub fn push_stream(listener: TcpListener, stream_path: &Path) -> Result<()> {
+    let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+    let accepting = Arc::clone(&clients);
+    thread::spawn(move || accept_clients(listener, &accepting));
+
+    let mut reader = open_at_end(stream_path)?;
+    let mut pos = reader.stream_position()?;
+    let mut line = String::new();
+    let mut sent = 0usize;
+    loop {
+        let read = reader.read_line(&mut line)?;
+        if read > 0 && line.ends_with('\n') {
+            pos += line.len() as u64;
+            broadcast(&clients, line.trim_end());
+            line.clear();
+            sent += 1;
+            if sent.is_multiple_of(600) {
+                debug!(sent, "frames pushed");
+            }
+            continue;
+        }
+        // A partial line stays in `line` until the writer finishes it.
+        thread::sleep(POLL_INTERVAL);
+        let len = std::fs::metadata(stream_path).map_or(0, |m| m.len());
+        if len < pos + line.len() as u64 {
+            // A new analysis truncated the stream; follow it from its start.
+            info!(path = ?stream_path, "frame stream restarted");
+            reader = BufReader::new(File::open(stream_path)?);
+            pos = 0;
+            line.clear();
+        }
+    }
+}
+
+/// Open the stream positioned after the last complete frame already written (a
+/// partial last line is sent once finished), waiting for the file to be created. A
+/// stream created while waiting is followed from its start.
+fn open_at_end(path: &Path) -> Result<BufReader<File>> {
+    let mut waited = false;
+    let file = loop {
+        match File::open(path) {
+            Ok(file) => break file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                if !waited {
+                    info!(?path, "waiting for the frame stream to be created");
+                    waited = true;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(e) => return Err(e).with_context(|| format!("failed to open {}", path.display())),
+        }
+    };
+    let mut reader = BufReader::new(file);
+    if !waited {
+        let mut existing = Vec::new();
+        reader.read_to_end(&mut existing)?;
+        let start = existing
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        reader.seek(SeekFrom::Start(start as u64))?;
+    }
+    info!(?path, "following frame stream");
+    Ok(reader)
+}
+
+/// Give every connection its own thread, so that a slow handshake or a slow client
+/// never holds up the others.
+fn accept_clients(listener: TcpListener, clients: &Clients) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(%e, "failed to accept connection");
+                continue;
+            }
+        };
+        let clients = Arc::clone(clients);
+        thread::spawn(move || serve_client(stream, &clients));
+    }
+}
+
+/// Open the WebSocket, then send the client every queued frame until it disconnects,
+/// times out or falls behind (its queue is then dropped by `broadcast`).
+fn serve_client(mut stream: TcpStream, clients: &Clients) {
+    let peer = stream.peer_addr().ok();
+    if let Err(e) = handshake(&stream) {
+        debug!(?peer, %e, "websocket handshake failed");
+        return;
+    }
+    if let Err(e) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+        warn!(?peer, %e, "failed to set write timeout");
+        return;
+    }
+    let (sender, frames): (_, Receiver<Arc<[u8]>>) = mpsc::sync_channel(CLIENT_QUEUE);
+    clients.lock().unwrap().push(sender);
+    info!(?peer, "websocket client connected");
+    for frame in frames {
+        if let Err(e) = stream.write_all(&frame) {
+            info!(?peer, %e, "websocket client disconnected");
+            return;
+        }
+    }
+    info!(?peer, "websocket client dropped for falling behind");
+}
+
+/// Answer the HTTP upgrade request that opens a WebSocket.
+fn handshake(stream: &TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_BYTES));
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+    }
+    let mut out = stream;
+    let Some(key) = key else {
+        write!(
+            out,
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a websocket request",
+        ));
+    };
+    write!(
+        out,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )
+}
+
+/// `Sec-WebSocket-Accept` for a client's `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    let digest = sha1_smol::Sha1::from(format!("{key}{WEBSOCKET_GUID}")).digest();
+    base64(&digest.bytes())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Queue `text` for every client, dropping those that disconnected or whose queue is
+/// full. Never blocks on a client.
+fn broadcast(clients: &Clients, text: &str) {
+    let frame: Arc<[u8]> = text_frame(text).into();
+    clients
+        .lock()
+        .unwrap()
+        .retain(|client| match client.try_send(Arc::clone(&frame)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    queued = CLIENT_QUEUE,
+                    "dropping websocket client that fell behind"
+                );
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+}
+
+/// An unmasked, unfragmented WebSocket text frame.
+fn text_frame(text: &str) -> Vec<u8> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x81];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= 0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc_6455_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn frame_length_uses_the_shortest_encoding() {
+        let frame = text_frame(&"a".repeat(125));
+        assert_eq!(frame[..2], [0x81, 125]);
+        assert_eq!(frame.len(), 2 + 125);
+
+        let frame = text_frame(&"a".repeat(126));
+        assert_eq!(frame[..4], [0x81, 126, 0, 126]);
+        assert_eq!(frame.len(), 4 + 126);
+
+        let frame = text_frame(&"a".repeat(65536));
+        assert_eq!(frame[..10], [0x81, 127, 0, 0, 0, 0, 0, 1, 0, 0]);
+        assert_eq!(frame.len(), 10 + 65536);
+    }
+
+    #[test]
+    fn client_that_falls_behind_is_dropped() {
+        let (sender, _frames) = mpsc::sync_channel(CLIENT_QUEUE);
+        let clients: Clients = Arc::new(Mutex::new(vec![sender]));
+        for _ in 0..CLIENT_QUEUE {
+            broadcast(&clients, "{}");
+        }
+        assert_eq!(clients.lock().unwrap().len(), 1);
+        broadcast(&clients, "{}");
+        assert!(clients.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn connected_client_receives_frames() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let accepting = Arc::clone(&clients);
+        thread::spawn(move || accept_clients(listener, &accepting));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        write!(
+            client,
+            "GET / HTTP/1.1\r\nUpgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n"
+        )
+        .unwrap();
+        let mut reader = BufReader::new(client);
+        let mut response = String::new();
+        while !response.ends_with("\r\n\r\n") {
+            reader.read_line(&mut response).unwrap();
+        }
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+        while clients.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        broadcast(&clients, "hello");
+        let mut frame = [0; 7];
+        reader.read_exact(&mut frame).unwrap();
+        assert_eq!(frame, *b"\x81\x05hello");
+    }
+}
@@ -0,0 +1,308 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+
+const VIEWER_HTML: &str = include_str!("../assets/viewer.html");
+
+/// Longest accepted request line or header line, including the line break.
+const MAX_HEADER_LINE: u64 = 8 * 1024;
+/// Largest accepted request line and headers together.
+const MAX_HEAD_BYTES: u64 = 32 * 1024;
+/// How long a connection may wait between two reads before it is closed, so slow or
+/// idle clients do not hold their thread forever.
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What the viewer serves: the page, the timeline JSON and the source video.
+pub struct Site {
+    pub timeline: String,
+    pub video: PathBuf,
+}
+
+/// Serve the viewer until the process is killed. Each connection gets its own thread
+/// so the video can stream while the page loads the timeline.
+pub fn serve(listener: TcpListener, site: Site) -> Result<()> {
+    let site = Arc::new(site);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(%e, "failed to accept connection");
+                continue;
+            }
+        };
+        let site = Arc::clone(&site);
+        thread::spawn(move || {
+            // Browsers drop video connections whenever the user seeks.
+            if let Err(e) = handle(stream, &site) {
+                debug!(%e, "connection closed");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Request line and headers of an HTTP request.
+pub(crate) struct Head {
+    pub(crate) method: String,
+    /// Path and query as sent.
+    pub(crate) target: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Head {
+    /// Trimmed value of the first header called `name` (case-insensitive).
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Read the request line and headers. A line longer than [`MAX_HEADER_LINE`], a head
+/// larger than [`MAX_HEAD_BYTES`] or a head that is not UTF-8 is an `InvalidData`
+/// error.
+pub(crate) fn read_head(reader: &mut impl BufRead) -> io::Result<Head> {
+    let mut budget = MAX_HEAD_BYTES;
+    let request_line = read_head_line(reader, &mut budget)?;
+    let mut headers = Vec::new();
+    loop {
+        let line = read_head_line(reader, &mut budget)?;
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    Ok(Head {
+        method: parts.next().unwrap_or("").to_string(),
+        target: parts.next().unwrap_or("").to_string(),
+        headers,
+    })
+}
+
+/// Read one line of the request head, taking its length from `budget`. Empty at the
+/// end of the stream.
+fn read_head_line(reader: &mut impl BufRead, budget: &mut u64) -> io::Result<String> {
+    let limit = MAX_HEADER_LINE.min(*budget);
+    let mut line = Vec::new();
+    let read = reader.by_ref().take(limit).read_until(b'\n', &mut line)? as u64;
+    if read == limit && !line.ends_with(b"\n") {
+        let message = if limit == *budget {
+            format!("request head longer than {MAX_HEAD_BYTES} bytes")
+        } else {
+            format!("header line longer than {MAX_HEADER_LINE} bytes")
+        };
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    *budget -= read;
+    String::from_utf8(line)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "request head is not UTF-8"))
+}
+
+fn handle(stream: TcpStream, site: &Site) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut out = stream;
+    let head = match read_head(&mut reader) {
+        Ok(head) => head,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            warn!(%e, "malformed request");
+            return respond(
+                &mut out,
+                "400 Bad Request",
+                "text/plain",
+                e.to_string().as_bytes(),
+            );
+        }
+        Err(e) => return Err(e),
+    };
+    let (method, path, range) = (
+        head.method.as_str(),
+        head.target.as_str(),
+        head.header("range"),
+    );
+    info!(method, path, ?range, "request");
+    if method != "GET" {
+        return respond(&mut out, "405 Method Not Allowed", "text/plain", b"");
+    }
+    match path {
+        "/" => respond(
+            &mut out,
+            "200 OK",
+            "text/html; charset=utf-8",
+            VIEWER_HTML.as_bytes(),
+        ),
+        "/timeline.json" => respond(
+            &mut out,
+            "200 OK",
+            "application/json",
+            site.timeline.as_bytes(),
+        ),
+        "/video" => send_video(&mut out, site, range),
+        _ => respond(&mut out, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+pub(crate) fn respond(
+    out: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    out.write_all(body)
+}
+
+/// Send the video, honoring a byte range so the browser can seek.
+fn send_video(out: &mut TcpStream, site: &Site, range: Option<&str>) -> io::Result<()> {
+    let mut file = File::open(&site.video)?;
+    let len = file.metadata()?.len();
+    let content_type = video_content_type(&site.video);
+    let (status, start, end) = match range.map(|r| parse_range(r, len)) {
+        None => ("200 OK", 0, len),
+        Some(Some((start, end))) => ("206 Partial Content", start, end),
+        Some(None) => {
+            write!(
+                out,
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{len}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )?;
+            return Ok(());
+        }
+    };
+    write!(
+        out,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\n",
+        end - start
+    )?;
+    if range.is_some() {
+        write!(out, "Content-Range: bytes {start}-{}/{len}\r\n", end - 1)?;
+    }
+    write!(out, "Connection: close\r\n\r\n")?;
+    file.seek(SeekFrom::Start(start))?;
+    io::copy(&mut file.take(end - start), out)?;
+    Ok(())
+}
+
+/// `[start, end)` of a single-range `bytes=` header, or None if unsatisfiable.
+fn parse_range(header: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = header.strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.saturating_sub(suffix), len)
+        }
+        (start, "") => (start.parse().ok()?, len),
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            (start.parse().ok()?, end.saturating_add(1).min(len))
+        }
+    };
+    (start < end).then_some((start, end))
+}
+
+fn video_content_type(path: &std::path::Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("mp4" | "m4v") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mkv") => "video/x-matroska",
+        Some("mov") => "video/quicktime",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Bind to `port` on localhost.
+pub fn bind(port: u16) -> Result<TcpListener> {
+    TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("failed to listen on port {port}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> io::Result<Head> {
+        read_head(&mut text.as_bytes())
+    }
+
+    #[test]
+    fn head_collects_headers() {
+        let head = read("GET /video HTTP/1.1\r\nHost: x\r\nRANGE:  bytes=0-9 \r\n\r\n").unwrap();
+        assert_eq!(
+            (head.method.as_str(), head.target.as_str()),
+            ("GET", "/video")
+        );
+        assert_eq!(head.header("range"), Some("bytes=0-9"));
+        assert_eq!(head.header("content-length"), None);
+    }
+
+    #[test]
+    fn oversized_heads_are_invalid_data() {
+        let long_line = format!(
+            "GET /jobs HTTP/1.1\r\nX-Pad: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER_LINE as usize)
+        );
+        let e = read(&long_line).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().starts_with("header line"), "{e}");
+
+        // A line without a break never ends, however long the client keeps sending.
+        let endless = format!("GET /{}", "a".repeat(MAX_HEAD_BYTES as usize));
+        assert_eq!(
+            read(&endless).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let header = format!("X-Pad: {}\r\n", "a".repeat(1000));
+        let many = format!("GET /jobs HTTP/1.1\r\n{}\r\n", header.repeat(40));
+        let e = read(&many).err().unwrap();
+        assert!(e.to_string().starts_with("request head"), "{e}");
+
+        let fits = format!("GET /jobs HTTP/1.1\r\n{}\r\n", header.repeat(20));
+        assert_eq!(read(&fits).unwrap().target, "/jobs");
+    }
+
+    #[test]
+    fn ranges_are_clamped_to_the_file() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 100)));
+        assert_eq!(parse_range("bytes=900-", 1000), Some((900, 1000)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 1000)));
+        assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 1000)));
+        assert_eq!(parse_range("bytes=500-5000", 1000), Some((500, 1000)));
+        assert_eq!(
+            parse_range("bytes=0-18446744073709551615", 1000),
+            Some((0, 1000))
+        );
+    }
+
+    #[test]
+    fn unsatisfiable_or_malformed_ranges_are_rejected() {
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=1000-1100", 1000), None);
+        assert_eq!(parse_range("bytes=10-5", 1000), None);
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+        assert_eq!(parse_range("bytes=abc-", 1000), None);
+        assert_eq!(parse_range("bytes=0-9,20-29", 1000), None);
+        assert_eq!(parse_range("items=0-9", 1000), None);
+        assert_eq!(parse_range("bytes=", 1000), None);
+    }
+}
@@ -0,0 +1,352 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use image::imageops::{self, FilterType};
+use image::ImageFormat;
+use serde_json::{json, Value};
+use tracing::{debug, error, info};
+
+use recmari_core::anomaly::AnomalyLog;
+use recmari_core::defense;
+use recmari_core::links::VideoLink;
+use recmari_core::output::write_atomic;
+use recmari_core::video::decoder::VideoDecoder;
+use recmari_proto::proto::{
+    DamageTotals, FrameData, Match, PressureInterval, Round, Winner, WinnerSignalSource,
+};
+
+/// Width of round thumbnails in pixels; the height keeps the frame's aspect ratio.
+const THUMBNAIL_WIDTH: u32 = 480;
+
+/// Write one JSON file per round next to `output`.
+///
+/// Files are named `<stem>.match<NNN>.round<N>.json` (1-based numbering),
+/// e.g. `result.pb` -> `result.match001.round1.json`.
+pub fn write_round_sidecars(matches: &[Match], output: &Path) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+
+    for (match_index, m) in matches.iter().enumerate() {
+        let link = VideoLink::for_match(m);
+        for round in &m.rounds {
+            let path = sidecar_path(output, match_index, round.round_index);
+            let body = round_json(match_index, round, link.as_ref());
+            let text = serde_json::to_string_pretty(&body).context("failed to serialize round")?;
+
+            if let Err(e) = write_atomic(&path, text.as_bytes()) {
+                error!(?path, %e, "failed to write round sidecar");
+                return Err(e.into());
+            }
+            written.push(path);
+        }
+    }
+
+    info!(
+        ?output,
+        files = written.len(),
+        "round sidecar files written"
+    );
+    Ok(written)
+}
+
+/// Save the round-start frame of every round, scaled to [`THUMBNAIL_WIDTH`], as a PNG
+/// next to `output` and record its file name in the round's `thumbnail_path`.
+///
+/// Files are named like the round sidecars: `result.pb` -> `result.match001.round1.png`.
+pub fn write_round_thumbnails(input: &Path, matches: &mut [Match], output: &Path) -> Result<usize> {
+    let mut written = 0usize;
+    for (match_index, m) in matches.iter_mut().enumerate() {
+        for round in &mut m.rounds {
+            let Some(frame_number) = round_start_frame(round) else {
+                continue;
+            };
+            let frame = VideoDecoder::open_at_frame(input, frame_number)?
+                .next_frame()?
+                .with_context(|| format!("frame {frame_number} is past the end of the video"))?;
+            let height = frame.image.height() * THUMBNAIL_WIDTH / frame.image.width();
+            let thumbnail =
+                imageops::resize(&frame.image, THUMBNAIL_WIDTH, height, FilterType::Triangle);
+            let path = sidecar_path(output, match_index, round.round_index).with_extension("png");
+            let mut png = Cursor::new(Vec::new());
+            thumbnail
+                .write_to(&mut png, ImageFormat::Png)
+                .context("failed to encode thumbnail")?;
+            if let Err(e) = write_atomic(&path, png.get_ref()) {
+                error!(?path, %e, "failed to write round thumbnail");
+                return Err(e.into());
+            }
+            round.thumbnail_path = path
+                .file_name()
+                .expect("sidecar path has a file name")
+                .to_string_lossy()
+                .into_owned();
+            debug!(?path, frame_number, "round thumbnail written");
+            written += 1;
+        }
+    }
+    info!(?output, files = written, "round thumbnails written");
+    Ok(written)
+}
+
+/// First sampled frame at or after the round's start, or None for a round without frames.
+fn round_start_frame(round: &Round) -> Option<u32> {
+    round
+        .frames
+        .iter()
+        .find(|fd| fd.timestamp_seconds >= round.start_timestamp_seconds)
+        .or(round.frames.first())
+        .map(|fd| fd.frame_number)
+}
+
+/// SVG path next to the anomaly timeline JSON at `path`. Fails when the two would be the
+/// same file.
+pub fn anomaly_svg_path(path: &Path) -> Result<PathBuf> {
+    let svg_path = path.with_extension("svg");
+    if svg_path == path {
+        error!(?path, "anomaly timeline path ends in .svg");
+        bail!(
+            "--anomaly-timeline {} would be overwritten by the SVG; use a .json path",
+            path.display()
+        );
+    }
+    Ok(svg_path)
+}
+
+/// Write the anomaly timeline as JSON to `path` and as SVG to `path` with an `.svg` extension.
+pub fn write_anomaly_timeline(anomalies: &AnomalyLog, path: &Path) -> Result<()> {
+    let svg_path = anomaly_svg_path(path)?;
+
+    let text = serde_json::to_string_pretty(&anomalies.to_json())
+        .context("failed to serialize anomalies")?;
+    for (target, body) in [(path, text), (svg_path.as_path(), anomalies.render_svg())] {
+        if let Err(e) = write_atomic(target, body.as_bytes()) {
+            error!(path = ?target, %e, "failed to write anomaly timeline");
+            return Err(e.into());
+        }
+    }
+
+    info!(
+        ?path,
+        ?svg_path,
+        anomalies = anomalies.anomalies.len(),
+        "anomaly timeline written"
+    );
+    Ok(())
+}
+
+fn sidecar_path(output: &Path, match_index: usize, round_index: u32) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    let name = format!(
+        "{stem}.match{:03}.round{}.json",
+        match_index + 1,
+        round_index + 1
+    );
+    output.with_file_name(name)
+}
+
+fn round_json(match_index: usize, round: &Round, link: Option<&VideoLink>) -> Value {
+    let start = round.frames.first().map(|f| f.timestamp_seconds);
+    let end = round.frames.last().map(|f| f.timestamp_seconds);
+    let duration = start.zip(end).map(|(s, e)| e - s);
+    let winner = Winner::try_from(round.winner).unwrap_or(Winner::Unknown);
+    let p1_hp = |fd: &FrameData| fd.player1.as_ref().and_then(|p| p.health_ratio);
+    let p2_hp = |fd: &FrameData| fd.player2.as_ref().and_then(|p| p.health_ratio);
+
+    json!({
+        "match_index": match_index,
+        "round_index": round.round_index,
+        "winner": winner_name(winner),
+        "winner_disputed": round.winner_disputed,
+        "draw": round.draw,
+        "perfect": round.perfect,
+        "burnout_ko": round.burnout_ko,
+        "comeback": round.comeback,
+        "winner_lowest_health": round.winner_lowest_health,
+        "winner_lowest_health_seconds": round.winner_lowest_health_seconds,
+        "winner_signals": round.winner_signals.iter().map(|s| json!({
+            "source": signal_source_name(s.source()),
+            "winner": winner_name(s.winner()),
+            "confidence": s.confidence,
+        })).collect::<Vec<_>>(),
+        "start_seconds": start,
+        "end_seconds": end,
+        "duration_seconds": duration,
+        "start_timestamp_seconds": round.start_timestamp_seconds,
+        "start_from_fight_banner": round.start_from_fight_banner,
+        "link": link.map(|l| l.at(round.start_timestamp_seconds)),
+        "thumbnail": (!round.thumbnail_path.is_empty()).then_some(&round.thumbnail_path),
+        "frame_count": round.frames.len(),
+        "stats": {
+            "player1": player_stats(&round.frames, p1_hp, &round.player1_pressure, round.player1_damage.as_ref()),
+            "player2": player_stats(&round.frames, p2_hp, &round.player2_pressure, round.player2_damage.as_ref()),
+        },
+        "events": round.events.iter().map(|e| json!({
+            "type": e.r#type().as_str_name(),
+            "frame_number": e.frame_number,
+            "timestamp_seconds": e.timestamp_seconds,
+            "player": (e.player > 0).then_some(e.player),
+            "sa_level": (e.sa_level > 0).then_some(e.sa_level),
+            "link": link.map(|l| l.at(e.timestamp_seconds)),
+        })).collect::<Vec<_>>(),
+        "chat_spikes": round.chat_spikes.iter().map(|s| json!({
+            "start_seconds": s.start_seconds,
+            "end_seconds": s.end_seconds,
+            "message_count": s.message_count,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Final and minimum health of one player over the round, their damage totals, and
+/// when they were under pressure.
+fn player_stats(
+    frames: &[FrameData],
+    hp: impl Fn(&FrameData) -> Option<f64>,
+    pressure: &[PressureInterval],
+    damage: Option<&DamageTotals>,
+) -> Value {
+    let final_hp = frames.iter().rev().find_map(&hp);
+    let min_hp = frames.iter().filter_map(&hp).reduce(f64::min);
+    json!({
+        "final_health_ratio": final_hp,
+        "min_health_ratio": min_hp,
+        "damage": damage.map(|d| json!({
+            "dealt": d.dealt,
+            "received": d.received,
+            "recovered": d.recovered,
+        })),
+        "pressure_seconds": defense::pressure_seconds(pressure),
+        "pressure": pressure.iter().map(|i| json!({
+            "start_seconds": i.start_seconds,
+            "end_seconds": i.end_seconds,
+            "drive_lost": i.drive_lost,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn signal_source_name(source: WinnerSignalSource) -> &'static str {
+    match source {
+        WinnerSignalSource::Health => "health",
+        WinnerSignalSource::Ko => "ko",
+        WinnerSignalSource::Unknown => "unknown",
+    }
+}
+
+fn winner_name(winner: Winner) -> &'static str {
+    match winner {
+        Winner::P1 => "p1",
+        Winner::P2 => "p2",
+        Winner::Unknown => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use recmari_proto::proto::{
+        source_metadata::Source, PlayerState, SourceMetadata, VideoFileSource,
+    };
+
+    use super::*;
+
+    fn frame(timestamp_seconds: f64, p1: Option<f64>, p2: Option<f64>) -> FrameData {
+        let player = |health_ratio| {
+            Some(PlayerState {
+                health_ratio,
+                ..Default::default()
+            })
+        };
+        FrameData {
+            timestamp_seconds,
+            player1: player(p1),
+            player2: player(p2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sidecar_path_numbers_matches_and_rounds_from_one() {
+        assert_eq!(
+            sidecar_path(Path::new("out/result.pb"), 0, 0),
+            Path::new("out/result.match001.round1.json")
+        );
+        assert_eq!(
+            sidecar_path(Path::new("result.pb"), 11, 2),
+            Path::new("result.match012.round3.json")
+        );
+    }
+
+    #[test]
+    fn round_json_reports_stats_and_link() {
+        let m = Match {
+            source: Some(SourceMetadata {
+                source: Some(Source::VideoFile(VideoFileSource {
+                    file_path: "https://www.youtube.com/watch?v=abc".to_string(),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let round = Round {
+            round_index: 1,
+            frames: vec![
+                frame(10.0, Some(1.0), Some(1.0)),
+                frame(25.0, Some(0.4), None),
+                frame(40.0, Some(0.6), Some(0.0)),
+            ],
+            winner: Winner::P1.into(),
+            start_timestamp_seconds: 10.0,
+            ..Default::default()
+        };
+        let body = round_json(2, &round, VideoLink::for_match(&m).as_ref());
+
+        assert_eq!(body["match_index"], 2);
+        assert_eq!(body["round_index"], 1);
+        assert_eq!(body["winner"], "p1");
+        assert_eq!(body["start_seconds"], 10.0);
+        assert_eq!(body["end_seconds"], 40.0);
+        assert_eq!(body["duration_seconds"], 30.0);
+        assert_eq!(body["frame_count"], 3);
+        assert_eq!(body["link"], "https://www.youtube.com/watch?v=abc&t=10s");
+        let p1 = &body["stats"]["player1"];
+        assert_eq!(p1["final_health_ratio"], 0.6);
+        assert_eq!(p1["min_health_ratio"], 0.4);
+        assert_eq!(body["stats"]["player2"]["final_health_ratio"], 0.0);
+    }
+
+    #[test]
+    fn round_json_uses_null_for_missing_values() {
+        let body = round_json(0, &Round::default(), None);
+
+        assert_eq!(body["winner"], "unknown");
+        for key in [
+            "start_seconds",
+            "end_seconds",
+            "duration_seconds",
+            "link",
+            "thumbnail",
+        ] {
+            assert!(body[key].is_null(), "{key}: {}", body[key]);
+        }
+        let p1 = &body["stats"]["player1"];
+        assert!(p1["final_health_ratio"].is_null());
+        assert!(p1["min_health_ratio"].is_null());
+        assert!(p1["damage"].is_null());
+        assert_eq!(body["events"], json!([]));
+    }
+
+    #[test]
+    fn anomaly_svg_path_sits_next_to_the_json() {
+        assert_eq!(
+            anomaly_svg_path(Path::new("out/anomalies.json")).unwrap(),
+            Path::new("out/anomalies.svg")
+        );
+        assert_eq!(
+            anomaly_svg_path(Path::new("anomalies")).unwrap(),
+            Path::new("anomalies.svg")
+        );
+        assert!(anomaly_svg_path(Path::new("out/anomalies.svg")).is_err());
+    }
+}
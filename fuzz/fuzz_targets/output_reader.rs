@@ -0,0 +1,9 @@
+//! Arbitrary bytes passed as an analysis output file must produce errors, not panics.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = recmari_core::output::decode_matches(data);
+});